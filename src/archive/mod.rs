@@ -0,0 +1,309 @@
+pub mod tar_reader;
+pub mod zip_reader;
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::time::{Instant, SystemTime};
+
+use tokio::io::AsyncRead;
+
+use crate::fs::path_safety::PathSafety;
+
+/// 支持浏览的归档类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// 按扩展名识别归档类型
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// 归档内的一条记录，name 为规范化后的内部路径（以 / 分隔）
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<u64>,
+}
+
+/// 归档内单个文件的解压数据流
+pub struct MemberStream {
+    pub size: u64,
+    pub modified: Option<u64>,
+    pub reader: Pin<Box<dyn AsyncRead + Send>>,
+}
+
+/// 相对路径中定位到的归档文件
+pub struct ArchiveLocation {
+    pub abs: PathBuf,
+    pub kind: ArchiveKind,
+    /// 归档文件相对 root 的路径
+    pub archive_rel: String,
+    /// 归档内部路径，空串表示归档根
+    pub inner: String,
+}
+
+/// 在相对路径中查找第一个归档文件组件，例如 `a/b.zip/c/d` -> (`a/b.zip`, `c/d`)
+pub fn locate(path_safety: &PathSafety, rel: &str) -> Option<ArchiveLocation> {
+    let decoded = percent_encoding::percent_decode_str(rel)
+        .decode_utf8_lossy()
+        .to_string();
+    let parts: Vec<&str> = decoded
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+        .collect();
+
+    for i in 1..=parts.len() {
+        let prefix = parts[..i].join("/");
+        let Some(kind) = ArchiveKind::detect(Path::new(&prefix)) else {
+            continue;
+        };
+        let Ok(abs) = path_safety.resolve(&prefix) else {
+            return None;
+        };
+        if abs.is_file() {
+            return Some(ArchiveLocation {
+                abs,
+                kind,
+                archive_rel: prefix,
+                inner: parts[i..].join("/"),
+            });
+        }
+    }
+    None
+}
+
+/// 最多缓存多少个 tar 归档的索引
+const INDEX_CACHE_SIZE: usize = 32;
+
+/// tar 没有目录区，列出条目要扫描（tar.gz 还要解压）整个文件，结果按路径缓存
+struct CachedIndex {
+    /// 归档的修改时间和大小，变化后缓存失效
+    stamp: (Option<SystemTime>, u64),
+    used: Instant,
+    entries: Arc<Vec<ArchiveEntry>>,
+}
+
+fn index_cache() -> &'static parking_lot::Mutex<HashMap<PathBuf, CachedIndex>> {
+    static CACHE: OnceLock<parking_lot::Mutex<HashMap<PathBuf, CachedIndex>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+async fn file_stamp(path: &Path) -> std::io::Result<(Option<SystemTime>, u64)> {
+    let meta = tokio::fs::metadata(path).await?;
+    Ok((meta.modified().ok(), meta.len()))
+}
+
+/// 归档未变化时返回缓存的 tar 索引
+async fn cached_index(path: &Path) -> Option<Arc<Vec<ArchiveEntry>>> {
+    let stamp = file_stamp(path).await.ok()?;
+    let mut cache = index_cache().lock();
+    let cached = cache.get_mut(path).filter(|c| c.stamp == stamp)?;
+    cached.used = Instant::now();
+    Some(cached.entries.clone())
+}
+
+async fn tar_index(path: &Path, kind: ArchiveKind) -> anyhow::Result<Arc<Vec<ArchiveEntry>>> {
+    if let Some(entries) = cached_index(path).await {
+        return Ok(entries);
+    }
+    // 扫描前取时间戳，扫描期间被改写的归档下次会重新读取
+    let stamp = file_stamp(path).await?;
+    let owned = path.to_path_buf();
+    let entries = Arc::new(tokio::task::spawn_blocking(move || tar_reader::entries(&owned, kind)).await??);
+
+    let mut cache = index_cache().lock();
+    if cache.len() >= INDEX_CACHE_SIZE && !cache.contains_key(path) {
+        let oldest = cache.iter().min_by_key(|(_, c)| c.used).map(|(p, _)| p.clone());
+        if let Some(oldest) = oldest {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(
+        path.to_path_buf(),
+        CachedIndex {
+            stamp,
+            used: Instant::now(),
+            entries: entries.clone(),
+        },
+    );
+    Ok(entries)
+}
+
+/// 读取归档的全部条目
+pub async fn read_entries(path: &Path, kind: ArchiveKind) -> anyhow::Result<Vec<ArchiveEntry>> {
+    match kind {
+        ArchiveKind::Zip => zip_reader::entries(path).await,
+        ArchiveKind::Tar | ArchiveKind::TarGz => Ok(tar_index(path, kind).await?.to_vec()),
+    }
+}
+
+/// 打开归档内的单个文件，只解压该成员；不存在或是目录时返回 None
+pub async fn open_member(
+    path: &Path,
+    kind: ArchiveKind,
+    inner: &str,
+) -> anyhow::Result<Option<MemberStream>> {
+    match kind {
+        ArchiveKind::Zip => zip_reader::open_member(path, inner).await,
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            // 有缓存的索引时，不存在的成员不必再扫描一遍
+            if let Some(entries) = cached_index(path).await {
+                if !entries.iter().any(|e| !e.is_dir && e.name == inner) {
+                    return Ok(None);
+                }
+            }
+            tar_reader::open_member(path, kind, inner).await
+        }
+    }
+}
+
+/// 规范化归档内路径：去掉 `./` 与开头的 `/`，拒绝包含 `..` 的条目
+pub fn normalize(name: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => return None,
+            p => parts.push(p),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// 取 inner 目录下的直接子项；没有显式目录条目的中间目录会被补齐
+pub fn children(entries: &[ArchiveEntry], inner: &str) -> Vec<ArchiveEntry> {
+    let prefix = if inner.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", inner)
+    };
+
+    let mut out: BTreeMap<String, ArchiveEntry> = BTreeMap::new();
+    for entry in entries {
+        let Some(rest) = entry.name.strip_prefix(&prefix) else {
+            continue;
+        };
+        if rest.is_empty() {
+            continue;
+        }
+        match rest.split_once('/') {
+            Some((dir, _)) => {
+                out.entry(dir.to_string()).or_insert_with(|| ArchiveEntry {
+                    name: dir.to_string(),
+                    is_dir: true,
+                    size: 0,
+                    modified: None,
+                });
+            }
+            None => {
+                let mut child = entry.clone();
+                child.name = rest.to_string();
+                out.insert(rest.to_string(), child);
+            }
+        }
+    }
+    out.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str) -> ArchiveEntry {
+        ArchiveEntry {
+            name: name.to_string(),
+            is_dir: false,
+            size: 1,
+            modified: None,
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(ArchiveKind::detect(Path::new("a/B.ZIP")), Some(ArchiveKind::Zip));
+        assert_eq!(ArchiveKind::detect(Path::new("x.tar.gz")), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::detect(Path::new("x.tgz")), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::detect(Path::new("x.tar")), Some(ArchiveKind::Tar));
+        assert_eq!(ArchiveKind::detect(Path::new("x.gz")), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("./a/b.txt").as_deref(), Some("a/b.txt"));
+        assert_eq!(normalize("/a//b/").as_deref(), Some("a/b"));
+        assert_eq!(normalize("a/../../etc/passwd"), None);
+        assert_eq!(normalize("./"), None);
+    }
+
+    #[test]
+    fn test_children_synthesizes_dirs() {
+        let entries = vec![file("a/b/c.txt"), file("a/d.txt"), file("e.txt")];
+
+        let root = children(&entries, "");
+        let names: Vec<_> = root.iter().map(|e| (e.name.as_str(), e.is_dir)).collect();
+        assert_eq!(names, vec![("a", true), ("e.txt", false)]);
+
+        let sub = children(&entries, "a");
+        let names: Vec<_> = sub.iter().map(|e| (e.name.as_str(), e.is_dir)).collect();
+        assert_eq!(names, vec![("b", true), ("d.txt", false)]);
+    }
+
+    #[tokio::test]
+    async fn test_tar_index_cached_until_changed() {
+        fn write_tar(path: &Path, names: &[&str]) {
+            let mut builder = tar::Builder::new(std::fs::File::create(path).unwrap());
+            for name in names {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(1);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder.append_data(&mut header, name, &b"x"[..]).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("pack.tar");
+        write_tar(&path, &["a.txt"]);
+        let first = tar_index(&path, ArchiveKind::Tar).await.unwrap();
+        let again = tar_index(&path, ArchiveKind::Tar).await.unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert!(open_member(&path, ArchiveKind::Tar, "missing.txt").await.unwrap().is_none());
+
+        write_tar(&path, &["a.txt", "b.txt"]);
+        let changed = read_entries(&path, ArchiveKind::Tar).await.unwrap();
+        assert_eq!(changed.len(), 2);
+    }
+
+    #[test]
+    fn test_locate() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub/pack.zip"), b"").unwrap();
+        let safety = PathSafety::new(dunce::canonicalize(dir.path()).unwrap());
+
+        let loc = locate(&safety, "sub/pack.zip/inner/dir").unwrap();
+        assert_eq!(loc.archive_rel, "sub/pack.zip");
+        assert_eq!(loc.inner, "inner/dir");
+        assert!(locate(&safety, "sub/other").is_none());
+    }
+}
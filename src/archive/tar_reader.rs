@@ -0,0 +1,122 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use tokio::sync::oneshot;
+use tokio_util::io::SyncIoBridge;
+
+use crate::archive::{normalize, ArchiveEntry, ArchiveKind, MemberStream};
+
+/// 顺序读取 tar 头信息（同步，需放在 blocking 线程执行）
+pub fn entries(path: &Path, kind: ArchiveKind) -> anyhow::Result<Vec<ArchiveEntry>> {
+    let file = File::open(path)?;
+    let mut out = Vec::new();
+
+    if kind == ArchiveKind::TarGz {
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(BufReader::new(file)));
+        collect(archive.entries()?, &mut out)?;
+    } else {
+        // 未压缩 tar 可以 seek 跳过数据区
+        let mut archive = tar::Archive::new(file);
+        collect(archive.entries_with_seek()?, &mut out)?;
+    }
+    Ok(out)
+}
+
+fn collect<R: std::io::Read>(
+    entries: tar::Entries<'_, R>,
+    out: &mut Vec<ArchiveEntry>,
+) -> anyhow::Result<()> {
+    for entry in entries {
+        let entry = entry?;
+        let header = entry.header();
+        let kind = header.entry_type();
+        if !kind.is_file() && !kind.is_dir() {
+            continue;
+        }
+        let raw_name = entry.path()?.to_string_lossy().to_string();
+        let Some(name) = normalize(&raw_name) else {
+            continue;
+        };
+        out.push(ArchiveEntry {
+            name,
+            is_dir: kind.is_dir(),
+            size: header.size().unwrap_or(0),
+            modified: header.mtime().ok(),
+        });
+    }
+    Ok(())
+}
+
+/// tar 没有索引，只能顺序扫描到目标成员；找到后在 blocking 线程里把数据写入管道
+pub async fn open_member(
+    path: &Path,
+    kind: ArchiveKind,
+    inner: &str,
+) -> anyhow::Result<Option<MemberStream>> {
+    let (writer, reader) = tokio::io::duplex(256 * 1024);
+    let (found_tx, found_rx) = oneshot::channel();
+    let (path, inner) = (path.to_path_buf(), inner.to_string());
+    let handle = tokio::runtime::Handle::current();
+
+    tokio::task::spawn_blocking(move || {
+        let mut sink = SyncIoBridge::new_with_handle(writer, handle);
+        if let Err(e) = copy_member(&path, kind, &inner, found_tx, &mut sink) {
+            tracing::warn!(path = %path.display(), inner = %inner, error = %e, "tar member stream failed");
+        }
+    });
+
+    // 发送端被丢弃说明扫描出错，错误已在上面记录
+    let Ok(found) = found_rx.await else {
+        anyhow::bail!("cannot read archive");
+    };
+    Ok(found.map(|(size, modified)| MemberStream {
+        size,
+        modified,
+        reader: Box::pin(reader),
+    }))
+}
+
+type Found = oneshot::Sender<Option<(u64, Option<u64>)>>;
+
+fn copy_member(
+    path: &Path,
+    kind: ArchiveKind,
+    inner: &str,
+    found: Found,
+    sink: &mut impl std::io::Write,
+) -> anyhow::Result<()> {
+    let file = File::open(path)?;
+    if kind == ArchiveKind::TarGz {
+        let archive = tar::Archive::new(flate2::read::GzDecoder::new(BufReader::new(file)));
+        seek_and_copy(archive, inner, found, sink)
+    } else {
+        seek_and_copy(tar::Archive::new(file), inner, found, sink)
+    }
+}
+
+fn seek_and_copy<R: Read>(
+    mut archive: tar::Archive<R>,
+    inner: &str,
+    found: Found,
+    sink: &mut impl std::io::Write,
+) -> anyhow::Result<()> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        if normalize(&entry.path()?.to_string_lossy()).as_deref() != Some(inner) {
+            continue;
+        }
+        let size = entry.header().size().unwrap_or(0);
+        let modified = entry.header().mtime().ok();
+        if found.send(Some((size, modified))).is_err() {
+            return Ok(());
+        }
+        std::io::copy(&mut entry, sink)?;
+        return Ok(());
+    }
+    let _ = found.send(None);
+    Ok(())
+}
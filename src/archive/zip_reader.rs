@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use async_zip::tokio::read::fs::ZipFileReader;
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+use crate::archive::{normalize, ArchiveEntry, MemberStream};
+
+/// 读取 ZIP 中央目录，不解压数据
+pub async fn entries(path: &Path) -> anyhow::Result<Vec<ArchiveEntry>> {
+    let reader = ZipFileReader::new(path).await?;
+
+    let mut out = Vec::with_capacity(reader.file().entries().len());
+    for entry in reader.file().entries() {
+        let raw_name = String::from_utf8_lossy(entry.filename().as_bytes()).to_string();
+        let Some(name) = normalize(&raw_name) else {
+            continue;
+        };
+        let date = entry.last_modification_date();
+        out.push(ArchiveEntry {
+            name,
+            is_dir: entry.dir().unwrap_or(false),
+            size: entry.uncompressed_size(),
+            modified: zip_date_to_unix(
+                date.year(),
+                date.month(),
+                date.day(),
+                date.hour(),
+                date.minute(),
+                date.second(),
+            ),
+        });
+    }
+    Ok(out)
+}
+
+/// 定位成员后从其数据偏移处开始流式解压
+pub async fn open_member(path: &Path, inner: &str) -> anyhow::Result<Option<MemberStream>> {
+    let reader = ZipFileReader::new(path).await?;
+
+    let found = reader.file().entries().iter().position(|entry| {
+        !entry.dir().unwrap_or(false)
+            && normalize(&String::from_utf8_lossy(entry.filename().as_bytes())).as_deref()
+                == Some(inner)
+    });
+    let Some(index) = found else {
+        return Ok(None);
+    };
+
+    let entry = &reader.file().entries()[index];
+    let size = entry.uncompressed_size();
+    let date = entry.last_modification_date();
+    let modified = zip_date_to_unix(
+        date.year(),
+        date.month(),
+        date.day(),
+        date.hour(),
+        date.minute(),
+        date.second(),
+    );
+
+    let member = reader.reader_without_entry(index).await?;
+    Ok(Some(MemberStream {
+        size,
+        modified,
+        reader: Box::pin(member.compat()),
+    }))
+}
+
+/// ZIP 内的 DOS 时间没有时区信息，按 UTC 处理
+fn zip_date_to_unix(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32) -> Option<u64> {
+    let month = time::Month::try_from(mo as u8).ok()?;
+    let date = time::Date::from_calendar_date(y, month, d as u8).ok()?;
+    let dt = date.with_hms(h as u8, mi as u8, s as u8).ok()?;
+    u64::try_from(dt.assume_utc().unix_timestamp()).ok()
+}
@@ -0,0 +1,306 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use bytes::Bytes;
+use clap::Args;
+use futures_util::StreamExt;
+
+/// `transfer-app bench` 的参数
+#[derive(Debug, Clone, Args)]
+pub struct BenchArgs {
+    /// 目标服务地址，例如 http://192.168.1.10:8080
+    #[arg(long)]
+    pub target: String,
+
+    /// 总传输量，支持 K/M/G 后缀
+    #[arg(long, default_value = "256M", value_parser = parse_size)]
+    pub size: u64,
+
+    /// 并发连接数，总量平均分给每个连接
+    #[arg(long, default_value_t = 4)]
+    pub parallel: usize,
+
+    /// 每个 PATCH 请求的大小，支持 K/M/G 后缀
+    #[arg(long, default_value = "8M", value_parser = parse_size)]
+    pub chunk_size: u64,
+
+    /// 测试文件所在目录（相对共享根目录），结束后删除
+    #[arg(long, default_value = "")]
+    pub dir: String,
+}
+
+/// 解析 `1G`、`512M`、`64k`、`1000` 这样的大小（1024 进制）
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (num, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, ""),
+    };
+    let n: u64 = num.parse().map_err(|_| format!("invalid size: {}", s))?;
+    let mul: u64 = match unit.trim().to_ascii_uppercase().trim_end_matches(['B', 'I']) {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("invalid size unit: {}", s)),
+    };
+    n.checked_mul(mul).ok_or_else(|| format!("size too large: {}", s))
+}
+
+/// 一个连接的测试结果
+struct WorkerReport {
+    path: String,
+    bytes: u64,
+    upload: Duration,
+    download: Duration,
+    /// 每个 PATCH 请求的耗时
+    chunk_latencies: Vec<Duration>,
+    /// 下载首字节时间
+    ttfb: Duration,
+}
+
+/// 先并发上传再并发下载，输出吞吐和延迟，最后删除测试文件
+pub async fn run(args: BenchArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(args.parallel > 0, "--parallel must be at least 1");
+    anyhow::ensure!(args.chunk_size > 0, "--chunk-size must be positive");
+    let base = args.target.trim_end_matches('/').to_string();
+    let client = reqwest::Client::builder()
+        // 测的是原始吞吐，不让服务端压缩下载内容
+        .no_zstd()
+        .build()
+        .context("build http client")?;
+
+    let per_worker = (args.size / args.parallel as u64).max(1);
+    let chunk = Bytes::from(pseudo_random(args.chunk_size.min(per_worker) as usize));
+
+    println!(
+        "bench {}: {} x {} bytes, chunk {} bytes",
+        base, args.parallel, per_worker, chunk.len()
+    );
+
+    let started = Instant::now();
+    let mut uploads = Vec::with_capacity(args.parallel);
+    for i in 0..args.parallel {
+        let (client, base, chunk, dir) = (client.clone(), base.clone(), chunk.clone(), args.dir.clone());
+        uploads.push(tokio::spawn(async move {
+            upload_one(&client, &base, &dir, i, per_worker, chunk).await
+        }));
+    }
+    let mut reports = Vec::with_capacity(args.parallel);
+    for handle in uploads {
+        reports.push(handle.await??);
+    }
+    let upload_wall = started.elapsed();
+
+    let started = Instant::now();
+    let mut downloads = Vec::with_capacity(reports.len());
+    for report in reports {
+        let (client, base) = (client.clone(), base.clone());
+        downloads.push(tokio::spawn(async move { download_one(&client, &base, report).await }));
+    }
+    let mut reports = Vec::with_capacity(downloads.len());
+    for handle in downloads {
+        reports.push(handle.await??);
+    }
+    let download_wall = started.elapsed();
+
+    // 清理测试文件，失败只提示
+    let paths: Vec<&str> = reports.iter().map(|r| r.path.as_str()).collect();
+    if let Err(e) = client
+        .post(format!("{}/api/files/delete", base))
+        .json(&serde_json::json!({ "paths": paths }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+    {
+        eprintln!("warning: failed to delete bench files: {}", e);
+    }
+
+    let total: u64 = reports.iter().map(|r| r.bytes).sum();
+    let mut chunks: Vec<Duration> = reports
+        .iter()
+        .flat_map(|r| r.chunk_latencies.iter().copied())
+        .collect();
+    chunks.sort();
+    let mut ttfb: Vec<Duration> = reports.iter().map(|r| r.ttfb).collect();
+    ttfb.sort();
+
+    println!();
+    println!("  upload    {:>10}  {}", throughput(total, upload_wall), fmt_dur(upload_wall));
+    for r in &reports {
+        println!("    {:<24} {:>10}", r.path, throughput(r.bytes, r.upload));
+    }
+    println!(
+        "    PATCH latency  p50 {}  p95 {}  max {}",
+        fmt_dur(percentile(&chunks, 50)),
+        fmt_dur(percentile(&chunks, 95)),
+        fmt_dur(chunks.last().copied().unwrap_or_default())
+    );
+    println!("  download  {:>10}  {}", throughput(total, download_wall), fmt_dur(download_wall));
+    for r in &reports {
+        println!("    {:<24} {:>10}", r.path, throughput(r.bytes, r.download));
+    }
+    println!(
+        "    TTFB  p50 {}  max {}",
+        fmt_dur(percentile(&ttfb, 50)),
+        fmt_dur(ttfb.last().copied().unwrap_or_default())
+    );
+    Ok(())
+}
+
+async fn upload_one(
+    client: &reqwest::Client,
+    base: &str,
+    dir: &str,
+    index: usize,
+    size: u64,
+    chunk: Bytes,
+) -> anyhow::Result<WorkerReport> {
+    use base64::Engine;
+    let b64 = |s: &str| base64::engine::general_purpose::STANDARD.encode(s);
+
+    let name = format!("bench-{}-{}.bin", std::process::id(), index);
+    let resp = client
+        .post(format!("{}/api/upload", base))
+        .header("Tus-Resumable", "1.0.0")
+        .header("Upload-Length", size.to_string())
+        .header(
+            "Upload-Metadata",
+            format!("filename {},targetDir {}", b64(&name), b64(dir)),
+        )
+        .send()
+        .await?
+        .error_for_status()?;
+    let location = resp
+        .headers()
+        .get("location")
+        .and_then(|v| v.to_str().ok())
+        .context("missing Location header")?
+        .to_string();
+
+    let started = Instant::now();
+    let mut offset = 0u64;
+    let mut latencies = Vec::new();
+    let mut final_path = None;
+    while offset < size {
+        let len = (size - offset).min(chunk.len() as u64) as usize;
+        let t = Instant::now();
+        let resp = client
+            .patch(format!("{}{}", base, location))
+            .header("Tus-Resumable", "1.0.0")
+            .header("Upload-Offset", offset.to_string())
+            .header("Content-Type", "application/offset+octet-stream")
+            .body(chunk.slice(..len))
+            .send()
+            .await?
+            .error_for_status()?;
+        latencies.push(t.elapsed());
+        offset += len as u64;
+        if let Some(path) = resp.headers().get("x-final-path").and_then(|v| v.to_str().ok()) {
+            final_path = Some(
+                percent_encoding::percent_decode_str(path)
+                    .decode_utf8_lossy()
+                    .to_string(),
+            );
+        }
+    }
+
+    Ok(WorkerReport {
+        path: final_path.context("server did not report the final path")?,
+        bytes: size,
+        upload: started.elapsed(),
+        download: Duration::ZERO,
+        chunk_latencies: latencies,
+        ttfb: Duration::ZERO,
+    })
+}
+
+async fn download_one(
+    client: &reqwest::Client,
+    base: &str,
+    mut report: WorkerReport,
+) -> anyhow::Result<WorkerReport> {
+    let encoded: String = report
+        .path
+        .split('/')
+        .map(|seg| {
+            percent_encoding::utf8_percent_encode(seg, percent_encoding::NON_ALPHANUMERIC)
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let started = Instant::now();
+    let resp = client
+        .get(format!("{}/api/download/{}", base, encoded))
+        .send()
+        .await?
+        .error_for_status()?;
+    report.ttfb = started.elapsed();
+
+    let mut received = 0u64;
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        received += chunk?.len() as u64;
+    }
+    report.download = started.elapsed();
+    anyhow::ensure!(
+        received == report.bytes,
+        "{}: downloaded {} of {} bytes",
+        report.path,
+        received,
+        report.bytes
+    );
+    Ok(report)
+}
+
+/// 不可压缩的测试数据，避免压缩或去重影响结果
+fn pseudo_random(len: usize) -> Vec<u8> {
+    let mut x: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut buf = Vec::with_capacity(len + 8);
+    while buf.len() < len {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        buf.extend_from_slice(&x.to_le_bytes());
+    }
+    buf.truncate(len);
+    buf
+}
+
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    sorted[(sorted.len() - 1) * p / 100]
+}
+
+fn throughput(bytes: u64, elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64().max(1e-9);
+    format!("{:.1} MiB/s", bytes as f64 / secs / (1024.0 * 1024.0))
+}
+
+fn fmt_dur(d: Duration) -> String {
+    if d.as_secs() >= 1 {
+        format!("{:.2}s", d.as_secs_f64())
+    } else {
+        format!("{:.1}ms", d.as_secs_f64() * 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1000"), Ok(1000));
+        assert_eq!(parse_size("64k"), Ok(64 * 1024));
+        assert_eq!(parse_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_size("1G"), Ok(1 << 30));
+        assert_eq!(parse_size("2GiB"), Ok(2 << 30));
+        assert!(parse_size("1X").is_err());
+        assert!(parse_size("G").is_err());
+    }
+}
@@ -0,0 +1,44 @@
+//! 留言板：留言保存在元数据库中，新留言和删除通过 SSE 推送给打开页面的人
+
+use tokio::sync::broadcast;
+
+use crate::db::messages::Message;
+
+/// 留言正文上限（字符）
+pub const MAX_TEXT_CHARS: usize = 2000;
+/// 署名上限（字符）
+pub const MAX_AUTHOR_CHARS: usize = 40;
+
+#[derive(Debug, Clone)]
+pub enum BoardEvent {
+    Posted(Message),
+    Deleted(i64),
+}
+
+/// 留言变化的广播
+pub struct MessageBoard {
+    events: broadcast::Sender<BoardEvent>,
+}
+
+impl Default for MessageBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessageBoard {
+    pub fn new() -> Self {
+        Self {
+            events: broadcast::Sender::new(64),
+        }
+    }
+
+    pub fn publish(&self, event: BoardEvent) {
+        // 没有订阅者时发送失败，忽略
+        let _ = self.events.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<BoardEvent> {
+        self.events.subscribe()
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::AppConfig;
+
+/// 页面默认名称，自定义标题时替换
+const DEFAULT_TITLE: &str = "FileTransfer";
+
+/// 自定义页面外观：标题、Logo、主题色和底部说明，在返回 HTML 时注入
+#[derive(Debug, Clone, Default)]
+pub struct Branding {
+    title: Option<String>,
+    logo: Option<PathBuf>,
+    accent: Option<[u8; 3]>,
+    footer: Option<String>,
+}
+
+impl Branding {
+    pub fn from_config(config: &AppConfig) -> anyhow::Result<Self> {
+        let accent = config
+            .brand_accent
+            .as_deref()
+            .map(|c| {
+                parse_hex_color(c)
+                    .ok_or_else(|| anyhow::anyhow!("invalid --brand-accent '{}': expected #RRGGBB", c))
+            })
+            .transpose()?;
+        if let Some(logo) = &config.brand_logo {
+            if !logo.is_file() {
+                anyhow::bail!("--brand-logo '{}' is not a file", logo.display());
+            }
+        }
+        let non_empty = |s: &Option<String>| s.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(String::from);
+        Ok(Self {
+            title: non_empty(&config.brand_title),
+            logo: config.brand_logo.clone(),
+            accent,
+            footer: non_empty(&config.brand_footer),
+        })
+    }
+
+    pub fn logo(&self) -> Option<&Path> {
+        self.logo.as_deref()
+    }
+
+    /// 把自定义内容写入页面；未配置时原样返回
+    pub fn apply(&self, html: &str) -> String {
+        let mut html = html.to_string();
+        if let Some(title) = &self.title {
+            let title = escape_html(title);
+            html = html
+                .replace(&format!("{}</title>", DEFAULT_TITLE), &format!("{}</title>", title))
+                .replace(
+                    &format!("class=\"topbar-title\">{}<", DEFAULT_TITLE),
+                    &format!("class=\"topbar-title\">{}<", title),
+                );
+        }
+        if self.logo.is_some() {
+            html = html.replace(
+                "<div class=\"topbar-left\">",
+                "<div class=\"topbar-left\">\n                <img class=\"topbar-logo\" src=\"/branding/logo\" alt=\"\">",
+            );
+        }
+        if let Some([r, g, b]) = self.accent {
+            // 深色主题也用同一主题色，!important 覆盖各主题下的默认值
+            let darker = |c: u8| (c as u16 * 85 / 100) as u8;
+            let style = format!(
+                "<style>:root {{ --accent: #{:02x}{:02x}{:02x} !important; --accent-hover: #{:02x}{:02x}{:02x} !important; --accent-light: rgba({}, {}, {}, 0.12) !important; }}</style>\n</head>",
+                r, g, b, darker(r), darker(g), darker(b), r, g, b
+            );
+            html = html.replacen("</head>", &style, 1);
+        }
+        if let Some(footer) = &self.footer {
+            html = html.replacen(
+                "<!-- 品牌说明 -->",
+                &format!("<footer class=\"brand-footer\">{}</footer>", escape_html(footer)),
+                1,
+            );
+        }
+        html
+    }
+}
+
+fn parse_hex_color(s: &str) -> Option<[u8; 3]> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 {
+        return None;
+    }
+    let bytes = hex::decode(hex).ok()?;
+    Some([bytes[0], bytes[1], bytes[2]])
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_branding() {
+        let branding = Branding {
+            title: Some("Acme <Drop>".into()),
+            logo: None,
+            accent: parse_hex_color("#FF8000"),
+            footer: Some("仅限内部使用".into()),
+        };
+        let html = "<head><title>FileTransfer</title></head><h1 class=\"topbar-title\">FileTransfer</h1><!-- 品牌说明 -->";
+        let out = branding.apply(html);
+        assert!(out.contains("<title>Acme &lt;Drop&gt;</title>"));
+        assert!(out.contains("class=\"topbar-title\">Acme &lt;Drop&gt;</h1>"));
+        assert!(out.contains("--accent: #ff8000 !important; --accent-hover: #d86c00"));
+        assert!(out.contains("<footer class=\"brand-footer\">仅限内部使用</footer>"));
+        assert!(!out.contains("topbar-logo"));
+
+        assert_eq!(Branding::default().apply(html), html);
+        assert!(parse_hex_color("red").is_none());
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::header::USER_AGENT;
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use bytes::Bytes;
+use hyper::body::{Body as HttpBody, Frame, SizeHint};
+use serde::Serialize;
+
+use crate::db::now_secs;
+use crate::middleware::auth;
+use crate::state::AppState;
+
+/// 最后一次请求超过该时长 (秒) 且没有进行中请求的客户端不再显示
+const RECENT_SECS: u64 = 24 * 3600;
+
+/// 最多记住的客户端数，超出时丢弃最久没有请求的
+const MAX_CLIENTS: usize = 256;
+
+/// 按来源 IP 记录当前连接的客户端：进行中的请求、最近一次请求和累计流量，只保存在内存中
+#[derive(Default)]
+pub struct ClientTracker {
+    clients: Arc<parking_lot::Mutex<HashMap<IpAddr, Client>>>,
+    next_id: AtomicU64,
+}
+
+struct Client {
+    user: Option<String>,
+    user_agent: Option<String>,
+    first_seen: u64,
+    last_seen: u64,
+    requests: u64,
+    /// 已结束请求的流量；进行中的请求在快照时另外累加
+    bytes_sent: u64,
+    bytes_received: u64,
+    active: HashMap<u64, Arc<ActiveRequest>>,
+}
+
+struct ActiveRequest {
+    method: Method,
+    path: String,
+    kind: Option<TransferKind>,
+    started: u64,
+    sent: AtomicU64,
+    received: AtomicU64,
+}
+
+/// 进行中的请求属于哪种传输；列目录等普通请求为 None
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferKind {
+    Upload,
+    Download,
+}
+
+impl TransferKind {
+    fn classify(method: &Method, path: &str) -> Option<Self> {
+        let api = path.strip_prefix("/api/")?;
+        let upload = ["upload", "put/", "localsend/v2/upload", "delta/"];
+        let download = ["download", "hls", "transcode/"];
+        if *method != Method::GET && *method != Method::HEAD && upload.iter().any(|p| api.starts_with(p)) {
+            Some(Self::Upload)
+        // archive-progress 是进度推送，不算传输
+        } else if download.iter().any(|p| api.starts_with(p)) || api == "archive" {
+            Some(Self::Download)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClientInfo {
+    pub ip: IpAddr,
+    /// 最近一次请求通过认证的用户名
+    pub user: Option<String>,
+    pub user_agent: Option<String>,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub requests: u64,
+    /// 发给客户端的字节数（压缩后）
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// 进行中的请求数，包括实时跟随、事件推送等长连接
+    pub active_requests: usize,
+    pub transfers: Vec<TransferInfo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransferInfo {
+    pub kind: TransferKind,
+    pub method: String,
+    pub path: String,
+    pub started: u64,
+    /// 已传输的字节数
+    pub bytes: u64,
+}
+
+impl ClientTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 所有最近的客户端，有进行中请求的排在前面，其余按最后一次请求时间倒序
+    pub fn snapshot(&self) -> Vec<ClientInfo> {
+        let clients = self.clients.lock();
+        let mut list: Vec<ClientInfo> = clients
+            .iter()
+            .map(|(ip, c)| {
+                let active = c.active.values();
+                let sent: u64 = active.clone().map(|a| a.sent.load(Ordering::Relaxed)).sum();
+                let received: u64 = active.clone().map(|a| a.received.load(Ordering::Relaxed)).sum();
+                let mut transfers: Vec<TransferInfo> = active
+                    .filter_map(|a| {
+                        Some(TransferInfo {
+                            kind: a.kind?,
+                            method: a.method.to_string(),
+                            path: a.path.clone(),
+                            started: a.started,
+                            bytes: match a.kind? {
+                                TransferKind::Upload => a.received.load(Ordering::Relaxed),
+                                TransferKind::Download => a.sent.load(Ordering::Relaxed),
+                            },
+                        })
+                    })
+                    .collect();
+                transfers.sort_by_key(|t| t.started);
+                ClientInfo {
+                    ip: *ip,
+                    user: c.user.clone(),
+                    user_agent: c.user_agent.clone(),
+                    first_seen: c.first_seen,
+                    last_seen: c.last_seen,
+                    requests: c.requests,
+                    bytes_sent: c.bytes_sent + sent,
+                    bytes_received: c.bytes_received + received,
+                    active_requests: c.active.len(),
+                    transfers,
+                }
+            })
+            .collect();
+        list.sort_by(|a, b| {
+            (b.active_requests > 0)
+                .cmp(&(a.active_requests > 0))
+                .then(b.last_seen.cmp(&a.last_seen))
+        });
+        list
+    }
+
+    /// 进行中的上传和下载请求数
+    pub fn active_transfers(&self) -> usize {
+        self.clients
+            .lock()
+            .values()
+            .flat_map(|c| c.active.values())
+            .filter(|a| a.kind.is_some())
+            .count()
+    }
+
+    /// 登记一个新请求，返回的 guard 随请求体和响应体一起释放
+    fn begin(&self, ip: IpAddr, req: &Request) -> Arc<Guard> {
+        let now = now_secs();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let path = req.uri().path().to_string();
+        let active = Arc::new(ActiveRequest {
+            kind: TransferKind::classify(req.method(), &path),
+            method: req.method().clone(),
+            path,
+            started: now,
+            sent: AtomicU64::new(0),
+            received: AtomicU64::new(0),
+        });
+
+        let mut clients = self.clients.lock();
+        if !clients.contains_key(&ip) {
+            prune(&mut clients, now);
+        }
+        let client = clients.entry(ip).or_insert_with(|| Client {
+            user: None,
+            user_agent: None,
+            first_seen: now,
+            last_seen: now,
+            requests: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+            active: HashMap::new(),
+        });
+        client.last_seen = now;
+        client.requests += 1;
+        client.user = auth::current_user().or(client.user.take());
+        if let Some(ua) = req.headers().get(USER_AGENT).and_then(|v| v.to_str().ok()) {
+            client.user_agent = Some(ua.to_string());
+        }
+        client.active.insert(id, active.clone());
+
+        Arc::new(Guard {
+            clients: self.clients.clone(),
+            ip,
+            id,
+            active,
+        })
+    }
+}
+
+/// 删除过期的客户端，数量仍超过上限时丢弃最久没有请求的空闲客户端
+fn prune(clients: &mut HashMap<IpAddr, Client>, now: u64) {
+    clients.retain(|_, c| !c.active.is_empty() || now.saturating_sub(c.last_seen) < RECENT_SECS);
+    while clients.len() >= MAX_CLIENTS {
+        let Some(oldest) = clients
+            .iter()
+            .filter(|(_, c)| c.active.is_empty())
+            .min_by_key(|(_, c)| c.last_seen)
+            .map(|(ip, _)| *ip)
+        else {
+            break;
+        };
+        clients.remove(&oldest);
+    }
+}
+
+/// 请求体和响应体都释放后，把这次请求的流量并入客户端的累计值
+struct Guard {
+    clients: Arc<parking_lot::Mutex<HashMap<IpAddr, Client>>>,
+    ip: IpAddr,
+    id: u64,
+    active: Arc<ActiveRequest>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let mut clients = self.clients.lock();
+        if let Some(client) = clients.get_mut(&self.ip) {
+            client.active.remove(&self.id);
+            client.bytes_sent += self.active.sent.load(Ordering::Relaxed);
+            client.bytes_received += self.active.received.load(Ordering::Relaxed);
+            client.last_seen = now_secs();
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// 统计经过的字节数，不改变长度提示，Content-Length 和分块编码照旧
+    struct Counted {
+        #[pin]
+        inner: Body,
+        guard: Arc<Guard>,
+        outgoing: bool,
+    }
+}
+
+impl HttpBody for Counted {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        let this = self.project();
+        let poll = this.inner.poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &poll {
+            if let Some(data) = frame.data_ref() {
+                let counter = if *this.outgoing {
+                    &this.guard.active.sent
+                } else {
+                    &this.guard.active.received
+                };
+                counter.fetch_add(data.len() as u64, Ordering::Relaxed);
+            }
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// 记录每个请求的来源和流量，挂在认证之后、压缩之前，统计的是实际发送的字节
+pub async fn track(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let guard = state.clients.begin(peer.ip(), &req);
+    let req = req.map(|inner| {
+        Body::new(Counted {
+            inner,
+            guard: guard.clone(),
+            outgoing: false,
+        })
+    });
+    next.run(req).await.map(|inner| {
+        Body::new(Counted {
+            inner,
+            guard,
+            outgoing: true,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let kind = |m: Method, p: &str| TransferKind::classify(&m, p);
+        assert_eq!(kind(Method::PATCH, "/api/upload/abc"), Some(TransferKind::Upload));
+        assert_eq!(kind(Method::POST, "/api/upload/form"), Some(TransferKind::Upload));
+        assert_eq!(kind(Method::PUT, "/api/put/logs/out.txt"), Some(TransferKind::Upload));
+        assert_eq!(kind(Method::HEAD, "/api/upload/abc"), None);
+        assert_eq!(kind(Method::GET, "/api/download/a.iso"), Some(TransferKind::Download));
+        assert_eq!(kind(Method::POST, "/api/download-zip"), Some(TransferKind::Download));
+        assert_eq!(kind(Method::POST, "/api/archive"), Some(TransferKind::Download));
+        assert_eq!(kind(Method::GET, "/api/archive-progress/abc"), None);
+        assert_eq!(kind(Method::GET, "/api/files"), None);
+        assert_eq!(kind(Method::GET, "/assets/js/main.js"), None);
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// 保留的条目上限，超出时丢弃最旧的
+const MAX_ITEMS: usize = 50;
+/// 单条文本上限
+pub const MAX_TEXT_BYTES: usize = 32 * 1024;
+const MAX_NAME_CHARS: usize = 40;
+
+/// 剪贴板条目
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipItem {
+    pub id: String,
+    pub text: String,
+    /// 来源设备名
+    pub from: String,
+    pub created_at: u64,
+    /// 到期后服务端不再保留
+    pub expires_at: u64,
+}
+
+/// 服务端推送给浏览器的消息
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ClipboardEvent {
+    /// 连接建立后发送一次，新的在前
+    History { items: Vec<ClipItem> },
+    Added { item: ClipItem },
+    Removed { id: String },
+    Cleared,
+    Error { message: String },
+}
+
+/// 浏览器发给服务端的消息
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ClipboardCommand {
+    Copy { text: String },
+    Remove { id: String },
+    Clear,
+}
+
+/// 跨设备剪贴板：条目只保存在内存中，超过保留时长后丢弃
+pub struct ClipboardHub {
+    items: parking_lot::Mutex<VecDeque<ClipItem>>,
+    ttl: Duration,
+    events: broadcast::Sender<ClipboardEvent>,
+}
+
+impl ClipboardHub {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            items: parking_lot::Mutex::new(VecDeque::new()),
+            ttl,
+            events: broadcast::Sender::new(64),
+        }
+    }
+
+    /// 未过期的条目，新的在前
+    pub fn history(&self) -> Vec<ClipItem> {
+        let mut items = self.items.lock();
+        prune(&mut items, now_secs());
+        items.iter().rev().cloned().collect()
+    }
+
+    pub fn add(&self, text: String, from: &str) -> Result<ClipItem, &'static str> {
+        if text.trim().is_empty() {
+            return Err("empty text");
+        }
+        if text.len() > MAX_TEXT_BYTES {
+            return Err("text too long");
+        }
+        let now = now_secs();
+        let item = ClipItem {
+            id: uuid::Uuid::new_v4().simple().to_string()[..12].to_string(),
+            text,
+            from: display_name(from),
+            created_at: now,
+            expires_at: now + self.ttl.as_secs(),
+        };
+        {
+            let mut items = self.items.lock();
+            prune(&mut items, now);
+            if items.len() >= MAX_ITEMS {
+                items.pop_front();
+            }
+            items.push_back(item.clone());
+        }
+        let _ = self.events.send(ClipboardEvent::Added { item: item.clone() });
+        Ok(item)
+    }
+
+    pub fn remove(&self, id: &str) -> bool {
+        let mut items = self.items.lock();
+        let before = items.len();
+        items.retain(|i| i.id != id);
+        let removed = items.len() != before;
+        drop(items);
+        if removed {
+            let _ = self.events.send(ClipboardEvent::Removed { id: id.to_string() });
+        }
+        removed
+    }
+
+    pub fn clear(&self) {
+        self.items.lock().clear();
+        let _ = self.events.send(ClipboardEvent::Cleared);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ClipboardEvent> {
+        self.events.subscribe()
+    }
+}
+
+fn prune(items: &mut VecDeque<ClipItem>, now: u64) {
+    items.retain(|i| i.expires_at > now);
+}
+
+fn display_name(name: &str) -> String {
+    let name: String = name.trim().chars().take(MAX_NAME_CHARS).collect();
+    if name.is_empty() {
+        "未命名设备".to_string()
+    } else {
+        name
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_limit() {
+        let hub = ClipboardHub::new(Duration::from_secs(60));
+        let mut rx = hub.subscribe();
+        assert!(hub.add("  ".into(), "a").is_err());
+        assert!(hub.add("x".repeat(MAX_TEXT_BYTES + 1), "a").is_err());
+
+        let first = hub.add("hello".into(), "").unwrap();
+        assert_eq!(first.from, "未命名设备");
+        assert!(matches!(rx.try_recv(), Ok(ClipboardEvent::Added { .. })));
+        for i in 0..MAX_ITEMS {
+            hub.add(format!("text {}", i), "phone").unwrap();
+        }
+        let history = hub.history();
+        assert_eq!(history.len(), MAX_ITEMS);
+        assert_eq!(history[0].text, format!("text {}", MAX_ITEMS - 1));
+        assert!(history.iter().all(|i| i.id != first.id));
+
+        assert!(hub.remove(&history[0].id));
+        assert!(!hub.remove(&history[0].id));
+        hub.clear();
+        assert!(hub.history().is_empty());
+    }
+
+    #[test]
+    fn test_expired_items_dropped() {
+        let hub = ClipboardHub::new(Duration::ZERO);
+        hub.add("gone".into(), "a").unwrap();
+        assert!(hub.history().is_empty());
+    }
+}
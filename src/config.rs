@@ -1,44 +1,296 @@
+use std::collections::BTreeMap;
 use std::net::IpAddr;
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use serde::Deserialize;
 
+use crate::bench::{parse_size, BenchArgs};
+use crate::fs::filename::Normalization;
+use crate::fs::operations::ConflictPolicy;
+use crate::fs::permissions::PermissionRule;
+use crate::hooks::{HookCommand, HookEvent};
+use crate::middleware::access_log::AccessLogFormat;
+use crate::sync::SyncArgs;
+use crate::upload::subdir::UploadSubdir;
+use crate::util::logfile::LogRotation;
+
 #[derive(Debug, Clone, Parser, Deserialize)]
 #[command(name = "transfer-app", version, about = "High-performance LAN file transfer server")]
 pub struct AppConfig {
-    /// 共享根目录
-    #[arg(short = 'p', long, env = "TRANSFER_PATH")]
+    /// 子命令；不指定时启动服务
+    #[command(subcommand)]
+    #[serde(skip)]
+    pub command: Option<Command>,
+
+    /// 共享根目录（启动服务时必填，也可以写在配置文件中）
+    #[arg(
+        short = 'p',
+        long,
+        env = "TRANSFER_PATH",
+        default_value = "",
+        hide_default_value = true,
+        value_parser = parse_path
+    )]
+    #[serde(default)]
     pub path: PathBuf,
 
     /// 监听地址
     #[arg(short = 'b', long, default_value = "0.0.0.0", env = "TRANSFER_BIND")]
     pub bind: IpAddr,
 
+    /// 以 root 启动（如绑定 80 / 443 端口）时，绑定后切换到该用户运行，用户名或 uid；仅 Unix
+    #[arg(long, env = "TRANSFER_USER")]
+    pub user: Option<String>,
+
+    /// 配合 --user 切换到的组，组名或 gid；默认为该用户的主组
+    #[arg(long, env = "TRANSFER_GROUP", requires = "user")]
+    pub group: Option<String>,
+
+    /// 用 Landlock 把进程的文件访问限制在共享目录、临时目录等必要路径内；仅 Linux 5.13+
+    #[arg(long, env = "TRANSFER_SANDBOX")]
+    #[serde(default)]
+    pub sandbox: bool,
+
+    /// 只在指定网卡上监听（如 eth0），使用该网卡的第一个地址，IPv4 优先
+    #[arg(long, env = "TRANSFER_INTERFACE", conflicts_with = "bind")]
+    pub interface: Option<String>,
+
     /// 端口
     #[arg(short = 'P', long, default_value_t = 8080, env = "TRANSFER_PORT")]
     pub port: u16,
 
+    /// 端口被占用时依次尝试后面 10 个端口，仍不可用则由系统分配
+    #[arg(long, env = "TRANSFER_PORT_FALLBACK")]
+    #[serde(default)]
+    pub port_fallback: bool,
+
+    /// 通过 UPnP / NAT-PMP 请求路由器映射端口，并打印公网访问地址
+    #[arg(long, env = "TRANSFER_PUBLIC")]
+    #[serde(default)]
+    pub public: bool,
+
+    /// 通过中继服务器对外提供访问（bore 协议，host 或 host:port），无需路由器配置
+    #[arg(long, env = "TRANSFER_RELAY")]
+    pub relay: Option<String>,
+
+    /// 中继服务器的共享密钥
+    #[arg(long, env = "TRANSFER_RELAY_SECRET")]
+    pub relay_secret: Option<String>,
+
+    /// 希望在中继服务器上使用的端口，0 表示由中继分配
+    #[arg(long, default_value_t = 0, env = "TRANSFER_RELAY_PORT")]
+    #[serde(default)]
+    pub relay_port: u16,
+
+    /// 启动后用默认浏览器打开本机地址
+    #[arg(long, env = "TRANSFER_OPEN")]
+    #[serde(default)]
+    pub open: bool,
+
+    /// 启用 HTTPS；证书和私钥不存在时生成自签名证书并保存，之后启动沿用。
+    /// 不指定 --tls-cert / --tls-key 时保存在临时目录的 tls/ 下
+    #[arg(long, env = "TRANSFER_TLS")]
+    #[serde(default)]
+    pub tls: bool,
+
     /// TLS 证书 (PEM)
-    #[arg(long, env = "TRANSFER_TLS_CERT")]
+    #[arg(long, env = "TRANSFER_TLS_CERT", requires = "tls_key")]
     pub tls_cert: Option<PathBuf>,
 
     /// TLS 私钥 (PEM)
-    #[arg(long, env = "TRANSFER_TLS_KEY")]
+    #[arg(long, env = "TRANSFER_TLS_KEY", requires = "tls_cert")]
     pub tls_key: Option<PathBuf>,
 
-    /// 单文件最大上传 (字节, 0 = 无限制)
+    /// 本次启动生成了自签名证书
+    #[arg(skip)]
+    #[serde(skip)]
+    pub tls_generated: bool,
+
+    /// 单文件最大上传 (字节, 0 = 无限制)；未指定时可以写在配置文件中
     #[arg(long, default_value_t = 0, env = "TRANSFER_MAX_UPLOAD")]
     pub max_upload_size: u64,
 
+    /// --max-upload-size 来自命令行或环境变量，重新加载配置文件时不覆盖
+    #[arg(skip)]
+    #[serde(skip)]
+    pub max_upload_size_pinned: bool,
+
     /// 全局并发传输上限
     #[arg(long, default_value_t = 32)]
     pub max_concurrent_transfers: usize,
 
+    /// 投递箱模式：访客只能上传，不能列出、下载或删除已有文件
+    #[arg(long, env = "TRANSFER_DROP_BOX")]
+    #[serde(default)]
+    pub drop_box: bool,
+
+    /// 只读模式：只能浏览和下载，所有上传、新建、重命名、移动、删除都返回 403
+    #[arg(long, env = "TRANSFER_READ_ONLY", conflicts_with = "drop_box")]
+    #[serde(default)]
+    pub read_only: bool,
+
+    /// 上传文件自动放入子目录：none | date (YYYY/MM/DD, UTC) | client (用户名或 IP)
+    #[arg(long, value_enum, default_value = "none", env = "TRANSFER_UPLOAD_SUBDIR")]
+    #[serde(default)]
+    pub upload_subdir: UploadSubdir,
+
+    /// 上传文件与已有文件同名时：rename (追加序号) | overwrite | reject
+    #[arg(long, value_enum, default_value = "rename", env = "TRANSFER_ON_CONFLICT")]
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+
+    /// 上传文件名的 Unicode 规范化：nfc | none
+    #[arg(long, value_enum, default_value = "nfc", env = "TRANSFER_FILENAME_NORMALIZATION")]
+    #[serde(default)]
+    pub filename_normalization: Normalization,
+
+    /// 上传文件名按 Windows 规则处理：保留名 (CON、NUL 等) 前加 _，去掉结尾的点和空格
+    #[arg(long, env = "TRANSFER_WINDOWS_SAFE_NAMES")]
+    #[serde(default)]
+    pub windows_safe_names: bool,
+
+    /// 只有大小写不同的文件名视为同名，按 --on-conflict 处理；共享目录不区分大小写时自动开启
+    #[arg(long, env = "TRANSFER_CASE_INSENSITIVE_NAMES")]
+    #[serde(default)]
+    pub case_insensitive_names: bool,
+
+    /// CPU 密集任务（校验和、图片缩放、归档解压）的并发上限 (0 = CPU 核数)
+    #[arg(long, default_value_t = 0, env = "TRANSFER_CPU_WORKERS")]
+    #[serde(default)]
+    pub cpu_workers: usize,
+
+    /// 管理类接口（列目录、搜索、重命名等）的超时 (秒)；上传、下载和流式接口不受限制
+    #[arg(long, default_value_t = 30, env = "TRANSFER_API_TIMEOUT")]
+    #[serde(default)]
+    pub api_timeout_secs: u64,
+
+    /// 每个客户端 (认证用户或来源 IP) 每分钟最多的 /api 请求数，超出返回 429 (0 = 不限制)
+    #[arg(long, default_value_t = 0, env = "TRANSFER_RATE_LIMIT")]
+    #[serde(default)]
+    pub rate_limit: u32,
+
+    /// 每个客户端每分钟最多的修改类请求 (POST / PUT / PATCH / DELETE) 数 (0 = 不限制)
+    #[arg(long, default_value_t = 0, env = "TRANSFER_RATE_LIMIT_WRITES")]
+    #[serde(default)]
+    pub rate_limit_writes: u32,
+
+    /// 整个网页和接口都需要 HTTP Basic 认证，用户名任意、密码为该值；配置文件 `[users]` 中的用户同样可以登录。
+    /// 命令行参数对本机其他用户可见，建议用环境变量传入
+    #[arg(long, env = "TRANSFER_PASSWORD", hide_env_values = true)]
+    pub password: Option<String>,
+
+    /// 整个网页和接口都需要以配置文件 `[users]` 中的用户登录
+    #[arg(long, env = "TRANSFER_REQUIRE_AUTH")]
+    #[serde(default)]
+    pub require_auth: bool,
+
+    /// 维护接口 (暂停上传、远程关机) 的令牌，放在 Authorization: Bearer 中；不设置时这些接口不可用
+    #[arg(long, env = "TRANSFER_ADMIN_TOKEN")]
+    pub admin_token: Option<String>,
+
+    /// 上传分片与会话元数据目录 (默认 <path>/.transfer-tmp，建议与共享目录在同一文件系统)
+    #[arg(long, env = "TRANSFER_TMP_DIR")]
+    pub tmp_dir: Option<PathBuf>,
+
+    /// 落盘加密的主密钥文件 (64 位 hex，不存在时自动生成)；设置后上传的文件加密保存，下载时透明解密。
+    /// 密钥文件不能放在共享目录中
+    #[arg(long, env = "TRANSFER_ENCRYPT_KEY")]
+    pub encrypt_key: Option<PathBuf>,
+
+    /// 页面标题和顶栏名称 (默认 FileTransfer)
+    #[arg(long, env = "TRANSFER_BRAND_TITLE")]
+    pub brand_title: Option<String>,
+
+    /// 顶栏 Logo 图片 (PNG、SVG 等)
+    #[arg(long, env = "TRANSFER_BRAND_LOGO")]
+    pub brand_logo: Option<PathBuf>,
+
+    /// 主题色 (#RRGGBB)，按钮、链接和选中状态使用
+    #[arg(long, env = "TRANSFER_BRAND_ACCENT")]
+    pub brand_accent: Option<String>,
+
+    /// 页面底部显示的说明文字
+    #[arg(long, env = "TRANSFER_BRAND_FOOTER")]
+    pub brand_footer: Option<String>,
+
+    /// WASM 插件 (可重复，按顺序调用)；需要以 `plugins` 特性编译
+    #[arg(long = "plugin", env = "TRANSFER_PLUGINS", value_delimiter = ',')]
+    #[serde(default)]
+    pub plugins: Vec<PathBuf>,
+
+    /// 元数据库 (传输记录、下载计数) 路径 (默认 <tmp-dir>/transfer.db)
+    #[arg(long, env = "TRANSFER_DB")]
+    pub db_path: Option<PathBuf>,
+
     /// 上传会话过期 (秒, 默认 7 天)
     #[arg(long, default_value_t = 7 * 24 * 3600)]
     pub upload_expiration_secs: u64,
 
+    /// 后台清理任务的执行间隔 (秒)
+    #[arg(long, default_value_t = 3600, env = "TRANSFER_JANITOR_INTERVAL")]
+    pub janitor_interval_secs: u64,
+
+    /// 临时区中无会话对应的孤立分片，超过该时长 (秒) 未修改即删除
+    #[arg(long, default_value_t = 24 * 3600, env = "TRANSFER_ORPHAN_MAX_AGE")]
+    pub orphan_max_age_secs: u64,
+
+    /// 删除的文件在回收站中保留的天数，过期后由后台清理任务清除 (0 = 不使用回收站，直接删除)
+    #[arg(long, default_value_t = 30, env = "TRANSFER_TRASH_DAYS")]
+    pub trash_days: u64,
+
+    /// 删除后可以撤销的时长 (秒, 0 = 不提供撤销)；--trash-days 为 0 时删除的文件保留到撤销期结束再彻底删除
+    #[arg(long, default_value_t = 30, env = "TRANSFER_UNDO_SECS")]
+    pub undo_secs: u64,
+
+    /// 对外访问地址 (用于通知中的链接, 默认根据本机 IP 推断)
+    #[arg(long, env = "TRANSFER_PUBLIC_URL")]
+    pub public_url: Option<String>,
+
+    /// 上传完成通知: Telegram bot token
+    #[arg(long, env = "TRANSFER_TELEGRAM_TOKEN")]
+    pub telegram_token: Option<String>,
+
+    /// 上传完成通知: Telegram chat id
+    #[arg(long, env = "TRANSFER_TELEGRAM_CHAT")]
+    pub telegram_chat: Option<String>,
+
+    /// 上传完成通知: Slack incoming webhook URL
+    #[arg(long, env = "TRANSFER_SLACK_WEBHOOK")]
+    pub slack_webhook: Option<String>,
+
+    /// ffmpeg 可执行文件路径，设置后启用 MKV/AVI 等格式的 HLS 转码
+    #[arg(long, env = "TRANSFER_FFMPEG")]
+    pub ffmpeg: Option<PathBuf>,
+
+    /// 磁盘占用统计的后台重新扫描间隔 (秒, 0 = 仅启动时和手动触发)
+    #[arg(long, default_value_t = 6 * 3600, env = "TRANSFER_USAGE_SCAN_INTERVAL")]
+    pub usage_scan_interval: u64,
+
+    /// 剪贴板同步条目在服务端保留的时长 (秒, 0 = 关闭剪贴板同步)
+    #[arg(long, default_value_t = 3600, env = "TRANSFER_CLIPBOARD_TTL")]
+    #[serde(default)]
+    pub clipboard_ttl_secs: u64,
+
+    /// 兼容 LocalSend 协议：通过 UDP 组播 (53317) 被手机上的 LocalSend 应用发现
+    #[arg(long, env = "TRANSFER_LOCALSEND")]
+    #[serde(default)]
+    pub localsend: bool,
+
+    /// 在 LocalSend 中显示的设备名 (默认主机名)
+    #[arg(long, env = "TRANSFER_LOCALSEND_ALIAS")]
+    pub localsend_alias: Option<String>,
+
+    /// LocalSend 发送方需要输入的 PIN
+    #[arg(long, env = "TRANSFER_LOCALSEND_PIN")]
+    pub localsend_pin: Option<String>,
+
+    /// 接收模式：其他设备推送文件时先在控制台和网页上确认，而不是直接接收 (隐含 --localsend)
+    #[arg(long, env = "TRANSFER_LOCALSEND_CONFIRM")]
+    #[serde(default)]
+    pub localsend_confirm: bool,
+
     /// 配置文件 (TOML)
     #[arg(short = 'c', long, env = "TRANSFER_CONFIG")]
     pub config: Option<PathBuf>,
@@ -46,34 +298,205 @@ pub struct AppConfig {
     /// 日志级别
     #[arg(long, default_value = "info,transfer_app=debug", env = "RUST_LOG")]
     pub log_filter: String,
+
+    /// 日志同时写入该文件 (不带颜色)，按 --log-rotate / --log-max-size 轮转
+    #[arg(long, env = "TRANSFER_LOG_FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// 日志文件按时间轮转：never | hourly | daily (UTC)
+    #[arg(long, value_enum, default_value = "daily", env = "TRANSFER_LOG_ROTATE")]
+    #[serde(default)]
+    pub log_rotate: LogRotation,
+
+    /// 日志文件超过该大小时轮转，如 `100M` (0 = 不按大小轮转)
+    #[arg(long, default_value = "100M", value_parser = parse_size, env = "TRANSFER_LOG_MAX_SIZE")]
+    pub log_max_size: u64,
+
+    /// 保留的已轮转日志文件数 (0 = 全部保留)
+    #[arg(long, default_value_t = 14, env = "TRANSFER_LOG_KEEP")]
+    pub log_keep: usize,
+
+    /// 把请求和传输的链路以 OTLP/HTTP 导出到该地址，如 `http://collector:4318`；需要以 `otel` 特性编译
+    #[arg(long, env = "TRANSFER_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+
+    /// 访问日志：每个请求一行，写入该文件 (`-` 为标准输出)，按 --log-rotate / --log-max-size 轮转
+    #[arg(long, env = "TRANSFER_ACCESS_LOG")]
+    pub access_log: Option<PathBuf>,
+
+    /// 访问日志格式：text (类似 Common Log Format) | json (每行一个 JSON 对象)
+    #[arg(long, value_enum, default_value = "text", env = "TRANSFER_ACCESS_LOG_FORMAT")]
+    #[serde(default)]
+    pub access_log_format: AccessLogFormat,
+
+    /// 按路径前缀的访问规则 (仅配置文件 `[[rules]]`)
+    #[arg(skip)]
+    #[serde(default)]
+    pub rules: Vec<PermissionRule>,
+
+    /// Basic 认证用户 (仅配置文件 `[users]`，name = "password")
+    #[arg(skip)]
+    #[serde(default)]
+    pub users: BTreeMap<String, String>,
+
+    /// 事件钩子 (仅配置文件 `[hooks]`，事件名 = 程序路径或参数数组)
+    #[arg(skip)]
+    #[serde(default)]
+    pub hooks: BTreeMap<HookEvent, HookCommand>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// 对运行中的服务做上传/下载压测
+    Bench(BenchArgs),
+    /// 与运行中的服务同步目录，只传输缺少或变化的文件
+    Sync(SyncArgs),
+}
+
+/// 配置文件 (TOML) 中支持的项
+#[derive(Debug, Default, Deserialize)]
+pub struct FileConfig {
+    pub path: Option<PathBuf>,
+    pub max_upload_size: Option<u64>,
+    #[serde(default)]
+    pub rules: Vec<PermissionRule>,
+    #[serde(default)]
+    pub users: BTreeMap<String, String>,
+    #[serde(default)]
+    pub hooks: BTreeMap<HookEvent, HookCommand>,
+}
+
+impl FileConfig {
+    pub fn read(path: &std::path::Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
 }
 
 impl AppConfig {
     pub fn load() -> anyhow::Result<Self> {
-        let mut cli = Self::parse();
+        let matches = Self::command().get_matches();
+        let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        cli.max_upload_size_pinned = matches!(
+            matches.value_source("max_upload_size"),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        );
 
         // 如果指定了配置文件，合并 TOML 配置
         if let Some(ref cfg_path) = cli.config {
             if cfg_path.exists() {
-                let content = std::fs::read_to_string(cfg_path)?;
-                let file_cfg: toml::Value = toml::from_str(&content)?;
+                let file_cfg = FileConfig::read(cfg_path)?;
 
                 // TOML 配置作为默认值，CLI 参数优先
-                if let Some(path) = file_cfg.get("path").and_then(|v| v.as_str()) {
+                if let Some(path) = file_cfg.path {
                     if cli.path.as_os_str().is_empty() {
-                        cli.path = PathBuf::from(path);
+                        cli.path = path;
+                    }
+                }
+                if let Some(max) = file_cfg.max_upload_size {
+                    if !cli.max_upload_size_pinned {
+                        cli.max_upload_size = max;
                     }
                 }
+                cli.rules = file_cfg.rules;
+                cli.users = file_cfg.users;
+                cli.hooks = file_cfg.hooks;
             }
         }
 
+        if cli.require_auth && cli.users.is_empty() && cli.password.is_none() {
+            anyhow::bail!("--require-auth needs users in the config file's [users] table or --password");
+        }
+
+        if let Some(name) = &cli.interface {
+            cli.bind = crate::util::ip::interface_addrs(name)?[0];
+        }
+
+        // 子命令不需要共享目录
+        if cli.command.is_some() {
+            return Ok(cli);
+        }
+
+        if cli.path.as_os_str().is_empty() {
+            anyhow::bail!("missing shared directory: pass --path or set `path` in the config file");
+        }
+
         // 规范化路径
-        cli.path = dunce::canonicalize(&cli.path)?;
+        cli.path = crate::fs::path_safety::canonicalize(&cli.path)?;
 
         if !cli.path.is_dir() {
             anyhow::bail!("path '{}' is not a directory", cli.path.display());
         }
 
+        // 证书要在沙箱生效之前写好，沙箱只允许读取证书文件
+        if cli.tls {
+            cli.prepare_tls()?;
+        }
+
         Ok(cli)
     }
+
+    /// 是否所有请求都要先登录
+    pub fn auth_required(&self) -> bool {
+        self.require_auth || self.password.as_deref().is_some_and(|p| !p.is_empty())
+    }
+
+    /// 上传分片、会话元数据等的目录
+    pub fn tmp_dir(&self) -> PathBuf {
+        self.tmp_dir.clone().unwrap_or_else(|| self.path.join(".transfer-tmp"))
+    }
+
+    /// --tls：补上默认的证书路径，文件不存在时生成自签名证书
+    #[cfg(feature = "tls")]
+    fn prepare_tls(&mut self) -> anyhow::Result<()> {
+        let dir = self.tmp_dir().join("tls");
+        let cert = self.tls_cert.get_or_insert_with(|| dir.join("cert.pem")).clone();
+        let key = self.tls_key.get_or_insert_with(|| dir.join("key.pem")).clone();
+        match (cert.exists(), key.exists()) {
+            (true, true) => Ok(()),
+            (false, false) => {
+                let mut ips: Vec<IpAddr> = crate::util::ip::lan_addrs().into_iter().map(|lan| lan.ip).collect();
+                if !self.bind.is_unspecified() {
+                    ips.push(self.bind);
+                }
+                crate::tls::generate_self_signed(&cert, &key, &ips)?;
+                self.tls_generated = true;
+                Ok(())
+            }
+            // 只剩一半时不覆盖，可能是用户自己的证书
+            _ => anyhow::bail!(
+                "only one of '{}' and '{}' exists; remove it to generate a new self-signed certificate",
+                cert.display(),
+                key.display()
+            ),
+        }
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn prepare_tls(&mut self) -> anyhow::Result<()> {
+        anyhow::bail!("--tls requires a build with the `tls` feature")
+    }
+
+    /// 对外访问的基础 URL，不带末尾斜杠
+    pub fn base_url(&self) -> String {
+        if let Some(url) = &self.public_url {
+            return url.trim_end_matches('/').to_string();
+        }
+        let protocol = if self.tls_cert.is_some() { "https" } else { "http" };
+        // 只监听某个地址时链接必须指向它，否则用首选的局域网地址
+        let host = if self.bind.is_unspecified() {
+            crate::util::ip::lan_addrs()
+                .first()
+                .map(|a| a.ip)
+                .unwrap_or(IpAddr::from([127, 0, 0, 1]))
+        } else {
+            self.bind
+        };
+        format!("{}://{}", protocol, std::net::SocketAddr::new(host, self.port))
+    }
+}
+
+/// clap 自带的 PathBuf 解析器拒绝空值，这里允许空默认值，由 load() 统一报错
+fn parse_path(s: &str) -> Result<PathBuf, std::convert::Infallible> {
+    Ok(PathBuf::from(s))
 }
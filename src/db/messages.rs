@@ -0,0 +1,118 @@
+use std::net::IpAddr;
+
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+
+use super::{now_secs, Db};
+use crate::error::AppError;
+
+/// 留言板中的一条留言
+#[derive(Debug, Clone, Serialize)]
+pub struct Message {
+    pub id: i64,
+    pub author: String,
+    pub text: String,
+    /// 留言所在目录（相对 root），为空表示全局留言
+    pub path: Option<String>,
+    #[serde(skip)]
+    pub client_ip: Option<IpAddr>,
+    /// unix 秒
+    pub at: u64,
+}
+
+impl Db {
+    pub async fn insert_message(
+        &self,
+        author: String,
+        text: String,
+        path: Option<String>,
+        client_ip: Option<IpAddr>,
+    ) -> Result<Message, AppError> {
+        self.call(move |conn| {
+            let at = now_secs();
+            conn.execute(
+                "INSERT INTO messages (author, text, path, client_ip, at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![author, text, path, client_ip.map(|ip| ip.to_string()), at],
+            )?;
+            Ok(Message {
+                id: conn.last_insert_rowid(),
+                author,
+                text,
+                path,
+                client_ip,
+                at,
+            })
+        })
+        .await
+    }
+
+    /// 最近的留言，新的在前；before 用于向前翻页
+    pub async fn recent_messages(
+        &self,
+        limit: u32,
+        before: Option<i64>,
+    ) -> Result<Vec<Message>, AppError> {
+        self.call(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, author, text, path, client_ip, at FROM messages
+                 WHERE ?1 IS NULL OR id < ?1 ORDER BY id DESC LIMIT ?2",
+            )?;
+            let rows = stmt.query_map(params![before, limit], row_to_message)?;
+            rows.collect()
+        })
+        .await
+    }
+
+    pub async fn get_message(&self, id: i64) -> Result<Option<Message>, AppError> {
+        self.call(move |conn| {
+            conn.query_row(
+                "SELECT id, author, text, path, client_ip, at FROM messages WHERE id = ?1",
+                [id],
+                row_to_message,
+            )
+            .optional()
+        })
+        .await
+    }
+
+    pub async fn delete_message(&self, id: i64) -> Result<bool, AppError> {
+        self.call(move |conn| Ok(conn.execute("DELETE FROM messages WHERE id = ?1", [id])? > 0))
+            .await
+    }
+}
+
+fn row_to_message(row: &rusqlite::Row<'_>) -> rusqlite::Result<Message> {
+    let client_ip: Option<String> = row.get(4)?;
+    Ok(Message {
+        id: row.get(0)?,
+        author: row.get(1)?,
+        text: row.get(2)?,
+        path: row.get(3)?,
+        client_ip: client_ip.and_then(|ip| ip.parse().ok()),
+        at: row.get(5)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_messages_paging() {
+        let db = Db::open_in_memory().unwrap();
+        for i in 0..5 {
+            db.insert_message("a".into(), format!("note {}", i), None, None)
+                .await
+                .unwrap();
+        }
+        let page = db.recent_messages(2, None).await.unwrap();
+        assert_eq!(page.iter().map(|m| m.text.as_str()).collect::<Vec<_>>(), ["note 4", "note 3"]);
+        let next = db.recent_messages(10, Some(page[1].id)).await.unwrap();
+        assert_eq!(next.len(), 3);
+
+        assert!(db.delete_message(page[0].id).await.unwrap());
+        assert!(db.get_message(page[0].id).await.unwrap().is_none());
+        assert!(!db.delete_message(page[0].id).await.unwrap());
+    }
+}
@@ -0,0 +1,74 @@
+use rusqlite::Connection;
+
+/// 按顺序执行的 schema 变更，已执行到的版本记录在 PRAGMA user_version
+///
+/// 只能追加，不能修改已发布的条目
+const MIGRATIONS: &[&str] = &[
+    // 1: 传输记录与按文件的下载计数
+    "CREATE TABLE transfers (
+        id INTEGER PRIMARY KEY,
+        kind TEXT NOT NULL,
+        path TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        client_ip TEXT,
+        user TEXT,
+        at INTEGER NOT NULL
+    );
+    CREATE INDEX transfers_at ON transfers (at);
+    CREATE TABLE download_counts (
+        path TEXT PRIMARY KEY,
+        count INTEGER NOT NULL,
+        bytes INTEGER NOT NULL,
+        last_at INTEGER NOT NULL
+    );",
+    // 2: 留言板
+    "CREATE TABLE messages (
+        id INTEGER PRIMARY KEY,
+        author TEXT NOT NULL,
+        text TEXT NOT NULL,
+        path TEXT,
+        client_ip TEXT,
+        at INTEGER NOT NULL
+    );",
+    // 3: 回收站
+    // ID 不复用，客户端手里过时的 ID 不会指到后来删除的文件
+    "CREATE TABLE trash (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        path TEXT NOT NULL,
+        is_dir INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        user TEXT,
+        client_ip TEXT,
+        deleted_at INTEGER NOT NULL
+    );
+    CREATE INDEX trash_deleted_at ON trash (deleted_at);",
+];
+
+/// 执行尚未应用的迁移，每个版本一个事务
+pub fn run(conn: &mut Connection) -> rusqlite::Result<()> {
+    let current: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (version, sql) in MIGRATIONS.iter().enumerate().skip(current) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", version + 1)?;
+        tx.commit()?;
+        tracing::info!(version = version + 1, "database migrated");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run(&mut conn).unwrap();
+        run(&mut conn).unwrap();
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+}
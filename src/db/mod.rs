@@ -0,0 +1,213 @@
+pub mod messages;
+pub mod migrations;
+pub mod trash;
+
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::error::AppError;
+
+/// 嵌入式 SQLite 元数据库；连接只有一个，操作放在 blocking 线程执行
+#[derive(Clone)]
+pub struct Db {
+    conn: Arc<Mutex<Connection>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferKind {
+    Upload,
+    Download,
+}
+
+impl TransferKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Upload => "upload",
+            Self::Download => "download",
+        }
+    }
+}
+
+/// 一次完成的上传或开始的下载
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferRecord {
+    pub kind: TransferKind,
+    /// 相对 root
+    pub path: String,
+    pub size: u64,
+    pub client_ip: Option<IpAddr>,
+    pub user: Option<String>,
+    /// unix 秒
+    pub at: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DownloadCount {
+    pub path: String,
+    pub count: u64,
+    pub bytes: u64,
+    pub last_at: u64,
+}
+
+impl Db {
+    /// 打开（或创建）数据库并执行迁移
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)?;
+        // WAL 下读写互不阻塞；异常退出最多丢失最后一次提交
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        Self::init(conn)
+    }
+
+    pub fn open_in_memory() -> anyhow::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(mut conn: Connection) -> anyhow::Result<Self> {
+        migrations::run(&mut conn)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// 在 blocking 线程上使用连接
+    pub async fn call<T, F>(&self, f: F) -> Result<T, AppError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        let result = tokio::task::spawn_blocking(move || f(&mut conn.lock()))
+            .await
+            .map_err(|e| AppError::Internal(e.into()))?;
+        Ok(result?)
+    }
+
+    /// 写入传输记录，下载同时累加计数；失败只记日志，不影响传输本身
+    pub fn record_transfer(&self, record: TransferRecord) {
+        let db = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = db.call(move |conn| insert_transfer(conn, &record)).await {
+                tracing::warn!(error = %e, "failed to record transfer");
+            }
+        });
+    }
+
+    /// 最近的传输记录，新的在前
+    pub async fn recent_transfers(&self, limit: u32) -> Result<Vec<TransferRecord>, AppError> {
+        self.call(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT kind, path, size, client_ip, user, at
+                 FROM transfers ORDER BY id DESC LIMIT ?1",
+            )?;
+            let rows = stmt.query_map([limit], |row| {
+                let kind: String = row.get(0)?;
+                let client_ip: Option<String> = row.get(3)?;
+                Ok(TransferRecord {
+                    kind: if kind == "upload" {
+                        TransferKind::Upload
+                    } else {
+                        TransferKind::Download
+                    },
+                    path: row.get(1)?,
+                    size: row.get(2)?,
+                    client_ip: client_ip.and_then(|ip| ip.parse().ok()),
+                    user: row.get(4)?,
+                    at: row.get(5)?,
+                })
+            })?;
+            rows.collect()
+        })
+        .await
+    }
+
+    /// 下载次数最多的文件
+    pub async fn top_downloads(&self, limit: u32) -> Result<Vec<DownloadCount>, AppError> {
+        self.call(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT path, count, bytes, last_at
+                 FROM download_counts ORDER BY count DESC, last_at DESC LIMIT ?1",
+            )?;
+            let rows = stmt.query_map([limit], |row| {
+                Ok(DownloadCount {
+                    path: row.get(0)?,
+                    count: row.get(1)?,
+                    bytes: row.get(2)?,
+                    last_at: row.get(3)?,
+                })
+            })?;
+            rows.collect()
+        })
+        .await
+    }
+}
+
+fn insert_transfer(conn: &mut Connection, record: &TransferRecord) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO transfers (kind, path, size, client_ip, user, at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            record.kind.as_str(),
+            record.path,
+            record.size,
+            record.client_ip.map(|ip| ip.to_string()),
+            record.user,
+            record.at,
+        ],
+    )?;
+    if record.kind == TransferKind::Download {
+        tx.execute(
+            "INSERT INTO download_counts (path, count, bytes, last_at)
+             VALUES (?1, 1, ?2, ?3)
+             ON CONFLICT (path) DO UPDATE SET
+                count = count + 1,
+                bytes = bytes + excluded.bytes,
+                last_at = excluded.last_at",
+            params![record.path, record.size, record.at],
+        )?;
+    }
+    tx.commit()
+}
+
+/// 当前 unix 秒
+pub fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_download_counts_accumulate() {
+        let db = Db::open_in_memory().unwrap();
+        for size in [10, 20] {
+            let record = TransferRecord {
+                kind: TransferKind::Download,
+                path: "a.txt".into(),
+                size,
+                client_ip: Some("127.0.0.1".parse().unwrap()),
+                user: None,
+                at: size,
+            };
+            db.call(move |conn| insert_transfer(conn, &record)).await.unwrap();
+        }
+
+        let top = db.top_downloads(10).await.unwrap();
+        assert_eq!((top[0].count, top[0].bytes, top[0].last_at), (2, 30, 20));
+        let recent = db.recent_transfers(10).await.unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].size, 20);
+        assert_eq!(recent[0].kind, TransferKind::Download);
+    }
+}
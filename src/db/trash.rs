@@ -0,0 +1,101 @@
+use std::net::IpAddr;
+
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+
+use super::{now_secs, Db};
+use crate::error::AppError;
+
+/// 回收站中的一项
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashItem {
+    pub id: i64,
+    /// 删除前的位置（相对 root）
+    pub path: String,
+    pub is_dir: bool,
+    /// 目录为其中所有文件的大小之和
+    pub size: u64,
+    pub user: Option<String>,
+    #[serde(skip)]
+    pub client_ip: Option<IpAddr>,
+    /// unix 秒
+    pub deleted_at: u64,
+}
+
+const COLUMNS: &str = "id, path, is_dir, size, user, client_ip, deleted_at";
+
+impl Db {
+    pub async fn insert_trash(
+        &self,
+        path: String,
+        is_dir: bool,
+        size: u64,
+        user: Option<String>,
+        client_ip: Option<IpAddr>,
+    ) -> Result<TrashItem, AppError> {
+        self.call(move |conn| {
+            let deleted_at = now_secs();
+            conn.execute(
+                "INSERT INTO trash (path, is_dir, size, user, client_ip, deleted_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![path, is_dir, size, user, client_ip.map(|ip| ip.to_string()), deleted_at],
+            )?;
+            Ok(TrashItem {
+                id: conn.last_insert_rowid(),
+                path,
+                is_dir,
+                size,
+                user,
+                client_ip,
+                deleted_at,
+            })
+        })
+        .await
+    }
+
+    /// 回收站中的全部条目，最近删除的在前
+    pub async fn list_trash(&self) -> Result<Vec<TrashItem>, AppError> {
+        self.call(|conn| {
+            let mut stmt = conn.prepare(&format!("SELECT {COLUMNS} FROM trash ORDER BY id DESC"))?;
+            let rows = stmt.query_map([], row_to_item)?;
+            rows.collect()
+        })
+        .await
+    }
+
+    pub async fn get_trash(&self, id: i64) -> Result<Option<TrashItem>, AppError> {
+        self.call(move |conn| {
+            conn.query_row(&format!("SELECT {COLUMNS} FROM trash WHERE id = ?1"), [id], row_to_item)
+                .optional()
+        })
+        .await
+    }
+
+    /// 删除时间早于 before (unix 秒) 的条目
+    pub async fn expired_trash(&self, before: u64) -> Result<Vec<TrashItem>, AppError> {
+        self.call(move |conn| {
+            let mut stmt = conn.prepare(&format!("SELECT {COLUMNS} FROM trash WHERE deleted_at < ?1"))?;
+            let rows = stmt.query_map([before], row_to_item)?;
+            rows.collect()
+        })
+        .await
+    }
+
+    pub async fn delete_trash(&self, id: i64) -> Result<bool, AppError> {
+        self.call(move |conn| Ok(conn.execute("DELETE FROM trash WHERE id = ?1", [id])? > 0))
+            .await
+    }
+}
+
+fn row_to_item(row: &rusqlite::Row<'_>) -> rusqlite::Result<TrashItem> {
+    let client_ip: Option<String> = row.get(5)?;
+    Ok(TrashItem {
+        id: row.get(0)?,
+        path: row.get(1)?,
+        is_dir: row.get(2)?,
+        size: row.get(3)?,
+        user: row.get(4)?,
+        client_ip: client_ip.and_then(|ip| ip.parse().ok()),
+        deleted_at: row.get(6)?,
+    })
+}
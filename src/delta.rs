@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::io::{self, Read, SeekFrom, Write};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+/// 块大小取文件大小的平方根（向上取 2 的幂），限制在 4 KiB – 1 MiB
+const MIN_BLOCK: usize = 4 << 10;
+const MAX_BLOCK: usize = 1 << 20;
+/// 单个字面量片段的上限，生成和应用差量时都只需缓冲这么多数据
+const MAX_LITERAL: usize = 1 << 20;
+/// 生成差量时每次读取的大小
+const READ_SIZE: usize = 4 << 20;
+
+// 差量流由以下操作组成，整数均为小端
+
+/// `C` + u64 块序号：复制旧文件的一块
+const OP_COPY: u8 = b'C';
+/// `L` + u32 长度 + 数据：新内容
+const OP_LITERAL: u8 = b'L';
+/// `E` + 32 字节：结束，附新文件的 SHA-256
+const OP_END: u8 = b'E';
+
+/// 旧文件的分块校验和，接收方据此找出未变化的块
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signature {
+    pub size: u64,
+    pub block_size: usize,
+    pub blocks: Vec<BlockSum>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockSum {
+    /// 滚动校验和，逐字节滑动时 O(1) 更新
+    pub weak: u32,
+    /// SHA-256 前 16 字节的 hex，弱校验和相同时确认
+    pub strong: String,
+}
+
+/// 生成差量的统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeltaStats {
+    /// 从旧文件复制的字节数
+    pub copied: u64,
+    /// 需要发送的新内容字节数
+    pub literal: u64,
+}
+
+/// 应用差量的结果
+#[derive(Debug)]
+pub struct Applied {
+    pub size: u64,
+    /// 实际写出内容的 SHA-256
+    pub sha256: String,
+    /// 发送方声明的 SHA-256
+    pub expected: String,
+}
+
+pub fn block_size(size: u64) -> usize {
+    ((size as f64).sqrt() as usize)
+        .next_power_of_two()
+        .clamp(MIN_BLOCK, MAX_BLOCK)
+}
+
+/// 计算文件的分块签名（同步，需放在 blocking 线程执行）
+pub fn signature<R: Read>(mut input: R, size: u64) -> io::Result<Signature> {
+    let block_size = block_size(size);
+    let mut blocks = Vec::with_capacity(size.div_ceil(block_size as u64) as usize);
+    let mut buf = vec![0u8; block_size];
+    let mut total = 0u64;
+    loop {
+        let n = read_full(&mut input, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        total += n as u64;
+        blocks.push(BlockSum {
+            weak: Rolling::new(&buf[..n]).digest(),
+            strong: strong_sum(&buf[..n]),
+        });
+        if n < block_size {
+            break;
+        }
+    }
+    Ok(Signature {
+        size: total,
+        block_size,
+        blocks,
+    })
+}
+
+/// 对照旧文件的签名，把新文件编码为差量流写入 out（同步，需放在 blocking 线程执行）
+pub fn delta<R: Read, W: Write>(sig: &Signature, mut input: R, out: &mut W) -> io::Result<DeltaStats> {
+    let bs = sig.block_size;
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    // 绝大多数位置都不匹配，先查 16 位标记表，避免每个字节都查哈希表
+    let mut tags = vec![false; 1 << 16];
+    for (i, block) in sig.blocks.iter().enumerate() {
+        index.entry(block.weak).or_default().push(i);
+        tags[tag(block.weak)] = true;
+    }
+    let block_len = |i: usize| (sig.size - i as u64 * bs as u64).min(bs as u64) as usize;
+
+    let mut hasher = Sha256::new();
+    let mut stats = DeltaStats::default();
+    let mut buf: Vec<u8> = Vec::new();
+    // buf[lit..pos] 是尚未发送的新内容，buf[pos..] 是待匹配的数据
+    let (mut lit, mut pos) = (0usize, 0usize);
+    let mut eof = false;
+    let mut rolling: Option<Rolling> = None;
+    loop {
+        // 窗口之后至少还要有一个字节才能继续滑动
+        if !eof && buf.len() - pos <= bs {
+            buf.drain(..lit);
+            pos -= lit;
+            lit = 0;
+            let start = buf.len();
+            buf.resize(start + READ_SIZE, 0);
+            let n = read_full(&mut input, &mut buf[start..])?;
+            buf.truncate(start + n);
+            hasher.update(&buf[start..]);
+            eof = n < READ_SIZE;
+            continue;
+        }
+        if pos >= buf.len() {
+            break;
+        }
+        let end = (pos + bs).min(buf.len());
+        let window = &buf[pos..end];
+        let r = rolling.get_or_insert_with(|| Rolling::new(window));
+        let weak = r.digest();
+        let matched = if tags[tag(weak)] {
+            let mut strong = None;
+            index.get(&weak).and_then(|candidates| {
+                candidates.iter().copied().find(|&i| {
+                    block_len(i) == window.len()
+                        && *strong.get_or_insert_with(|| strong_sum(window)) == sig.blocks[i].strong
+                })
+            })
+        } else {
+            None
+        };
+        match matched {
+            Some(i) => {
+                write_literal(out, &buf[lit..pos], &mut stats)?;
+                out.write_all(&[OP_COPY])?;
+                out.write_all(&(i as u64).to_le_bytes())?;
+                stats.copied += window.len() as u64;
+                pos = end;
+                lit = pos;
+                rolling = None;
+            }
+            None => {
+                if end < buf.len() {
+                    r.roll(buf[pos], buf[end]);
+                } else {
+                    // 文件末尾，窗口逐渐缩短
+                    r.shrink(buf[pos]);
+                }
+                pos += 1;
+                if pos - lit >= MAX_LITERAL {
+                    write_literal(out, &buf[lit..pos], &mut stats)?;
+                    lit = pos;
+                }
+            }
+        }
+    }
+    write_literal(out, &buf[lit..pos], &mut stats)?;
+    out.write_all(&[OP_END])?;
+    out.write_all(&hasher.finalize())?;
+    Ok(stats)
+}
+
+/// 按差量流从旧文件 base 重建新文件写入 out；新文件超过 limit 字节时中止
+///
+/// 格式错误返回 `InvalidData`，校验和由调用方比较 `Applied` 中的两个值
+pub async fn apply<R, B, W>(
+    ops: &mut R,
+    base: &mut B,
+    base_size: u64,
+    out: &mut W,
+    limit: u64,
+) -> io::Result<Applied>
+where
+    R: AsyncRead + Unpin,
+    B: AsyncRead + AsyncSeek + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let bs = block_size(base_size);
+    let blocks = base_size.div_ceil(bs as u64);
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+    let mut buf = Vec::new();
+    loop {
+        match ops.read_u8().await? {
+            OP_COPY => {
+                let i = ops.read_u64_le().await?;
+                if i >= blocks {
+                    return Err(invalid("block index out of range"));
+                }
+                let start = i * bs as u64;
+                let len = (base_size - start).min(bs as u64) as usize;
+                buf.resize(len, 0);
+                base.seek(SeekFrom::Start(start)).await?;
+                base.read_exact(&mut buf).await?;
+            }
+            OP_LITERAL => {
+                let len = ops.read_u32_le().await? as usize;
+                if len > MAX_LITERAL {
+                    return Err(invalid("literal too long"));
+                }
+                buf.resize(len, 0);
+                ops.read_exact(&mut buf).await?;
+            }
+            OP_END => {
+                let mut expected = [0u8; 32];
+                ops.read_exact(&mut expected).await?;
+                out.flush().await?;
+                return Ok(Applied {
+                    size,
+                    sha256: hex::encode(hasher.finalize()),
+                    expected: hex::encode(expected),
+                });
+            }
+            _ => return Err(invalid("unknown delta op")),
+        }
+        size += buf.len() as u64;
+        if size > limit {
+            return Err(invalid("file exceeds size limit"));
+        }
+        hasher.update(&buf);
+        out.write_all(&buf).await?;
+    }
+}
+
+fn write_literal<W: Write>(out: &mut W, data: &[u8], stats: &mut DeltaStats) -> io::Result<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    out.write_all(&[OP_LITERAL])?;
+    out.write_all(&(data.len() as u32).to_le_bytes())?;
+    out.write_all(data)?;
+    stats.literal += data.len() as u64;
+    Ok(())
+}
+
+fn strong_sum(data: &[u8]) -> String {
+    hex::encode(&Sha256::digest(data)[..16])
+}
+
+fn tag(weak: u32) -> usize {
+    ((weak >> 16) ^ (weak & 0xffff)) as usize
+}
+
+/// rsync 的滚动校验和：a 为字节和，b 为按位置加权的和，各取低 16 位
+struct Rolling {
+    a: u32,
+    b: u32,
+    len: u32,
+}
+
+impl Rolling {
+    fn new(window: &[u8]) -> Self {
+        let len = window.len() as u32;
+        let (mut a, mut b) = (0u32, 0u32);
+        for (i, &x) in window.iter().enumerate() {
+            a = a.wrapping_add(x as u32);
+            b = b.wrapping_add((len - i as u32).wrapping_mul(x as u32));
+        }
+        Self { a, b, len }
+    }
+
+    /// 窗口右移一个字节
+    fn roll(&mut self, out: u8, incoming: u8) {
+        self.a = self.a.wrapping_sub(out as u32).wrapping_add(incoming as u32);
+        self.b = self
+            .b
+            .wrapping_sub(self.len.wrapping_mul(out as u32))
+            .wrapping_add(self.a);
+    }
+
+    /// 去掉窗口的第一个字节
+    fn shrink(&mut self, out: u8) {
+        self.a = self.a.wrapping_sub(out as u32);
+        self.b = self.b.wrapping_sub(self.len.wrapping_mul(out as u32));
+        self.len -= 1;
+    }
+
+    fn digest(&self) -> u32 {
+        (self.a & 0xffff) | (self.b << 16)
+    }
+}
+
+/// 读满 buf 或到末尾
+fn read_full<R: Read>(input: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_random(len: usize, seed: u64) -> Vec<u8> {
+        let mut x = seed;
+        (0..len)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                x as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rolling() {
+        let data = pseudo_random(100, 1);
+        let mut r = Rolling::new(&data[0..16]);
+        for i in 0..84 {
+            r.roll(data[i], data[i + 16]);
+            assert_eq!(r.digest(), Rolling::new(&data[i + 1..i + 17]).digest());
+        }
+        r.shrink(data[84]);
+        assert_eq!(r.digest(), Rolling::new(&data[85..]).digest());
+    }
+
+    #[tokio::test]
+    async fn test_delta_roundtrip() {
+        let old = pseudo_random(300_000, 7);
+        let mut new = old.clone();
+        // 中间改写、插入，末尾截短
+        new[50_000..50_100].fill(0);
+        new.splice(120_000..120_000, pseudo_random(3_000, 9));
+        new.truncate(280_000);
+
+        let sig = signature(&old[..], old.len() as u64).unwrap();
+        assert_eq!(sig.size, old.len() as u64);
+        let mut encoded = Vec::new();
+        let stats = delta(&sig, &new[..], &mut encoded).unwrap();
+        assert_eq!(stats.copied + stats.literal, new.len() as u64);
+        assert!(stats.literal < 20_000, "literal {}", stats.literal);
+        assert!(encoded.len() < 30_000);
+
+        let mut base = std::io::Cursor::new(old.clone());
+        let mut out = Vec::new();
+        let applied = apply(&mut &encoded[..], &mut base, old.len() as u64, &mut out, u64::MAX)
+            .await
+            .unwrap();
+        assert_eq!(out, new);
+        assert_eq!(applied.size, new.len() as u64);
+        assert_eq!(applied.sha256, applied.expected);
+
+        // 超过上限或被截断都会失败
+        let mut base = std::io::Cursor::new(old.clone());
+        assert!(apply(&mut &encoded[..], &mut base, old.len() as u64, &mut Vec::new(), 1000)
+            .await
+            .is_err());
+        let mut base = std::io::Cursor::new(old);
+        let truncated = &encoded[..encoded.len() - 10];
+        assert!(apply(&mut &truncated[..], &mut base, 300_000, &mut Vec::new(), u64::MAX)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+const MONTHS: [Month; 12] = [
+    Month::January,
+    Month::February,
+    Month::March,
+    Month::April,
+    Month::May,
+    Month::June,
+    Month::July,
+    Month::August,
+    Month::September,
+    Month::October,
+    Month::November,
+    Month::December,
+];
+
+/// 格式化为 HTTP-date (IMF-fixdate)，如 "Sun, 06 Nov 1994 08:49:37 GMT"
+pub fn format_http_date(time: SystemTime) -> Option<String> {
+    let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let dt = OffsetDateTime::from_unix_timestamp(secs as i64).ok()?;
+    Some(format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        &dt.weekday().to_string()[..3],
+        dt.day(),
+        &dt.month().to_string()[..3],
+        dt.year(),
+        dt.hour(),
+        dt.minute(),
+        dt.second(),
+    ))
+}
+
+/// 解析 IMF-fixdate；已废弃的 RFC 850 / asctime 格式不支持，按无效处理
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_weekday, day, month, year, hms, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    let month = *MONTHS
+        .iter()
+        .find(|m| m.to_string()[..3].eq_ignore_ascii_case(month))?;
+    let date = Date::from_calendar_date(year.parse().ok()?, month, day.parse().ok()?).ok()?;
+
+    let mut hms = hms.split(':').map(|s| s.parse::<u8>().ok());
+    let (Some(Some(h)), Some(Some(m)), Some(Some(s)), None) =
+        (hms.next(), hms.next(), hms.next(), hms.next())
+    else {
+        return None;
+    };
+    let time = Time::from_hms(h, m, s).ok()?;
+
+    let secs = PrimitiveDateTime::new(date, time).assume_utc().unix_timestamp();
+    Some(UNIX_EPOCH + std::time::Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+/// 截断到整秒，与 Last-Modified 的精度一致
+pub fn truncate_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+/// If-Range 是否仍指向当前版本；不匹配时应忽略 Range 返回完整内容
+///
+/// 只做强比较：弱 ETag 永不匹配，日期必须与 Last-Modified 完全相同
+pub fn if_range_matches(if_range: &str, etag: &str, modified: Option<SystemTime>) -> bool {
+    let if_range = if_range.trim();
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        return if_range == etag;
+    }
+    match (parse_http_date(if_range), modified) {
+        (Some(date), Some(modified)) => truncate_secs(date) == truncate_secs(modified),
+        _ => false,
+    }
+}
+
+/// If-Modified-Since：文件在该时间之后未被修改，应返回 304；日期无效时忽略该头
+pub fn not_modified_since(if_modified_since: &str, modified: Option<SystemTime>) -> bool {
+    match (parse_http_date(if_modified_since), modified) {
+        (Some(date), Some(modified)) => truncate_secs(modified) <= truncate_secs(date),
+        _ => false,
+    }
+}
+
+/// If-Unmodified-Since：文件在该时间之后被修改过，应返回 412；日期无效时忽略该头
+pub fn modified_after(if_unmodified_since: &str, modified: Option<SystemTime>) -> bool {
+    match (parse_http_date(if_unmodified_since), modified) {
+        (Some(date), Some(modified)) => truncate_secs(modified) > truncate_secs(date),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_date_roundtrip() {
+        let t = UNIX_EPOCH + std::time::Duration::from_secs(784111777);
+        assert_eq!(format_http_date(t).unwrap(), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(t));
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+    }
+
+    #[test]
+    fn test_if_range() {
+        let modified = UNIX_EPOCH + std::time::Duration::from_millis(784_111_777_500);
+        let etag = "\"abc-1\"";
+        assert!(if_range_matches("\"abc-1\"", etag, Some(modified)));
+        assert!(!if_range_matches("W/\"abc-1\"", etag, Some(modified)));
+        assert!(!if_range_matches("\"abc-2\"", etag, Some(modified)));
+        assert!(if_range_matches("Sun, 06 Nov 1994 08:49:37 GMT", etag, Some(modified)));
+        assert!(!if_range_matches("Sun, 06 Nov 1994 08:49:36 GMT", etag, Some(modified)));
+        assert!(!if_range_matches("garbage", etag, Some(modified)));
+    }
+
+    #[test]
+    fn test_modified_since() {
+        let modified = Some(UNIX_EPOCH + std::time::Duration::from_millis(784_111_777_500));
+        assert!(not_modified_since("Sun, 06 Nov 1994 08:49:37 GMT", modified));
+        assert!(!not_modified_since("Sun, 06 Nov 1994 08:49:36 GMT", modified));
+        assert!(modified_after("Sun, 06 Nov 1994 08:49:36 GMT", modified));
+        assert!(!modified_after("Sun, 06 Nov 1994 08:49:37 GMT", modified));
+        assert!(!not_modified_since("garbage", modified));
+        assert!(!modified_after("garbage", modified));
+    }
+}
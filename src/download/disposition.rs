@@ -0,0 +1,66 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+/// RFC 5987 的 attr-char 之外都要编码
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// 附件下载的 Content-Disposition：`filename*` 带完整的 UTF-8 文件名，
+/// `filename` 给不认识 `filename*` 的客户端，非 ASCII 字符和引号替换为 `_`
+pub fn attachment(filename: &str) -> String {
+    with_filename("attachment", filename)
+}
+
+/// 在浏览器中直接打开的 Content-Disposition，文件名编码同 [`attachment`]
+pub fn inline(filename: &str) -> String {
+    with_filename("inline", filename)
+}
+
+fn with_filename(kind: &str, filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            ' ' => ' ',
+            c if c.is_ascii_graphic() => c,
+            _ => '_',
+        })
+        .collect();
+    format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        kind,
+        fallback,
+        utf8_percent_encode(filename, ATTR_CHAR)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment() {
+        assert_eq!(
+            attachment("photos.zip"),
+            "attachment; filename=\"photos.zip\"; filename*=UTF-8''photos.zip"
+        );
+        assert_eq!(
+            attachment("照片 \"1\".zip"),
+            "attachment; filename=\"__ _1_.zip\"; filename*=UTF-8''%E7%85%A7%E7%89%87%20%221%22.zip"
+        );
+        assert_eq!(
+            inline("a\"b.txt"),
+            "inline; filename=\"a_b.txt\"; filename*=UTF-8''a%22b.txt"
+        );
+    }
+}
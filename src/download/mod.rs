@@ -1,2 +1,5 @@
+pub mod conditional;
+pub mod disposition;
 pub mod etag;
+pub mod precompressed;
 pub mod range;
@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+
+/// 按优先级尝试的预压缩旁路文件：(Content-Encoding, 扩展名)
+const SIDECARS: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+/// Accept-Encoding 是否接受指定编码（忽略 q=0 的项，支持 *）
+pub fn accepts(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|item| {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or("").trim();
+        let rejected = parts.any(|p| {
+            p.trim()
+                .strip_prefix("q=")
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        !rejected && (name.eq_ignore_ascii_case(encoding) || name == "*")
+    })
+}
+
+/// 查找 file.ext.br / file.ext.gz，返回 (旁路文件, Content-Encoding)
+///
+/// 旁路文件比原文件旧时视为过期，不使用
+pub async fn find_sidecar(path: &Path, accept_encoding: &str) -> Option<(PathBuf, &'static str)> {
+    let source_mtime = tokio::fs::metadata(path).await.ok()?.modified().ok()?;
+    for (encoding, ext) in SIDECARS {
+        if !accepts(accept_encoding, encoding) {
+            continue;
+        }
+        let mut name = path.file_name()?.to_os_string();
+        name.push(".");
+        name.push(ext);
+        let sidecar = path.with_file_name(name);
+        let Ok(meta) = tokio::fs::metadata(&sidecar).await else {
+            continue;
+        };
+        if meta.is_file() && meta.modified().is_ok_and(|m| m >= source_mtime) {
+            return Some((sidecar, encoding));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepts() {
+        assert!(accepts("gzip, deflate, br", "br"));
+        assert!(accepts("gzip;q=0.5", "gzip"));
+        assert!(!accepts("gzip;q=0, br", "gzip"));
+        assert!(accepts("*", "br"));
+        assert!(!accepts("identity", "gzip"));
+    }
+}
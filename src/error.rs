@@ -11,6 +11,9 @@ pub enum AppError {
     #[error("forbidden: {0}")]
     Forbidden(&'static str),
 
+    #[error("authentication required")]
+    Unauthorized,
+
     #[error("path traversal attempt")]
     PathTraversal,
 
@@ -20,15 +23,53 @@ pub enum AppError {
     #[error("checksum mismatch: expected {expected}, got {actual}")]
     ChecksumMismatch { expected: String, actual: String },
 
+    #[error("size mismatch: expected {expected} bytes, got {actual}")]
+    SizeMismatch { expected: u64, actual: u64 },
+
+    #[error("already exists: {0}")]
+    AlreadyExists(String),
+
+    #[error("upload is busy: another request is writing to it")]
+    UploadBusy,
+
+    #[error("another upload to the same file is in progress ({uploaded}/{total_size} bytes)")]
+    UploadInProgress {
+        uploaded: u64,
+        total_size: u64,
+        last_active: u64,
+    },
+
     #[error("upload offset conflict: server={server}, client={client}")]
     OffsetConflict { server: u64, client: u64 },
 
+    #[error("precondition failed: file was modified")]
+    PreconditionFailed,
+
+    #[error("server busy, try again later")]
+    Busy,
+
+    #[error("uploads are paused for maintenance")]
+    Paused { notice: String },
+
+    #[error("too many requests, retry after {retry_after}s")]
+    RateLimited { retry_after: u64 },
+
+    #[error("rejected by plugin {plugin}: {reason}")]
+    PluginRejected {
+        plugin: String,
+        status: StatusCode,
+        reason: String,
+    },
+
     #[error("bad request: {0}")]
     BadRequest(String),
 
     #[error("is a directory")]
     IsADirectory,
 
+    #[error("database: {0}")]
+    Database(#[from] rusqlite::Error),
+
     #[error("io: {0}")]
     Io(#[from] std::io::Error),
 
@@ -40,25 +81,78 @@ pub enum AppError {
 struct ErrorBody {
     code: &'static str,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<serde_json::Value>,
+    /// 与响应头 X-Request-Id 相同，方便把前端报错和服务端日志对上
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, code) = match &self {
+impl AppError {
+    /// HTTP 状态码和返回给前端的错误码
+    pub fn status_and_code(&self) -> (StatusCode, &'static str) {
+        match self {
             Self::NotFound(_) => (StatusCode::NOT_FOUND, "not_found"),
             Self::Forbidden(_) => (StatusCode::FORBIDDEN, "forbidden"),
+            Self::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized"),
             Self::PathTraversal => (StatusCode::FORBIDDEN, "path_traversal"),
             Self::PayloadTooLarge => (StatusCode::PAYLOAD_TOO_LARGE, "too_large"),
             Self::ChecksumMismatch { .. } => (StatusCode::CONFLICT, "checksum_mismatch"),
+            Self::SizeMismatch { .. } => (StatusCode::CONFLICT, "size_mismatch"),
+            Self::AlreadyExists(_) => (StatusCode::CONFLICT, "already_exists"),
+            Self::UploadBusy => (StatusCode::CONFLICT, "upload_busy"),
+            Self::UploadInProgress { .. } => (StatusCode::CONFLICT, "upload_in_progress"),
             Self::OffsetConflict { .. } => (StatusCode::CONFLICT, "offset_conflict"),
+            Self::PreconditionFailed => (StatusCode::PRECONDITION_FAILED, "precondition_failed"),
+            Self::Busy => (StatusCode::SERVICE_UNAVAILABLE, "busy"),
+            Self::Paused { .. } => (StatusCode::SERVICE_UNAVAILABLE, "paused"),
+            Self::RateLimited { .. } => (StatusCode::TOO_MANY_REQUESTS, "rate_limited"),
+            Self::PluginRejected { status, .. } => (*status, "plugin_rejected"),
             Self::BadRequest(_) => (StatusCode::BAD_REQUEST, "bad_request"),
             Self::IsADirectory => (StatusCode::BAD_REQUEST, "is_directory"),
             Self::Io(e) if e.kind() == std::io::ErrorKind::NotFound => {
                 (StatusCode::NOT_FOUND, "not_found")
             }
             Self::Io(_) => (StatusCode::INTERNAL_SERVER_ERROR, "io_error"),
+            Self::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "database"),
             Self::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
-        };
+        }
+    }
+}
+
+impl AppError {
+    /// 客户端可直接使用的结构化信息（期望值 / 实际值、服务端进度等）
+    pub fn detail(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::NotFound(what) | Self::AlreadyExists(what) => {
+                Some(serde_json::json!({ "path": what }))
+            }
+            Self::ChecksumMismatch { expected, actual } => {
+                Some(serde_json::json!({ "expected": expected, "actual": actual }))
+            }
+            Self::SizeMismatch { expected, actual } => {
+                Some(serde_json::json!({ "expected": expected, "actual": actual }))
+            }
+            Self::OffsetConflict { server, client } => {
+                Some(serde_json::json!({ "server_offset": server, "client_offset": client }))
+            }
+            Self::UploadInProgress { uploaded, total_size, last_active } => Some(serde_json::json!({
+                "uploaded": uploaded,
+                "total_size": total_size,
+                "last_active": last_active,
+            })),
+            Self::RateLimited { retry_after } => {
+                Some(serde_json::json!({ "retry_after": retry_after }))
+            }
+            Self::Paused { notice } => Some(serde_json::json!({ "notice": notice })),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, code) = self.status_and_code();
 
         if status.is_server_error() {
             tracing::error!(error = %self);
@@ -69,8 +163,42 @@ impl IntoResponse for AppError {
         let body = Json(ErrorBody {
             code,
             message: self.to_string(),
+            detail: self.detail(),
+            request_id: crate::middleware::request_id::current(),
         });
 
-        (status, body).into_response()
+        let retry_after = match &self {
+            Self::RateLimited { retry_after } => Some(*retry_after),
+            _ => None,
+        };
+        let mut response = (status, body).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(axum::http::header::RETRY_AFTER, secs.into());
+        }
+        if status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(
+                axum::http::header::WWW_AUTHENTICATE,
+                axum::http::HeaderValue::from_static("Basic realm=\"transfer-app\""),
+            );
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detail_shape() {
+        let err = AppError::OffsetConflict { server: 10, client: 4 };
+        assert_eq!(err.status_and_code(), (StatusCode::CONFLICT, "offset_conflict"));
+        assert_eq!(
+            err.detail(),
+            Some(serde_json::json!({ "server_offset": 10, "client_offset": 4 }))
+        );
+        assert!(AppError::Busy.detail().is_none());
     }
 }
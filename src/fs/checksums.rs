@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::fs::vault::Vault;
+use crate::util::blocking::BlockingPool;
+use crate::util::hash;
+
+const CACHE_FILE: &str = "checksums.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    size: u64,
+    /// 修改时间 (unix 纳秒)
+    mtime_ns: u64,
+    sha256: String,
+}
+
+impl Entry {
+    fn matches(&self, meta: &Metadata) -> bool {
+        self.size == meta.len() && Some(self.mtime_ns) == mtime_ns(meta)
+    }
+}
+
+fn mtime_ns(meta: &Metadata) -> Option<u64> {
+    let d = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(d.as_nanos()).ok()
+}
+
+/// 文件 SHA-256 缓存，按大小 + mtime 判断是否失效，持久化到临时区的 checksums.json
+pub struct ChecksumCache {
+    file: PathBuf,
+    entries: Mutex<HashMap<PathBuf, Entry>>,
+    /// 启用落盘加密时按明文计算
+    vault: Option<Arc<Vault>>,
+}
+
+impl ChecksumCache {
+    /// 读取缓存文件，丢弃已删除或已修改的文件对应的记录
+    pub fn load(tmp_dir: &Path, vault: Option<Arc<Vault>>) -> Self {
+        let file = tmp_dir.join(CACHE_FILE);
+        let mut entries: HashMap<PathBuf, Entry> = std::fs::read(&file)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        entries.retain(|path, entry| {
+            std::fs::metadata(path).is_ok_and(|meta| entry.matches(&meta))
+        });
+        Self {
+            file,
+            entries: Mutex::new(entries),
+            vault,
+        }
+    }
+
+    /// 缓存命中时直接返回，否则在 blocking 池中计算并写入缓存
+    pub async fn sha256(&self, pool: &BlockingPool, path: &Path) -> Result<String, AppError> {
+        let meta = tokio::fs::metadata(path).await?;
+        if let Some(entry) = self.entries.lock().get(path) {
+            if entry.matches(&meta) {
+                return Ok(entry.sha256.clone());
+            }
+        }
+
+        let src = path.to_path_buf();
+        let vault = self.vault.clone();
+        let digest = pool
+            .run(move || match vault {
+                Some(vault) => hash::sha256_reader(vault.open(&src)?),
+                None => hash::sha256_file(&src),
+            })
+            .await??;
+
+        // 计算期间文件被改写时不缓存，下次重新计算
+        let after = tokio::fs::metadata(path).await?;
+        if after.len() == meta.len() && mtime_ns(&after) == mtime_ns(&meta) {
+            self.insert(path, &after, digest.clone()).await;
+        }
+        Ok(digest)
+    }
+
+    /// 记录已知的校验和（如上传时已校验过的文件）
+    pub async fn insert(&self, path: &Path, meta: &Metadata, sha256: String) {
+        let Some(mtime_ns) = mtime_ns(meta) else {
+            return;
+        };
+        let snapshot = {
+            let mut entries = self.entries.lock();
+            entries.insert(
+                path.to_path_buf(),
+                Entry {
+                    size: meta.len(),
+                    mtime_ns,
+                    sha256,
+                },
+            );
+            serde_json::to_vec(&*entries)
+        };
+        if let Ok(data) = snapshot {
+            if let Err(e) = self.persist(&data).await {
+                tracing::warn!(error = %e, "failed to persist checksum cache");
+            }
+        }
+    }
+
+    /// 先写临时文件再 rename，避免并发写入或中途退出留下半个 JSON
+    async fn persist(&self, data: &[u8]) -> std::io::Result<()> {
+        let tmp = self
+            .file
+            .with_extension(format!("{}.tmp", uuid::Uuid::new_v4().simple()));
+        tokio::fs::write(&tmp, data).await?;
+        tokio::fs::rename(&tmp, &self.file).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cache_invalidated_on_change() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("a.txt");
+        std::fs::write(&file, b"abc").unwrap();
+        let pool = BlockingPool::new(1);
+
+        let cache = ChecksumCache::load(dir.path(), None);
+        let first = cache.sha256(&pool, &file).await.unwrap();
+        assert!(first.starts_with("ba7816bf"));
+
+        // 重新加载后仍命中
+        let cache = ChecksumCache::load(dir.path(), None);
+        assert_eq!(cache.entries.lock().len(), 1);
+
+        std::fs::write(&file, b"abcd").unwrap();
+        let second = cache.sha256(&pool, &file).await.unwrap();
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::AppConfig;
+use crate::fs::path_safety::PathSafety;
+
+/// 上传文件名的 Unicode 规范化方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalization {
+    /// 统一为组合形式；macOS 传来的分解形式 (NFD) 与其他系统上的同名文件一致
+    #[default]
+    Nfc,
+    /// 保持客户端给出的原样
+    None,
+}
+
+/// Windows 上不能用作文件名主干的设备名
+const WINDOWS_RESERVED: &[&str] = &["CON", "PRN", "AUX", "NUL"];
+
+/// 所有上传入口共用的文件名规则
+#[derive(Debug, Clone, Copy)]
+pub struct FilenamePolicy {
+    normalization: Normalization,
+    /// 处理 Windows 保留名和结尾的点、空格
+    windows: bool,
+    /// 只有大小写不同的文件名视为同名
+    fold_case: bool,
+}
+
+impl FilenamePolicy {
+    /// tmp_dir 用于探测文件系统是否区分大小写，应与共享目录位于同一文件系统
+    pub fn new(config: &AppConfig, tmp_dir: &Path) -> Self {
+        let insensitive = is_case_insensitive(tmp_dir);
+        if insensitive && !config.case_insensitive_names {
+            tracing::info!("shared directory is case-insensitive; names differing only in case are treated as the same file");
+        }
+        Self {
+            normalization: config.filename_normalization,
+            windows: config.windows_safe_names || cfg!(windows),
+            fold_case: config.case_insensitive_names || insensitive,
+        }
+    }
+
+    /// 清洗单个文件名；结果为空时由调用方按无效文件名处理
+    pub fn sanitize(&self, name: &str) -> String {
+        let name: String = match self.normalization {
+            Normalization::Nfc => name.nfc().collect(),
+            Normalization::None => name.to_string(),
+        };
+        // 双向文本控制符能让 "gpj.exe" 显示成 "exe.jpg"
+        let name: String = name.chars().filter(|c| !is_bidi_control(*c)).collect();
+        // 保留名在下面单独处理，改名而不是整个删掉
+        let name = sanitize_filename::sanitize_with_options(
+            &name,
+            sanitize_filename::Options {
+                windows: false,
+                ..Default::default()
+            },
+        );
+        if !self.windows {
+            return name;
+        }
+        let name = name.trim_end_matches(['.', ' ']);
+        let stem = name.split('.').next().unwrap_or_default().trim_end();
+        if is_windows_reserved(stem) {
+            format!("_{}", name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// 客户端给出的相对路径 (含文件名) 逐段清洗后用 / 连接，丢弃空段、. 和 ..
+    pub fn relative(&self, relative: &str) -> String {
+        relative
+            .split(['/', '\\'])
+            .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+            .map(|s| self.sanitize(s))
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// 相对路径中的目录部分，与 relative 使用同样的规则
+    pub fn relative_dir(&self, relative: &str) -> PathBuf {
+        PathSafety::relative_dir(&self.relative(relative))
+    }
+
+    /// 目录中已有只差大小写的文件时返回它的名字，让冲突策略作用于这个文件
+    pub async fn existing_case(&self, dir: &Path, name: &str) -> Option<String> {
+        if !self.fold_case {
+            return None;
+        }
+        let lower = name.to_lowercase();
+        let mut entries = tokio::fs::read_dir(dir).await.ok()?;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let existing = entry.file_name().to_string_lossy().into_owned();
+            if existing != name && existing.nfc().collect::<String>().to_lowercase() == lower {
+                return Some(existing);
+            }
+        }
+        None
+    }
+}
+
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+fn is_windows_reserved(stem: &str) -> bool {
+    if WINDOWS_RESERVED.iter().any(|r| stem.eq_ignore_ascii_case(r)) {
+        return true;
+    }
+    // COM0-9、LPT0-9
+    let bytes = stem.as_bytes();
+    bytes.len() == 4
+        && (bytes[..3].eq_ignore_ascii_case(b"COM") || bytes[..3].eq_ignore_ascii_case(b"LPT"))
+        && bytes[3].is_ascii_digit()
+}
+
+/// 在目录中建一个探测文件，用大写的名字能访问到说明不区分大小写
+fn is_case_insensitive(dir: &Path) -> bool {
+    let probe = dir.join(format!("case-probe-{}", uuid::Uuid::new_v4().simple()));
+    if std::fs::write(&probe, b"").is_err() {
+        return false;
+    }
+    let upper = dir.join(probe.file_name().unwrap_or_default().to_string_lossy().to_uppercase());
+    let insensitive = upper.exists();
+    let _ = std::fs::remove_file(&probe);
+    insensitive
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(windows: bool) -> FilenamePolicy {
+        FilenamePolicy {
+            normalization: Normalization::Nfc,
+            windows,
+            fold_case: false,
+        }
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let p = policy(false);
+        // "é" 的分解形式
+        assert_eq!(p.sanitize("Cafe\u{301}.txt"), "Caf\u{e9}.txt");
+        assert_eq!(p.sanitize("a\u{202E}gpj.exe"), "agpj.exe");
+        assert_eq!(p.sanitize("tab\there.txt"), "tabhere.txt");
+        assert_eq!(p.sanitize("CON.txt"), "CON.txt");
+
+        let p = policy(true);
+        assert_eq!(p.sanitize("CON.txt"), "_CON.txt");
+        assert_eq!(p.sanitize("nul"), "_nul");
+        assert_eq!(p.sanitize("com1.tar.gz"), "_com1.tar.gz");
+        assert_eq!(p.sanitize("console.txt"), "console.txt");
+        assert_eq!(p.sanitize("notes. "), "notes");
+        assert_eq!(p.relative("../a/./aux/b.txt"), "a/_aux/b.txt");
+    }
+}
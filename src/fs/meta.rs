@@ -2,6 +2,8 @@ use std::path::Path;
 
 use serde::Serialize;
 
+use crate::fs::mode::{self, UnixAttrs};
+
 /// 文件元信息
 #[derive(Debug, Clone, Serialize)]
 pub struct FileMeta {
@@ -13,6 +15,9 @@ pub struct FileMeta {
     pub modified: Option<u64>,
     pub mime_type: Option<String>,
     pub extension: Option<String>,
+    /// 权限位和属主，仅 Unix
+    #[serde(flatten)]
+    pub unix: Option<UnixAttrs>,
 }
 
 impl FileMeta {
@@ -52,6 +57,7 @@ impl FileMeta {
             modified,
             mime_type,
             extension,
+            unix: mode::attrs(&metadata),
         })
     }
 }
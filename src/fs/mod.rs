@@ -1,4 +1,12 @@
+pub mod checksums;
+pub mod filename;
 pub mod meta;
+pub mod mode;
 pub mod operations;
 pub mod path_safety;
+pub mod permissions;
+pub mod sparse;
+pub mod trash;
+pub mod usage;
+pub mod vault;
 pub mod walker;
@@ -0,0 +1,164 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::error::AppError;
+
+/// 通过接口可以修改的权限位：只有 rwx，不允许设置 setuid / setgid / sticky
+const SETTABLE: u32 = 0o777;
+
+/// Unix 上的权限位和属主，列表中显示
+#[derive(Debug, Clone, Serialize)]
+pub struct UnixAttrs {
+    /// 权限位 (含 setuid / setgid / sticky)
+    pub mode: u32,
+    pub owner: String,
+    pub group: String,
+}
+
+#[cfg(unix)]
+pub fn attrs(meta: &std::fs::Metadata) -> Option<UnixAttrs> {
+    use std::os::unix::fs::MetadataExt;
+    Some(UnixAttrs {
+        mode: meta.mode() & 0o7777,
+        owner: names::user(meta.uid()),
+        group: names::group(meta.gid()),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn attrs(_meta: &std::fs::Metadata) -> Option<UnixAttrs> {
+    None
+}
+
+/// 修改权限位，返回修改后的值
+#[cfg(unix)]
+pub async fn chmod(path: &Path, spec: &str) -> Result<u32, AppError> {
+    use std::os::unix::fs::PermissionsExt;
+    let current = tokio::fs::metadata(path).await?.permissions().mode();
+    let mode = apply(current, spec)?;
+    tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).await?;
+    Ok(mode)
+}
+
+#[cfg(not(unix))]
+pub async fn chmod(_path: &Path, _spec: &str) -> Result<u32, AppError> {
+    Err(AppError::BadRequest("changing permissions is only supported on Unix".into()))
+}
+
+/// 按 chmod 的写法计算新的权限位：八进制 (`755`) 或符号形式 (`+x`、`u+x,go-w`、`a=r`)
+///
+/// 省略 ugoa 时作用于所有人；结果只保留 rwx，原有的 setuid 等特殊位会被清除
+pub fn apply(current: u32, spec: &str) -> Result<u32, AppError> {
+    let spec = spec.trim();
+    let invalid = || AppError::BadRequest(format!("invalid mode: {}", spec));
+    if !spec.is_empty() && spec.bytes().all(|b| b.is_ascii_digit()) {
+        let mode = u32::from_str_radix(spec, 8).map_err(|_| invalid())?;
+        if spec.len() > 4 || mode & !SETTABLE != 0 {
+            return Err(AppError::BadRequest(format!(
+                "invalid mode: {} (only rwx bits can be set)",
+                spec
+            )));
+        }
+        return Ok(mode);
+    }
+
+    let mut mode = current & SETTABLE;
+    for clause in spec.split(',') {
+        let op_at = clause.find(['+', '-', '=']).ok_or_else(invalid)?;
+        let (who, rest) = clause.split_at(op_at);
+        let (op, perms) = rest.split_at(1);
+
+        let mut who_mask = 0;
+        for c in who.chars() {
+            who_mask |= match c {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                'a' => 0o777,
+                _ => return Err(invalid()),
+            };
+        }
+        if who_mask == 0 {
+            who_mask = 0o777;
+        }
+        let mut perm_mask = 0;
+        for c in perms.chars() {
+            perm_mask |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                _ => return Err(invalid()),
+            };
+        }
+
+        let bits = who_mask & perm_mask;
+        mode = match op {
+            "+" => mode | bits,
+            "-" => mode & !bits,
+            _ => (mode & !who_mask) | bits,
+        };
+    }
+    Ok(mode)
+}
+
+/// uid / gid 到名称的映射，读取 /etc/passwd 和 /etc/group，找不到时显示数字
+#[cfg(unix)]
+mod names {
+    use std::collections::HashMap;
+    use std::sync::OnceLock;
+
+    pub fn user(uid: u32) -> String {
+        static USERS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+        lookup(&USERS, "/etc/passwd", uid)
+    }
+
+    pub fn group(gid: u32) -> String {
+        static GROUPS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+        lookup(&GROUPS, "/etc/group", gid)
+    }
+
+    /// 文件只在第一次用到时读取，之后新增的账户显示为数字
+    fn lookup(cache: &OnceLock<HashMap<u32, String>>, file: &str, id: u32) -> String {
+        cache
+            .get_or_init(|| parse(&std::fs::read_to_string(file).unwrap_or_default()))
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| id.to_string())
+    }
+
+    /// `name:x:id:...` 格式，passwd 和 group 相同
+    fn parse(content: &str) -> HashMap<u32, String> {
+        content
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split(':');
+                let name = fields.next()?;
+                let id = fields.nth(1)?.parse().ok()?;
+                Some((id, name.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_mode() {
+        assert_eq!(apply(0o644, "755").unwrap(), 0o755);
+        assert_eq!(apply(0o644, "0600").unwrap(), 0o600);
+        assert_eq!(apply(0o644, "+x").unwrap(), 0o755);
+        assert_eq!(apply(0o644, "u+x").unwrap(), 0o744);
+        assert_eq!(apply(0o777, "go-w,o-x").unwrap(), 0o754);
+        assert_eq!(apply(0o4755, "a=r").unwrap(), 0o444);
+        assert_eq!(apply(0o4755, "u-w").unwrap(), 0o555);
+
+        assert!(apply(0o644, "4755").is_err());
+        assert!(apply(0o644, "789").is_err());
+        assert!(apply(0o644, "u+s").is_err());
+        assert!(apply(0o644, "x").is_err());
+        assert!(apply(0o644, "").is_err());
+    }
+}
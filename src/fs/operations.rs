@@ -1,6 +1,22 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
 
 use crate::error::AppError;
+use crate::fs::sparse;
+
+/// 上传目标已存在时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// 另存为 `name (1).ext`、`name (2).ext` ...
+    #[default]
+    Rename,
+    /// 覆盖已有文件
+    Overwrite,
+    /// 拒绝上传 (409)
+    Reject,
+}
 
 /// 创建目录
 pub async fn mkdir(path: &Path) -> Result<(), AppError> {
@@ -8,18 +24,28 @@ pub async fn mkdir(path: &Path) -> Result<(), AppError> {
     Ok(())
 }
 
-/// 重命名文件/目录
+/// 重命名文件/目录；目标是否已存在由调用方检查
 pub async fn rename(from: &Path, to: &Path) -> Result<(), AppError> {
-    if to.exists() {
-        return Err(AppError::BadRequest(format!(
-            "target already exists: {}",
-            to.display()
-        )));
-    }
     tokio::fs::rename(from, to).await?;
     Ok(())
 }
 
+/// 两个路径是否指向同一个文件 (例如不区分大小写的文件系统上只差大小写)
+pub fn same_entry(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (std::fs::symlink_metadata(a), std::fs::symlink_metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+    }
+}
+
 /// 复制文件
 pub async fn copy_file(from: &Path, to: &Path) -> Result<(), AppError> {
     if to.exists() {
@@ -28,14 +54,44 @@ pub async fn copy_file(from: &Path, to: &Path) -> Result<(), AppError> {
             to.display()
         )));
     }
-    if from.is_dir() {
-        copy_dir_recursive(from, to).await?;
+    let copied = if from.is_dir() {
+        copy_dir_recursive(from, to).await
     } else {
-        tokio::fs::copy(from, to).await?;
+        let (from, to) = (from.to_path_buf(), to.to_path_buf());
+        tokio::task::spawn_blocking(move || clone_or_copy(&from, &to))
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("join: {}", e)))
+            .and_then(|r| r.map_err(AppError::from))
+    };
+    // 复制到一半失败 (磁盘满等) 时不留下不完整的副本
+    if copied.is_err() {
+        let _ = delete(to).await;
+    }
+    copied
+}
+
+/// 复制单个文件：支持时用 reflink 共享数据块（Btrfs/XFS/APFS 上近乎瞬间完成），
+/// 否则退化为 copy_contents
+fn clone_or_copy(from: &Path, to: &Path) -> std::io::Result<()> {
+    if reflink_copy::reflink(from, to).is_ok() {
+        tracing::debug!(from = %from.display(), "reflinked");
+        return Ok(());
     }
+    let bytes = copy_contents(from, to)?;
+    tracing::debug!(from = %from.display(), bytes, "copied");
     Ok(())
 }
 
+/// 逐字节复制：稀疏文件 (磁盘镜像等) 只复制数据段，保留空洞；
+/// 其他文件用 std::fs::copy（Linux 上为内核态的 copy_file_range）
+fn copy_contents(from: &Path, to: &Path) -> std::io::Result<u64> {
+    if sparse::is_sparse(&std::fs::metadata(from)?) {
+        sparse::copy(from, to)
+    } else {
+        std::fs::copy(from, to)
+    }
+}
+
 /// 移动文件/目录
 pub async fn move_entry(from: &Path, to: &Path) -> Result<(), AppError> {
     if to.exists() {
@@ -44,12 +100,34 @@ pub async fn move_entry(from: &Path, to: &Path) -> Result<(), AppError> {
             to.display()
         )));
     }
-    // 先尝试 rename（同文件系统），失败则 copy + delete
-    if tokio::fs::rename(from, to).await.is_err() {
-        copy_file(from, to).await?;
-        delete(from).await?;
+    // 同一文件系统内直接 rename；跨文件系统时先完整复制再删除源，复制失败时源保持不动
+    match tokio::fs::rename(from, to).await {
+        Ok(()) => Ok(()),
+        Err(e) if is_cross_device(&e) => {
+            tracing::debug!(from = %from.display(), to = %to.display(), "cross-device move, copying");
+            copy_file(from, to).await?;
+            delete(from).await
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// rename 因源和目标不在同一文件系统而失败
+fn is_cross_device(err: &std::io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        err.raw_os_error() == Some(libc::EXDEV)
+    }
+    // ERROR_NOT_SAME_DEVICE
+    #[cfg(windows)]
+    {
+        err.raw_os_error() == Some(17)
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = err;
+        false
     }
-    Ok(())
 }
 
 /// 删除文件或目录
@@ -62,6 +140,72 @@ pub async fn delete(path: &Path) -> Result<(), AppError> {
     Ok(())
 }
 
+/// 设置文件的修改时间 (Unix 秒)
+pub async fn set_modified(path: &Path, secs: u64) -> std::io::Result<()> {
+    let file = tokio::fs::File::options().write(true).open(path).await?;
+    let file = file.into_std().await;
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+    tokio::task::spawn_blocking(move || file.set_modified(time))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+/// 在 dir 下原子地占用一个不冲突的文件名，冲突时追加 " (n)" 后缀
+///
+/// 通过 create_new 创建空的占位文件，并发上传同名文件时不会选中同一个路径
+pub async fn reserve_unique(dir: &Path, name: &str) -> std::io::Result<PathBuf> {
+    let base = Path::new(name);
+    let stem = base
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let ext = base
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    for i in 0..10_000 {
+        let candidate = if i == 0 {
+            dir.join(name)
+        } else {
+            dir.join(format!("{} ({}){}", stem, i, ext))
+        };
+        match reserve_exact(&candidate).await {
+            Ok(()) => return Ok(candidate),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        format!("no free file name for {}", name),
+    ))
+}
+
+/// 占用确切的路径，已存在时返回 AlreadyExists
+pub async fn reserve_exact(path: &Path) -> std::io::Result<()> {
+    tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .await
+        .map(drop)
+}
+
+/// 用 from 替换 to（to 可以是占位文件或要覆盖的文件），rename 失败时复制后删除源文件
+pub async fn replace_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if tokio::fs::rename(from, to).await.is_err() {
+        // 跨文件系统无法 reflink；目标可能是已存在的占位文件，直接覆盖写
+        let (src, dst) = (from.to_path_buf(), to.to_path_buf());
+        tokio::task::spawn_blocking(move || copy_contents(&src, &dst))
+            .await
+            .map_err(std::io::Error::other)??;
+        tokio::fs::remove_file(from).await?;
+    }
+    Ok(())
+}
+
 /// 递归复制目录
 async fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), AppError> {
     tokio::fs::create_dir_all(dst).await?;
@@ -83,7 +227,7 @@ async fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), AppError> {
             if entry.file_type().is_dir() {
                 std::fs::create_dir_all(&target)?;
             } else {
-                std::fs::copy(entry.path(), &target)?;
+                clone_or_copy(entry.path(), &target)?;
             }
         }
         Ok(())
@@ -93,3 +237,31 @@ async fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), AppError> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reserve_unique_suffixes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let a = reserve_unique(dir.path(), "a.tar.gz").await.unwrap();
+        let b = reserve_unique(dir.path(), "a.tar.gz").await.unwrap();
+        let c = reserve_unique(dir.path(), "a.tar.gz").await.unwrap();
+        assert_eq!(a, dir.path().join("a.tar.gz"));
+        assert_eq!(b, dir.path().join("a.tar (1).gz"));
+        assert_eq!(c, dir.path().join("a.tar (2).gz"));
+        assert!(reserve_exact(&a).await.is_err());
+    }
+
+    #[test]
+    fn test_same_entry() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let a = dir.path().join("a.txt");
+        std::fs::write(&a, b"").unwrap();
+        std::fs::write(dir.path().join("b.txt"), b"").unwrap();
+        assert!(same_entry(&a, &dir.path().join("./a.txt")));
+        assert!(!same_entry(&a, &dir.path().join("b.txt")));
+        assert!(!same_entry(&a, &dir.path().join("c.txt")));
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 use crate::error::AppError;
 
@@ -6,11 +6,19 @@ use crate::error::AppError;
 #[derive(Debug, Clone)]
 pub struct PathSafety {
     root: PathBuf,
+    /// 位于 root 内、但不对外暴露的目录（上传临时区）
+    hidden: Option<PathBuf>,
 }
 
 impl PathSafety {
     pub fn new(root: PathBuf) -> Self {
-        Self { root }
+        Self { root, hidden: None }
+    }
+
+    /// 隐藏指定目录：resolve 拒绝访问其中的任何路径
+    pub fn with_hidden(mut self, dir: PathBuf) -> Self {
+        self.hidden = Some(dir);
+        self
     }
 
     pub fn root(&self) -> &Path {
@@ -33,13 +41,13 @@ impl PathSafety {
 
         // canonicalize 存在的路径（处理符号链接）
         let canonical = if full_path.exists() {
-            dunce::canonicalize(&full_path)
+            canonicalize(&full_path)
                 .map_err(|_| AppError::NotFound(decoded.clone()))?
         } else {
             // 对于不存在的路径，canonicalize 父目录
             if let Some(parent) = full_path.parent() {
                 if parent.exists() {
-                    let canonical_parent = dunce::canonicalize(parent)
+                    let canonical_parent = canonicalize(parent)
                         .map_err(|_| AppError::NotFound(decoded.clone()))?;
                     let file_name = full_path
                         .file_name()
@@ -58,9 +66,49 @@ impl PathSafety {
             return Err(AppError::PathTraversal);
         }
 
+        // 上传中的分片不允许被列出或下载
+        if self.is_hidden(&canonical) {
+            return Err(AppError::NotFound(decoded));
+        }
+
         Ok(canonical)
     }
 
+    /// 确认即将创建的路径仍在 root 内：沿最近的已存在祖先 canonicalize，防止经由符号链接写到外面
+    pub fn check_within(&self, path: &Path) -> Result<(), AppError> {
+        let existing = path
+            .ancestors()
+            .find(|p| p.exists())
+            .ok_or(AppError::PathTraversal)?;
+        let canonical = canonicalize(existing)?;
+        if !canonical.starts_with(&self.root) || self.is_hidden(&canonical) {
+            return Err(AppError::PathTraversal);
+        }
+        Ok(())
+    }
+
+    /// 客户端给出的相对路径（含文件名）中的目录部分，逐段清洗
+    ///
+    /// 同时接受 / 和 \ 分隔，丢弃空段、. 和 ..，不会跳出调用方给定的目录
+    pub fn relative_dir(relative: &str) -> PathBuf {
+        let mut parts: Vec<&str> = relative
+            .split(['/', '\\'])
+            .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+            .collect();
+        parts.pop();
+        parts
+            .into_iter()
+            .map(sanitize_filename::sanitize)
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    /// 路径是否位于隐藏目录内（含隐藏目录本身）
+    pub fn is_hidden(&self, path: &Path) -> bool {
+        self.is_transfer_tmp(path)
+            || self.hidden.as_ref().is_some_and(|h| path.starts_with(h))
+    }
+
     /// 检查路径是否是 .transfer-tmp 目录（listing 时跳过）
     pub fn is_transfer_tmp(&self, path: &Path) -> bool {
         path.file_name()
@@ -69,6 +117,30 @@ impl PathSafety {
     }
 }
 
+/// 规范化已存在的路径
+///
+/// Windows 上保留 `\\?\` 前缀（UNC 共享为 `\\?\UNC\server\share`），在它之上 join 出的
+/// 深层路径不受 MAX_PATH 限制；根目录、临时区和每个请求的路径都要经过这里，前缀一致才能用
+/// `starts_with` 比较
+pub fn canonicalize(path: &Path) -> std::io::Result<PathBuf> {
+    std::fs::canonicalize(path)
+}
+
+/// 相对 root 的路径，统一用 `/` 分隔；不在 root 内时返回完整路径
+pub fn relative_path(path: &Path, root: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(rel) => rel
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => dunce::simplified(path).to_string_lossy().into_owned(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,4 +190,36 @@ mod tests {
         assert!(safety.is_transfer_tmp(Path::new("/some/path/.transfer-tmp")));
         assert!(!safety.is_transfer_tmp(Path::new("/some/path/normal")));
     }
+
+    #[test]
+    fn test_relative_dir_sanitized() {
+        assert_eq!(
+            PathSafety::relative_dir("a/../b/./c\\d/file.txt"),
+            PathBuf::from("a/b/c/d")
+        );
+        assert_eq!(PathSafety::relative_dir("/../../file.txt"), PathBuf::new());
+        assert_eq!(PathSafety::relative_dir("file.txt"), PathBuf::new());
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let root = Path::new("/srv/share");
+        let nested: PathBuf = ["/srv/share", "a b", "c.txt"].iter().collect();
+        assert_eq!(relative_path(&nested, root), "a b/c.txt");
+        assert_eq!(relative_path(root, root), "");
+        assert_eq!(relative_path(Path::new("/etc/passwd"), root), "/etc/passwd");
+    }
+
+    #[test]
+    fn test_hidden_dir_not_resolvable() {
+        let dir = TempDir::new().unwrap();
+        let root = dunce::canonicalize(dir.path()).unwrap();
+        std::fs::create_dir_all(root.join("staging")).unwrap();
+        std::fs::write(root.join("staging/abc.part"), b"x").unwrap();
+        let safety = PathSafety::new(root.clone()).with_hidden(root.join("staging"));
+
+        assert!(matches!(safety.resolve("staging/abc.part"), Err(AppError::NotFound(_))));
+        assert!(matches!(safety.resolve("staging"), Err(AppError::NotFound(_))));
+        assert!(safety.resolve("").is_ok());
+    }
 }
@@ -0,0 +1,200 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::middleware::auth;
+
+/// 可以按路径授权的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    /// 列目录、下载、预览
+    Read,
+    /// 上传、新建目录、重命名、移入
+    Write,
+    /// 删除、移出
+    Delete,
+}
+
+/// 配置文件中的一条规则
+///
+/// ```toml
+/// [[rules]]
+/// path = "/incoming"
+/// allow = ["write"]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct PermissionRule {
+    /// 相对共享根目录的路径前缀
+    pub path: String,
+    pub allow: Vec<Access>,
+    /// 需要通过 Basic 认证才能访问（用户见 `[users]`）
+    #[serde(default)]
+    pub auth: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    prefix: PathBuf,
+    allow: Vec<Access>,
+    auth: bool,
+}
+
+/// 按路径前缀的访问控制，最长前缀生效；没有规则匹配时全部允许
+///
+/// 克隆共享同一组规则，重新加载配置后所有副本立即生效
+#[derive(Debug, Clone, Default)]
+pub struct Permissions {
+    rules: Arc<parking_lot::RwLock<Arc<[Rule]>>>,
+}
+
+impl Permissions {
+    pub fn new(root: &Path, rules: &[PermissionRule]) -> Self {
+        Self {
+            rules: Arc::new(parking_lot::RwLock::new(compile(root, rules))),
+        }
+    }
+
+    /// 替换全部规则，进行中的请求按替换前的规则完成检查
+    pub fn replace(&self, root: &Path, rules: &[PermissionRule]) {
+        *self.rules.write() = compile(root, rules);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.read().is_empty()
+    }
+
+    /// 检查当前请求能否对 abs 执行 access
+    pub fn check(&self, abs: &Path, access: Access) -> Result<(), AppError> {
+        let rules = self.rules.read().clone();
+        let Some(rule) = rule_for(&rules, abs) else {
+            return Ok(());
+        };
+        if rule.auth && auth::current_user().is_none() {
+            return Err(AppError::Unauthorized);
+        }
+        if !rule.allow.contains(&access) {
+            return Err(AppError::Forbidden(match access {
+                Access::Read => "read not allowed here",
+                Access::Write => "write not allowed here",
+                Access::Delete => "delete not allowed here",
+            }));
+        }
+        Ok(())
+    }
+
+    /// 检查能否对目录 dir 下的所有路径执行 access，用于读请求体之前提前拒绝；
+    /// dir 之下另有规则时结果可能因路径而异，这里放行，交给逐个路径的检查
+    pub fn check_subtree(&self, dir: &Path, access: Access) -> Result<(), AppError> {
+        let nested = self
+            .rules
+            .read()
+            .iter()
+            .any(|r| r.prefix.starts_with(dir) && r.prefix != dir);
+        if nested {
+            return Ok(());
+        }
+        self.check(dir, access)
+    }
+
+    /// 不依赖请求上下文的判断，用于后台线程中过滤遍历结果
+    pub fn permits(&self, abs: &Path, access: Access, authenticated: bool) -> bool {
+        let rules = self.rules.read().clone();
+        match rule_for(&rules, abs) {
+            Some(rule) => (!rule.auth || authenticated) && rule.allow.contains(&access),
+            None => true,
+        }
+    }
+}
+
+fn compile(root: &Path, rules: &[PermissionRule]) -> Arc<[Rule]> {
+    let mut rules: Vec<Rule> = rules
+        .iter()
+        .map(|r| Rule {
+            prefix: r
+                .path
+                .split('/')
+                .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+                .fold(root.to_path_buf(), |p, s| p.join(s)),
+            allow: r.allow.clone(),
+            auth: r.auth,
+        })
+        .collect();
+    // 前缀越长越靠前，查找时取第一个匹配
+    rules.sort_by_key(|r| std::cmp::Reverse(r.prefix.components().count()));
+    rules.into()
+}
+
+fn rule_for<'a>(rules: &'a [Rule], abs: &Path) -> Option<&'a Rule> {
+    rules.iter().find(|r| abs.starts_with(&r.prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(path: &str, allow: &[Access], auth: bool) -> PermissionRule {
+        PermissionRule {
+            path: path.into(),
+            allow: allow.to_vec(),
+            auth,
+        }
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let root = Path::new("/srv");
+        let perms = Permissions::new(
+            root,
+            &[
+                rule("/public", &[Access::Read], false),
+                rule("/public/inbox", &[Access::Write], false),
+            ],
+        );
+        assert!(perms.permits(&root.join("public/a.txt"), Access::Read, false));
+        assert!(!perms.permits(&root.join("public/a.txt"), Access::Write, false));
+        assert!(perms.permits(&root.join("public/inbox/x"), Access::Write, false));
+        assert!(!perms.permits(&root.join("public/inbox/x"), Access::Read, false));
+        // 不匹配任何规则时不限制
+        assert!(perms.permits(&root.join("other"), Access::Delete, false));
+        // 按路径组件匹配，不是字符串前缀
+        assert!(perms.permits(&root.join("publication"), Access::Write, false));
+    }
+
+    #[test]
+    fn test_auth_rule() {
+        let root = Path::new("/srv");
+        let perms = Permissions::new(root, &[rule("staff", &[Access::Read], true)]);
+        assert!(!perms.permits(&root.join("staff/doc"), Access::Read, false));
+        assert!(perms.permits(&root.join("staff/doc"), Access::Read, true));
+    }
+
+    #[test]
+    fn test_check_subtree() {
+        let root = Path::new("/srv");
+        let perms = Permissions::new(
+            root,
+            &[
+                rule("/public", &[Access::Read], false),
+                rule("/public/inbox", &[Access::Write], false),
+            ],
+        );
+        // 下面还有可写的目录，不能整体拒绝
+        assert!(perms.check_subtree(&root.join("public"), Access::Write).is_ok());
+        assert!(perms.check_subtree(&root.join("public/docs"), Access::Write).is_err());
+        assert!(perms.check_subtree(&root.join("public/inbox"), Access::Write).is_ok());
+    }
+
+    #[test]
+    fn test_replace_applies_to_clones() {
+        let root = Path::new("/srv");
+        let perms = Permissions::new(root, &[]);
+        let snapshot = perms.clone();
+        perms.replace(root, &[rule("/", &[Access::Read], false)]);
+        assert!(!snapshot.permits(&root.join("a"), Access::Write, false));
+        perms.replace(root, &[]);
+        assert!(snapshot.is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+use std::fs::{File, Metadata, OpenOptions};
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+/// 复制时每次读写的大小
+const COPY_BUF: usize = 1024 * 1024;
+
+/// 实际占用的磁盘空间明显小于文件长度，说明文件中有空洞
+#[cfg(unix)]
+pub fn is_sparse(meta: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    meta.is_file() && meta.blocks().saturating_mul(512) < meta.len()
+}
+
+#[cfg(not(unix))]
+pub fn is_sparse(_meta: &Metadata) -> bool {
+    false
+}
+
+/// 文件中含有数据的区间，空洞之外的部分
+///
+/// 文件系统不支持 SEEK_DATA / SEEK_HOLE 时把整个文件当作一个数据段
+pub fn data_extents(file: &File) -> io::Result<Vec<Range<u64>>> {
+    let len = file.metadata()?.len();
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "macos"))]
+    if let Some(extents) = seek_extents(file, len)? {
+        return Ok(extents);
+    }
+    Ok((len > 0).then_some(0..len).into_iter().collect())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "macos"))]
+fn seek_extents(file: &File, len: u64) -> io::Result<Option<Vec<Range<u64>>>> {
+    use std::os::fd::AsRawFd;
+
+    let seek = |offset: u64, whence: libc::c_int| -> io::Result<Option<u64>> {
+        // SAFETY: 只对自己持有的 fd 调用 lseek，不涉及内存
+        let r = unsafe { libc::lseek(file.as_raw_fd(), offset as libc::off_t, whence) };
+        if r >= 0 {
+            return Ok(Some(r as u64));
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            // 之后再没有数据
+            Some(libc::ENXIO) => Ok(None),
+            _ => Err(err),
+        }
+    };
+
+    let mut extents = Vec::new();
+    let mut offset = 0;
+    while offset < len {
+        let start = match seek(offset, libc::SEEK_DATA) {
+            Ok(Some(start)) => start,
+            Ok(None) => break,
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let end = seek(start, libc::SEEK_HOLE)?.unwrap_or(len).min(len);
+        if end <= start {
+            break;
+        }
+        extents.push(start..end);
+        offset = end;
+    }
+    Ok(Some(extents))
+}
+
+/// 只复制数据段，空洞在目标文件中保持为空洞；目标已存在时覆盖
+pub fn copy(from: &Path, to: &Path) -> io::Result<u64> {
+    let src = File::open(from)?;
+    let len = src.metadata()?.len();
+    let dst = OpenOptions::new().write(true).create(true).truncate(true).open(to)?;
+
+    let mut buf = vec![0u8; COPY_BUF];
+    for extent in data_extents(&src)? {
+        let mut offset = extent.start;
+        while offset < extent.end {
+            let n = (extent.end - offset).min(COPY_BUF as u64) as usize;
+            read_exact_at(&src, &mut buf[..n], offset)?;
+            write_all_at(&dst, &buf[..n], offset)?;
+            offset += n as u64;
+        }
+    }
+    // 末尾的空洞靠 set_len 补齐长度
+    dst.set_len(len)?;
+    dst.set_permissions(src.metadata()?.permissions())?;
+    Ok(len)
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(unix)]
+fn write_all_at(file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn write_all_at(file: &File, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        let n = file.seek_write(buf, offset)?;
+        buf = &buf[n..];
+        offset += n as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Seek, SeekFrom, Write};
+
+    #[test]
+    fn test_copy_keeps_holes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let src = dir.path().join("disk.img");
+        let mut f = File::create(&src).unwrap();
+        f.seek(SeekFrom::Start(8 << 20)).unwrap();
+        f.write_all(b"middle").unwrap();
+        f.set_len(32 << 20).unwrap();
+        drop(f);
+
+        let dst = dir.path().join("copy.img");
+        assert_eq!(copy(&src, &dst).unwrap(), 32 << 20);
+        let data = std::fs::read(&dst).unwrap();
+        assert_eq!(data.len(), 32 << 20);
+        assert_eq!(&data[8 << 20..(8 << 20) + 6], b"middle");
+        assert!(data[..8 << 20].iter().all(|&b| b == 0));
+
+        // 支持空洞的文件系统上副本同样是稀疏的
+        let src_meta = std::fs::metadata(&src).unwrap();
+        if is_sparse(&src_meta) {
+            assert!(is_sparse(&std::fs::metadata(&dst).unwrap()));
+        }
+        let extents = data_extents(&File::open(&dst).unwrap()).unwrap();
+        assert!(extents.iter().any(|e| e.contains(&(8 << 20))));
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use crate::db::trash::TrashItem;
+use crate::db::{now_secs, Db};
+use crate::error::AppError;
+use crate::fs::operations;
+
+/// 回收站：删除的文件移到临时区的 trash 目录下，以数据库中的 ID 命名，原位置记录在数据库中
+pub struct Trash {
+    dir: PathBuf,
+    db: Db,
+    /// 保留天数，0 表示不使用回收站
+    days: u64,
+    /// 删除后可以撤销的时长，0 表示不提供撤销
+    undo_window: Duration,
+    /// 撤销令牌 → 同一次删除移进回收站的条目
+    undo: Mutex<HashMap<String, Undo>>,
+}
+
+struct Undo {
+    ids: Vec<i64>,
+    expires: Instant,
+}
+
+impl Trash {
+    pub fn new(dir: PathBuf, db: Db, days: u64, undo_window: Duration) -> Self {
+        Self {
+            dir,
+            db,
+            days,
+            undo_window,
+            undo: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 是否启用回收站；未启用时删除的文件只保留到撤销期结束
+    pub fn is_enabled(&self) -> bool {
+        self.days > 0
+    }
+
+    /// 删除时先移进回收站，而不是直接删掉
+    pub fn keeps_deleted(&self) -> bool {
+        self.is_enabled() || !self.undo_window.is_zero()
+    }
+
+    pub fn undo_window(&self) -> Duration {
+        self.undo_window
+    }
+
+    /// 条目在回收站中保留的时长
+    fn retention(&self) -> Duration {
+        if self.is_enabled() {
+            Duration::from_secs(self.days * 24 * 3600)
+        } else {
+            self.undo_window
+        }
+    }
+
+    /// 为一次删除登记撤销令牌，不提供撤销时返回 None
+    pub fn register_undo(&self, ids: Vec<i64>) -> Option<String> {
+        if self.undo_window.is_zero() || ids.is_empty() {
+            return None;
+        }
+        let now = Instant::now();
+        let mut undo = self.undo.lock();
+        undo.retain(|_, u| u.expires > now);
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        undo.insert(
+            token.clone(),
+            Undo {
+                ids,
+                expires: now + self.undo_window,
+            },
+        );
+        Some(token)
+    }
+
+    /// 取出未过期的令牌对应的条目，每个令牌只能用一次
+    pub fn take_undo(&self, token: &str) -> Option<Vec<i64>> {
+        let undo = self.undo.lock().remove(token)?;
+        (undo.expires > Instant::now()).then_some(undo.ids)
+    }
+
+    /// 未启用回收站时，撤销期结束后彻底删除这些条目；已经撤销的会跳过
+    pub async fn purge_after_undo(&self, ids: Vec<i64>) {
+        // 多等一会儿，不和刚好在期限前到达的撤销请求抢同一个文件
+        tokio::time::sleep(self.undo_window + Duration::from_secs(5)).await;
+        for id in ids {
+            if let Err(e) = self.purge(id).await {
+                tracing::warn!(id, error = %e, "failed to purge deleted entry");
+            }
+        }
+    }
+
+    /// 条目在回收站中的实际位置
+    fn stored(&self, id: i64) -> PathBuf {
+        self.dir.join(id.to_string())
+    }
+
+    /// 把 abs 移进回收站；relative 是它相对 root 的路径，恢复时放回这里
+    pub async fn put(
+        &self,
+        abs: &Path,
+        relative: String,
+        user: Option<String>,
+        client_ip: Option<IpAddr>,
+    ) -> Result<TrashItem, AppError> {
+        let is_dir = abs.is_dir();
+        let size = {
+            let abs = abs.to_path_buf();
+            tokio::task::spawn_blocking(move || entry_size(&abs))
+                .await
+                .map_err(|e| AppError::Internal(anyhow::anyhow!("join: {}", e)))?
+        };
+        // 先占用 ID 再移动，回收站中的文件总有对应的记录
+        let item = self.db.insert_trash(relative, is_dir, size, user, client_ip).await?;
+        let moved = async {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            operations::move_entry(abs, &self.stored(item.id)).await
+        };
+        if let Err(e) = moved.await {
+            let _ = self.db.delete_trash(item.id).await;
+            return Err(e);
+        }
+        Ok(item)
+    }
+
+    /// 把条目移回 to，调用方负责检查目标位置
+    pub async fn restore(&self, item: &TrashItem, to: &Path) -> Result<(), AppError> {
+        let stored = self.stored(item.id);
+        if !stored.exists() {
+            self.db.delete_trash(item.id).await?;
+            return Err(AppError::NotFound(item.path.clone()));
+        }
+        operations::move_entry(&stored, to).await?;
+        self.db.delete_trash(item.id).await?;
+        Ok(())
+    }
+
+    /// 彻底删除一项
+    pub async fn purge(&self, id: i64) -> Result<(), AppError> {
+        let stored = self.stored(id);
+        if stored.exists() || stored.is_symlink() {
+            operations::delete(&stored).await?;
+        }
+        self.db.delete_trash(id).await?;
+        Ok(())
+    }
+
+    /// 清除超过保留期的条目，以及没有记录的文件 (如异常退出留下的)，返回清除的数量
+    pub async fn expire(&self) -> Result<usize, AppError> {
+        if !self.keeps_deleted() {
+            return Ok(0);
+        }
+        let before = now_secs().saturating_sub(self.retention().as_secs());
+        let expired = self.db.expired_trash(before).await?;
+        for item in &expired {
+            self.purge(item.id).await?;
+        }
+        let mut removed = expired.len();
+
+        // 先列目录再查记录：移入回收站前已经写好记录，这时目录中的文件都应该能查到
+        let mut stored = Vec::new();
+        if let Ok(mut entries) = tokio::fs::read_dir(&self.dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                stored.push(entry);
+            }
+        }
+        let known: HashSet<String> = self
+            .db
+            .list_trash()
+            .await?
+            .iter()
+            .map(|item| item.id.to_string())
+            .collect();
+        for entry in stored {
+            if known.contains(&*entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            // 同时被恢复或清除的条目已经不在了
+            match operations::delete(&entry.path()).await {
+                Ok(()) => removed += 1,
+                Err(e) => tracing::debug!(path = %entry.path().display(), error = %e, "cannot remove stray trash entry"),
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// 文件的大小，或目录中所有文件的大小之和
+fn entry_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_put_restore_purge() {
+        let root = tempfile::TempDir::new().unwrap();
+        let trash = Trash::new(
+            root.path().join(".trash"),
+            Db::open_in_memory().unwrap(),
+            30,
+            Duration::ZERO,
+        );
+        let dir = root.path().join("docs");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), b"hello").unwrap();
+
+        let item = trash.put(&dir, "docs".into(), None, None).await.unwrap();
+        assert!(item.is_dir);
+        assert_eq!(item.size, 5);
+        assert!(!dir.exists());
+
+        trash.restore(&item, &dir).await.unwrap();
+        assert_eq!(std::fs::read(dir.join("a.txt")).unwrap(), b"hello");
+        assert!(trash.db.list_trash().await.unwrap().is_empty());
+
+        let item = trash.put(&dir.join("a.txt"), "docs/a.txt".into(), None, None).await.unwrap();
+        // 没有记录的文件在过期清理时一并删除
+        std::fs::write(root.path().join(".trash/stray"), b"").unwrap();
+        assert_eq!(trash.expire().await.unwrap(), 1);
+        trash.purge(item.id).await.unwrap();
+        assert_eq!(std::fs::read_dir(root.path().join(".trash")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_undo_token() {
+        let db = Db::open_in_memory().unwrap();
+        let trash = Trash::new(PathBuf::from("trash"), db.clone(), 0, Duration::from_secs(30));
+        assert!(trash.keeps_deleted());
+        assert_eq!(trash.register_undo(Vec::new()), None);
+        let token = trash.register_undo(vec![1, 2]).unwrap();
+        assert_eq!(trash.take_undo(&token), Some(vec![1, 2]));
+        // 只能用一次
+        assert_eq!(trash.take_undo(&token), None);
+
+        let trash = Trash::new(PathBuf::from("trash"), db, 0, Duration::ZERO);
+        assert!(!trash.keeps_deleted());
+        assert_eq!(trash.register_undo(vec![1]), None);
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+use serde::Serialize;
+
+use crate::state::AppState;
+
+/// 目录占用统计树，只记录目录，文件大小汇总到所在目录
+#[derive(Debug, Default)]
+pub struct DirNode {
+    /// 含子目录的总大小
+    pub size: u64,
+    /// 含子目录的文件数
+    pub files: u64,
+    /// 直接位于该目录下的文件大小
+    pub own_size: u64,
+    pub own_files: u64,
+    pub children: BTreeMap<String, DirNode>,
+}
+
+impl DirNode {
+    /// 按 `a/b/c` 查找子目录
+    pub fn find(&self, rel: &str) -> Option<&DirNode> {
+        rel.split('/')
+            .filter(|s| !s.is_empty())
+            .try_fold(self, |node, name| node.children.get(name))
+    }
+}
+
+/// 一次完整扫描的结果
+pub struct UsageReport {
+    pub root: DirNode,
+    /// 扫描完成时间 (unix 秒)
+    pub scanned_at: u64,
+    /// 扫描耗时 (毫秒)
+    pub elapsed_ms: u64,
+}
+
+/// 单个子目录的占用
+#[derive(Debug, Serialize)]
+pub struct UsageEntry {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    pub files: u64,
+}
+
+/// 后台扫描共享目录的磁盘占用，结果缓存在内存中
+pub struct UsageScanner {
+    root: PathBuf,
+    /// 扫描时跳过的目录（上传临时区）
+    skip: PathBuf,
+    report: RwLock<Option<Arc<UsageReport>>>,
+    scanning: Arc<AtomicBool>,
+}
+
+impl UsageScanner {
+    pub fn new(root: PathBuf, skip: PathBuf) -> Self {
+        Self {
+            root,
+            skip,
+            report: RwLock::new(None),
+            scanning: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn report(&self) -> Option<Arc<UsageReport>> {
+        self.report.read().clone()
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.scanning.load(Ordering::Relaxed)
+    }
+
+    /// 执行一次扫描；已有扫描在进行时直接返回 false
+    pub async fn rescan(&self) -> bool {
+        if self.scanning.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        let _guard = ScanGuard(self.scanning.clone());
+
+        let (root, skip) = (self.root.clone(), self.skip.clone());
+        let started = std::time::Instant::now();
+        let result = tokio::task::spawn_blocking(move || scan_dir(&root, &skip)).await;
+        match result {
+            Ok(node) => {
+                let elapsed_ms = started.elapsed().as_millis() as u64;
+                tracing::info!(
+                    size = node.size,
+                    files = node.files,
+                    elapsed_ms,
+                    "usage scan finished"
+                );
+                *self.report.write() = Some(Arc::new(UsageReport {
+                    root: node,
+                    scanned_at: now_secs(),
+                    elapsed_ms,
+                }));
+            }
+            Err(e) => tracing::warn!(error = %e, "usage scan failed"),
+        }
+        true
+    }
+}
+
+/// 扫描结束（包括 panic）时复位标记
+struct ScanGuard(Arc<AtomicBool>);
+
+impl Drop for ScanGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// 子目录按大小降序
+pub fn sorted_children(node: &DirNode, base: &str) -> Vec<UsageEntry> {
+    let mut entries: Vec<UsageEntry> = node
+        .children
+        .iter()
+        .map(|(name, child)| UsageEntry {
+            name: name.clone(),
+            path: if base.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", base, name)
+            },
+            size: child.size,
+            files: child.files,
+        })
+        .collect();
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
+
+/// 启动时扫描一次，之后按间隔刷新；interval 为 0 时只在手动触发时重新扫描
+pub fn spawn(state: AppState) {
+    let interval = state.config.usage_scan_interval;
+    tokio::spawn(async move {
+        state.usage.rescan().await;
+        if interval == 0 {
+            return;
+        }
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            state.usage.rescan().await;
+        }
+    });
+}
+
+/// 递归统计目录；不跟随符号链接，跳过无权限的目录
+fn scan_dir(dir: &Path, skip: &Path) -> DirNode {
+    let mut node = DirNode::default();
+    let Ok(read) = std::fs::read_dir(dir) else {
+        return node;
+    };
+
+    for entry in read.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if path == skip || name == ".transfer-tmp" {
+            continue;
+        }
+        if file_type.is_dir() {
+            let child = scan_dir(&path, skip);
+            node.size += child.size;
+            node.files += child.files;
+            node.children.insert(name, child);
+        } else if file_type.is_file() {
+            let len = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            node.size += len;
+            node.files += 1;
+            node.own_size += len;
+            node.own_files += 1;
+        }
+    }
+    node
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,438 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// 加密文件的格式标识
+const MAGIC: &[u8; 8] = b"TAVAULT1";
+/// 明文按段加密，每段可单独解密，Range 请求只需读取涉及的段
+const SEGMENT: u64 = 64 * 1024;
+const TAG_LEN: u64 = 16;
+const KEY_LEN: usize = 32;
+/// magic + 明文大小 + 包装文件密钥的 nonce + 包装后的文件密钥
+const HEADER_LEN: usize = MAGIC.len() + 8 + NONCE_LEN + KEY_LEN + TAG_LEN as usize;
+/// 下载时每次读取的段数
+const STREAM_SEGMENTS: u64 = 4;
+
+/// 落盘加密：每个文件使用随机生成的 AES-256-GCM 密钥，文件密钥由主密钥加密后写在文件头部
+///
+/// 不是加密格式的文件（启用前已存在、或由其他程序放入的文件）按原样读取
+pub struct Vault {
+    master: LessSafeKey,
+    rng: SystemRandom,
+}
+
+/// 已解开的文件头
+struct Header {
+    size: u64,
+    key: LessSafeKey,
+}
+
+impl Vault {
+    /// 读取主密钥文件 (64 位 hex)，文件不存在时生成新密钥
+    pub fn load_or_create(path: &Path) -> anyhow::Result<Self> {
+        let rng = SystemRandom::new();
+        let key = match std::fs::read_to_string(path) {
+            Ok(text) => hex::decode(text.trim())
+                .ok()
+                .filter(|key| key.len() == KEY_LEN)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("{}: expected {} hex-encoded bytes", path.display(), KEY_LEN)
+                })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let mut key = vec![0u8; KEY_LEN];
+                rng.fill(&mut key)
+                    .map_err(|_| anyhow::anyhow!("cannot generate encryption key"))?;
+                write_key_file(path, &hex::encode(&key))?;
+                tracing::warn!(
+                    path = %path.display(),
+                    "generated new encryption key; back it up, encrypted files cannot be read without it"
+                );
+                key
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self::from_key(&key, rng))
+    }
+
+    fn from_key(key: &[u8], rng: SystemRandom) -> Self {
+        let master = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).expect("key length"));
+        Self { master, rng }
+    }
+
+    /// 把明文文件 src 加密写入 dst（同步，需放在 blocking 线程执行）
+    pub fn seal(&self, src: &Path, dst: &Path) -> io::Result<()> {
+        let mut input = io::BufReader::with_capacity(1024 * 1024, std::fs::File::open(src)?);
+        let size = input.get_ref().metadata()?.len();
+
+        let mut file_key = [0u8; KEY_LEN];
+        let mut wrap_nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut file_key).map_err(|_| crypto_error())?;
+        self.rng.fill(&mut wrap_nonce).map_err(|_| crypto_error())?;
+
+        // 明文大小作为包装文件密钥时的附加数据，被截断或篡改的文件无法解开
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&size.to_le_bytes());
+        let mut wrapped = file_key.to_vec();
+        self.master
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(wrap_nonce),
+                Aad::from(&header[..]),
+                &mut wrapped,
+            )
+            .map_err(|_| crypto_error())?;
+        header.extend_from_slice(&wrap_nonce);
+        header.extend_from_slice(&wrapped);
+
+        let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &file_key).map_err(|_| crypto_error())?);
+        let mut out = io::BufWriter::with_capacity(1024 * 1024, std::fs::File::create(dst)?);
+        out.write_all(&header)?;
+        let mut buf = Vec::with_capacity((SEGMENT + TAG_LEN) as usize);
+        for index in 0..size.div_ceil(SEGMENT) {
+            let len = (size - index * SEGMENT).min(SEGMENT) as usize;
+            buf.resize(len, 0);
+            input.read_exact(&mut buf)?;
+            key.seal_in_place_append_tag(segment_nonce(index), Aad::empty(), &mut buf)
+                .map_err(|_| crypto_error())?;
+            out.write_all(&buf)?;
+        }
+        out.into_inner().map_err(|e| e.into_error())?.sync_data()
+    }
+
+    /// 打开文件读取明文；不是加密格式的文件按原样读取（同步）
+    pub fn open(&self, path: &Path) -> io::Result<Content> {
+        let mut file = std::fs::File::open(path)?;
+        let mut buf = [0u8; HEADER_LEN];
+        let header = match file.read_exact(&mut buf) {
+            Ok(()) => self.parse_header(&buf)?,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => return Err(e),
+        };
+        match header {
+            Some(header) => Ok(Content::Sealed(Box::new(SealedReader {
+                file,
+                header,
+                pos: 0,
+                segment: None,
+                buf: Vec::new(),
+            }))),
+            None => {
+                file.rewind()?;
+                Ok(Content::Plain(file))
+            }
+        }
+    }
+
+    /// 打开加密文件用于流式下载；不是加密格式时返回 None
+    pub async fn open_sealed(&self, path: &Path) -> io::Result<Option<SealedFile>> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut buf = [0u8; HEADER_LEN];
+        match file.read_exact(&mut buf).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        Ok(self
+            .parse_header(&buf)?
+            .map(|header| SealedFile { file, header }))
+    }
+
+    /// 读取整个文件的明文；不是加密格式的文件按原样读取
+    pub async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.open_sealed(path).await? {
+            Some(sealed) => {
+                let size = sealed.size();
+                let mut out = Vec::with_capacity(size as usize);
+                let mut stream = std::pin::pin!(sealed.into_stream(0, size));
+                while let Some(chunk) = stream.next().await {
+                    out.extend_from_slice(&chunk?);
+                }
+                Ok(out)
+            }
+            None => tokio::fs::read(path).await,
+        }
+    }
+
+    /// 加密文件的明文大小；不是加密格式时返回 None
+    pub async fn sealed_size(&self, path: &Path) -> io::Result<Option<u64>> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut buf = [0u8; MAGIC.len() + 8];
+        match file.read_exact(&mut buf).await {
+            Ok(_) if buf.starts_with(MAGIC) => Ok(Some(u64::from_le_bytes(
+                buf[MAGIC.len()..].try_into().unwrap(),
+            ))),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn parse_header(&self, buf: &[u8; HEADER_LEN]) -> io::Result<Option<Header>> {
+        if !buf.starts_with(MAGIC) {
+            return Ok(None);
+        }
+        let (aad, rest) = buf.split_at(MAGIC.len() + 8);
+        let (nonce, wrapped) = rest.split_at(NONCE_LEN);
+        let mut wrapped = wrapped.to_vec();
+        let file_key = self
+            .master
+            .open_in_place(
+                Nonce::try_assume_unique_for_key(nonce).map_err(|_| crypto_error())?,
+                Aad::from(aad),
+                &mut wrapped,
+            )
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "cannot decrypt file key (wrong encryption key or corrupt file)",
+                )
+            })?;
+        let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, file_key).map_err(|_| crypto_error())?);
+        Ok(Some(Header {
+            size: u64::from_le_bytes(aad[MAGIC.len()..].try_into().unwrap()),
+            key,
+        }))
+    }
+}
+
+/// 列表、校验等接口展示的文件大小：加密文件取明文大小，其余为磁盘上的大小
+pub async fn content_len(vault: Option<&Vault>, path: &Path, len: u64) -> u64 {
+    match vault {
+        Some(vault) => vault.sealed_size(path).await.ok().flatten().unwrap_or(len),
+        None => len,
+    }
+}
+
+impl Header {
+    /// 第 index 段密文在文件中的偏移和长度
+    fn segment_range(&self, index: u64) -> (u64, usize) {
+        let offset = HEADER_LEN as u64 + index * (SEGMENT + TAG_LEN);
+        let len = (self.size - index * SEGMENT).min(SEGMENT) + TAG_LEN;
+        (offset, len as usize)
+    }
+
+    /// 原地解密一段，buf 由密文变为明文
+    fn open_segment(&self, index: u64, buf: &mut Vec<u8>) -> io::Result<()> {
+        let len = self
+            .key
+            .open_in_place(segment_nonce(index), Aad::empty(), buf)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "corrupt encrypted segment"))?
+            .len();
+        buf.truncate(len);
+        Ok(())
+    }
+}
+
+fn segment_nonce(index: u64) -> Nonce {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[NONCE_LEN - 8..].copy_from_slice(&index.to_be_bytes());
+    Nonce::assume_unique_for_key(nonce)
+}
+
+fn crypto_error() -> io::Error {
+    io::Error::other("encryption failed")
+}
+
+#[cfg(unix)]
+fn write_key_file(path: &Path, key: &str) -> io::Result<()> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    writeln!(file, "{}", key)
+}
+
+#[cfg(not(unix))]
+fn write_key_file(path: &Path, key: &str) -> io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    writeln!(file, "{}", key)
+}
+
+/// 文件的明文内容，支持随机读取
+pub enum Content {
+    Plain(std::fs::File),
+    Sealed(Box<SealedReader>),
+}
+
+impl Content {
+    /// 明文大小
+    pub fn size(&self) -> io::Result<u64> {
+        match self {
+            Content::Plain(file) => Ok(file.metadata()?.len()),
+            Content::Sealed(reader) => Ok(reader.header.size),
+        }
+    }
+}
+
+impl Read for Content {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Content::Plain(file) => file.read(out),
+            Content::Sealed(reader) => reader.read(out),
+        }
+    }
+}
+
+impl Seek for Content {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Content::Plain(file) => file.seek(pos),
+            Content::Sealed(reader) => reader.seek(pos),
+        }
+    }
+}
+
+/// 加密文件的同步读取器，按段解密并缓存当前段
+pub struct SealedReader {
+    file: std::fs::File,
+    header: Header,
+    pos: u64,
+    /// buf 中已解密的段号
+    segment: Option<u64>,
+    buf: Vec<u8>,
+}
+
+impl Read for SealedReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.header.size || out.is_empty() {
+            return Ok(0);
+        }
+        let index = self.pos / SEGMENT;
+        if self.segment != Some(index) {
+            let (offset, len) = self.header.segment_range(index);
+            self.segment = None;
+            self.buf.resize(len, 0);
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.file.read_exact(&mut self.buf)?;
+            self.header.open_segment(index, &mut self.buf)?;
+            self.segment = Some(index);
+        }
+        let start = (self.pos - index * SEGMENT) as usize;
+        let n = out.len().min(self.buf.len() - start);
+        out[..n].copy_from_slice(&self.buf[start..start + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for SealedReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.header.size.checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+        self.pos = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative position")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+/// 用于流式下载的加密文件
+pub struct SealedFile {
+    file: tokio::fs::File,
+    header: Header,
+}
+
+impl SealedFile {
+    /// 明文大小
+    pub fn size(&self) -> u64 {
+        self.header.size
+    }
+
+    /// 从明文偏移 start 开始输出 len 字节，只读取并解密涉及的段
+    pub fn into_stream(self, start: u64, len: u64) -> impl Stream<Item = io::Result<Bytes>> + Send {
+        let end = (start + len).min(self.header.size);
+        futures_util::stream::try_unfold(
+            (self, start, false),
+            move |(mut sealed, pos, positioned)| async move {
+                if pos >= end {
+                    return Ok(None);
+                }
+                let first = pos / SEGMENT;
+                let last = ((end - 1) / SEGMENT).min(first + STREAM_SEGMENTS - 1);
+                let (offset, _) = sealed.header.segment_range(first);
+                let (last_offset, last_len) = sealed.header.segment_range(last);
+                // 段在文件中连续存放，只有第一次读取需要 seek
+                if !positioned {
+                    sealed.file.seek(SeekFrom::Start(offset)).await?;
+                }
+                let mut data = vec![0u8; (last_offset - offset) as usize + last_len];
+                sealed.file.read_exact(&mut data).await?;
+
+                let mut plain = Vec::with_capacity(data.len());
+                let mut buf = Vec::with_capacity((SEGMENT + TAG_LEN) as usize);
+                for (i, chunk) in data.chunks((SEGMENT + TAG_LEN) as usize).enumerate() {
+                    buf.clear();
+                    buf.extend_from_slice(chunk);
+                    sealed.header.open_segment(first + i as u64, &mut buf)?;
+                    plain.extend_from_slice(&buf);
+                }
+                let skip = (pos - first * SEGMENT) as usize;
+                let take = (end - pos).min((plain.len() - skip) as u64) as usize;
+                let bytes = Bytes::from(plain).slice(skip..skip + take);
+                Ok(Some((bytes, (sealed, pos + take as u64, true))))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+
+    #[tokio::test]
+    async fn test_seal_roundtrip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let vault = Vault::load_or_create(&dir.path().join("key")).unwrap();
+        let data: Vec<u8> = (0..SEGMENT * 3 + 123).map(|i| (i * 7 % 251) as u8).collect();
+        let (plain, sealed) = (dir.path().join("a.bin"), dir.path().join("a.sealed"));
+        std::fs::write(&plain, &data).unwrap();
+        vault.seal(&plain, &sealed).unwrap();
+
+        let raw = std::fs::read(&sealed).unwrap();
+        assert!(!raw.windows(64).any(|w| w == &data[1000..1064]));
+        assert_eq!(vault.sealed_size(&sealed).await.unwrap(), Some(data.len() as u64));
+        assert_eq!(vault.sealed_size(&plain).await.unwrap(), None);
+
+        let mut content = vault.open(&sealed).unwrap();
+        let mut out = Vec::new();
+        content.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data);
+        content.seek(SeekFrom::Start(SEGMENT - 10)).unwrap();
+        let mut part = [0u8; 20];
+        content.read_exact(&mut part).unwrap();
+        assert_eq!(part, data[SEGMENT as usize - 10..SEGMENT as usize + 10]);
+
+        for (start, len) in [(0, data.len() as u64), (SEGMENT + 5, SEGMENT * 2), (100, 0)] {
+            let file = vault.open_sealed(&sealed).await.unwrap().unwrap();
+            let chunks: Vec<Bytes> = file.into_stream(start, len).try_collect().await.unwrap();
+            let (start, len) = (start as usize, len as usize);
+            assert_eq!(chunks.concat(), data[start..start + len]);
+        }
+
+        // 重新加载同一密钥文件可以解密；篡改密文、换用其他密钥都无法读取
+        let reloaded = Vault::load_or_create(&dir.path().join("key")).unwrap();
+        assert!(reloaded.open(&sealed).is_ok());
+        let other = Vault::load_or_create(&dir.path().join("other")).unwrap();
+        assert!(other.open(&sealed).is_err());
+        let mut tampered = raw.clone();
+        tampered[HEADER_LEN + 10] ^= 1;
+        std::fs::write(&sealed, &tampered).unwrap();
+        let mut out = Vec::new();
+        assert!(vault.open(&sealed).unwrap().read_to_end(&mut out).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+
+/// 钩子程序的最长运行时间，超时后结束进程
+const HOOK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// 可以挂钩子的事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    /// 文件上传完成并落盘
+    UploadComplete,
+    /// 通过接口删除了文件或目录
+    Delete,
+    /// 未完成的上传会话过期被清理
+    SessionExpired,
+    /// 服务开始监听
+    ServerStart,
+}
+
+impl HookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::UploadComplete => "upload_complete",
+            Self::Delete => "delete",
+            Self::SessionExpired => "session_expired",
+            Self::ServerStart => "server_start",
+        }
+    }
+}
+
+/// 配置文件中的钩子命令
+///
+/// ```toml
+/// [hooks]
+/// upload_complete = "/usr/local/bin/scan-upload"
+/// delete = ["/usr/bin/logger", "-t", "transfer"]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum HookCommand {
+    /// 程序路径，不经过 shell 解析
+    Program(String),
+    /// 程序路径和参数
+    Args(Vec<String>),
+}
+
+impl HookCommand {
+    /// 要执行的程序
+    pub fn program(&self) -> Option<&str> {
+        match self {
+            Self::Program(program) => Some(program.as_str()),
+            Self::Args(args) => args.first().map(String::as_str),
+        }
+    }
+
+    fn argv(&self) -> Vec<String> {
+        match self {
+            Self::Program(program) => vec![program.clone()],
+            Self::Args(args) => args.clone(),
+        }
+    }
+}
+
+/// 事件钩子：事件发生时在后台调用配置的外部程序，事件内容以 JSON 写入其标准输入
+#[derive(Clone, Default)]
+pub struct Hooks {
+    commands: Arc<BTreeMap<HookEvent, Vec<String>>>,
+    /// 钩子程序的工作目录 (共享根目录)，事件中的路径相对于它
+    workdir: PathBuf,
+    /// 实现了 on_event 的 WASM 插件同样收到事件
+    #[cfg(feature = "plugins")]
+    plugins: Option<Arc<crate::plugins::PluginHost>>,
+}
+
+impl Hooks {
+    pub fn new(config: &BTreeMap<HookEvent, HookCommand>, workdir: PathBuf) -> Self {
+        let commands = config
+            .iter()
+            .filter_map(|(event, cmd)| {
+                let argv = cmd.argv();
+                if argv.first().is_none_or(|p| p.is_empty()) {
+                    tracing::warn!(event = event.as_str(), "empty hook command ignored");
+                    return None;
+                }
+                Some((*event, argv))
+            })
+            .collect();
+        Self {
+            commands: Arc::new(commands),
+            workdir,
+            #[cfg(feature = "plugins")]
+            plugins: None,
+        }
+    }
+
+    #[cfg(feature = "plugins")]
+    pub fn with_plugins(mut self, host: Arc<crate::plugins::PluginHost>) -> Self {
+        self.plugins = host.has(crate::plugins::Entry::Event).then_some(host);
+        self
+    }
+
+    /// 后台触发事件；data 中的字段与 event、time 合并后写入 stdin，失败只记录日志
+    pub fn fire(&self, event: HookEvent, data: Value) {
+        let payload = payload(event, data);
+        #[cfg(feature = "plugins")]
+        if let Some(host) = self.plugins.clone() {
+            let payload = payload.clone();
+            tokio::task::spawn_blocking(move || host.notify(&payload));
+        }
+        let Some(argv) = self.commands.get(&event).cloned() else {
+            return;
+        };
+        let payload = to_line(&payload);
+        let workdir = self.workdir.clone();
+        tokio::spawn(async move {
+            match run(&argv, &workdir, &payload).await {
+                Ok(status) if status.success() => {
+                    tracing::debug!(event = event.as_str(), "hook finished");
+                }
+                Ok(status) => {
+                    tracing::warn!(event = event.as_str(), %status, "hook exited with failure");
+                }
+                Err(e) => {
+                    tracing::warn!(event = event.as_str(), program = %argv[0], error = %e, "hook failed");
+                }
+            }
+        });
+    }
+}
+
+fn payload(event: HookEvent, data: Value) -> Value {
+    let mut payload = json!({
+        "event": event.as_str(),
+        "time": crate::db::now_secs(),
+    });
+    if let (Some(obj), Value::Object(data)) = (payload.as_object_mut(), data) {
+        obj.extend(data);
+    }
+    payload
+}
+
+fn to_line(payload: &Value) -> Vec<u8> {
+    let mut bytes = serde_json::to_vec(payload).unwrap_or_default();
+    bytes.push(b'\n');
+    bytes
+}
+
+async fn run(
+    argv: &[String],
+    workdir: &std::path::Path,
+    payload: &[u8],
+) -> std::io::Result<std::process::ExitStatus> {
+    let mut child = tokio::process::Command::new(&argv[0])
+        .args(&argv[1..])
+        // 去掉 Windows 的 \\?\ 前缀，钩子程序拿到常见写法的路径
+        .current_dir(dunce::simplified(workdir))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // 程序不读 stdin 就退出时写入会失败，不算钩子失败
+        let _ = stdin.write_all(payload).await;
+    }
+    tokio::time::timeout(HOOK_TIMEOUT, child.wait())
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "hook timed out"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hook_config() {
+        let cfg: BTreeMap<HookEvent, HookCommand> = toml::from_str(
+            r#"
+            upload_complete = "/bin/scan"
+            delete = ["/usr/bin/logger", "-t", "transfer"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(cfg[&HookEvent::UploadComplete].argv(), ["/bin/scan"]);
+        assert_eq!(cfg[&HookEvent::Delete].argv(), ["/usr/bin/logger", "-t", "transfer"]);
+
+        let unknown: Result<BTreeMap<HookEvent, HookCommand>, _> = toml::from_str("uploaded = \"/bin/true\"");
+        assert!(unknown.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_writes_payload_to_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let argv = ["sh", "-c", "cat > out.json"].map(String::from);
+        let data = to_line(&payload(HookEvent::Delete, json!({ "path": "a/b.txt" })));
+        let status = run(&argv, dir.path(), &data).await.unwrap();
+        assert!(status.success());
+
+        let written: Value = serde_json::from_slice(&std::fs::read(dir.path().join("out.json")).unwrap()).unwrap();
+        assert_eq!(written["event"], "delete");
+        assert_eq!(written["path"], "a/b.txt");
+        assert!(written["time"].as_u64().unwrap() > 0);
+    }
+}
@@ -1,16 +1,37 @@
+pub mod archive;
+pub mod bench;
+pub mod board;
+pub mod branding;
+pub mod clients;
+pub mod clipboard;
 pub mod config;
+pub mod db;
+pub mod delta;
 pub mod download;
 pub mod error;
 pub mod fs;
+pub mod hooks;
+pub mod localsend;
+pub mod metrics;
 pub mod middleware;
+pub mod notifier;
 pub mod observability;
+pub mod portmap;
 pub mod preview;
+pub mod privileges;
+pub mod relay;
+pub mod reload;
 pub mod routes;
+pub mod sandbox;
 pub mod server;
+pub mod signaling;
 pub mod state;
+pub mod sync;
 pub mod upload;
 pub mod util;
 pub mod zip;
 
+#[cfg(feature = "plugins")]
+pub mod plugins;
 #[cfg(feature = "tls")]
 pub mod tls;
@@ -0,0 +1,233 @@
+use std::io::IsTerminal;
+use std::net::IpAddr;
+
+use serde::Serialize;
+use tokio::io::AsyncBufReadExt;
+use tokio::sync::{oneshot, watch};
+
+use crate::state::AppState;
+
+/// 请求中列出的文件名数量上限，其余只计数
+const MAX_LISTED_NAMES: usize = 10;
+
+/// 等待确认的接收请求
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceiveRequest {
+    pub id: String,
+    /// 发送方设备名
+    pub sender: String,
+    pub device_model: Option<String>,
+    pub ip: IpAddr,
+    pub files: usize,
+    pub total_size: u64,
+    /// 前几个文件名
+    pub names: Vec<String>,
+    pub created_at: u64,
+}
+
+impl ReceiveRequest {
+    pub fn new(
+        sender: String,
+        device_model: Option<String>,
+        ip: IpAddr,
+        files: &[(String, u64)],
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            sender,
+            device_model,
+            ip,
+            files: files.len(),
+            total_size: files.iter().map(|(_, size)| size).sum(),
+            names: files
+                .iter()
+                .take(MAX_LISTED_NAMES)
+                .map(|(name, _)| name.clone())
+                .collect(),
+            created_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
+
+/// 接收确认队列：控制台和网页任一处先作出的决定生效
+pub struct Approvals {
+    pending: parking_lot::Mutex<Vec<(ReceiveRequest, oneshot::Sender<bool>)>>,
+    /// 待确认列表的快照，变化时通知订阅者
+    changed: watch::Sender<Vec<ReceiveRequest>>,
+}
+
+impl Default for Approvals {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Approvals {
+    pub fn new() -> Self {
+        Self {
+            pending: parking_lot::Mutex::new(Vec::new()),
+            changed: watch::Sender::new(Vec::new()),
+        }
+    }
+
+    /// 加入队列，返回等待决定的接收端；接收端被丢弃视为撤回
+    pub fn request(&self, request: ReceiveRequest) -> oneshot::Receiver<bool> {
+        let (tx, rx) = oneshot::channel();
+        let mut pending = self.pending.lock();
+        pending.push((request, tx));
+        self.publish(&pending);
+        rx
+    }
+
+    /// 作出决定；请求不存在（已被处理或撤回）时返回 false
+    pub fn decide(&self, id: &str, accept: bool) -> bool {
+        let mut pending = self.pending.lock();
+        let Some(pos) = pending.iter().position(|(r, _)| r.id == id) else {
+            return false;
+        };
+        let (_, tx) = pending.remove(pos);
+        self.publish(&pending);
+        tx.send(accept).is_ok()
+    }
+
+    /// 超时或发送方断开时移出队列
+    pub fn withdraw(&self, id: &str) {
+        let mut pending = self.pending.lock();
+        let before = pending.len();
+        pending.retain(|(r, _)| r.id != id);
+        if pending.len() != before {
+            self.publish(&pending);
+        }
+    }
+
+    pub fn list(&self) -> Vec<ReceiveRequest> {
+        self.changed.borrow().clone()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Vec<ReceiveRequest>> {
+        self.changed.subscribe()
+    }
+
+    fn publish(&self, pending: &[(ReceiveRequest, oneshot::Sender<bool>)]) {
+        self.changed
+            .send_replace(pending.iter().map(|(r, _)| r.clone()).collect());
+    }
+}
+
+/// 确认请求在退出作用域时撤回，发送方断开连接（handler 被取消）也不会残留在队列中
+pub struct PendingGuard<'a> {
+    pub approvals: &'a Approvals,
+    pub id: String,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.approvals.withdraw(&self.id);
+    }
+}
+
+/// 在控制台提示并读取 y / n，回答最早的一个请求；标准输入不是终端时只提示不读取
+pub fn spawn_console(state: AppState) {
+    let Some(ls) = state.localsend.as_ref() else {
+        return;
+    };
+    if ls.approvals.is_none() {
+        return;
+    }
+    let interactive = std::io::stdin().is_terminal();
+
+    let prompt_state = state.clone();
+    tokio::spawn(async move {
+        let Some(approvals) = prompt_state.localsend.as_ref().and_then(|ls| ls.approvals.as_ref()) else {
+            return;
+        };
+        let mut rx = approvals.subscribe();
+        let mut announced: Vec<String> = Vec::new();
+        while rx.changed().await.is_ok() {
+            let list = rx.borrow_and_update().clone();
+            for r in list.iter().filter(|r| !announced.contains(&r.id)) {
+                println!(
+                    "\n[LocalSend] {} ({}) 想发送 {} 个文件，共 {}：{}{}",
+                    r.sender,
+                    r.ip,
+                    r.files,
+                    humansize::format_size(r.total_size, humansize::BINARY),
+                    r.names.join(", "),
+                    if r.files > r.names.len() { " ..." } else { "" },
+                );
+                if interactive {
+                    println!("接收？[y/N]");
+                }
+            }
+            announced = list.into_iter().map(|r| r.id).collect();
+        }
+    });
+
+    if !interactive {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Some(approvals) = state.localsend.as_ref().and_then(|ls| ls.approvals.as_ref()) else {
+                return;
+            };
+            let Some(oldest) = approvals.list().into_iter().next() else {
+                continue;
+            };
+            let accept = matches!(line.trim().to_ascii_lowercase().as_str(), "y" | "yes");
+            approvals.decide(&oldest.id, accept);
+            println!(
+                "[LocalSend] 已{} {} 的传输",
+                if accept { "接受" } else { "拒绝" },
+                oldest.sender
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(sender: &str) -> ReceiveRequest {
+        ReceiveRequest::new(
+            sender.into(),
+            None,
+            "192.168.1.9".parse().unwrap(),
+            &[("a.jpg".into(), 10), ("b.jpg".into(), 20)],
+        )
+    }
+
+    #[tokio::test]
+    async fn test_decide() {
+        let approvals = Approvals::new();
+        let r = request("Phone");
+        assert_eq!(r.total_size, 30);
+        let id = r.id.clone();
+        let rx = approvals.request(r);
+        assert_eq!(approvals.list().len(), 1);
+        assert!(approvals.decide(&id, true));
+        assert!(rx.await.unwrap());
+        assert!(approvals.list().is_empty());
+        // 已经处理过
+        assert!(!approvals.decide(&id, false));
+    }
+
+    #[test]
+    fn test_guard_withdraws() {
+        let approvals = Approvals::new();
+        let r = request("Tablet");
+        let id = r.id.clone();
+        let _rx = approvals.request(r);
+        drop(PendingGuard {
+            approvals: &approvals,
+            id: id.clone(),
+        });
+        assert!(approvals.list().is_empty());
+        assert!(!approvals.decide(&id, true));
+    }
+}
@@ -0,0 +1,113 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+
+use super::protocol::{Announcement, DeviceInfo, MULTICAST_ADDR, MULTICAST_PORT};
+use crate::state::AppState;
+
+/// 定期公告，让刚打开应用的设备也能看到本机，同时刷新附近设备列表
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 启动组播发现；未启用 LocalSend 时不做任何事
+pub fn spawn(state: AppState) {
+    if state.localsend.is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(e) = run(&state).await {
+            tracing::warn!(error = %e, "localsend discovery stopped");
+        }
+    });
+}
+
+async fn run(state: &AppState) -> std::io::Result<()> {
+    let Some(ls) = state.localsend.as_ref() else {
+        return Ok(());
+    };
+    let socket = Arc::new(bind_multicast()?);
+    tracing::info!(alias = %ls.info.alias, "localsend discovery started");
+
+    let mut announce = tokio::time::interval(ANNOUNCE_INTERVAL);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        tokio::select! {
+            _ = announce.tick() => {
+                send_announcement(&socket, &ls.info, true).await;
+            }
+            received = socket.recv_from(&mut buf) => {
+                let (n, from) = received?;
+                let Ok(msg) = serde_json::from_slice::<Announcement>(&buf[..n]) else {
+                    continue;
+                };
+                // 组播回环会收到自己的公告
+                if msg.info.fingerprint == ls.info.fingerprint {
+                    continue;
+                }
+                ls.devices.upsert(from.ip(), &msg.info);
+                if msg.announce {
+                    let state = state.clone();
+                    let socket = socket.clone();
+                    tokio::spawn(async move {
+                        respond(&state, &socket, from.ip(), msg.info).await;
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// 回应新设备：优先 HTTP register，对方不可达时用组播回应
+async fn respond(state: &AppState, socket: &UdpSocket, ip: IpAddr, peer: DeviceInfo) {
+    let Some(ls) = state.localsend.as_ref() else {
+        return;
+    };
+    let protocol = match peer.protocol.as_deref() {
+        Some("http") => "http",
+        _ => "https",
+    };
+    let url = format!(
+        "{}://{}/api/localsend/v2/register",
+        protocol,
+        SocketAddr::new(ip, peer.port.unwrap_or(MULTICAST_PORT))
+    );
+    let registered = ls
+        .sender
+        .client()
+        .post(url)
+        .json(&ls.info)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .is_ok_and(|r| r.status().is_success());
+    if !registered {
+        send_announcement(socket, &ls.info, false).await;
+    }
+}
+
+async fn send_announcement(socket: &UdpSocket, info: &DeviceInfo, announce: bool) {
+    let msg = Announcement {
+        info: info.clone(),
+        announce,
+    };
+    let Ok(payload) = serde_json::to_vec(&msg) else {
+        return;
+    };
+    let target = SocketAddr::from((MULTICAST_ADDR, MULTICAST_PORT));
+    if let Err(e) = socket.send_to(&payload, target).await {
+        tracing::debug!(error = %e, "localsend announcement failed");
+    }
+}
+
+fn bind_multicast() -> std::io::Result<UdpSocket> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    // 本机同时运行 LocalSend 应用时共用端口
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, MULTICAST_PORT)).into())?;
+    socket.join_multicast_v4(&MULTICAST_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket.into())
+}
@@ -0,0 +1,200 @@
+//! LocalSend 协议兼容
+//!
+//! 通过 UDP 组播互相发现；手机上的 LocalSend 应用可以把文件发到共享目录，
+//! 也可以从网页把共享目录中的文件推送给附近的 LocalSend 设备
+
+pub mod approval;
+pub mod discovery;
+pub mod protocol;
+pub mod receiver;
+pub mod sender;
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::config::AppConfig;
+use approval::Approvals;
+use protocol::{DeviceInfo, PROTOCOL_VERSION};
+use receiver::ReceiveSessions;
+use sender::SendManager;
+
+/// 超过该时间没有公告或回应的设备不再列出
+const DEVICE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// 本程序公告的设备型号，用来从附近设备中认出其他运行中的实例
+pub const DEVICE_MODEL: &str = "FileTransfer";
+
+/// 附近的 LocalSend 设备
+#[derive(Debug, Clone, Serialize)]
+pub struct NearbyDevice {
+    pub fingerprint: String,
+    pub alias: String,
+    pub device_model: Option<String>,
+    pub device_type: Option<String>,
+    pub ip: IpAddr,
+    pub port: u16,
+    /// http | https
+    pub protocol: String,
+    /// 对方也是本程序的实例时，其网页界面的地址
+    pub web_url: Option<String>,
+    #[serde(skip)]
+    last_seen: Instant,
+}
+
+impl NearbyDevice {
+    /// 协议接口的前缀，如 `https://192.168.1.20:53317/api/localsend/v2`
+    pub fn base_url(&self) -> String {
+        format!(
+            "{}://{}/api/localsend/v2",
+            self.protocol,
+            SocketAddr::new(self.ip, self.port)
+        )
+    }
+}
+
+/// 通过组播公告和 register 请求得知的设备，按指纹索引
+#[derive(Default)]
+pub struct DeviceRegistry {
+    devices: parking_lot::RwLock<HashMap<String, NearbyDevice>>,
+}
+
+impl DeviceRegistry {
+    pub fn upsert(&self, ip: IpAddr, info: &DeviceInfo) {
+        let port = info.port.unwrap_or(protocol::MULTICAST_PORT);
+        let protocol = match info.protocol.as_deref() {
+            Some("http") => "http",
+            _ => "https",
+        };
+        let device = NearbyDevice {
+            fingerprint: info.fingerprint.clone(),
+            alias: info.alias.clone(),
+            device_model: info.device_model.clone(),
+            device_type: info.device_type.clone(),
+            ip,
+            port,
+            protocol: protocol.to_string(),
+            web_url: (info.device_model.as_deref() == Some(DEVICE_MODEL))
+                .then(|| format!("{}://{}/", protocol, SocketAddr::new(ip, port))),
+            last_seen: Instant::now(),
+        };
+        self.devices.write().insert(device.fingerprint.clone(), device);
+    }
+
+    pub fn get(&self, fingerprint: &str) -> Option<NearbyDevice> {
+        self.devices
+            .read()
+            .get(fingerprint)
+            .filter(|d| d.last_seen.elapsed() < DEVICE_TTL)
+            .cloned()
+    }
+
+    /// 最近在线的设备，按名称排序
+    pub fn list(&self) -> Vec<NearbyDevice> {
+        let mut devices = self.devices.write();
+        devices.retain(|_, d| d.last_seen.elapsed() < DEVICE_TTL);
+        let mut list: Vec<NearbyDevice> = devices.values().cloned().collect();
+        list.sort_by(|a, b| a.alias.cmp(&b.alias));
+        list
+    }
+}
+
+/// 启用 LocalSend 时的共享状态
+pub struct LocalSend {
+    /// 本机的设备信息
+    pub info: DeviceInfo,
+    /// 设置后发送方必须带上 PIN
+    pub pin: Option<String>,
+    pub devices: DeviceRegistry,
+    pub receiver: ReceiveSessions,
+    pub sender: SendManager,
+    /// 接收模式下等待确认的请求；未开启时直接接收
+    pub approvals: Option<Approvals>,
+}
+
+impl LocalSend {
+    pub fn new(config: &AppConfig, tmp_dir: &Path) -> Self {
+        let info = DeviceInfo {
+            alias: config
+                .localsend_alias
+                .clone()
+                .filter(|a| !a.trim().is_empty())
+                .unwrap_or_else(default_alias),
+            version: PROTOCOL_VERSION.to_string(),
+            device_model: Some(DEVICE_MODEL.to_string()),
+            device_type: Some("server".to_string()),
+            fingerprint: load_fingerprint(tmp_dir),
+            port: Some(config.port),
+            protocol: Some(if config.tls_cert.is_some() { "https" } else { "http" }.to_string()),
+            download: false,
+        };
+        Self {
+            sender: SendManager::new(info.clone()),
+            info,
+            pin: config.localsend_pin.clone().filter(|p| !p.is_empty()),
+            devices: DeviceRegistry::default(),
+            receiver: ReceiveSessions::new(),
+            approvals: config.localsend_confirm.then(Approvals::new),
+        }
+    }
+}
+
+/// 主机名，取不到时用固定名称
+fn default_alias() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "FileTransfer".to_string())
+}
+
+/// 指纹在重启后保持不变，LocalSend 应用据此识别同一设备（收藏、历史记录）
+fn load_fingerprint(tmp_dir: &Path) -> String {
+    let path = tmp_dir.join("localsend-fingerprint");
+    if let Ok(saved) = std::fs::read_to_string(&path) {
+        let saved = saved.trim();
+        if !saved.is_empty() {
+            return saved.to_string();
+        }
+    }
+    let fingerprint = uuid::Uuid::new_v4().simple().to_string();
+    if let Err(e) = std::fs::write(&path, &fingerprint) {
+        tracing::warn!(error = %e, "cannot persist localsend fingerprint");
+    }
+    fingerprint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let registry = DeviceRegistry::default();
+        let info: DeviceInfo = serde_json::from_str(
+            r#"{"alias":"Pixel","fingerprint":"fp1","port":53318,"protocol":"http"}"#,
+        )
+        .unwrap();
+        registry.upsert("192.168.1.5".parse().unwrap(), &info);
+        let device = registry.get("fp1").unwrap();
+        assert_eq!(device.base_url(), "http://192.168.1.5:53318/api/localsend/v2");
+        assert!(device.web_url.is_none());
+        assert_eq!(registry.list().len(), 1);
+
+        let peer: DeviceInfo = serde_json::from_str(
+            r#"{"alias":"nas","fingerprint":"fp2","port":8080,"protocol":"http","deviceModel":"FileTransfer"}"#,
+        )
+        .unwrap();
+        registry.upsert("192.168.1.6".parse().unwrap(), &peer);
+        assert_eq!(
+            registry.get("fp2").unwrap().web_url.as_deref(),
+            Some("http://192.168.1.6:8080/")
+        );
+        assert!(registry.get("missing").is_none());
+    }
+}
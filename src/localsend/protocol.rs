@@ -0,0 +1,113 @@
+//! LocalSend v2 协议的消息格式
+//!
+//! 字段名与官方实现一致（camelCase）；未知字段（如 preview、metadata）忽略
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+use serde::{Deserialize, Serialize};
+
+pub const PROTOCOL_VERSION: &str = "2.1";
+pub const MULTICAST_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 167);
+pub const MULTICAST_PORT: u16 = 53317;
+
+/// 设备信息：组播公告、register 和 info 接口共用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    pub alias: String,
+    #[serde(default = "default_version")]
+    pub version: String,
+    #[serde(default)]
+    pub device_model: Option<String>,
+    /// mobile | desktop | web | headless | server
+    #[serde(default)]
+    pub device_type: Option<String>,
+    pub fingerprint: String,
+    /// 组播和 register 中才有，info 接口不返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// http | https
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    /// 是否支持通过下载接口取文件
+    #[serde(default)]
+    pub download: bool,
+}
+
+fn default_version() -> String {
+    "1.0".to_string()
+}
+
+impl DeviceInfo {
+    /// info / register 响应：不带端口和协议
+    pub fn without_address(&self) -> Self {
+        Self {
+            port: None,
+            protocol: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// UDP 组播消息；announce 为 true 时收到的设备应回应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Announcement {
+    #[serde(flatten)]
+    pub info: DeviceInfo,
+    /// v1 中叫 announcement
+    #[serde(default, alias = "announcement")]
+    pub announce: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDto {
+    pub id: String,
+    /// 发送文件夹时包含相对路径，如 `photos/a.jpg`
+    pub file_name: String,
+    pub size: u64,
+    #[serde(default)]
+    pub file_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrepareUploadRequest {
+    pub info: DeviceInfo,
+    pub files: HashMap<String, FileDto>,
+}
+
+/// files：文件 ID -> 上传令牌；接收方不要的文件不出现在这里
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrepareUploadResponse {
+    pub session_id: String,
+    pub files: HashMap<String, String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_announcement() {
+        let json = r#"{"alias":"Nice Orange","version":"2.0","deviceModel":"Samsung","deviceType":"mobile",
+            "fingerprint":"abc","port":53317,"protocol":"https","download":true,"announce":true}"#;
+        let msg: Announcement = serde_json::from_str(json).unwrap();
+        assert!(msg.announce);
+        assert_eq!(msg.info.port, Some(53317));
+        assert_eq!(msg.info.device_type.as_deref(), Some("mobile"));
+
+        // v1 的字段名和缺省字段
+        let msg: Announcement =
+            serde_json::from_str(r#"{"alias":"x","fingerprint":"f","announcement":true}"#).unwrap();
+        assert!(msg.announce);
+        assert_eq!(msg.info.version, "1.0");
+
+        let out = serde_json::to_value(msg.info.without_address()).unwrap();
+        assert!(out.get("port").is_none());
+        assert_eq!(out["deviceModel"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::error::AppError;
+
+/// 会话无活动超过该时间后可以被其他发送方取代
+const SESSION_IDLE: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileState {
+    Pending,
+    Receiving,
+    Done,
+}
+
+/// 会话中等待上传的文件
+#[derive(Debug, Clone)]
+pub struct PendingFile {
+    /// 保存目录，prepare 时已按上传子目录和发送方给出的相对路径确定
+    pub target_dir: PathBuf,
+    /// 清理后的文件名
+    pub file_name: String,
+    pub size: u64,
+    token: String,
+    state: FileState,
+}
+
+struct ReceiveSession {
+    id: String,
+    sender_ip: IpAddr,
+    files: HashMap<String, PendingFile>,
+    last_activity: Instant,
+}
+
+/// 接收会话；协议规定同一时间只服务一个发送方，其他发送方收到 409
+#[derive(Default)]
+pub struct ReceiveSessions {
+    current: parking_lot::Mutex<Option<ReceiveSession>>,
+}
+
+/// prepare-upload 的待接收文件
+pub struct IncomingFile {
+    pub id: String,
+    pub target_dir: PathBuf,
+    pub file_name: String,
+    pub size: u64,
+}
+
+impl ReceiveSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 新建会话，返回会话 ID 和每个文件的上传令牌
+    ///
+    /// 同一发送方重新发起时取代旧会话；其他发送方的会话仍活跃时返回 UploadBusy
+    pub fn prepare(
+        &self,
+        sender_ip: IpAddr,
+        files: Vec<IncomingFile>,
+    ) -> Result<(String, HashMap<String, String>), AppError> {
+        let mut current = self.current.lock();
+        if let Some(session) = current.as_ref() {
+            let active = session.last_activity.elapsed() < SESSION_IDLE;
+            if active && session.sender_ip != sender_ip {
+                return Err(AppError::UploadBusy);
+            }
+        }
+
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let mut tokens = HashMap::new();
+        let files = files
+            .into_iter()
+            .map(|f| {
+                let token = uuid::Uuid::new_v4().simple().to_string();
+                tokens.insert(f.id.clone(), token.clone());
+                let pending = PendingFile {
+                    target_dir: f.target_dir,
+                    file_name: f.file_name,
+                    size: f.size,
+                    token,
+                    state: FileState::Pending,
+                };
+                (f.id, pending)
+            })
+            .collect();
+        *current = Some(ReceiveSession {
+            id: id.clone(),
+            sender_ip,
+            files,
+            last_activity: Instant::now(),
+        });
+        Ok((id, tokens))
+    }
+
+    /// 校验上传参数并把文件标记为接收中
+    pub fn begin(
+        &self,
+        session_id: &str,
+        file_id: &str,
+        token: &str,
+        sender_ip: IpAddr,
+    ) -> Result<PendingFile, AppError> {
+        let mut current = self.current.lock();
+        let session = current
+            .as_mut()
+            .filter(|s| s.id == session_id)
+            .ok_or(AppError::UploadBusy)?;
+        if session.sender_ip != sender_ip {
+            return Err(AppError::Forbidden("invalid ip address"));
+        }
+        let file = session
+            .files
+            .get_mut(file_id)
+            .filter(|f| f.token == token)
+            .ok_or(AppError::Forbidden("invalid token"))?;
+        match file.state {
+            FileState::Pending => {}
+            FileState::Receiving => return Err(AppError::UploadBusy),
+            FileState::Done => return Err(AppError::AlreadyExists(file.file_name.clone())),
+        }
+        file.state = FileState::Receiving;
+        session.last_activity = Instant::now();
+        Ok(file.clone())
+    }
+
+    /// 文件接收结束；失败的文件可以重传。全部完成后结束会话
+    pub fn finish(&self, session_id: &str, file_id: &str, ok: bool) {
+        let mut current = self.current.lock();
+        let Some(session) = current.as_mut().filter(|s| s.id == session_id) else {
+            return;
+        };
+        if let Some(file) = session.files.get_mut(file_id) {
+            file.state = if ok { FileState::Done } else { FileState::Pending };
+        }
+        session.last_activity = Instant::now();
+        if session.files.values().all(|f| f.state == FileState::Done) {
+            *current = None;
+        }
+    }
+
+    /// 发送方取消；只有会话的发送方可以取消
+    pub fn cancel(&self, session_id: &str, sender_ip: IpAddr) -> bool {
+        let mut current = self.current.lock();
+        if current
+            .as_ref()
+            .is_some_and(|s| s.id == session_id && s.sender_ip == sender_ip)
+        {
+            *current = None;
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(id: &str) -> IncomingFile {
+        IncomingFile {
+            id: id.into(),
+            target_dir: PathBuf::from("/srv"),
+            file_name: format!("{}.txt", id),
+            size: 3,
+        }
+    }
+
+    #[test]
+    fn test_session_lifecycle() {
+        let sessions = ReceiveSessions::new();
+        let phone: IpAddr = "192.168.1.20".parse().unwrap();
+        let other: IpAddr = "192.168.1.21".parse().unwrap();
+
+        let (sid, tokens) = sessions.prepare(phone, vec![file("a"), file("b")]).unwrap();
+        assert_eq!(tokens.len(), 2);
+        // 另一个发送方被拒绝
+        assert!(matches!(sessions.prepare(other, vec![file("c")]), Err(AppError::UploadBusy)));
+
+        assert!(sessions.begin(&sid, "a", "wrong", phone).is_err());
+        assert!(sessions.begin(&sid, "a", &tokens["a"], other).is_err());
+        let a = sessions.begin(&sid, "a", &tokens["a"], phone).unwrap();
+        assert_eq!(a.file_name, "a.txt");
+        // 同一文件不能并发上传，失败后可以重传
+        assert!(sessions.begin(&sid, "a", &tokens["a"], phone).is_err());
+        sessions.finish(&sid, "a", false);
+        sessions.begin(&sid, "a", &tokens["a"], phone).unwrap();
+        sessions.finish(&sid, "a", true);
+
+        sessions.begin(&sid, "b", &tokens["b"], phone).unwrap();
+        sessions.finish(&sid, "b", true);
+        // 全部完成后其他发送方可以开始
+        sessions.prepare(other, vec![file("c")]).unwrap();
+    }
+
+    #[test]
+    fn test_cancel() {
+        let sessions = ReceiveSessions::new();
+        let phone: IpAddr = "10.0.0.2".parse().unwrap();
+        let (sid, _) = sessions.prepare(phone, vec![file("a")]).unwrap();
+        assert!(!sessions.cancel(&sid, "10.0.0.3".parse().unwrap()));
+        assert!(sessions.cancel(&sid, phone));
+        assert!(sessions.prepare("10.0.0.3".parse().unwrap(), vec![file("b")]).is_ok());
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_compression::tokio::bufread::ZstdEncoder;
+use async_compression::Level;
+use serde::Serialize;
+use tokio::io::AsyncRead;
+use tokio_util::io::{InspectReader, ReaderStream, StreamReader};
+use tokio_util::sync::CancellationToken;
+
+use super::protocol::{DeviceInfo, FileDto, PrepareUploadRequest, PrepareUploadResponse};
+use super::NearbyDevice;
+use crate::fs::vault::Vault;
+use crate::util::{encoding, mime};
+
+/// 对方在手机上确认接收的等待时间
+const ACCEPT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// 推送任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SendStatus {
+    /// 等待对方确认
+    Waiting,
+    Sending,
+    Done,
+    Rejected,
+    Failed,
+    Cancelled,
+}
+
+/// 推送给 LocalSend 设备的任务
+#[derive(Debug, Clone, Serialize)]
+pub struct SendTask {
+    pub id: String,
+    pub device: String,
+    pub files: usize,
+    pub files_done: usize,
+    /// 对方接受的文件总字节数
+    pub total: u64,
+    pub sent: u64,
+    pub status: SendStatus,
+    pub error: Option<String>,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+/// 要推送的文件
+pub struct SendFile {
+    pub path: PathBuf,
+    /// 对方保存时使用的名称，可以包含相对目录
+    pub name: String,
+    /// 明文大小
+    pub size: u64,
+}
+
+struct SendEntry {
+    task: Arc<parking_lot::Mutex<SendTask>>,
+    cancel: CancellationToken,
+}
+
+/// 管理推送任务；LocalSend 设备通常使用自签名证书，不校验证书
+pub struct SendManager {
+    tasks: parking_lot::RwLock<HashMap<String, SendEntry>>,
+    client: reqwest::Client,
+    info: DeviceInfo,
+}
+
+impl SendManager {
+    pub fn new(info: DeviceInfo) -> Self {
+        Self {
+            tasks: parking_lot::RwLock::new(HashMap::new()),
+            client: reqwest::Client::builder()
+                .connect_timeout(Duration::from_secs(5))
+                .danger_accept_invalid_certs(true)
+                .build()
+                .unwrap_or_default(),
+            info,
+        }
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// 创建任务并在后台推送；启用落盘加密时传入 vault，推送解密后的内容
+    pub fn start(
+        &self,
+        device: NearbyDevice,
+        files: Vec<SendFile>,
+        vault: Option<Arc<Vault>>,
+    ) -> SendTask {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let task = SendTask {
+            id: id.clone(),
+            device: device.alias.clone(),
+            files: files.len(),
+            files_done: 0,
+            total: files.iter().map(|f| f.size).sum(),
+            sent: 0,
+            status: SendStatus::Waiting,
+            error: None,
+            created_at: now_secs(),
+            finished_at: None,
+        };
+        let shared = Arc::new(parking_lot::Mutex::new(task.clone()));
+        let cancel = CancellationToken::new();
+        self.tasks.write().insert(
+            id,
+            SendEntry {
+                task: shared.clone(),
+                cancel: cancel.clone(),
+            },
+        );
+
+        let client = self.client.clone();
+        let info = self.info.clone();
+        tokio::spawn(async move {
+            let session: parking_lot::Mutex<Option<String>> = parking_lot::Mutex::new(None);
+            let result = tokio::select! {
+                r = run_send(&client, info, &device, &files, vault.as_deref(), &shared, &session) => r,
+                _ = cancel.cancelled() => Err(SendError::Cancelled),
+            };
+
+            if matches!(result, Err(SendError::Cancelled)) {
+                // 通知对方结束会话，失败无妨
+                let session_id = session.lock().take();
+                if let Some(session_id) = session_id {
+                    let url = format!("{}/cancel?sessionId={}", device.base_url(), session_id);
+                    let _ = client.post(url).timeout(Duration::from_secs(5)).send().await;
+                }
+            }
+
+            let mut task = shared.lock();
+            task.finished_at = Some(now_secs());
+            match result {
+                Ok(()) => {
+                    tracing::info!(id = %task.id, device = %task.device, files = task.files_done, "localsend push done");
+                    task.status = SendStatus::Done;
+                }
+                Err(e) => {
+                    task.status = match e {
+                        SendError::Cancelled => SendStatus::Cancelled,
+                        SendError::Rejected => SendStatus::Rejected,
+                        _ => SendStatus::Failed,
+                    };
+                    tracing::warn!(id = %task.id, device = %task.device, error = %e, "localsend push failed");
+                    task.error = Some(e.to_string());
+                }
+            }
+        });
+
+        task
+    }
+
+    pub fn get(&self, id: &str) -> Option<SendTask> {
+        self.tasks.read().get(id).map(|e| e.task.lock().clone())
+    }
+
+    /// 取消进行中的任务，或移除已结束的任务
+    pub fn cancel(&self, id: &str) -> bool {
+        let mut tasks = self.tasks.write();
+        let Some(entry) = tasks.get(id) else {
+            return false;
+        };
+        if matches!(entry.task.lock().status, SendStatus::Waiting | SendStatus::Sending) {
+            entry.cancel.cancel();
+        } else {
+            tasks.remove(id);
+        }
+        true
+    }
+
+    /// 清理结束超过 max_age 秒的任务记录
+    pub fn prune_finished(&self, max_age: u64) -> usize {
+        let now = now_secs();
+        let mut tasks = self.tasks.write();
+        let before = tasks.len();
+        tasks.retain(|_, e| {
+            e.task
+                .lock()
+                .finished_at
+                .is_none_or(|f| now.saturating_sub(f) <= max_age)
+        });
+        before - tasks.len()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum SendError {
+    #[error("cancelled")]
+    Cancelled,
+    #[error("rejected by receiver")]
+    Rejected,
+    #[error("receiver is busy with another transfer")]
+    Busy,
+    #[error("receiver requires a PIN")]
+    PinRequired,
+    #[error("receiver responded {0}")]
+    Status(reqwest::StatusCode),
+    #[error("{0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+}
+
+async fn run_send(
+    client: &reqwest::Client,
+    info: DeviceInfo,
+    device: &NearbyDevice,
+    files: &[SendFile],
+    vault: Option<&Vault>,
+    task: &Arc<parking_lot::Mutex<SendTask>>,
+    session: &parking_lot::Mutex<Option<String>>,
+) -> Result<(), SendError> {
+    let base = device.base_url();
+    let dtos = files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            let dto = FileDto {
+                id: i.to_string(),
+                file_name: f.name.clone(),
+                size: f.size,
+                file_type: mime_guess::from_path(&f.path)
+                    .first_or_octet_stream()
+                    .to_string(),
+                sha256: None,
+            };
+            (dto.id.clone(), dto)
+        })
+        .collect();
+
+    // 对方确认之前请求一直挂起
+    let resp = client
+        .post(format!("{}/prepare-upload", base))
+        .json(&PrepareUploadRequest { info, files: dtos })
+        .timeout(ACCEPT_TIMEOUT)
+        .send()
+        .await?;
+    match resp.status() {
+        // 对方不需要任何文件
+        reqwest::StatusCode::NO_CONTENT => return Ok(()),
+        reqwest::StatusCode::FORBIDDEN => return Err(SendError::Rejected),
+        reqwest::StatusCode::CONFLICT => return Err(SendError::Busy),
+        reqwest::StatusCode::UNAUTHORIZED => return Err(SendError::PinRequired),
+        s if !s.is_success() => return Err(SendError::Status(s)),
+        _ => {}
+    }
+    // 对方同为本程序时声明接受 zstd 压缩的请求体
+    let zstd = encoding::accepts_zstd(resp.headers());
+    let prepared: PrepareUploadResponse = resp.json().await?;
+    *session.lock() = Some(prepared.session_id.clone());
+
+    let accepted: Vec<(usize, &SendFile, &String)> = files
+        .iter()
+        .enumerate()
+        .filter_map(|(i, f)| prepared.files.get(&i.to_string()).map(|token| (i, f, token)))
+        .collect();
+    {
+        let mut t = task.lock();
+        t.status = SendStatus::Sending;
+        t.files = accepted.len();
+        t.total = accepted.iter().map(|(_, f, _)| f.size).sum();
+    }
+
+    for (i, file, token) in accepted {
+        let mut url = reqwest::Url::parse(&format!("{}/upload", base))
+            .map_err(|_| SendError::Status(reqwest::StatusCode::BAD_REQUEST))?;
+        url.query_pairs_mut()
+            .append_pair("sessionId", &prepared.session_id)
+            .append_pair("fileId", &i.to_string())
+            .append_pair("token", token);
+
+        // 进度按读出的原始字节计，压缩与否都一样
+        let counter = task.clone();
+        let reader = InspectReader::new(open_plain(&file.path, vault).await?, move |buf| {
+            counter.lock().sent += buf.len() as u64;
+        });
+        let request = client.post(url);
+        let request = if zstd && !mime::is_compressed(&mime::guess_mime(&file.path)) {
+            let encoder = ZstdEncoder::with_quality(
+                tokio::io::BufReader::with_capacity(256 * 1024, reader),
+                Level::Precise(encoding::ZSTD_LEVEL),
+            );
+            request
+                .header(reqwest::header::CONTENT_ENCODING, encoding::ZSTD)
+                .body(reqwest::Body::wrap_stream(ReaderStream::with_capacity(encoder, 256 * 1024)))
+        } else {
+            request
+                .header(reqwest::header::CONTENT_LENGTH, file.size)
+                .body(reqwest::Body::wrap_stream(ReaderStream::with_capacity(reader, 256 * 1024)))
+        };
+        let resp = request.send().await?;
+        if !resp.status().is_success() {
+            return Err(SendError::Status(resp.status()));
+        }
+        task.lock().files_done += 1;
+    }
+    Ok(())
+}
+
+/// 打开文件的明文内容；落盘加密的文件边读边解密
+async fn open_plain(
+    path: &std::path::Path,
+    vault: Option<&Vault>,
+) -> std::io::Result<Box<dyn AsyncRead + Send + Unpin>> {
+    if let Some(vault) = vault {
+        if let Some(sealed) = vault.open_sealed(path).await? {
+            let size = sealed.size();
+            return Ok(Box::new(StreamReader::new(Box::pin(sealed.into_stream(0, size)))));
+        }
+    }
+    Ok(Box::new(tokio::fs::File::open(path).await?))
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
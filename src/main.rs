@@ -1,12 +1,29 @@
-use transfer_app::config::AppConfig;
+use transfer_app::bench;
+use transfer_app::config::{AppConfig, Command};
 use transfer_app::observability;
+use transfer_app::sandbox;
 use transfer_app::server;
+use transfer_app::sync;
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
     let config = AppConfig::load()?;
 
-    observability::init(&config.log_filter);
+    // 沙箱只约束之后创建的线程，要赶在日志线程和运行时之前
+    let sandbox = match config.command {
+        None => sandbox::apply(&config)?,
+        Some(_) => sandbox::Status::Disabled,
+    };
 
-    server::run(config).await
+    // 文件日志在后台线程写入，guard 在退出时把剩余日志写完
+    let _log_guard = observability::init(&config)?;
+    sandbox::report(sandbox);
+
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    runtime.block_on(async move {
+        match config.command {
+            Some(Command::Bench(args)) => bench::run(args).await,
+            Some(Command::Sync(args)) => sync::run(args).await,
+            None => server::run(config).await,
+        }
+    })
 }
@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use serde::Serialize;
+
+/// 进程内的传输计数，重启后清零
+pub struct Metrics {
+    started_at: Instant,
+    uploads_completed: AtomicU64,
+    upload_bytes: AtomicU64,
+    downloads: AtomicU64,
+    download_bytes: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetricsSnapshot {
+    pub uptime_secs: u64,
+    pub uploads_completed: u64,
+    pub upload_bytes: u64,
+    pub downloads: u64,
+    /// 按响应长度累计，客户端中途断开时会偏大
+    pub download_bytes: u64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            uploads_completed: AtomicU64::new(0),
+            upload_bytes: AtomicU64::new(0),
+            downloads: AtomicU64::new(0),
+            download_bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// 上传落盘完成（tus 或表单）
+    pub fn upload_completed(&self, size: u64) {
+        self.uploads_completed.fetch_add(1, Ordering::Relaxed);
+        self.upload_bytes.fetch_add(size, Ordering::Relaxed);
+    }
+
+    /// 开始发送一次下载响应
+    pub fn download_started(&self, length: u64) {
+        self.downloads.fetch_add(1, Ordering::Relaxed);
+        self.download_bytes.fetch_add(length, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            uploads_completed: self.uploads_completed.load(Ordering::Relaxed),
+            upload_bytes: self.upload_bytes.load(Ordering::Relaxed),
+            downloads: self.downloads.load(Ordering::Relaxed),
+            download_bytes: self.download_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,229 @@
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use bytes::Bytes;
+use hyper::body::{Body as HttpBody, Frame, SizeHint};
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing_appender::non_blocking::{NonBlocking, NonBlockingBuilder, WorkerGuard};
+
+use crate::config::AppConfig;
+use crate::middleware::{auth, request_id};
+use crate::state::AppState;
+use crate::util::logfile::RollingFile;
+
+/// 访问日志的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    /// 类似 Common Log Format 的一行文本
+    #[default]
+    Text,
+    /// 每行一个 JSON 对象 (NDJSON)，可以直接导入 Loki / Elasticsearch
+    Json,
+}
+
+/// 访问日志：每个请求在响应体发送完 (或连接断开) 时写一行
+pub struct AccessLog {
+    format: AccessLogFormat,
+    writer: NonBlocking,
+    _guard: WorkerGuard,
+}
+
+impl AccessLog {
+    /// `-` 写到标准输出，其余按 --log-rotate 等参数轮转
+    pub fn open(config: &AppConfig, path: &Path) -> anyhow::Result<Self> {
+        // 与文件日志一样，写不过来时阻塞而不是丢弃
+        let builder = NonBlockingBuilder::default().lossy(false).thread_name("access-log");
+        let (writer, guard) = if path.as_os_str() == "-" {
+            builder.finish(std::io::stdout())
+        } else {
+            let file = RollingFile::open(path.to_path_buf(), config.log_rotate, config.log_max_size, config.log_keep)
+                .map_err(|e| anyhow::anyhow!("cannot open access log '{}': {}", path.display(), e))?;
+            builder.finish(file)
+        };
+        Ok(Self {
+            format: config.access_log_format,
+            writer,
+            _guard: guard,
+        })
+    }
+
+    fn write(&self, entry: &Entry) {
+        let mut line = match self.format {
+            AccessLogFormat::Text => entry.text(),
+            AccessLogFormat::Json => serde_json::to_string(entry).unwrap_or_default(),
+        };
+        line.push('\n');
+        // 整行一次写入，轮转不会把一行拆到两个文件
+        let _ = self.writer.clone().write_all(line.as_bytes());
+    }
+}
+
+/// 一行访问日志
+#[derive(Serialize)]
+struct Entry {
+    ts: String,
+    ip: IpAddr,
+    method: String,
+    /// 不含查询串，分享令牌等不会写进日志
+    path: String,
+    status: u16,
+    /// 实际发送的响应体字节数 (压缩后)
+    bytes: u64,
+    /// 从收到请求到响应体发送完的秒数
+    duration: f64,
+    user: Option<String>,
+    req_id: Option<String>,
+    #[serde(skip)]
+    started: Instant,
+    #[serde(skip)]
+    version: String,
+}
+
+impl Entry {
+    fn text(&self) -> String {
+        format!(
+            "{} - {} [{}] \"{} {} {}\" {} {} {:.3}",
+            self.ip,
+            self.user.as_deref().unwrap_or("-"),
+            self.ts,
+            self.method,
+            self.path,
+            self.version,
+            self.status,
+            self.bytes,
+            self.duration,
+        )
+    }
+}
+
+/// 挂在认证之后、压缩之前：能拿到用户名，记录的是实际发送的字节
+pub async fn log(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if state.access_log.is_none() {
+        return next.run(req).await;
+    }
+    let mut entry = Entry {
+        ts: OffsetDateTime::now_utc().format(&Rfc3339).unwrap_or_default(),
+        ip: peer.ip(),
+        method: req.method().to_string(),
+        path: req.uri().path().to_string(),
+        status: 0,
+        bytes: 0,
+        duration: 0.0,
+        user: auth::current_user(),
+        req_id: request_id::current(),
+        started: Instant::now(),
+        version: format!("{:?}", req.version()),
+    };
+    let response = next.run(req).await;
+    entry.status = response.status().as_u16();
+    // HEAD、304 等没有响应体的请求在 body 释放时写出
+    response.map(|inner| {
+        Body::new(Logged {
+            inner,
+            pending: Some(Pending { state, entry }),
+        })
+    })
+}
+
+/// 响应体释放时写出的日志
+struct Pending {
+    state: AppState,
+    entry: Entry,
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        if let Some(log) = &self.state.access_log {
+            self.entry.duration = self.entry.started.elapsed().as_secs_f64();
+            log.write(&self.entry);
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    struct Logged {
+        #[pin]
+        inner: Body,
+        pending: Option<Pending>,
+    }
+}
+
+impl HttpBody for Logged {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        let this = self.project();
+        let poll = this.inner.poll_frame(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let (Some(data), Some(pending)) = (frame.data_ref(), this.pending.as_mut()) {
+                    pending.entry.bytes += data.len() as u64;
+                }
+            }
+            // 发送完立即写出，不等连接上的下一个请求
+            Poll::Ready(None) | Poll::Ready(Some(Err(_))) => {
+                this.pending.take();
+            }
+            Poll::Pending => {}
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_format() {
+        let entry = Entry {
+            ts: "2026-01-02T03:04:05Z".into(),
+            ip: IpAddr::from([192, 168, 1, 20]),
+            method: "GET".into(),
+            path: "/api/download/a.txt".into(),
+            status: 206,
+            bytes: 1024,
+            duration: 0.25,
+            user: Some("alice".into()),
+            req_id: None,
+            started: Instant::now(),
+            version: "HTTP/1.1".into(),
+        };
+        assert_eq!(
+            entry.text(),
+            r#"192.168.1.20 - alice [2026-01-02T03:04:05Z] "GET /api/download/a.txt HTTP/1.1" 206 1024 0.250"#
+        );
+        assert_eq!(
+            serde_json::to_string(&entry).unwrap(),
+            r#"{"ts":"2026-01-02T03:04:05Z","ip":"192.168.1.20","method":"GET","path":"/api/download/a.txt","status":206,"bytes":1024,"duration":0.25,"user":"alice","req_id":null}"#
+        );
+    }
+}
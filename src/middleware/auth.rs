@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+use axum::extract::{Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// 开启全局认证后仍然公开的接口：健康检查、用令牌鉴权的维护接口、无法做 Basic 认证的 LocalSend 设备
+const PUBLIC_PREFIXES: &[&str] = &[
+    "/api/healthz",
+    "/api/readyz",
+    "/api/admin/maintenance",
+    "/api/admin/shutdown",
+    "/api/localsend/v2/",
+];
+
+tokio::task_local! {
+    /// 当前请求通过认证的用户名
+    static CURRENT_USER: Option<String>;
+}
+
+/// 当前请求的用户；未认证或不在请求上下文中时为 None
+pub fn current_user() -> Option<String> {
+    CURRENT_USER.try_with(|u| u.clone()).ok().flatten()
+}
+
+/// 在指定用户上下文中运行 future，用于把身份带进后台任务
+pub async fn scope<F: std::future::Future>(user: Option<String>, fut: F) -> F::Output {
+    CURRENT_USER.scope(user, fut).await
+}
+
+/// 解析 Basic 认证头并记录用户；凭据错误不在这里拒绝，由需要认证的规则返回 401
+pub async fn identify(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let user = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| verify_basic(v, &state.live.users()));
+    CURRENT_USER.scope(user, next.run(req)).await
+}
+
+/// 设置 --password 或 --require-auth 时，未登录的请求一律返回 401，由浏览器弹出登录框
+///
+/// 挂在访问日志之内，被拒绝的请求也会记录下来
+pub async fn require(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if !state.config.auth_required()
+        || current_user().is_some()
+        // CORS 预检请求不带凭据
+        || req.method() == Method::OPTIONS
+        || PUBLIC_PREFIXES.iter().any(|p| req.uri().path().starts_with(p))
+    {
+        return next.run(req).await;
+    }
+    // 共享密码登录的请求不算作具体用户，进不了 auth = true 的目录
+    let password_ok = state.config.password.as_deref().filter(|p| !p.is_empty()).is_some_and(|expected| {
+        req.headers()
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(decode_basic)
+            .is_some_and(|(_, pass)| constant_time_eq(expected.as_bytes(), pass.as_bytes()))
+    });
+    if password_ok {
+        next.run(req).await
+    } else {
+        AppError::Unauthorized.into_response()
+    }
+}
+
+fn verify_basic(header: &str, users: &BTreeMap<String, String>) -> Option<String> {
+    let (user, pass) = decode_basic(header)?;
+    let expected = users.get(&user)?;
+    constant_time_eq(expected.as_bytes(), pass.as_bytes()).then_some(user)
+}
+
+/// Basic 认证头中的用户名和密码
+fn decode_basic(header: &str) -> Option<(String, String)> {
+    let encoded = header.strip_prefix("Basic ")?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (user, pass) = decoded.split_once(':')?;
+    Some((user.to_string(), pass.to_string()))
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn basic(credentials: &str) -> String {
+        format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials))
+    }
+
+    #[test]
+    fn test_verify_basic() {
+        let users = BTreeMap::from([("alice".to_string(), "secret".to_string())]);
+        assert_eq!(verify_basic(&basic("alice:secret"), &users).as_deref(), Some("alice"));
+        assert_eq!(verify_basic(&basic("alice:wrong"), &users), None);
+        assert_eq!(verify_basic(&basic("bob:secret"), &users), None);
+        // 密码中可以有冒号
+        assert_eq!(
+            decode_basic(&basic("x:a:b")),
+            Some(("x".to_string(), "a:b".to_string()))
+        );
+        assert_eq!(decode_basic("Bearer abc"), None);
+    }
+}
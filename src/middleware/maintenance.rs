@@ -0,0 +1,115 @@
+use std::time::{Duration, Instant};
+
+use axum::extract::{Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// 关机时等待进行中传输结束的默认时长
+pub const DEFAULT_DRAIN: Duration = Duration::from_secs(60);
+
+/// 传输请求结束后，传输记录、钩子等还在后台任务中进行，退出前再留一点时间
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// 维护状态：暂停新的上传、远程关机
+#[derive(Default)]
+pub struct Maintenance {
+    /// 暂停上传时给用户看的说明，未暂停时为 None
+    notice: parking_lot::RwLock<Option<String>>,
+    shutdown: CancellationToken,
+    /// 关机时最多等待进行中传输的时长
+    drain: parking_lot::Mutex<Duration>,
+}
+
+impl Maintenance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 暂停上传，已在进行中的请求照常完成
+    pub fn pause(&self, notice: String) {
+        *self.notice.write() = Some(notice);
+    }
+
+    pub fn resume(&self) {
+        if !self.is_shutting_down() {
+            *self.notice.write() = None;
+        }
+    }
+
+    pub fn notice(&self) -> Option<String> {
+        self.notice.read().clone()
+    }
+
+    /// 暂停上传，等进行中的传输结束 (最多 drain) 后停止服务
+    pub fn shutdown(&self, notice: String, drain: Duration) {
+        *self.drain.lock() = drain;
+        self.pause(notice);
+        self.shutdown.cancel();
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    /// 收到关机请求时返回，此后不再接受新连接
+    pub async fn shutdown_requested(&self) {
+        self.shutdown.cancelled().await
+    }
+}
+
+/// 进行中的上传、下载请求和服务端抓取任务数
+pub fn active_transfers(state: &AppState) -> usize {
+    state.clients.active_transfers() + state.fetch_manager.running()
+}
+
+/// 收到关机请求后等待进行中的传输结束，超时后不再等待
+pub async fn drained(state: &AppState) {
+    state.maintenance.shutdown_requested().await;
+    let deadline = Instant::now() + *state.maintenance.drain.lock();
+    loop {
+        let active = active_transfers(state);
+        if active == 0 {
+            tracing::info!("transfers drained, shutting down");
+            tokio::time::sleep(SETTLE).await;
+            return;
+        }
+        if Instant::now() >= deadline {
+            tracing::warn!(active, "drain timed out, shutting down with transfers in progress");
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// 暂停期间拒绝上传路由上的写请求；tus 的进度查询、能力发现和取消照常响应
+pub async fn guard(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS | Method::DELETE) {
+        if let Some(notice) = state.maintenance.notice() {
+            return AppError::Paused { notice }.into_response();
+        }
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_after_shutdown() {
+        let m = Maintenance::new();
+        m.pause("a".into());
+        m.resume();
+        assert_eq!(m.notice(), None);
+
+        m.shutdown("b".into(), Duration::from_secs(1));
+        m.resume();
+        assert!(m.is_shutting_down());
+        assert_eq!(m.notice().as_deref(), Some("b"));
+    }
+}
@@ -1,2 +1,6 @@
+pub mod access_log;
+pub mod auth;
+pub mod maintenance;
+pub mod rate_limit;
 pub mod request_id;
 pub mod trace;
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::middleware::auth;
+use crate::state::AppState;
+
+/// 超过该时长没有请求的客户端桶已经回满，清理时删除
+const IDLE: Duration = Duration::from_secs(60);
+
+/// 按客户端限制 /api 的请求频率，超出时返回 429 和 Retry-After
+///
+/// 通过认证的请求按用户名计数，其余按来源 IP；每分钟的配额可以一次用完，之后匀速恢复
+pub struct RateLimiter {
+    /// 所有接口，每分钟请求数 (0 = 不限制)
+    api: u32,
+    /// 修改类请求 (POST / PUT / PATCH / DELETE)，每分钟请求数 (0 = 不限制)
+    writes: u32,
+    buckets: parking_lot::Mutex<Buckets>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Client {
+    User(String),
+    Ip(IpAddr),
+}
+
+struct Buckets {
+    map: HashMap<(Client, bool), Bucket>,
+    last_prune: Instant,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// 按流逝的时间补充配额后的余量
+    fn refill(&mut self, per_minute: u32, now: Instant) {
+        let rate = per_minute as f64 / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(per_minute as f64);
+        self.updated = now;
+    }
+
+    /// 还要等多少秒才有一个配额
+    fn wait_secs(&self, per_minute: u32) -> u64 {
+        let rate = per_minute as f64 / 60.0;
+        ((1.0 - self.tokens) / rate).ceil().max(1.0) as u64
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            api: config.rate_limit,
+            writes: config.rate_limit_writes,
+            buckets: parking_lot::Mutex::new(Buckets {
+                map: HashMap::new(),
+                last_prune: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.api > 0 || self.writes > 0
+    }
+
+    /// 记一次请求；超出任一配额时不扣减，返回需要等待的秒数
+    fn check(&self, client: Client, write: bool, now: Instant) -> Result<(), u64> {
+        let mut buckets = self.buckets.lock();
+        if now.saturating_duration_since(buckets.last_prune) >= IDLE {
+            buckets.map.retain(|_, b| now.saturating_duration_since(b.updated) < IDLE);
+            buckets.last_prune = now;
+        }
+
+        let limits = [(false, self.api), (true, if write { self.writes } else { 0 })];
+        let mut wait = 0;
+        for (class, per_minute) in limits {
+            if per_minute == 0 {
+                continue;
+            }
+            let bucket = buckets.map.entry((client.clone(), class)).or_insert(Bucket {
+                tokens: per_minute as f64,
+                updated: now,
+            });
+            bucket.refill(per_minute, now);
+            if bucket.tokens < 1.0 {
+                wait = wait.max(bucket.wait_secs(per_minute));
+            }
+        }
+        if wait > 0 {
+            return Err(wait);
+        }
+        for (class, per_minute) in limits {
+            if per_minute > 0 {
+                if let Some(bucket) = buckets.map.get_mut(&(client.clone(), class)) {
+                    bucket.tokens -= 1.0;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 只挂在 /api 下；tus 续传的数据块和 LocalSend 的文件上传属于传输本身，不计数
+pub async fn limit(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let limiter = &state.rate_limiter;
+    let method = req.method();
+    let path = req.uri().path();
+    let transfer = (path.starts_with("/upload/") && (method == Method::PATCH || method == Method::HEAD))
+        || path == "/localsend/v2/upload";
+    if !limiter.is_enabled() || transfer {
+        return next.run(req).await;
+    }
+
+    let write = matches!(*method, Method::POST | Method::PUT | Method::PATCH | Method::DELETE);
+    let client = match auth::current_user() {
+        Some(user) => Client::User(user),
+        None => Client::Ip(peer.ip()),
+    };
+    match limiter.check(client.clone(), write, Instant::now()) {
+        Ok(()) => next.run(req).await,
+        Err(retry_after) => {
+            tracing::debug!(?client, %path, retry_after, "rate limited");
+            AppError::RateLimited { retry_after }.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets() {
+        let limiter = RateLimiter {
+            api: 120,
+            writes: 2,
+            buckets: parking_lot::Mutex::new(Buckets {
+                map: HashMap::new(),
+                last_prune: Instant::now(),
+            }),
+        };
+        let ip = |n| Client::Ip(IpAddr::from([10, 0, 0, n]));
+        let t0 = Instant::now();
+
+        assert!(limiter.check(ip(1), true, t0).is_ok());
+        assert!(limiter.check(ip(1), true, t0).is_ok());
+        // 每分钟 2 次写入，下一次要等 30 秒；读请求和其他客户端不受影响
+        assert_eq!(limiter.check(ip(1), true, t0), Err(30));
+        assert!(limiter.check(ip(1), false, t0).is_ok());
+        assert!(limiter.check(ip(2), true, t0).is_ok());
+        assert!(limiter.check(ip(1), true, t0 + Duration::from_secs(30)).is_ok());
+
+        // 被拒绝的请求不扣减配额
+        for _ in 0..117 {
+            assert!(limiter.check(ip(3), false, t0).is_ok());
+        }
+        assert!(limiter.check(ip(3), true, t0).is_ok());
+        assert!(limiter.check(ip(3), true, t0).is_ok());
+        assert_eq!(limiter.check(ip(3), false, t0), Ok(()));
+        assert_eq!(limiter.check(ip(3), false, t0), Err(1));
+    }
+}
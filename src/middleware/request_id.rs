@@ -1,3 +1,6 @@
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
 use tower_http::request_id::{MakeRequestId, RequestId};
 
 /// 为每个请求生成 UUID request id
@@ -13,3 +16,23 @@ impl MakeRequestId for MakeRequestUuid {
         Some(RequestId::new(id.parse().unwrap()))
     }
 }
+
+tokio::task_local! {
+    /// 当前请求的 X-Request-Id
+    static CURRENT_REQUEST_ID: Option<String>;
+}
+
+/// 当前请求的 id；不在请求上下文中时为 None
+pub fn current() -> Option<String> {
+    CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok().flatten()
+}
+
+/// 把 SetRequestIdLayer 生成（或客户端带来）的 id 放进 task-local，错误响应体会带上它
+pub async fn scope(req: Request, next: Next) -> Response {
+    let id = req
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .map(str::to_string);
+    CURRENT_REQUEST_ID.scope(id, next.run(req)).await
+}
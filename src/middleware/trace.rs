@@ -1,5 +1,7 @@
-use axum::http::Request;
-use tower_http::trace::MakeSpan;
+use std::time::Duration;
+
+use axum::http::{Request, Response};
+use tower_http::trace::{DefaultOnResponse, MakeSpan, OnResponse};
 
 /// 自定义 trace span
 #[derive(Clone)]
@@ -18,6 +20,19 @@ impl<B> MakeSpan<B> for CustomMakeSpan {
             method = %request.method(),
             uri = %request.uri(),
             req_id = %req_id,
+            status = tracing::field::Empty,
+            otel.kind = "server",
         )
     }
 }
+
+/// 把状态码记到请求 span 上，导出的链路里可以按状态码筛选
+#[derive(Clone, Default)]
+pub struct CustomOnResponse(DefaultOnResponse);
+
+impl<B> OnResponse<B> for CustomOnResponse {
+    fn on_response(self, response: &Response<B>, latency: Duration, span: &tracing::Span) {
+        span.record("status", response.status().as_u16());
+        self.0.on_response(response, latency, span)
+    }
+}
@@ -0,0 +1,112 @@
+use std::net::IpAddr;
+
+use serde_json::json;
+
+use crate::config::AppConfig;
+
+/// 上传完成通知器，支持 Telegram bot 与 Slack webhook
+#[derive(Clone)]
+pub struct Notifier {
+    client: reqwest::Client,
+    telegram: Option<(String, String)>,
+    slack_webhook: Option<String>,
+    base_url: String,
+}
+
+/// 一次上传完成事件
+pub struct UploadNotice {
+    pub filename: String,
+    /// 相对于 root 的路径
+    pub path: String,
+    pub size: u64,
+    pub uploader: Option<IpAddr>,
+}
+
+impl Notifier {
+    pub fn new(config: &AppConfig) -> Self {
+        let telegram = match (&config.telegram_token, &config.telegram_chat) {
+            (Some(token), Some(chat)) => Some((token.clone(), chat.clone())),
+            (Some(_), None) | (None, Some(_)) => {
+                tracing::warn!("telegram notifier needs both --telegram-token and --telegram-chat");
+                None
+            }
+            (None, None) => None,
+        };
+
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            telegram,
+            slack_webhook: config.slack_webhook.clone(),
+            base_url: config.base_url(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.telegram.is_some() || self.slack_webhook.is_some()
+    }
+
+    /// 后台发送上传完成通知，失败只记录日志
+    pub fn upload_completed(&self, notice: UploadNotice) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let text = self.format_message(&notice);
+        let this = self.clone();
+        tokio::spawn(async move {
+            if let Some((token, chat)) = &this.telegram {
+                let url = format!("https://api.telegram.org/bot{}/sendMessage", token);
+                let body = json!({
+                    "chat_id": chat,
+                    "text": text,
+                    "disable_web_page_preview": true,
+                });
+                this.post("telegram", &url, &body).await;
+            }
+            if let Some(webhook) = &this.slack_webhook {
+                this.post("slack", webhook, &json!({ "text": text })).await;
+            }
+        });
+    }
+
+    fn format_message(&self, notice: &UploadNotice) -> String {
+        let encoded: Vec<String> = notice
+            .path
+            .split('/')
+            .map(|seg| {
+                percent_encoding::utf8_percent_encode(seg, percent_encoding::NON_ALPHANUMERIC)
+                    .to_string()
+            })
+            .collect();
+        let uploader = notice
+            .uploader
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        format!(
+            "📥 上传完成\n文件: {}\n大小: {}\n来源: {}\n链接: {}/api/download/{}",
+            notice.filename,
+            humansize::format_size(notice.size, humansize::BINARY),
+            uploader,
+            self.base_url,
+            encoded.join("/"),
+        )
+    }
+
+    async fn post(&self, channel: &str, url: &str, body: &serde_json::Value) {
+        match self.client.post(url).json(body).send().await {
+            Ok(resp) if resp.status().is_success() => {
+                tracing::debug!(channel, "upload notification sent");
+            }
+            Ok(resp) => {
+                tracing::warn!(channel, status = %resp.status(), "upload notification rejected");
+            }
+            Err(e) => {
+                tracing::warn!(channel, error = %e, "upload notification failed");
+            }
+        }
+    }
+}
@@ -1,11 +1,71 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::Stream;
+use tracing_appender::non_blocking::{NonBlockingBuilder, WorkerGuard};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::{DefaultFields, Writer};
+use tracing_subscriber::fmt::FormatFields;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
-pub fn init(log_filter: &str) {
-    let filter = EnvFilter::try_new(log_filter)
+use crate::config::AppConfig;
+use crate::util::logfile::RollingFile;
+
+/// 文件日志的写入线程和链路导出器，drop 时把剩余的日志和 span 写完
+pub struct Guard {
+    _file: Option<WorkerGuard>,
+    #[cfg(feature = "otel")]
+    tracer: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(tracer) = self.tracer.take() {
+            if let Err(e) = tracer.shutdown() {
+                eprintln!("flushing traces failed: {}", e);
+            }
+        }
+    }
+}
+
+/// 初始化日志；返回的 guard 需要保持到进程退出
+pub fn init(config: &AppConfig) -> anyhow::Result<Guard> {
+    let filter = EnvFilter::try_new(&config.log_filter)
         .unwrap_or_else(|_| EnvFilter::new("info,transfer_app=debug"));
 
+    let (file_layer, guard) = match &config.log_file {
+        Some(path) => {
+            let file = RollingFile::open(path.clone(), config.log_rotate, config.log_max_size, config.log_keep)
+                .map_err(|e| anyhow::anyhow!("cannot open log file '{}': {}", path.display(), e))?;
+            // 写不过来时阻塞而不是丢弃，避免日志文件出现空缺
+            let (writer, guard) = NonBlockingBuilder::default().lossy(false).finish(file);
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_target(true)
+                .fmt_fields(PlainFields::default())
+                .with_writer(writer);
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    #[cfg(feature = "otel")]
+    let (otel_layer, tracer) = match &config.otlp_endpoint {
+        Some(endpoint) => {
+            let (layer, provider) = otlp_layer(endpoint)?;
+            (Some(layer), Some(provider))
+        }
+        None => (None, None),
+    };
+    #[cfg(not(feature = "otel"))]
+    let otel_layer: Option<tracing_subscriber::layer::Identity> = match &config.otlp_endpoint {
+        Some(_) => anyhow::bail!("--otlp-endpoint requires a build with the `otel` feature"),
+        None => None,
+    };
+
     tracing_subscriber::registry()
         .with(filter)
         .with(
@@ -15,5 +75,87 @@ pub fn init(log_filter: &str) {
                 .with_thread_ids(false)
                 .with_file(false),
         )
+        .with(file_layer)
+        .with(otel_layer)
         .init();
+    Ok(Guard {
+        _file: guard,
+        #[cfg(feature = "otel")]
+        tracer,
+    })
+}
+
+/// 导出器在后台线程里批量发送，不依赖 tokio 运行时
+#[cfg(feature = "otel")]
+fn otlp_layer<S>(
+    endpoint: &str,
+) -> anyhow::Result<(
+    tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry_sdk::trace::SdkTracer>,
+    opentelemetry_sdk::trace::SdkTracerProvider,
+)>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+
+    // 与 OTEL_EXPORTER_OTLP_ENDPOINT 一致，给的是采集器的基础地址
+    let endpoint = endpoint.trim_end_matches('/');
+    let endpoint = if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    };
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| anyhow::anyhow!("cannot create OTLP exporter: {}", e))?;
+    let resource = opentelemetry_sdk::Resource::builder()
+        .with_service_name(env!("CARGO_PKG_NAME"))
+        .build();
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME")));
+    Ok((layer, provider))
+}
+
+pin_project_lite::pin_project! {
+    /// 响应体发送完或连接断开时才结束的 span，读取文件等操作都记在它下面
+    pub struct InSpan<S> {
+        #[pin]
+        inner: S,
+        span: tracing::Span,
+    }
+}
+
+/// 让流式响应体的耗时计入 span，而不只是处理函数返回之前的部分
+pub fn in_span<S: Stream>(inner: S, span: tracing::Span) -> InSpan<S> {
+    InSpan { inner, span }
+}
+
+impl<S: Stream> Stream for InSpan<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let this = self.project();
+        let _enter = this.span.enter();
+        this.inner.poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// span 字段按格式化器类型缓存；文件日志用单独的类型，避免复用终端输出带颜色的结果
+#[derive(Default)]
+struct PlainFields(DefaultFields);
+
+impl<'w> FormatFields<'w> for PlainFields {
+    fn format_fields<R: RecordFields>(&self, writer: Writer<'w>, fields: R) -> std::fmt::Result {
+        self.0.format_fields(writer, fields)
+    }
 }
@@ -0,0 +1,354 @@
+//! WASM 插件宿主
+//!
+//! 插件是不依赖 WASI 的 wasm 模块，只能调用宿主提供的 `env.log`，不能访问文件和网络。
+//! 需要导出 `memory` 和 `alloc(len: i32) -> i32`，以及以下任意几个入口，
+//! 参数都是写入插件内存的 JSON (ptr, len)：
+//!
+//! - `on_request(ptr, len) -> i64`：每个 HTTP 请求，返回 `{"deny": {"status": 403, "message": "..."}}` 拒绝
+//! - `on_upload(ptr, len) -> i64`：上传落盘前，返回 `{"reject": "..."}` 拒绝或 `{"rename": "新文件名"}` 改名
+//! - `on_event(ptr, len) -> i64`：与事件钩子相同的事件，返回值忽略
+//!
+//! 返回 0 表示不干预，否则为 `(ptr << 32) | len` 指向插件内存中的 JSON 结果。
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use wasmtime::{Caller, Engine, InstancePre, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::error::AppError;
+use crate::fs::path_safety::relative_path;
+use crate::middleware::auth;
+use crate::state::AppState;
+
+/// 单次调用的 fuel 上限 (约等于执行的指令数)，防止插件死循环
+const CALL_FUEL: u64 = 500_000_000;
+/// 插件线性内存上限
+const MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+/// 插件返回结果的长度上限
+const MAX_OUTPUT: usize = 1024 * 1024;
+
+/// 插件可以实现的入口
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entry {
+    Request,
+    Upload,
+    Event,
+}
+
+impl Entry {
+    const ALL: [Entry; 3] = [Entry::Request, Entry::Upload, Entry::Event];
+
+    fn export_name(self) -> &'static str {
+        match self {
+            Self::Request => "on_request",
+            Self::Upload => "on_upload",
+            Self::Event => "on_event",
+        }
+    }
+}
+
+struct Ctx {
+    plugin: String,
+    limits: StoreLimits,
+}
+
+struct Plugin {
+    name: String,
+    pre: InstancePre<Ctx>,
+    entries: Vec<Entry>,
+}
+
+/// 启动时加载的全部插件，按命令行顺序依次调用
+pub struct PluginHost {
+    engine: Engine,
+    plugins: Vec<Plugin>,
+}
+
+#[derive(Deserialize)]
+struct Deny {
+    #[serde(default)]
+    status: Option<u16>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct Verdict {
+    #[serde(default)]
+    deny: Option<Deny>,
+    #[serde(default)]
+    reject: Option<String>,
+    #[serde(default)]
+    rename: Option<String>,
+}
+
+impl PluginHost {
+    pub fn load(paths: &[PathBuf]) -> anyhow::Result<Self> {
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+
+        let mut linker: Linker<Ctx> = Linker::new(&engine);
+        linker.func_wrap("env", "log", |mut caller: Caller<'_, Ctx>, ptr: i32, len: i32| {
+            if let Ok(bytes) = read_memory(&mut caller, ptr, len) {
+                let plugin = &caller.data().plugin;
+                tracing::info!(plugin = %plugin, "{}", String::from_utf8_lossy(&bytes));
+            }
+        })?;
+
+        let mut plugins = Vec::with_capacity(paths.len());
+        for path in paths {
+            let module = Module::from_file(&engine, path)
+                .map_err(|e| anyhow::Error::from(e).context(format!("cannot load plugin {}", path.display())))?;
+            let pre = linker.instantiate_pre(&module).map_err(|e| {
+                anyhow::Error::from(e).context(format!("plugin {} imports unsupported functions", path.display()))
+            })?;
+            let entries: Vec<Entry> = Entry::ALL
+                .into_iter()
+                .filter(|e| module.get_export(e.export_name()).is_some())
+                .collect();
+            if module.get_export("memory").is_none() || module.get_export("alloc").is_none() {
+                anyhow::bail!("plugin {} must export `memory` and `alloc`", path.display());
+            }
+            let name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            tracing::info!(plugin = %name, entries = ?entries, "plugin loaded");
+            plugins.push(Plugin { name, pre, entries });
+        }
+        Ok(Self { engine, plugins })
+    }
+
+    /// 是否有插件实现了该入口
+    pub fn has(&self, entry: Entry) -> bool {
+        self.plugins.iter().any(|p| p.entries.contains(&entry))
+    }
+
+    /// 请求过滤：任一插件拒绝即返回其状态码
+    pub fn check_request(&self, input: &Value) -> Result<(), AppError> {
+        for plugin in self.implementing(Entry::Request) {
+            let verdict = self.call(plugin, Entry::Request, input)?;
+            if let Some(deny) = verdict.deny {
+                let status = deny
+                    .status
+                    .and_then(|s| StatusCode::from_u16(s).ok())
+                    .filter(|s| s.is_client_error() || s.is_server_error())
+                    .unwrap_or(StatusCode::FORBIDDEN);
+                return Err(AppError::PluginRejected {
+                    plugin: plugin.name.clone(),
+                    status,
+                    reason: deny.message.unwrap_or_else(|| "request denied".into()),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 上传落盘前的校验和改名，返回最终文件名（未改名时为 None）
+    pub fn check_upload(&self, mut input: Value) -> Result<Option<String>, AppError> {
+        let mut renamed = None;
+        for plugin in self.implementing(Entry::Upload) {
+            let verdict = self.call(plugin, Entry::Upload, &input)?;
+            if let Some(reason) = verdict.reject {
+                return Err(AppError::PluginRejected {
+                    plugin: plugin.name.clone(),
+                    status: StatusCode::UNPROCESSABLE_ENTITY,
+                    reason,
+                });
+            }
+            if let Some(name) = verdict.rename {
+                let name = sanitize_filename::sanitize(&name);
+                if name.is_empty() {
+                    return Err(AppError::Internal(anyhow::anyhow!(
+                        "plugin {} returned an invalid filename",
+                        plugin.name
+                    )));
+                }
+                // 后面的插件看到的是改名后的文件名
+                input["filename"] = Value::String(name.clone());
+                renamed = Some(name);
+            }
+        }
+        Ok(renamed)
+    }
+
+    /// 把事件交给插件，失败只记录日志
+    pub fn notify(&self, event: &Value) {
+        for plugin in self.implementing(Entry::Event) {
+            if let Err(e) = self.call(plugin, Entry::Event, event) {
+                tracing::warn!(plugin = %plugin.name, error = %e, "plugin on_event failed");
+            }
+        }
+    }
+
+    fn implementing(&self, entry: Entry) -> impl Iterator<Item = &Plugin> {
+        self.plugins.iter().filter(move |p| p.entries.contains(&entry))
+    }
+
+    /// 每次调用使用新的实例，插件之间、请求之间不共享状态
+    fn call(&self, plugin: &Plugin, entry: Entry, input: &Value) -> Result<Verdict, AppError> {
+        self.call_raw(plugin, entry, input).map_err(|e| {
+            AppError::Internal(anyhow::anyhow!(
+                "plugin {} {} failed: {:#}",
+                plugin.name,
+                entry.export_name(),
+                e
+            ))
+        })
+    }
+
+    fn call_raw(&self, plugin: &Plugin, entry: Entry, input: &Value) -> anyhow::Result<Verdict> {
+        let ctx = Ctx {
+            plugin: plugin.name.clone(),
+            limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+        };
+        let mut store = Store::new(&self.engine, ctx);
+        store.limiter(|ctx| &mut ctx.limits);
+        store.set_fuel(CALL_FUEL)?;
+
+        let instance = plugin.pre.instantiate(&mut store)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .context("missing memory export")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let func = instance.get_typed_func::<(i32, i32), i64>(&mut store, entry.export_name())?;
+
+        let input = serde_json::to_vec(input)?;
+        let len = i32::try_from(input.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, ptr as u32 as usize, &input)?;
+        let packed = func.call(&mut store, (ptr, len))? as u64;
+        if packed == 0 || entry == Entry::Event {
+            return Ok(Verdict::default());
+        }
+
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        anyhow::ensure!(out_len <= MAX_OUTPUT, "result too large: {} bytes", out_len);
+        let mut out = vec![0; out_len];
+        memory.read(&store, out_ptr, &mut out)?;
+        serde_json::from_slice(&out).context("invalid result JSON")
+    }
+}
+
+fn read_memory(caller: &mut Caller<'_, Ctx>, ptr: i32, len: i32) -> anyhow::Result<Vec<u8>> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(|e| e.into_memory())
+        .context("missing memory export")?;
+    let len = (len as u32 as usize).min(MAX_OUTPUT);
+    let mut buf = vec![0; len];
+    memory.read(&*caller, ptr as u32 as usize, &mut buf)?;
+    Ok(buf)
+}
+
+/// 请求过滤中间件，只在有插件实现 on_request 时挂载
+pub async fn filter(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(host) = state.plugins.clone().filter(|h| h.has(Entry::Request)) else {
+        return next.run(req).await;
+    };
+    let client_ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let input = json!({
+        "method": req.method().as_str(),
+        "path": req.uri().path(),
+        "query": req.uri().query(),
+        "client_ip": client_ip,
+        "user": auth::current_user(),
+    });
+    match state.blocking.run(move || host.check_request(&input)).await.and_then(|r| r) {
+        Ok(()) => next.run(req).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// 上传落盘前询问插件，返回插件指定的新文件名
+pub async fn check_upload(
+    state: &AppState,
+    src: &Path,
+    final_dir: &Path,
+    filename: &str,
+) -> Result<Option<String>, AppError> {
+    let Some(host) = state.plugins.clone().filter(|h| h.has(Entry::Upload)) else {
+        return Ok(None);
+    };
+    let size = tokio::fs::metadata(src).await?.len();
+    let input = json!({
+        "filename": filename,
+        "dir": relative_path(final_dir, &state.root),
+        "size": size,
+        "user": auth::current_user(),
+    });
+    state.blocking.run(move || host.check_upload(input)).await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 拒绝路径以 /api/secret 开头的请求，上传一律改名为 renamed.txt
+    const PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 0) "{\"deny\":{\"status\":451,\"message\":\"no\"}}")
+          (data (i32.const 100) "{\"rename\":\"renamed.txt\"}")
+          (data (i32.const 200) "\"path\":\"/api/secret")
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $p i32)
+            (local.set $p (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $p))
+          ;; 在输入中查找 200 处的 19 字节
+          (func $contains (param $ptr i32) (param $len i32) (result i32)
+            (local $i i32) (local $j i32)
+            (block $done
+              (loop $outer
+                (br_if $done (i32.gt_s (i32.add (local.get $i) (i32.const 19)) (local.get $len)))
+                (local.set $j (i32.const 0))
+                (block $mismatch
+                  (loop $inner
+                    (br_if $mismatch
+                      (i32.ne
+                        (i32.load8_u (i32.add (i32.add (local.get $ptr) (local.get $i)) (local.get $j)))
+                        (i32.load8_u (i32.add (i32.const 200) (local.get $j)))))
+                    (local.set $j (i32.add (local.get $j) (i32.const 1)))
+                    (br_if $inner (i32.lt_s (local.get $j) (i32.const 19)))
+                    (return (i32.const 1))))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $outer)))
+            (i32.const 0))
+          (func (export "on_request") (param $ptr i32) (param $len i32) (result i64)
+            (if (result i64) (call $contains (local.get $ptr) (local.get $len))
+              (then (i64.const 38))
+              (else (i64.const 0))))
+          (func (export "on_upload") (param i32 i32) (result i64)
+            (i64.or (i64.shl (i64.const 100) (i64.const 32)) (i64.const 24))))
+    "#;
+
+    #[test]
+    fn test_plugin_verdicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("guard.wat");
+        std::fs::write(&path, PLUGIN).unwrap();
+        let host = PluginHost::load(&[path]).unwrap();
+        assert!(host.has(Entry::Request) && host.has(Entry::Upload) && !host.has(Entry::Event));
+
+        assert!(host.check_request(&json!({ "path": "/api/files" })).is_ok());
+        let err = host.check_request(&json!({ "path": "/api/secret/x" })).unwrap_err();
+        assert_eq!(err.status_and_code().0, StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
+
+        let renamed = host.check_upload(json!({ "filename": "a.exe" })).unwrap();
+        assert_eq!(renamed.as_deref(), Some("renamed.txt"));
+    }
+}
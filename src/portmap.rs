@@ -0,0 +1,257 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+use igd_next::aio::tokio::Tokio;
+use igd_next::aio::Gateway;
+use igd_next::{AddPortError, PortMappingProtocol, SearchOptions};
+use tokio::net::UdpSocket;
+
+use crate::config::AppConfig;
+use crate::util::ip;
+
+/// 映射的租期；到期前续租，进程退出后路由器会自行回收
+const LEASE: Duration = Duration::from_secs(30 * 60);
+
+/// UPnP 网关搜索的超时
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// NAT-PMP 网关端口 (RFC 6886)
+const NATPMP_PORT: u16 = 5351;
+
+/// NAT-PMP 请求的重传次数，间隔从 250ms 起每次翻倍
+const NATPMP_TRIES: u32 = 4;
+
+const DESCRIPTION: &str = "transfer-app";
+
+/// 已建立的端口映射
+struct Mapping {
+    method: Method,
+    external: SocketAddrV4,
+    /// 路由器实际给出的租期，0 表示永久
+    lease: Duration,
+}
+
+enum Method {
+    Upnp(Gateway<Tokio>),
+    NatPmp(Ipv4Addr),
+}
+
+impl Method {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Upnp(_) => "UPnP",
+            Self::NatPmp(_) => "NAT-PMP",
+        }
+    }
+}
+
+/// 开启 --public 时在后台请求端口映射，成功后打印公网地址并定期续租
+pub fn spawn(config: &AppConfig, port: u16) {
+    if !config.public {
+        return;
+    }
+    let local = match local_ipv4(config.bind) {
+        Some(ip) => SocketAddrV4::new(ip, port),
+        None => {
+            tracing::warn!(bind = %config.bind, "--public needs an IPv4 LAN address to forward to");
+            return;
+        }
+    };
+    if config.users.is_empty() {
+        tracing::warn!("--public without any users configured: anyone who learns the public address can access the shared directory");
+    }
+    let protocol = if config.tls_cert.is_some() { "https" } else { "http" };
+
+    tokio::spawn(async move {
+        let mut mapping = match map(local).await {
+            Ok(mapping) => mapping,
+            Err(e) => {
+                tracing::warn!(error = %e, "port forwarding failed; the server is only reachable on the LAN");
+                return;
+            }
+        };
+        tracing::info!(
+            method = mapping.method.name(),
+            external = %mapping.external,
+            lease_secs = mapping.lease.as_secs(),
+            "port forwarded"
+        );
+        println!("  公网地址: {}://{}", protocol, mapping.external);
+        println!();
+
+        // 永久映射不需要续租
+        while !mapping.lease.is_zero() {
+            tokio::time::sleep(mapping.lease / 2).await;
+            let renewed = match &mapping.method {
+                Method::Upnp(gateway) => upnp_add(gateway, local, mapping.external.port()).await,
+                Method::NatPmp(gateway) => natpmp_map(*gateway, local.port(), mapping.external.port()).await,
+            };
+            match renewed {
+                Ok((external_port, lease)) => {
+                    if external_port != mapping.external.port() {
+                        tracing::warn!(port = external_port, "router changed the external port");
+                        mapping.external.set_port(external_port);
+                        println!("  公网地址: {}://{}", protocol, mapping.external);
+                    }
+                    mapping.lease = lease;
+                }
+                Err(e) => tracing::warn!(error = %e, method = mapping.method.name(), "renewing port mapping failed"),
+            }
+        }
+    });
+}
+
+/// 监听的 IPv4 地址；监听所有地址时用本机局域网 IP
+fn local_ipv4(bind: IpAddr) -> Option<Ipv4Addr> {
+    match bind {
+        IpAddr::V4(ip) if ip.is_unspecified() => ip::lan_addrs().into_iter().find_map(|a| match a.ip {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(_) => None,
+        }),
+        IpAddr::V4(ip) if !ip.is_loopback() => Some(ip),
+        _ => None,
+    }
+}
+
+/// 先试 UPnP，路由器不支持时再试 NAT-PMP
+async fn map(local: SocketAddrV4) -> anyhow::Result<Mapping> {
+    let upnp_err = match upnp_map(local).await {
+        Ok(mapping) => return Ok(mapping),
+        Err(e) => e,
+    };
+    let Some(gateway) = default_gateway() else {
+        anyhow::bail!("UPnP: {}; NAT-PMP: default gateway unknown", upnp_err);
+    };
+    natpmp(gateway, local)
+        .await
+        .map_err(|e| anyhow::anyhow!("UPnP: {}; NAT-PMP: {}", upnp_err, e))
+}
+
+async fn upnp_map(local: SocketAddrV4) -> anyhow::Result<Mapping> {
+    let gateway = igd_next::aio::tokio::search_gateway(SearchOptions {
+        timeout: Some(SEARCH_TIMEOUT),
+        ..Default::default()
+    })
+    .await?;
+    let external_ip = match gateway.get_external_ip().await? {
+        IpAddr::V4(ip) => ip,
+        IpAddr::V6(ip) => anyhow::bail!("gateway reported an IPv6 external address {}", ip),
+    };
+    let (port, lease) = upnp_add(&gateway, local, local.port()).await?;
+    Ok(Mapping {
+        method: Method::Upnp(gateway),
+        external: SocketAddrV4::new(external_ip, port),
+        lease,
+    })
+}
+
+/// 优先使用与本地相同的外部端口，被占用时让路由器另选一个
+async fn upnp_add(gateway: &Gateway<Tokio>, local: SocketAddrV4, port: u16) -> anyhow::Result<(u16, Duration)> {
+    let addr = SocketAddr::V4(local);
+    let tcp = PortMappingProtocol::TCP;
+    match gateway.add_port(tcp, port, addr, LEASE.as_secs() as u32, DESCRIPTION).await {
+        Ok(()) => Ok((port, LEASE)),
+        // 有些路由器只接受永久映射
+        Err(AddPortError::OnlyPermanentLeasesSupported) => {
+            gateway.add_port(tcp, port, addr, 0, DESCRIPTION).await?;
+            tracing::warn!("router only supports permanent mappings; it stays open after exit");
+            Ok((port, Duration::ZERO))
+        }
+        Err(AddPortError::PortInUse) => {
+            let port = gateway.add_any_port(tcp, addr, LEASE.as_secs() as u32, DESCRIPTION).await?;
+            Ok((port, LEASE))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn natpmp(gateway: Ipv4Addr, local: SocketAddrV4) -> anyhow::Result<Mapping> {
+    let external_ip = natpmp_external_ip(gateway).await?;
+    let (port, lease) = natpmp_map(gateway, local.port(), local.port()).await?;
+    Ok(Mapping {
+        method: Method::NatPmp(gateway),
+        external: SocketAddrV4::new(external_ip, port),
+        lease,
+    })
+}
+
+/// 查询外部地址：请求 [版本 0, 操作 0]，响应第 8..12 字节为 IPv4 地址
+async fn natpmp_external_ip(gateway: Ipv4Addr) -> anyhow::Result<Ipv4Addr> {
+    let resp = natpmp_request(gateway, &[0, 0], 12).await?;
+    Ok(Ipv4Addr::new(resp[8], resp[9], resp[10], resp[11]))
+}
+
+/// 映射 TCP 端口，返回路由器分配的外部端口和租期
+async fn natpmp_map(gateway: Ipv4Addr, internal: u16, external: u16) -> anyhow::Result<(u16, Duration)> {
+    let mut req = [0u8; 12];
+    req[1] = 2;
+    req[4..6].copy_from_slice(&internal.to_be_bytes());
+    req[6..8].copy_from_slice(&external.to_be_bytes());
+    req[8..12].copy_from_slice(&(LEASE.as_secs() as u32).to_be_bytes());
+    let resp = natpmp_request(gateway, &req, 16).await?;
+    let port = u16::from_be_bytes([resp[10], resp[11]]);
+    let lease = u32::from_be_bytes([resp[12], resp[13], resp[14], resp[15]]);
+    Ok((port, Duration::from_secs(lease.into())))
+}
+
+/// 发送请求并等待对应操作的响应，检查结果码
+async fn natpmp_request(gateway: Ipv4Addr, req: &[u8], len: usize) -> anyhow::Result<Vec<u8>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.connect((gateway, NATPMP_PORT)).await?;
+    let mut buf = [0u8; 16];
+    let mut wait = Duration::from_millis(250);
+    for _ in 0..NATPMP_TRIES {
+        socket.send(req).await?;
+        if let Ok(received) = tokio::time::timeout(wait, socket.recv(&mut buf)).await {
+            let n = received?;
+            // 响应的操作码是请求的加 128
+            if n >= len && buf[1] == req[1] + 128 {
+                return match u16::from_be_bytes([buf[2], buf[3]]) {
+                    0 => Ok(buf[..len].to_vec()),
+                    code => anyhow::bail!("gateway returned result code {}", code),
+                };
+            }
+        }
+        wait *= 2;
+    }
+    anyhow::bail!("no response from {}:{}", gateway, NATPMP_PORT)
+}
+
+/// 默认网关，目前只在 Linux 上从路由表读取
+#[cfg(target_os = "linux")]
+fn default_gateway() -> Option<Ipv4Addr> {
+    let table = std::fs::read_to_string("/proc/net/route").ok()?;
+    parse_route_table(&table)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn default_gateway() -> Option<Ipv4Addr> {
+    None
+}
+
+/// /proc/net/route 中目标为 0.0.0.0 的一行，网关是按本机字节序打印的网络序地址
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_route_table(table: &str) -> Option<Ipv4Addr> {
+    table.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.get(1) != Some(&"00000000") {
+            return None;
+        }
+        let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+        (gateway != 0).then(|| Ipv4Addr::from(gateway.to_ne_bytes()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_route_table() {
+        let table = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+                     eth0\t0001A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n\
+                     eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\n";
+        assert_eq!(parse_route_table(table), Some(Ipv4Addr::new(192, 168, 1, 1)));
+        assert_eq!(parse_route_table("Iface\tDestination\tGateway\n"), None);
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+use tokio::process::{Child, Command};
+
+/// 浏览器原生无法播放、需要转码的视频扩展名
+pub fn needs_transcode(path: &Path) -> bool {
+    matches!(
+        path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .as_deref(),
+        Some("mkv" | "avi" | "wmv" | "flv" | "mpg" | "mpeg" | "ts" | "m2ts" | "3gp" | "rmvb")
+    )
+}
+
+/// 基于 ffmpeg 的 HLS 转码管理器，输出缓存在 tmp/hls/<key>/
+pub struct HlsManager {
+    ffmpeg: PathBuf,
+    cache_dir: PathBuf,
+    running: Arc<parking_lot::Mutex<HashSet<String>>>,
+}
+
+const PLAYLIST: &str = "index.m3u8";
+
+impl HlsManager {
+    pub fn new(ffmpeg: PathBuf, cache_dir: PathBuf) -> Self {
+        discard_unfinished(&cache_dir);
+        Self {
+            ffmpeg,
+            cache_dir,
+            running: Arc::new(parking_lot::Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// 源文件 + etag 决定缓存 key，文件变化后重新转码
+    pub fn cache_key(source: &Path, etag: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(source.to_string_lossy().as_bytes());
+        hasher.update(etag.as_bytes());
+        hex::encode(&hasher.finalize()[..16])
+    }
+
+    /// 确保转码已启动，等待播放列表生成后返回其路径
+    pub async fn playlist(&self, source: &Path, key: &str) -> anyhow::Result<PathBuf> {
+        let out_dir = self.cache_dir.join(key);
+        let playlist = out_dir.join(PLAYLIST);
+
+        let spawn = !playlist.exists() && self.running.lock().insert(key.to_string());
+        if spawn {
+            tokio::fs::create_dir_all(&out_dir).await?;
+            let child = self.spawn_hls(source, &out_dir)?;
+            tracing::info!(source = %source.display(), key, "hls transcode started");
+            self.watch(child, key.to_string());
+        }
+
+        // ffmpeg 写出第一个分片后才会生成播放列表
+        for _ in 0..60 {
+            if playlist.exists() {
+                return Ok(playlist);
+            }
+            if !self.running.lock().contains(key) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        anyhow::bail!("transcode did not produce a playlist")
+    }
+
+    /// 分片文件路径；key 和文件名都做白名单校验
+    pub fn segment_path(&self, key: &str, name: &str) -> Option<PathBuf> {
+        let key_ok = key.len() == 32 && key.chars().all(|c| c.is_ascii_hexdigit());
+        let name_ok = name.starts_with("seg")
+            && name.ends_with(".ts")
+            && name[3..name.len() - 3].chars().all(|c| c.is_ascii_digit());
+        (key_ok && name_ok).then(|| self.cache_dir.join(key).join(name))
+    }
+
+    /// 以 fragmented MP4 输出到 stdout，供不支持 HLS 的浏览器渐进播放
+    pub fn spawn_mp4_stream(&self, source: &Path) -> std::io::Result<Child> {
+        Command::new(&self.ffmpeg)
+            .args(["-hide_banner", "-loglevel", "error", "-i"])
+            .arg(source)
+            .args(TRANSCODE_ARGS)
+            .args(["-movflags", "frag_keyframe+empty_moov+default_base_moof", "-f", "mp4", "pipe:1"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+    }
+
+    fn spawn_hls(&self, source: &Path, out_dir: &Path) -> std::io::Result<Child> {
+        Command::new(&self.ffmpeg)
+            .args(["-hide_banner", "-loglevel", "error", "-i"])
+            .arg(source)
+            .args(TRANSCODE_ARGS)
+            .args(["-f", "hls", "-hls_time", "6", "-hls_playlist_type", "event"])
+            .arg("-hls_segment_filename")
+            .arg(out_dir.join("seg%05d.ts"))
+            .arg(out_dir.join(PLAYLIST))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+    }
+
+    fn watch(&self, child: Child, key: String) {
+        let out_dir = self.cache_dir.join(&key);
+        let running = self.running.clone();
+        tokio::spawn(async move {
+            match child.wait_with_output().await {
+                Ok(out) if out.status.success() => {
+                    tracing::info!(key = %key, "hls transcode finished");
+                }
+                Ok(out) => {
+                    tracing::warn!(
+                        key = %key,
+                        stderr = %String::from_utf8_lossy(&out.stderr).trim(),
+                        "hls transcode failed"
+                    );
+                    let _ = tokio::fs::remove_dir_all(&out_dir).await;
+                }
+                Err(e) => {
+                    tracing::warn!(key = %key, error = %e, "hls transcode failed");
+                    let _ = tokio::fs::remove_dir_all(&out_dir).await;
+                }
+            }
+            running.lock().remove(&key);
+        });
+    }
+}
+
+/// 上次运行中断的转码没有 ENDLIST，播放器会一直等待，启动时删掉重新生成
+fn discard_unfinished(cache_dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(cache_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let playlist = entry.path().join(PLAYLIST);
+        let finished = std::fs::read_to_string(&playlist)
+            .map(|s| s.contains("#EXT-X-ENDLIST"))
+            .unwrap_or(false);
+        if !finished {
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+const TRANSCODE_ARGS: [&str; 12] = [
+    "-c:v", "libx264", "-preset", "veryfast", "-crf", "23",
+    "-c:a", "aac", "-b:a", "128k", "-ac", "2",
+];
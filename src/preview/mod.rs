@@ -1,2 +1,5 @@
+pub mod hls;
 pub mod markdown;
+pub mod tail;
 pub mod text;
+pub mod thumbnail;
@@ -0,0 +1,150 @@
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use futures_util::Stream;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// 单次最多读取的字节数，避免追加量很大时一次读入过多
+const READ_CHUNK: u64 = 256 * 1024;
+/// 未换行的残余数据超过该长度时强制作为一行输出
+const MAX_PARTIAL: usize = 64 * 1024;
+
+/// 跟随过程中产生的事件
+#[derive(Debug)]
+pub enum TailEvent {
+    Lines(Vec<String>),
+    /// 文件被截断或替换，从头重新读取
+    Truncated,
+}
+
+/// 读取文件末尾最多 `n` 行，返回这些行和读取结束时的偏移
+pub async fn last_lines(path: &Path, n: usize) -> std::io::Result<(Vec<String>, u64)> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+
+    // 按每行 ~200 字节估算需要回读的长度
+    let window = ((n as u64).saturating_mul(200)).clamp(4096, 1024 * 1024).min(len);
+    let start = len - window;
+    file.seek(SeekFrom::Start(start)).await?;
+    let mut buf = Vec::with_capacity(window as usize);
+    file.take(window).read_to_end(&mut buf).await?;
+
+    // 末尾未换行的部分留给后续跟随；按原始字节定位，替换字符会改变解码后的长度
+    let complete = match buf.iter().rposition(|&b| b == b'\n') {
+        Some(pos) => pos + 1,
+        None => 0,
+    };
+    let offset = start + complete as u64;
+    let text = String::from_utf8_lossy(&buf[..complete]);
+    let mut lines: Vec<&str> = text.split('\n').collect();
+    // 最后一个换行之后是空串
+    lines.pop();
+    // 没有从文件开头读取时，第一行可能不完整
+    if start > 0 && !lines.is_empty() {
+        lines.remove(0);
+    }
+
+    let skip = lines.len().saturating_sub(n);
+    let lines = lines[skip..]
+        .iter()
+        .map(|l| l.trim_end_matches('\r').to_string())
+        .collect();
+    Ok((lines, offset))
+}
+
+struct Follower {
+    path: PathBuf,
+    offset: u64,
+    partial: Vec<u8>,
+    interval: tokio::time::Interval,
+}
+
+/// 从 offset 开始轮询文件，输出新追加的完整行
+pub fn follow(path: PathBuf, offset: u64, poll: Duration) -> impl Stream<Item = TailEvent> {
+    let mut interval = tokio::time::interval(poll);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let state = Follower {
+        path,
+        offset,
+        partial: Vec::new(),
+        interval,
+    };
+
+    futures_util::stream::unfold(state, |mut st| async move {
+        loop {
+            st.interval.tick().await;
+            // 文件暂时不存在（例如日志轮转中）时继续等待
+            let Ok(meta) = tokio::fs::metadata(&st.path).await else {
+                continue;
+            };
+            let len = meta.len();
+
+            if len < st.offset {
+                st.offset = 0;
+                st.partial.clear();
+                return Some((TailEvent::Truncated, st));
+            }
+            if len == st.offset {
+                continue;
+            }
+
+            let Ok(chunk) = read_from(&st.path, st.offset, (len - st.offset).min(READ_CHUNK)).await
+            else {
+                continue;
+            };
+            st.offset += chunk.len() as u64;
+            st.partial.extend_from_slice(&chunk);
+
+            let lines = split_complete_lines(&mut st.partial);
+            if !lines.is_empty() {
+                return Some((TailEvent::Lines(lines), st));
+            }
+        }
+    })
+}
+
+async fn read_from(path: &Path, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = tokio::fs::File::open(path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut buf = Vec::with_capacity(len as usize);
+    file.take(len).read_to_end(&mut buf).await?;
+    Ok(buf)
+}
+
+/// 取出缓冲区中所有以换行结束的行，剩余部分留在缓冲区
+fn split_complete_lines(buf: &mut Vec<u8>) -> Vec<String> {
+    let cut = match buf.iter().rposition(|&b| b == b'\n') {
+        Some(pos) => pos + 1,
+        None if buf.len() > MAX_PARTIAL => buf.len(),
+        None => return Vec::new(),
+    };
+    let rest = buf.split_off(cut);
+    let complete = std::mem::replace(buf, rest);
+    String::from_utf8_lossy(&complete)
+        .trim_end_matches('\n')
+        .split('\n')
+        .map(|l| l.trim_end_matches('\r').to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_last_lines_invalid_utf8_partial() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+
+        std::fs::write(&path, b"\xff").unwrap();
+        let (lines, offset) = last_lines(&path, 10).await.unwrap();
+        assert!(lines.is_empty());
+        assert_eq!(offset, 0);
+
+        std::fs::write(&path, b"one\ntwo\r\nbad \xff\xfe").unwrap();
+        let (lines, offset) = last_lines(&path, 10).await.unwrap();
+        assert_eq!(lines, vec!["one", "two"]);
+        assert_eq!(offset, 9);
+    }
+}
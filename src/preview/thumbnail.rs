@@ -0,0 +1,141 @@
+use std::io::{BufRead, Seek};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::ImageFormat;
+use sha2::{Digest, Sha256};
+
+/// 单边最大尺寸，避免请求生成超大图片
+pub const MAX_DIMENSION: u32 = 4096;
+const DEFAULT_QUALITY: u8 = 80;
+/// 缓存文件超过这个时间未重新生成就清理
+pub const CACHE_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// 缩放参数：按比例缩放到 w×h 以内，不放大
+#[derive(Debug, Clone, Copy)]
+pub struct ResizeSpec {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub quality: Option<u8>,
+}
+
+impl ResizeSpec {
+    pub fn is_empty(&self) -> bool {
+        self.width.is_none() && self.height.is_none() && self.quality.is_none()
+    }
+
+    fn quality(&self) -> u8 {
+        self.quality.unwrap_or(DEFAULT_QUALITY).clamp(1, 100)
+    }
+}
+
+/// 是否为可缩放的图片类型
+pub fn is_resizable(mime: &str) -> bool {
+    matches!(
+        mime,
+        "image/jpeg" | "image/png" | "image/gif" | "image/webp" | "image/bmp"
+    )
+}
+
+/// 缓存文件路径：由源文件 etag 和缩放参数决定，源文件变化后自动失效
+pub fn cache_path(cache_dir: &Path, source: &Path, etag: &str, spec: &ResizeSpec) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(source.to_string_lossy().as_bytes());
+    hasher.update(etag.as_bytes());
+    hasher.update(format!("{:?}x{:?}q{}", spec.width, spec.height, spec.quality()).as_bytes());
+    let key = hex::encode(&hasher.finalize()[..16]);
+    cache_dir.join(format!("{}.{}", key, output_ext(source)))
+}
+
+/// 解码、缩放并写入缓存文件（同步，需放在 blocking 线程执行）
+///
+/// input 为 source 的内容，格式按 source 的扩展名判断
+pub fn render<R: BufRead + Seek>(
+    source: &Path,
+    input: R,
+    target: &Path,
+    spec: &ResizeSpec,
+) -> anyhow::Result<()> {
+    let img = image::ImageReader::with_format(input, ImageFormat::from_path(source)?).decode()?;
+
+    let max_w = spec.width.unwrap_or(u32::MAX).clamp(1, MAX_DIMENSION);
+    let max_h = spec.height.unwrap_or(u32::MAX).clamp(1, MAX_DIMENSION);
+    let img = if img.width() > max_w || img.height() > max_h {
+        img.resize(max_w, max_h, image::imageops::FilterType::Triangle)
+    } else {
+        img
+    };
+
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // 先写临时文件再 rename，避免并发请求读到半成品或互相覆盖
+    let tmp = temp_path(target, "tmp");
+    let result = (|| -> anyhow::Result<()> {
+        let mut out = std::io::BufWriter::new(std::fs::File::create(&tmp)?);
+        if output_ext(source) == "png" {
+            img.write_to(&mut out, ImageFormat::Png)?;
+        } else {
+            let encoder = JpegEncoder::new_with_quality(&mut out, spec.quality());
+            img.into_rgb8().write_with_encoder(encoder)?;
+        }
+        drop(out);
+        std::fs::rename(&tmp, target)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// 与 target 同目录的唯一临时文件名，并发生成同一缓存时互不干扰
+pub fn temp_path(target: &Path, ext: &str) -> PathBuf {
+    target.with_extension(format!("{}.{}", uuid::Uuid::new_v4().simple(), ext))
+}
+
+/// 删除缓存目录中超过 max_age 的文件（包括中断留下的临时文件），返回删除数量
+pub async fn prune(cache_dir: &Path, max_age: Duration) -> usize {
+    let Ok(mut entries) = tokio::fs::read_dir(cache_dir).await else {
+        return 0;
+    };
+    let now = std::time::SystemTime::now();
+    let mut removed = 0;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Ok(meta) = entry.metadata().await else {
+            continue;
+        };
+        let age = meta
+            .modified()
+            .ok()
+            .and_then(|t| now.duration_since(t).ok())
+            .unwrap_or_default();
+        if meta.is_file() && age >= max_age && tokio::fs::remove_file(entry.path()).await.is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// 输出 MIME
+pub fn output_mime(source: &Path) -> &'static str {
+    if output_ext(source) == "png" {
+        "image/png"
+    } else {
+        "image/jpeg"
+    }
+}
+
+/// PNG/GIF 可能带透明通道，保留为 PNG；其余统一输出 JPEG
+fn output_ext(source: &Path) -> &'static str {
+    match source
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("png") | Some("gif") => "png",
+        _ => "jpg",
+    }
+}
@@ -0,0 +1,116 @@
+use crate::config::AppConfig;
+
+/// 绑定端口之后切换到 --user / --group 指定的身份，之后处理请求、写文件都不再是 root
+///
+/// 没指定时什么都不做；指定了但进程不是 root 时报错，而不是带着原来的身份继续运行
+pub fn drop(config: &AppConfig) -> anyhow::Result<()> {
+    match &config.user {
+        Some(user) => imp::drop(user, config.group.as_deref()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::ffi::{CStr, CString};
+
+    use anyhow::{bail, Context};
+
+    pub fn drop(user: &str, group: Option<&str>) -> anyhow::Result<()> {
+        // SAFETY: geteuid 没有前置条件
+        if unsafe { libc::geteuid() } != 0 {
+            bail!("--user requires starting as root");
+        }
+        let user = lookup_user(user)?;
+        // 不指定组时使用该用户的主组
+        let gid = match group {
+            Some(group) => lookup_group(group)?,
+            None => user.gid,
+        };
+
+        // 先清掉 root 的附加组，再换组，最后换用户；顺序反了就没有权限再改组
+        // SAFETY: 传入的指针和长度描述一个有效的 gid 数组
+        if unsafe { libc::setgroups(1, &gid) } != 0 {
+            return Err(std::io::Error::last_os_error()).context("setgroups");
+        }
+        // SAFETY: 普通系统调用，失败通过返回值判断
+        if unsafe { libc::setgid(gid) } != 0 {
+            return Err(std::io::Error::last_os_error()).context("setgid");
+        }
+        // SAFETY: 同上
+        if unsafe { libc::setuid(user.uid) } != 0 {
+            return Err(std::io::Error::last_os_error()).context("setuid");
+        }
+        // 确认无法再切回 root
+        // SAFETY: 同上
+        if unsafe { libc::setuid(0) } == 0 {
+            bail!("privileges could not be dropped: setuid(0) still succeeds");
+        }
+        tracing::info!(user = %user.name, uid = user.uid, gid, "dropped privileges");
+        Ok(())
+    }
+
+    struct User {
+        name: String,
+        uid: libc::uid_t,
+        gid: libc::gid_t,
+    }
+
+    /// 用户名或数字 uid
+    fn lookup_user(name: &str) -> anyhow::Result<User> {
+        let mut buf = vec![0 as libc::c_char; 16 * 1024];
+        // SAFETY: passwd 是纯 C 结构体，全零是合法的初始值
+        let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        let rc = match name.parse::<libc::uid_t>() {
+            // SAFETY: 缓冲区和输出参数在调用期间有效
+            Ok(uid) => unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) },
+            Err(_) => {
+                let cname = CString::new(name).context("invalid user name")?;
+                // SAFETY: 同上
+                unsafe { libc::getpwnam_r(cname.as_ptr(), &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) }
+            }
+        };
+        if rc != 0 {
+            return Err(std::io::Error::from_raw_os_error(rc)).context("getpwnam");
+        }
+        if result.is_null() {
+            bail!("user '{}' not found", name);
+        }
+        // SAFETY: 查询成功时 pw_name 指向 buf 中以 NUL 结尾的字符串
+        let user_name = unsafe { CStr::from_ptr(pwd.pw_name) }.to_string_lossy().into_owned();
+        Ok(User {
+            name: user_name,
+            uid: pwd.pw_uid,
+            gid: pwd.pw_gid,
+        })
+    }
+
+    /// 组名或数字 gid
+    fn lookup_group(name: &str) -> anyhow::Result<libc::gid_t> {
+        if let Ok(gid) = name.parse() {
+            return Ok(gid);
+        }
+        let cname = CString::new(name).context("invalid group name")?;
+        let mut buf = vec![0 as libc::c_char; 16 * 1024];
+        // SAFETY: group 是纯 C 结构体，全零是合法的初始值
+        let mut grp: libc::group = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        // SAFETY: 缓冲区和输出参数在调用期间有效
+        let rc = unsafe { libc::getgrnam_r(cname.as_ptr(), &mut grp, buf.as_mut_ptr(), buf.len(), &mut result) };
+        if rc != 0 {
+            return Err(std::io::Error::from_raw_os_error(rc)).context("getgrnam");
+        }
+        if result.is_null() {
+            bail!("group '{}' not found", name);
+        }
+        Ok(grp.gr_gid)
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    pub fn drop(_user: &str, _group: Option<&str>) -> anyhow::Result<()> {
+        anyhow::bail!("--user is only supported on Unix")
+    }
+}
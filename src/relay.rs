@@ -0,0 +1,238 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use futures_util::{SinkExt, StreamExt};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio_util::codec::{AnyDelimiterCodec, Framed};
+
+use crate::config::AppConfig;
+
+/// bore 中继的控制端口
+const CONTROL_PORT: u16 = 7835;
+
+/// 控制消息以 \0 结尾，单条不超过 256 字节
+const MAX_FRAME: usize = 256;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 中继每 500ms 发一次心跳，超过该时长没有任何消息即认为连接已断开
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 控制连接断开后重连的间隔上限
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+type Control = Framed<TcpStream, AnyDelimiterCodec>;
+
+#[derive(Serialize)]
+enum ClientMessage {
+    Authenticate(String),
+    Hello(u16),
+    Accept(String),
+}
+
+#[derive(Deserialize)]
+enum ServerMessage {
+    Challenge(String),
+    Hello(u16),
+    Heartbeat,
+    Connection(String),
+    Error(String),
+}
+
+/// 中继连接参数
+struct Relay {
+    host: String,
+    control_port: u16,
+    /// 希望在中继上使用的端口，0 由中继分配
+    remote_port: u16,
+    auth: Option<hmac::Key>,
+    /// 本地服务的地址，中继转来的连接原样接到这里
+    local: SocketAddr,
+}
+
+/// 配置了 --relay 时在后台连接中继，把中继转来的连接接到本地服务
+///
+/// 中继只转发 TCP 字节流，认证、权限和 TLS 都仍由本地服务处理
+pub fn spawn(config: &AppConfig, port: u16) {
+    let Some(target) = config.relay.as_deref() else {
+        return;
+    };
+    let (host, control_port) = parse_target(target);
+    let local_ip = match config.bind {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(std::net::Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+    if config.users.is_empty() {
+        tracing::warn!("--relay without any users configured: anyone who learns the relay address can access the shared directory");
+    }
+    let relay = Arc::new(Relay {
+        host,
+        control_port,
+        remote_port: config.relay_port,
+        auth: config.relay_secret.as_deref().map(authenticator),
+        local: SocketAddr::new(local_ip, port),
+    });
+    let protocol = if config.tls_cert.is_some() { "https" } else { "http" };
+
+    tokio::spawn(async move {
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            let mut established = false;
+            if let Err(e) = relay.run(protocol, &mut established).await {
+                tracing::warn!(relay = %relay.host, error = %e, "relay connection lost");
+            }
+            // 建立过隧道后断开的，从最短间隔开始重连
+            if established {
+                backoff = Duration::from_secs(1);
+            }
+            tracing::info!(retry_secs = backoff.as_secs(), "reconnecting to relay");
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+}
+
+impl Relay {
+    /// 建立控制连接并处理转发请求，直到连接断开
+    async fn run(self: &Arc<Self>, protocol: &str, established: &mut bool) -> anyhow::Result<()> {
+        let mut control = self.connect().await?;
+        send(&mut control, ClientMessage::Hello(self.remote_port)).await?;
+        let remote_port = match recv(&mut control).await? {
+            ServerMessage::Hello(port) => port,
+            ServerMessage::Error(message) => bail!("relay error: {}", message),
+            ServerMessage::Challenge(_) => bail!("relay requires a secret; pass --relay-secret"),
+            _ => bail!("unexpected message from relay"),
+        };
+        *established = true;
+        tracing::info!(relay = %self.host, port = remote_port, "relay tunnel established");
+        println!("  中继地址: {}://{}:{}", protocol, self.host, remote_port);
+        println!();
+
+        loop {
+            match recv(&mut control).await? {
+                ServerMessage::Heartbeat => {}
+                ServerMessage::Connection(id) => {
+                    let relay = self.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = relay.forward(id).await {
+                            tracing::debug!(error = %e, "relay connection failed");
+                        }
+                    });
+                }
+                ServerMessage::Error(message) => tracing::warn!(%message, "relay error"),
+                ServerMessage::Hello(_) | ServerMessage::Challenge(_) => {
+                    tracing::warn!("unexpected message from relay")
+                }
+            }
+        }
+    }
+
+    /// 为一个外部连接另开一条到中继的连接，认领后与本地服务双向转发
+    async fn forward(&self, id: String) -> anyhow::Result<()> {
+        let mut remote = self.connect().await?;
+        send(&mut remote, ClientMessage::Accept(id)).await?;
+        let mut local = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(self.local))
+            .await
+            .context("local connect timed out")??;
+        let parts = remote.into_parts();
+        // 中继可能已经把客户端的首批数据跟在认领响应后面发过来
+        local.write_all(&parts.read_buf).await?;
+        let mut remote = parts.io;
+        tokio::io::copy_bidirectional(&mut local, &mut remote).await?;
+        Ok(())
+    }
+
+    /// 连接中继的控制端口，配置了密钥时先完成挑战应答
+    async fn connect(&self) -> anyhow::Result<Control> {
+        let stream = tokio::time::timeout(
+            CONNECT_TIMEOUT,
+            TcpStream::connect((self.host.as_str(), self.control_port)),
+        )
+        .await
+        .context("connect timed out")??;
+        stream.set_nodelay(true)?;
+        let codec = AnyDelimiterCodec::new_with_max_length(vec![0], vec![0], MAX_FRAME);
+        let mut control = Framed::new(stream, codec);
+        if let Some(key) = &self.auth {
+            let challenge = match recv(&mut control).await? {
+                ServerMessage::Challenge(challenge) => challenge,
+                _ => bail!("relay did not send an authentication challenge"),
+            };
+            send(&mut control, ClientMessage::Authenticate(answer(key, &challenge)?)).await?;
+        }
+        Ok(control)
+    }
+}
+
+/// `host`、`host:port`、`[v6]:port`，不带端口时用默认控制端口
+fn parse_target(target: &str) -> (String, u16) {
+    if let Ok(addr) = target.parse::<SocketAddr>() {
+        return (addr.ip().to_string(), addr.port());
+    }
+    match target.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') => match port.parse() {
+            Ok(port) => (host.to_string(), port),
+            Err(_) => (target.to_string(), CONTROL_PORT),
+        },
+        _ => (target.trim_matches(['[', ']']).to_string(), CONTROL_PORT),
+    }
+}
+
+/// 与 bore 相同：HMAC-SHA256，密钥为共享密钥的 SHA-256
+fn authenticator(secret: &str) -> hmac::Key {
+    hmac::Key::new(hmac::HMAC_SHA256, &Sha256::digest(secret.as_bytes()))
+}
+
+/// 对挑战 UUID 的 16 字节签名，返回 hex
+fn answer(key: &hmac::Key, challenge: &str) -> anyhow::Result<String> {
+    let challenge = uuid::Uuid::parse_str(challenge).context("invalid challenge")?;
+    Ok(hex::encode(hmac::sign(key, challenge.as_bytes())))
+}
+
+async fn send(control: &mut Control, message: ClientMessage) -> anyhow::Result<()> {
+    control.send(serde_json::to_string(&message)?).await?;
+    Ok(())
+}
+
+async fn recv(control: &mut Control) -> anyhow::Result<ServerMessage> {
+    match tokio::time::timeout(IDLE_TIMEOUT, control.next()).await {
+        Ok(Some(frame)) => Ok(serde_json::from_slice(&frame?)?),
+        Ok(None) => bail!("relay closed the connection"),
+        Err(_) => bail!("relay went silent"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(parse_target("bore.pub"), ("bore.pub".into(), CONTROL_PORT));
+        assert_eq!(parse_target("relay.lan:9000"), ("relay.lan".into(), 9000));
+        assert_eq!(parse_target("[::1]:9000"), ("::1".into(), 9000));
+        assert_eq!(parse_target("::1"), ("::1".into(), CONTROL_PORT));
+    }
+
+    #[test]
+    fn test_message_format() {
+        let hello = serde_json::to_string(&ClientMessage::Hello(8080)).unwrap();
+        assert_eq!(hello, r#"{"Hello":8080}"#);
+        assert!(matches!(
+            serde_json::from_str(r#""Heartbeat""#).unwrap(),
+            ServerMessage::Heartbeat
+        ));
+
+        let key = authenticator("secret");
+        let challenge = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        assert_eq!(answer(&key, challenge).unwrap().len(), 64);
+        assert!(answer(&key, "nope").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::config::{AppConfig, FileConfig};
+use crate::state::{effective_rules, AppState};
+
+/// 检查配置文件是否变化的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 运行中可以替换的设置；权限规则在 `Permissions` 内部替换
+pub struct LiveSettings {
+    users: parking_lot::RwLock<Arc<BTreeMap<String, String>>>,
+    max_upload_size: AtomicU64,
+}
+
+impl LiveSettings {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            users: parking_lot::RwLock::new(Arc::new(config.users.clone())),
+            max_upload_size: AtomicU64::new(config.max_upload_size),
+        }
+    }
+
+    /// Basic 认证用户 (用户名 -> 密码)
+    pub fn users(&self) -> Arc<BTreeMap<String, String>> {
+        self.users.read().clone()
+    }
+
+    /// 单文件最大上传 (字节, 0 = 无限制)
+    pub fn max_upload_size(&self) -> u64 {
+        self.max_upload_size.load(Ordering::Relaxed)
+    }
+}
+
+/// 监视 --config 指定的文件，内容变化或收到 SIGHUP 时重新加载
+///
+/// 只替换权限规则、认证用户和上传大小上限，进行中的上传和下载不受影响
+pub fn spawn(state: AppState) {
+    let Some(path) = state.config.config.clone() else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut last = fingerprint(&path);
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        #[cfg(unix)]
+        let mut hangup =
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    tracing::warn!(error = %e, "cannot listen for SIGHUP");
+                    None
+                }
+            };
+        loop {
+            #[cfg(unix)]
+            let forced = tokio::select! {
+                _ = interval.tick() => false,
+                Some(()) = async { hangup.as_mut()?.recv().await } => true,
+            };
+            #[cfg(not(unix))]
+            let forced = {
+                interval.tick().await;
+                false
+            };
+
+            let current = fingerprint(&path);
+            if !forced && current == last {
+                continue;
+            }
+            last = current;
+            match reload(&state, &path) {
+                Ok(()) => tracing::info!(path = %path.display(), "configuration reloaded"),
+                // 写到一半或写错时保留原有设置，文件再次变化时重试
+                Err(e) => tracing::warn!(path = %path.display(), error = %e, "configuration reload failed, keeping previous settings"),
+            }
+        }
+    });
+}
+
+fn fingerprint(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// 解析成功后一次性替换，出错时不做任何修改
+fn reload(state: &AppState, path: &Path) -> anyhow::Result<()> {
+    let file = FileConfig::read(path)?;
+    let rules = effective_rules(&file.rules, &state.config);
+
+    state.permissions.replace(&state.root, &rules);
+    *state.live.users.write() = Arc::new(file.users);
+    if !state.config.max_upload_size_pinned {
+        state
+            .live
+            .max_upload_size
+            .store(file.max_upload_size.unwrap_or(0), Ordering::Relaxed);
+    }
+    tracing::debug!(
+        rules = rules.len(),
+        users = state.live.users.read().len(),
+        max_upload_size = state.live.max_upload_size(),
+        "live settings updated"
+    );
+    Ok(())
+}
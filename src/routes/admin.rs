@@ -0,0 +1,236 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use axum::extract::{Path, Query, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::clients::ClientInfo;
+use crate::db::{DownloadCount, TransferRecord};
+use crate::error::AppError;
+use crate::fs::path_safety::relative_path;
+use crate::fs::permissions::Access;
+use crate::metrics::MetricsSnapshot;
+use crate::middleware::{auth, maintenance};
+use crate::state::AppState;
+
+#[derive(Serialize)]
+pub struct UploadInfo {
+    pub file_id: String,
+    pub filename: String,
+    /// 目标目录（相对 root）
+    pub target_dir: String,
+    pub total_size: u64,
+    pub uploaded: u64,
+    /// 0.0 - 1.0
+    pub progress: f64,
+    pub client_ip: Option<IpAddr>,
+    pub created_at: u64,
+    pub last_active: u64,
+    /// 距离上次写入的秒数
+    pub idle_secs: u64,
+}
+
+/// GET /api/admin/uploads — 进行中的上传会话，按空闲时间降序；需要管理令牌
+pub async fn list_uploads(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<UploadInfo>>, AppError> {
+    check_token(&state, &headers)?;
+    state.permissions.check(&state.root, Access::Read)?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut uploads: Vec<UploadInfo> = state
+        .upload_manager
+        .list()
+        .await
+        .into_iter()
+        .map(|s| UploadInfo {
+            target_dir: relative_path(&s.target_dir, &state.root),
+            progress: if s.total_size == 0 {
+                1.0
+            } else {
+                s.uploaded as f64 / s.total_size as f64
+            },
+            idle_secs: now.saturating_sub(s.last_active),
+            file_id: s.file_id,
+            filename: s.filename,
+            total_size: s.total_size,
+            uploaded: s.uploaded,
+            client_ip: s.client_ip,
+            created_at: s.created_at,
+            last_active: s.last_active,
+        })
+        .collect();
+    uploads.sort_by_key(|u| std::cmp::Reverse(u.idle_secs));
+
+    Ok(Json(uploads))
+}
+
+/// DELETE /api/admin/uploads/{file_id} — 强制取消会话并删除分片；需要管理令牌
+pub async fn cancel_upload(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(file_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    check_token(&state, &headers)?;
+    state.permissions.check(&state.root, Access::Delete)?;
+    if !state.upload_manager.cancel(&file_id).await {
+        return Err(AppError::NotFound(file_id));
+    }
+    tracing::info!(file_id = %file_id, "upload cancelled by admin");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/admin/metrics — 启动以来的上传 / 下载计数
+pub async fn metrics(State(state): State<AppState>) -> Result<Json<MetricsSnapshot>, AppError> {
+    state.permissions.check(&state.root, Access::Read)?;
+    Ok(Json(state.metrics.snapshot()))
+}
+
+#[derive(Deserialize)]
+pub struct LimitParams {
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+}
+
+fn default_limit() -> u32 {
+    100
+}
+
+/// GET /api/admin/transfers?limit= — 最近的上传 / 下载记录
+pub async fn transfers(
+    State(state): State<AppState>,
+    Query(params): Query<LimitParams>,
+) -> Result<Json<Vec<TransferRecord>>, AppError> {
+    state.permissions.check(&state.root, Access::Read)?;
+    Ok(Json(state.db.recent_transfers(params.limit.min(1000)).await?))
+}
+
+/// GET /api/admin/downloads?limit= — 下载次数最多的文件
+pub async fn downloads(
+    State(state): State<AppState>,
+    Query(params): Query<LimitParams>,
+) -> Result<Json<Vec<DownloadCount>>, AppError> {
+    state.permissions.check(&state.root, Access::Read)?;
+    Ok(Json(state.db.top_downloads(params.limit.min(1000)).await?))
+}
+
+/// GET /api/admin/clients — 最近 24 小时访问过的客户端，以及各自进行中的传输
+pub async fn clients(State(state): State<AppState>) -> Result<Json<Vec<ClientInfo>>, AppError> {
+    state.permissions.check(&state.root, Access::Read)?;
+    Ok(Json(state.clients.snapshot()))
+}
+
+/// 暂停上传时默认显示的说明
+const PAUSE_NOTICE: &str = "服务器维护中，暂时无法上传";
+
+/// 关机前显示的说明
+const SHUTDOWN_NOTICE: &str = "服务器即将关闭，暂时无法上传";
+
+#[derive(Serialize)]
+pub struct MaintenanceStatus {
+    /// 暂停上传时给用户看的说明
+    pub notice: Option<String>,
+    pub shutting_down: bool,
+    /// 进行中的上传、下载和服务端抓取，为 0 时可以安全地卸载存储
+    pub active_transfers: usize,
+}
+
+#[derive(Deserialize, Default)]
+pub struct PauseRequest {
+    pub notice: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ShutdownRequest {
+    pub notice: Option<String>,
+    /// 最多等待进行中传输的秒数，默认 60
+    pub drain_secs: Option<u64>,
+}
+
+/// 维护接口只认 --admin-token，与 Basic 认证用户和访问规则无关
+fn check_token(state: &AppState, headers: &HeaderMap) -> Result<(), AppError> {
+    let Some(expected) = state.config.admin_token.as_deref() else {
+        return Err(AppError::Forbidden("admin token not configured"));
+    };
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !auth::constant_time_eq(expected.as_bytes(), token.trim().as_bytes()) {
+        return Err(AppError::Forbidden("invalid admin token"));
+    }
+    Ok(())
+}
+
+fn maintenance_status(state: &AppState) -> MaintenanceStatus {
+    MaintenanceStatus {
+        notice: state.maintenance.notice(),
+        shutting_down: state.maintenance.is_shutting_down(),
+        active_transfers: maintenance::active_transfers(state),
+    }
+}
+
+/// GET /api/admin/maintenance — 是否暂停以及进行中的传输数
+pub async fn maintenance(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<MaintenanceStatus>, AppError> {
+    check_token(&state, &headers)?;
+    Ok(Json(maintenance_status(&state)))
+}
+
+/// POST /api/admin/maintenance/pause — 拒绝新的上传，进行中的请求照常完成
+pub async fn pause(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Option<Json<PauseRequest>>,
+) -> Result<Json<MaintenanceStatus>, AppError> {
+    check_token(&state, &headers)?;
+    let notice = body
+        .and_then(|Json(b)| b.notice)
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| PAUSE_NOTICE.to_string());
+    tracing::warn!(%notice, "uploads paused by admin");
+    state.maintenance.pause(notice);
+    Ok(Json(maintenance_status(&state)))
+}
+
+/// POST /api/admin/maintenance/resume
+pub async fn resume(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<MaintenanceStatus>, AppError> {
+    check_token(&state, &headers)?;
+    if state.maintenance.is_shutting_down() {
+        return Err(AppError::BadRequest("server is shutting down".into()));
+    }
+    tracing::info!("uploads resumed by admin");
+    state.maintenance.resume();
+    Ok(Json(maintenance_status(&state)))
+}
+
+/// POST /api/admin/shutdown — 暂停上传、停止接受新连接，等进行中的传输结束后退出
+pub async fn shutdown(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Option<Json<ShutdownRequest>>,
+) -> Result<(StatusCode, Json<MaintenanceStatus>), AppError> {
+    check_token(&state, &headers)?;
+    let body = body.map(|Json(b)| b).unwrap_or_default();
+    let notice = body
+        .notice
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| SHUTDOWN_NOTICE.to_string());
+    let drain = body.drain_secs.map(Duration::from_secs).unwrap_or(maintenance::DEFAULT_DRAIN);
+    tracing::warn!(drain_secs = drain.as_secs(), "shutdown requested by admin");
+    state.maintenance.shutdown(notice, drain);
+    Ok((StatusCode::ACCEPTED, Json(maintenance_status(&state))))
+}
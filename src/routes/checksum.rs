@@ -0,0 +1,36 @@
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::fs::permissions::Access;
+use crate::fs::vault;
+use crate::state::AppState;
+
+#[derive(Serialize)]
+pub struct ChecksumResponse {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// GET /api/checksum/{*path} — 文件的 SHA-256，按大小 + mtime 缓存
+pub async fn get(
+    State(state): State<AppState>,
+    Path(rel): Path<String>,
+) -> Result<Json<ChecksumResponse>, AppError> {
+    let abs = state.path_safety.resolve(&rel)?;
+    if abs.is_dir() {
+        return Err(AppError::IsADirectory);
+    }
+    state.permissions.check(&abs, Access::Read)?;
+
+    let sha256 = state.checksums.sha256(&state.blocking, &abs).await?;
+    let size = tokio::fs::metadata(&abs).await?.len();
+    let size = vault::content_len(state.vault.as_deref(), &abs, size).await;
+    Ok(Json(ChecksumResponse {
+        path: rel,
+        size,
+        sha256,
+    }))
+}
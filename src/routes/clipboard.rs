@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::clipboard::{ClipboardCommand, ClipboardEvent, MAX_TEXT_BYTES};
+use crate::error::AppError;
+use crate::fs::permissions::Access;
+use crate::state::AppState;
+
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize)]
+pub struct ClipboardParams {
+    /// 来源设备名
+    #[serde(default)]
+    pub name: String,
+}
+
+/// GET /api/clipboard?name= — 剪贴板同步 WebSocket
+pub async fn connect(
+    State(state): State<AppState>,
+    Query(params): Query<ClipboardParams>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, AppError> {
+    if state.config.clipboard_ttl_secs == 0 {
+        return Err(AppError::NotFound("clipboard".into()));
+    }
+    // 剪贴板内容对所有能浏览共享目录的人可见，投递箱访客看不到
+    state.permissions.check(&state.root, Access::Read)?;
+    Ok(ws
+        .max_message_size(MAX_TEXT_BYTES + 1024)
+        .on_upgrade(move |socket| handle(state, socket, params.name)))
+}
+
+async fn handle(state: AppState, socket: WebSocket, name: String) {
+    let hub = &state.clipboard;
+    // 先订阅再取历史，两者之间新增的条目不会漏掉（前端按 id 去重）
+    let mut events = hub.subscribe();
+    let (mut sink, mut stream) = socket.split();
+    let (reply_tx, mut reply_rx) = tokio::sync::mpsc::channel::<ClipboardEvent>(8);
+
+    let outgoing = async {
+        let mut pending = Some(ClipboardEvent::History {
+            items: hub.history(),
+        });
+        let mut ping = tokio::time::interval(PING_INTERVAL);
+        ping.tick().await;
+        loop {
+            let event = match pending.take() {
+                Some(event) => event,
+                None => tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) => event,
+                        // 落后太多时整体重发
+                        Err(RecvError::Lagged(_)) => ClipboardEvent::History { items: hub.history() },
+                        Err(RecvError::Closed) => break,
+                    },
+                    Some(reply) = reply_rx.recv() => reply,
+                    _ = ping.tick() => {
+                        if sink.send(Message::Ping(Default::default())).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                },
+            };
+            let Ok(text) = serde_json::to_string(&event) else {
+                continue;
+            };
+            if sink.send(Message::Text(text.into())).await.is_err() {
+                break;
+            }
+        }
+    };
+    let incoming = async {
+        while let Some(Ok(msg)) = stream.next().await {
+            let text = match msg {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            let error = match serde_json::from_str::<ClipboardCommand>(&text) {
+                Ok(ClipboardCommand::Copy { text }) => hub.add(text, &name).err().map(str::to_string),
+                Ok(ClipboardCommand::Remove { id }) => {
+                    hub.remove(&id);
+                    None
+                }
+                Ok(ClipboardCommand::Clear) => {
+                    hub.clear();
+                    None
+                }
+                Err(e) => Some(format!("invalid message: {}", e)),
+            };
+            if let Some(message) = error {
+                let _ = reply_tx.try_send(ClipboardEvent::Error { message });
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = outgoing => {}
+        _ = incoming => {}
+    }
+}
@@ -0,0 +1,163 @@
+use std::net::SocketAddr;
+
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Path, Query, State};
+use axum::http::header::{ACCEPT_ENCODING, ETAG, IF_MATCH};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use tokio_util::io::StreamReader;
+
+use crate::delta;
+use crate::download::etag;
+use crate::error::AppError;
+use crate::fs::operations;
+use crate::fs::permissions::Access;
+use crate::routes::upload::{record_upload, seal_part};
+use crate::state::AppState;
+use crate::util::encoding;
+
+/// GET /api/delta/{*path} — 文件的分块签名，ETag 用于提交差量时确认文件未变
+pub async fn signature(
+    State(state): State<AppState>,
+    Path(rel): Path<String>,
+) -> Result<Response, AppError> {
+    let abs = state.path_safety.resolve(&rel)?;
+    let meta = tokio::fs::metadata(&abs).await?;
+    if meta.is_dir() {
+        return Err(AppError::IsADirectory);
+    }
+    state.permissions.check(&abs, Access::Read)?;
+
+    let etag = etag::compute_etag(&meta);
+    let vault = state.vault.clone();
+    let sig = state
+        .blocking
+        .run(move || match vault {
+            Some(vault) => {
+                let content = vault.open(&abs)?;
+                let size = content.size()?;
+                delta::signature(std::io::BufReader::new(content), size)
+            }
+            None => delta::signature(std::io::BufReader::new(std::fs::File::open(&abs)?), meta.len()),
+        })
+        .await??;
+    Ok((
+        [
+            (ETAG, etag),
+            (ACCEPT_ENCODING, encoding::ZSTD.to_string()),
+        ],
+        Json(sig),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+pub struct ApplyParams {
+    /// 新文件的修改时间 (Unix 秒)
+    pub modified: Option<u64>,
+}
+
+/// POST /api/delta/{*path} — 按差量更新文件，If-Match 必须是取签名时的 ETag
+///
+/// 新内容先写到临时区，校验 SHA-256 后原子替换，失败时原文件不受影响
+pub async fn apply(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Path(rel): Path<String>,
+    Query(params): Query<ApplyParams>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<StatusCode, AppError> {
+    let abs = state.path_safety.resolve(&rel)?;
+    if abs.is_dir() {
+        return Err(AppError::IsADirectory);
+    }
+    state.permissions.check(&abs, Access::Read)?;
+    state.permissions.check(&abs, Access::Write)?;
+    let if_match = headers
+        .get(IF_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| AppError::BadRequest("missing If-Match".into()))?;
+
+    // 与上传落盘共用目标路径锁，期间文件不会被其他上传替换
+    let _target_guard = state.upload_manager.lock_target(abs.clone()).await;
+    let meta = tokio::fs::metadata(&abs).await?;
+    if !etag::matches_etag(Some(if_match), &etag::compute_etag(&meta)) {
+        return Err(AppError::PreconditionFailed);
+    }
+
+    let id = uuid::Uuid::new_v4().simple();
+    let tmp_path = state.upload_manager.tmp_dir().join(format!("delta-{}.part", id));
+    // 加密保存的旧文件先解密到临时区，差量按明文计算
+    let base_path = state.upload_manager.tmp_dir().join(format!("delta-{}.base", id));
+    let limit = match state.live.max_upload_size() {
+        0 => u64::MAX,
+        max => max,
+    };
+    let result = async {
+        let base_size = match &state.vault {
+            Some(vault) if vault.sealed_size(&abs).await?.is_some() => {
+                let (vault, from, to) = (vault.clone(), abs.clone(), base_path.clone());
+                state
+                    .blocking
+                    .run(move || std::io::copy(&mut vault.open(&from)?, &mut std::fs::File::create(&to)?))
+                    .await??
+            }
+            _ => meta.len(),
+        };
+        let mut ops = StreamReader::new(encoding::decode_body(&headers, body)?);
+        let base_file = if base_path.exists() { &base_path } else { &abs };
+        let mut base = tokio::fs::File::open(base_file).await?;
+        let mut out = tokio::io::BufWriter::new(tokio::fs::File::create(&tmp_path).await?);
+        let applied = delta::apply(&mut ops, &mut base, base_size, &mut out, limit)
+            .await
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof => {
+                    AppError::BadRequest(format!("invalid delta: {}", e))
+                }
+                _ => e.into(),
+            })?;
+        out.into_inner().sync_data().await?;
+        if applied.sha256 != applied.expected {
+            return Err(AppError::ChecksumMismatch {
+                expected: applied.expected,
+                actual: applied.sha256,
+            });
+        }
+        match &state.vault {
+            Some(vault) => {
+                let sealed = seal_part(&state, vault, &tmp_path).await?;
+                if let Err(e) = operations::replace_file(&sealed, &abs).await {
+                    let _ = tokio::fs::remove_file(&sealed).await;
+                    return Err(e.into());
+                }
+                let _ = tokio::fs::remove_file(&tmp_path).await;
+            }
+            None => operations::replace_file(&tmp_path, &abs).await?,
+        }
+        Ok(applied)
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&base_path).await;
+    let applied = match result {
+        Ok(applied) => applied,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(e);
+        }
+    };
+
+    if let Some(secs) = params.modified {
+        if let Err(e) = operations::set_modified(&abs, secs).await {
+            tracing::warn!(error = %e, path = %abs.display(), "cannot set modified time");
+        }
+    }
+    if let Ok(meta) = tokio::fs::metadata(&abs).await {
+        state.checksums.insert(&abs, &meta, applied.sha256).await;
+    }
+    let rel_path = record_upload(&state, &abs, applied.size, peer);
+    tracing::info!(path = %rel_path, size = applied.size, "delta applied");
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,15 +1,24 @@
 use std::io::SeekFrom;
+use std::net::SocketAddr;
 
 use axum::body::Body;
-use axum::extract::{Path, Query, State};
+use axum::extract::{ConnectInfo, Path, Query, State};
 use axum::http::header::*;
 use axum::http::{HeaderMap, Response, StatusCode};
 use serde::Deserialize;
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio_util::io::ReaderStream;
 
-use crate::download::{etag, range};
+use crate::archive;
+use crate::db::{self, TransferKind, TransferRecord};
+use crate::download::{conditional, disposition, etag, precompressed, range};
 use crate::error::AppError;
+use crate::fs::path_safety::relative_path;
+use crate::fs::permissions::Access;
+use crate::middleware::auth;
+use crate::observability;
+use crate::preview::thumbnail::{self, ResizeSpec};
+use crate::routes::zipdl;
 use crate::state::AppState;
 use crate::util::mime::guess_mime;
 
@@ -17,37 +26,114 @@ use crate::util::mime::guess_mime;
 pub struct DownloadParams {
     #[serde(default)]
     pub download: Option<String>,
+    /// 图片缩放: 最大宽度
+    pub w: Option<u32>,
+    /// 图片缩放: 最大高度
+    pub h: Option<u32>,
+    /// 图片缩放: JPEG 质量 (1-100)
+    pub q: Option<u8>,
+    /// 目录打包为 ZIP 下载
+    pub zip: Option<String>,
+    /// 目录打包的进度任务 ID，同 /api/download-zip
+    pub progress: Option<String>,
 }
 
 /// GET /api/download/{*path} — 文件下载 + Range + ETag
 pub async fn get(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     Path(rel): Path<String>,
     Query(params): Query<DownloadParams>,
     headers: HeaderMap,
 ) -> Result<Response<Body>, AppError> {
+    if let Some(location) = archive::locate(&state.path_safety, &rel) {
+        if !location.inner.is_empty() {
+            state.permissions.check(&location.abs, Access::Read)?;
+            return archive_member(location, &params).await;
+        }
+    }
+
     let abs = state.path_safety.resolve(&rel)?;
 
     if abs.is_dir() {
-        return Err(AppError::IsADirectory);
+        if params.zip.is_none() {
+            return Err(AppError::IsADirectory);
+        }
+        let name = format!("{}.zip", abs.file_name().unwrap_or_default().to_string_lossy());
+        return zipdl::stream(state, vec![abs], Some(name), params.progress, None);
     }
+    state.permissions.check(&abs, Access::Read)?;
+
+    let mut mime_type = guess_mime(&abs);
+
+    // 图片缩放：生成（或命中）缓存后改为发送缓存文件
+    let spec = ResizeSpec {
+        width: params.w,
+        height: params.h,
+        quality: params.q,
+    };
+    let mut content_encoding = None;
+    let served = if !spec.is_empty() && thumbnail::is_resizable(&mime_type) {
+        mime_type = thumbnail::output_mime(&abs).to_string();
+        resized_image(&state, &abs, &spec).await?
+    } else {
+        // 同目录下有 .br / .gz 预压缩文件时直接发送；续传请求始终按原文件处理
+        let accept = headers
+            .get(ACCEPT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .filter(|_| !headers.contains_key(RANGE));
+        let sidecar = match accept {
+            Some(accept) => precompressed::find_sidecar(&abs, accept).await,
+            None => None,
+        };
+        match sidecar {
+            Some((path, encoding)) => {
+                content_encoding = Some(encoding);
+                path
+            }
+            None => abs.clone(),
+        }
+    };
 
-    let meta = tokio::fs::metadata(&abs).await?;
-    let size = meta.len();
+    let meta = tokio::fs::metadata(&served).await?;
+    let sealed = match &state.vault {
+        Some(vault) => vault.open_sealed(&served).await?,
+        None => None,
+    };
+    let size = sealed.as_ref().map_or(meta.len(), |s| s.size());
     let etag_val = etag::compute_etag(&meta);
-    let mime_type = guess_mime(&abs);
-
-    // 304 Not Modified
-    if let Some(inm) = headers.get(IF_NONE_MATCH) {
-        if etag::matches_etag(inm.to_str().ok(), &etag_val) {
-            return Ok(Response::builder()
-                .status(StatusCode::NOT_MODIFIED)
-                .body(Body::empty())
-                .unwrap());
+
+    let modified = meta.modified().ok();
+    let header_str = |name| headers.get(name).and_then(|v| v.to_str().ok());
+
+    // 412：调用方要求文件自某时刻起未被修改
+    if header_str(IF_UNMODIFIED_SINCE)
+        .is_some_and(|v| conditional::modified_after(v, modified))
+    {
+        return Err(AppError::PreconditionFailed);
+    }
+
+    // 304 Not Modified：有 If-None-Match 时忽略 If-Modified-Since
+    let not_modified = match headers.get(IF_NONE_MATCH) {
+        Some(inm) => etag::matches_etag(inm.to_str().ok(), &etag_val),
+        None => header_str(IF_MODIFIED_SINCE)
+            .is_some_and(|v| conditional::not_modified_since(v, modified)),
+    };
+    if not_modified {
+        let mut builder = Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(ETAG, &etag_val);
+        if let Some(lm) = modified.and_then(conditional::format_http_date) {
+            builder = builder.header(LAST_MODIFIED, lm);
         }
+        return Ok(builder.body(Body::empty()).unwrap());
     }
 
-    let range_result = range::parse_range(headers.get(RANGE), size);
+    // If-Range 指向的版本已变化时忽略 Range，返回完整的新文件，避免续传拼出新旧混合的内容
+    let range_header = headers.get(RANGE).filter(|_| {
+        header_str(IF_RANGE).is_none_or(|v| conditional::if_range_matches(v, &etag_val, modified))
+    });
+    let range_result = range::parse_range(range_header, size);
 
     let (status, start, end) = match range_result {
         None => (StatusCode::OK, 0, size.saturating_sub(1)),
@@ -55,7 +141,7 @@ pub async fn get(
     };
 
     // Range 无效 -> 416
-    if headers.get(RANGE).is_some() && range_result.is_none() && size > 0 {
+    if range_header.is_some() && range_result.is_none() && size > 0 {
         return Ok(Response::builder()
             .status(StatusCode::RANGE_NOT_SATISFIABLE)
             .header(CONTENT_RANGE, format!("bytes */{}", size))
@@ -65,14 +151,33 @@ pub async fn get(
 
     let length = if size == 0 { 0 } else { end - start + 1 };
 
-    // 完全流式，不缓存到内存
-    let mut file = tokio::fs::File::open(&abs).await?;
-    if start > 0 {
-        file.seek(SeekFrom::Start(start)).await?;
+    // 完全流式，不缓存到内存；加密文件只解密请求范围涉及的段
+    // span 持续到响应体发送完，记录的是整个传输的耗时
+    let span = tracing::info_span!("download", path = %rel, offset = start, bytes = length);
+    let body = match sealed {
+        Some(sealed) => Body::from_stream(observability::in_span(sealed.into_stream(start, length), span)),
+        None => {
+            let mut file = tokio::fs::File::open(&served).await?;
+            if start > 0 {
+                file.seek(SeekFrom::Start(start)).await?;
+            }
+            let limited = file.take(length);
+            let stream = ReaderStream::with_capacity(limited, 256 * 1024); // 256KB
+            Body::from_stream(observability::in_span(stream, span))
+        }
+    };
+    state.metrics.download_started(length);
+    // 续传和拖动进度条产生的后续 Range 请求不计入下载记录
+    if start == 0 {
+        state.db.record_transfer(TransferRecord {
+            kind: TransferKind::Download,
+            path: relative_path(&abs, &state.root),
+            size: length,
+            client_ip: Some(peer.ip()),
+            user: auth::current_user(),
+            at: db::now_secs(),
+        });
     }
-    let limited = file.take(length);
-    let stream = ReaderStream::with_capacity(limited, 256 * 1024); // 256KB
-    let body = Body::from_stream(stream);
 
     // Content-Disposition
     let filename = abs
@@ -82,15 +187,12 @@ pub async fn get(
         .to_string();
     let is_download = params.download.is_some();
     let disposition = if is_download {
-        format!("attachment; filename=\"{}\"", filename)
+        disposition::attachment(&filename)
     } else {
-        format!("inline; filename=\"{}\"", filename)
+        disposition::inline(&filename)
     };
 
-    let last_modified = meta
-        .modified()
-        .ok()
-        .and_then(httpdate_format);
+    let last_modified = modified.and_then(conditional::format_http_date);
 
     let mut builder = Response::builder()
         .status(status)
@@ -105,6 +207,12 @@ pub async fn get(
     if let Some(lm) = &last_modified {
         builder = builder.header(LAST_MODIFIED, lm);
     }
+    if spec.is_empty() {
+        builder = builder.header(VARY, "Accept-Encoding");
+    }
+    if let Some(encoding) = content_encoding {
+        builder = builder.header(CONTENT_ENCODING, encoding);
+    }
 
     if status == StatusCode::PARTIAL_CONTENT {
         builder = builder.header(
@@ -116,9 +224,81 @@ pub async fn get(
     Ok(builder.body(body).unwrap())
 }
 
-fn httpdate_format(time: std::time::SystemTime) -> Option<String> {
-    let duration = time.duration_since(std::time::UNIX_EPOCH).ok()?;
-    let secs = duration.as_secs();
-    // 简单的 HTTP date 格式
-    Some(format!("{}", secs))
+/// 从归档中流式取出单个文件，不支持 Range
+async fn archive_member(
+    location: archive::ArchiveLocation,
+    params: &DownloadParams,
+) -> Result<Response<Body>, AppError> {
+    let member = archive::open_member(&location.abs, location.kind, &location.inner)
+        .await
+        .map_err(|e| AppError::BadRequest(format!("cannot read archive: {}", e)))?
+        .ok_or_else(|| AppError::NotFound(location.inner.clone()))?;
+
+    let inner_path = std::path::Path::new(&location.inner);
+    let filename = inner_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let disposition = if params.download.is_some() {
+        disposition::attachment(&filename)
+    } else {
+        disposition::inline(&filename)
+    };
+
+    let stream = ReaderStream::with_capacity(member.reader.take(member.size), 256 * 1024);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, guess_mime(inner_path))
+        .header(CONTENT_LENGTH, member.size)
+        .header(ACCEPT_RANGES, "none")
+        .header(CACHE_CONTROL, "no-cache")
+        .header(CONTENT_DISPOSITION, disposition)
+        .header("X-File-Size", member.size.to_string())
+        .body(Body::from_stream(stream))
+        .map_err(|e| AppError::Internal(e.into()))
+}
+
+/// 返回缩放后图片的缓存路径，缓存不存在时在 blocking 线程生成
+async fn resized_image(
+    state: &AppState,
+    source: &std::path::Path,
+    spec: &ResizeSpec,
+) -> Result<std::path::PathBuf, AppError> {
+    let source_meta = tokio::fs::metadata(source).await?;
+    let cache_dir = state.upload_manager.tmp_dir().join("thumbs");
+    let cached = thumbnail::cache_path(
+        &cache_dir,
+        source,
+        &etag::compute_etag(&source_meta),
+        spec,
+    );
+
+    if !cached.exists() {
+        let (src, dst, spec) = (source.to_path_buf(), cached.clone(), *spec);
+        let vault = state.vault.clone();
+        state
+            .blocking
+            .run(move || match vault {
+                // 缓存同样加密保存，下载时和原文件一样透明解密
+                Some(vault) => {
+                    let input = std::io::BufReader::new(vault.open(&src)?);
+                    let (plain, sealed) = (thumbnail::temp_path(&dst, "plain"), thumbnail::temp_path(&dst, "sealed"));
+                    thumbnail::render(&src, input, &plain, &spec)?;
+                    let result = vault.seal(&plain, &sealed).and_then(|()| std::fs::rename(&sealed, &dst));
+                    let _ = std::fs::remove_file(&plain);
+                    let _ = std::fs::remove_file(&sealed);
+                    Ok(result?)
+                }
+                None => {
+                    let input = std::io::BufReader::new(std::fs::File::open(&src)?);
+                    thumbnail::render(&src, input, &dst, &spec)
+                }
+            })
+            .await?
+            .map_err(|e| AppError::BadRequest(format!("cannot resize image: {}", e)))?;
+    }
+
+    Ok(cached)
 }
@@ -0,0 +1,133 @@
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::fs::permissions::Access;
+use crate::middleware::auth;
+use crate::routes::upload::{place_file, record_upload};
+use crate::state::AppState;
+use crate::upload::remote::{self, FetchRequest, FetchTask, Placer};
+
+#[derive(Deserialize)]
+pub struct FetchBody {
+    pub url: String,
+    /// 目标目录（相对 root）
+    #[serde(default)]
+    pub path: String,
+    /// 保存的文件名，默认取 URL 最后一段
+    pub name: Option<String>,
+}
+
+/// POST /api/fetch — 服务端从远程 URL 下载到共享目录
+pub async fn start(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Json(body): Json<FetchBody>,
+) -> Result<(StatusCode, Json<FetchTask>), AppError> {
+    if let Some(notice) = state.maintenance.notice() {
+        return Err(AppError::Paused { notice });
+    }
+    let url = reqwest::Url::parse(body.url.trim())
+        .map_err(|e| AppError::BadRequest(format!("invalid url: {}", e)))?;
+    remote::check_url(&url).map_err(AppError::BadRequest)?;
+
+    let target_dir = if body.path.is_empty() {
+        state.root.clone()
+    } else {
+        state.path_safety.resolve(&body.path)?
+    };
+    if !target_dir.is_dir() {
+        return Err(AppError::BadRequest("target is not a directory".into()));
+    }
+
+    let raw_name = body
+        .name
+        .filter(|n| !n.trim().is_empty())
+        .or_else(|| {
+            url.path_segments()
+                .and_then(|mut segs| segs.next_back().map(str::to_string))
+                .filter(|s| !s.is_empty())
+                .map(|s| {
+                    percent_encoding::percent_decode_str(&s)
+                        .decode_utf8_lossy()
+                        .to_string()
+                })
+        })
+        .unwrap_or_else(|| "download".to_string());
+    let filename = state.filenames.sanitize(&raw_name);
+    if filename.is_empty() {
+        return Err(AppError::BadRequest("invalid file name".into()));
+    }
+    state.permissions.check(&target_dir.join(&filename), Access::Write)?;
+
+    // 和其他上传方式一样按冲突策略、文件名规则和插件检查落盘，并记录传输、触发钩子和通知
+    let creator = auth::current_user();
+    let place: Placer = {
+        let (state, dir, name, user) = (state.clone(), target_dir.clone(), filename.clone(), creator.clone());
+        Box::new(move |part, size| {
+            Box::pin(auth::scope(user, async move {
+                let final_path = place_file(&state, &part, &dir, &name)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(record_upload(&state, &final_path, size, peer))
+            }))
+        })
+    };
+    let task = state.fetch_manager.start(FetchRequest {
+        url,
+        filename,
+        target_dir,
+        max_size: state.live.max_upload_size(),
+        creator,
+        place,
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(task)))
+}
+
+/// GET /api/fetch — 所有抓取任务
+pub async fn list(State(state): State<AppState>) -> Result<Json<Vec<FetchTask>>, AppError> {
+    // 任务列表会暴露其他人保存的文件名
+    state.permissions.check(&state.root, Access::Read)?;
+    Ok(Json(state.fetch_manager.list()))
+}
+
+/// GET /api/fetch/{id} — 查询抓取进度
+pub async fn status(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<FetchTask>, AppError> {
+    let task = state.fetch_manager.get(&id).ok_or(AppError::NotFound(id))?;
+    check_owner(&state, &task)?;
+    Ok(Json(task))
+}
+
+/// DELETE /api/fetch/{id} — 取消任务或移除已结束的记录
+pub async fn cancel(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let task = state
+        .fetch_manager
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound(id.clone()))?;
+    check_owner(&state, &task)?;
+    if state.fetch_manager.cancel(&id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(id))
+    }
+}
+
+/// 需要目标目录的写权限；认证用户发起的任务只有本人能查看和取消
+fn check_owner(state: &AppState, task: &FetchTask) -> Result<(), AppError> {
+    state.permissions.check(&task.target_dir, Access::Write)?;
+    if task.creator.is_some() && task.creator != auth::current_user() {
+        return Err(AppError::Forbidden("fetch task belongs to another user"));
+    }
+    Ok(())
+}
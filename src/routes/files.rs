@@ -1,11 +1,21 @@
-use axum::extract::{Query, State};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use axum::extract::{ConnectInfo, Query, State};
 use axum::http::StatusCode;
 use axum::Json;
 use serde::{Deserialize, Serialize};
 
+use crate::archive;
+use crate::db::trash::TrashItem;
 use crate::error::AppError;
-use crate::fs::{meta::FileMeta, operations, walker};
+use crate::fs::path_safety::relative_path;
+use crate::fs::permissions::Access;
+use crate::fs::{meta::FileMeta, mode, operations, vault, walker};
+use crate::hooks::HookEvent;
+use crate::middleware::auth;
 use crate::state::AppState;
+use crate::util::mime::{categorize, FileCategory};
 
 #[derive(Deserialize)]
 pub struct ListParams {
@@ -18,6 +28,9 @@ pub struct ListResponse {
     pub path: String,
     pub entries: Vec<FileMeta>,
     pub breadcrumbs: Vec<Breadcrumb>,
+    /// 当前位于归档内部时，为归档文件的相对路径
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -31,42 +44,112 @@ pub async fn list(
     State(state): State<AppState>,
     Query(params): Query<ListParams>,
 ) -> Result<Json<ListResponse>, AppError> {
+    if let Some(location) = archive::locate(&state.path_safety, &params.path) {
+        state.permissions.check(&location.abs, Access::Read)?;
+        return list_archive(&state, location).await.map(Json);
+    }
+
     let abs = if params.path.is_empty() {
         state.root.clone()
     } else {
         state.path_safety.resolve(&params.path)?
     };
 
+    if !abs.exists() {
+        return Err(AppError::NotFound(params.path));
+    }
     if !abs.is_dir() {
         return Err(AppError::IsADirectory);
     }
+    state.permissions.check(&abs, Access::Read)?;
 
     let mut entries = walker::list_directory(&abs).await?;
+    entries.retain(|e| !state.path_safety.is_hidden(&abs.join(&e.name)));
     // 填充相对路径
     let prefix = &state.root;
     for entry in &mut entries {
         let entry_abs = abs.join(&entry.name);
-        entry.path = entry_abs
-            .strip_prefix(prefix)
-            .unwrap_or(&entry_abs)
-            .to_string_lossy()
-            .to_string();
+        if !entry.is_dir {
+            entry.size = vault::content_len(state.vault.as_deref(), &entry_abs, entry.size).await;
+        }
+        entry.path = relative_path(&entry_abs, prefix);
     }
     let breadcrumbs = build_breadcrumbs(&abs, &state.root);
 
-    let display_path = abs
-        .strip_prefix(&state.root)
-        .unwrap_or(&abs)
-        .to_string_lossy()
-        .to_string();
+    let display_path = relative_path(&abs, &state.root);
 
     Ok(Json(ListResponse {
         path: display_path,
         entries,
         breadcrumbs,
+        archive: None,
     }))
 }
 
+/// 把归档当作虚拟目录列出，不解压
+async fn list_archive(
+    state: &AppState,
+    location: archive::ArchiveLocation,
+) -> Result<ListResponse, AppError> {
+    // tar.gz 需要完整解压一遍才能列出条目
+    let _permit = state.blocking.acquire().await?;
+    let all = archive::read_entries(&location.abs, location.kind)
+        .await
+        .map_err(|e| AppError::BadRequest(format!("cannot read archive: {}", e)))?;
+
+    let children = archive::children(&all, &location.inner);
+    if children.is_empty()
+        && !location.inner.is_empty()
+        && !all.iter().any(|e| e.is_dir && e.name == location.inner)
+    {
+        return Err(AppError::NotFound(location.inner));
+    }
+
+    let display_path = if location.inner.is_empty() {
+        location.archive_rel.clone()
+    } else {
+        format!("{}/{}", location.archive_rel, location.inner)
+    };
+
+    let mut entries: Vec<FileMeta> = children
+        .into_iter()
+        .map(|e| {
+            let name_path = std::path::Path::new(&e.name);
+            FileMeta {
+                path: format!("{}/{}", display_path, e.name),
+                is_dir: e.is_dir,
+                size: e.size,
+                modified: e.modified,
+                mime_type: (!e.is_dir).then(|| {
+                    mime_guess::from_path(name_path)
+                        .first_or_octet_stream()
+                        .to_string()
+                }),
+                extension: name_path
+                    .extension()
+                    .map(|x| x.to_string_lossy().to_string()),
+                name: e.name,
+                unix: None,
+            }
+        })
+        .collect();
+    // 与 walker 保持一致：目录在前，按名称排序
+    entries.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+
+    let breadcrumbs = build_breadcrumbs(&state.root.join(&display_path), &state.root);
+
+    Ok(ListResponse {
+        path: display_path,
+        entries,
+        breadcrumbs,
+        archive: Some(location.archive_rel),
+    })
+}
+
 fn build_breadcrumbs(
     current: &std::path::Path,
     root: &std::path::Path,
@@ -94,6 +177,150 @@ fn build_breadcrumbs(
     crumbs
 }
 
+#[derive(Serialize)]
+pub struct CategoryStats {
+    pub category: FileCategory,
+    pub count: u64,
+    pub size: u64,
+}
+
+#[derive(Serialize)]
+pub struct StatsResponse {
+    pub path: String,
+    pub dirs: u64,
+    pub files: u64,
+    /// 当前目录下文件的总大小（不含子目录）
+    pub size: u64,
+    /// 按总大小降序，只包含出现过的分类
+    pub categories: Vec<CategoryStats>,
+}
+
+/// GET /api/files/stats?path=xxx — 当前目录按文件类型统计
+pub async fn stats(
+    State(state): State<AppState>,
+    Query(params): Query<ListParams>,
+) -> Result<Json<StatsResponse>, AppError> {
+    let abs = if params.path.is_empty() {
+        state.root.clone()
+    } else {
+        state.path_safety.resolve(&params.path)?
+    };
+    if !abs.is_dir() {
+        return Err(AppError::BadRequest("not a directory".into()));
+    }
+    state.permissions.check(&abs, Access::Read)?;
+
+    let mut entries = walker::list_directory(&abs).await?;
+    entries.retain(|e| !state.path_safety.is_hidden(&abs.join(&e.name)));
+
+    let mut dirs = 0;
+    let mut files = 0;
+    let mut size = 0;
+    let mut by_category: std::collections::HashMap<FileCategory, CategoryStats> =
+        std::collections::HashMap::new();
+    for entry in &entries {
+        if entry.is_dir {
+            dirs += 1;
+            continue;
+        }
+        files += 1;
+        size += entry.size;
+        let category = categorize(std::path::Path::new(&entry.name));
+        let stats = by_category.entry(category).or_insert(CategoryStats {
+            category,
+            count: 0,
+            size: 0,
+        });
+        stats.count += 1;
+        stats.size += entry.size;
+    }
+
+    let mut categories: Vec<CategoryStats> = by_category.into_values().collect();
+    categories.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| b.count.cmp(&a.count)));
+
+    Ok(Json(StatsResponse {
+        path: relative_path(&abs, &state.root),
+        dirs,
+        files,
+        size,
+        categories,
+    }))
+}
+
+#[derive(Serialize)]
+pub struct DirsResponse {
+    pub path: String,
+    pub dirs: Vec<DirNode>,
+}
+
+#[derive(Serialize)]
+pub struct DirNode {
+    pub name: String,
+    pub path: String,
+    /// 是否还有可展开的子目录，前端据此决定是否显示展开箭头
+    pub has_children: bool,
+}
+
+/// GET /api/dirs?path=xxx — 只列出下一级子目录，供移动、复制等对话框逐级展开目录树
+pub async fn dirs(
+    State(state): State<AppState>,
+    Query(params): Query<ListParams>,
+) -> Result<Json<DirsResponse>, AppError> {
+    let abs = if params.path.is_empty() {
+        state.root.clone()
+    } else {
+        state.path_safety.resolve(&params.path)?
+    };
+    if !abs.is_dir() {
+        return Err(AppError::BadRequest("not a directory".into()));
+    }
+    state.permissions.check(&abs, Access::Read)?;
+
+    let _permit = state.blocking.acquire().await?;
+    let path_safety = state.path_safety.clone();
+    let permissions = state.permissions.clone();
+    let authenticated = auth::current_user().is_some();
+    let dir = abs.clone();
+    let children = tokio::task::spawn_blocking(move || -> std::io::Result<_> {
+        let visible = |p: &std::path::Path| {
+            p.is_dir()
+                && p.file_name() != Some(std::ffi::OsStr::new(".transfer-tmp"))
+                && !path_safety.is_hidden(p)
+                && permissions.permits(p, Access::Read, authenticated)
+        };
+        let mut children = Vec::new();
+        for entry in std::fs::read_dir(&dir)?.filter_map(Result::ok) {
+            let path = entry.path();
+            if !visible(&path) {
+                continue;
+            }
+            // 只要找到一个可见的子目录就够了，不遍历整个目录
+            let has_children = std::fs::read_dir(&path)
+                .map(|rd| rd.filter_map(Result::ok).any(|e| visible(&e.path())))
+                .unwrap_or(false);
+            children.push((path, has_children));
+        }
+        Ok(children)
+    })
+    .await
+    .map_err(|e| AppError::Internal(anyhow::anyhow!("list dirs: {}", e)))??;
+
+    let mut dirs: Vec<DirNode> = children
+        .into_iter()
+        .map(|(path, has_children)| DirNode {
+            name: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            path: relative_path(&path, &state.root),
+            has_children,
+        })
+        .collect();
+    dirs.sort_by_key(|d| d.name.to_lowercase());
+
+    Ok(Json(DirsResponse {
+        path: relative_path(&abs, &state.root),
+        dirs,
+    }))
+}
+
 #[derive(Deserialize)]
 pub struct MkdirRequest {
     pub path: String,
@@ -110,8 +337,19 @@ pub async fn mkdir(
     } else {
         state.path_safety.resolve(&req.path)?
     };
-    let name = sanitize_filename::sanitize(&req.name);
+    // 与上传使用同一套文件名规则
+    let name = state.filenames.sanitize(&req.name);
+    if name.is_empty() {
+        return Err(AppError::BadRequest("invalid folder name".into()));
+    }
     let target = parent.join(&name);
+    state.permissions.check(&target, Access::Write)?;
+    if target.exists() {
+        return Err(AppError::AlreadyExists(relative_path(&target, &state.root)));
+    }
+    if let Some(existing) = state.filenames.existing_case(&parent, &name).await {
+        return Err(AppError::AlreadyExists(relative_path(&parent.join(existing), &state.root)));
+    }
     operations::mkdir(&target).await?;
     Ok(StatusCode::CREATED)
 }
@@ -128,32 +366,121 @@ pub async fn rename(
     Json(req): Json<RenameRequest>,
 ) -> Result<StatusCode, AppError> {
     let from = state.path_safety.resolve(&req.path)?;
-    let new_name = sanitize_filename::sanitize(&req.new_name);
-    let to = from
-        .parent()
-        .ok_or(AppError::BadRequest("no parent".into()))?
-        .join(&new_name);
+    if from == state.root {
+        return Err(AppError::Forbidden("cannot rename root directory"));
+    }
+    if !from.exists() {
+        return Err(AppError::NotFound(req.path));
+    }
+    // 与上传、新建文件夹使用同一套文件名规则
+    let new_name = state.filenames.sanitize(&req.new_name);
+    if new_name.is_empty() {
+        return Err(AppError::BadRequest("invalid file name".into()));
+    }
+    let parent = from.parent().ok_or(AppError::BadRequest("no parent".into()))?;
+    let to = parent.join(&new_name);
+    if to == from {
+        return Ok(StatusCode::OK);
+    }
+    state.permissions.check(&from, Access::Write)?;
+    state.permissions.check(&to, Access::Write)?;
+    // 只改大小写时，不区分大小写的文件系统上目标就是文件自己
+    if to.exists() && !operations::same_entry(&from, &to) {
+        return Err(AppError::AlreadyExists(relative_path(&to, &state.root)));
+    }
+    if let Some(existing) = state.filenames.existing_case(parent, &new_name).await {
+        let existing = parent.join(existing);
+        if existing != from {
+            return Err(AppError::AlreadyExists(relative_path(&existing, &state.root)));
+        }
+    }
     operations::rename(&from, &to).await?;
     Ok(StatusCode::OK)
 }
 
+#[derive(Deserialize)]
+pub struct ChmodRequest {
+    pub path: String,
+    /// 八进制 (`755`) 或符号形式 (`+x`、`u+x,go-w`)
+    pub mode: String,
+}
+
+#[derive(Serialize)]
+pub struct ChmodResponse {
+    pub mode: u32,
+}
+
+/// POST /api/files/chmod
+pub async fn chmod(
+    State(state): State<AppState>,
+    Json(req): Json<ChmodRequest>,
+) -> Result<Json<ChmodResponse>, AppError> {
+    let path = state.path_safety.resolve(&req.path)?;
+    if path == state.root {
+        return Err(AppError::Forbidden("cannot change permissions of root directory"));
+    }
+    state.permissions.check(&path, Access::Write)?;
+    let mode = mode::chmod(&path, &req.mode).await?;
+    Ok(Json(ChmodResponse { mode }))
+}
+
 #[derive(Deserialize)]
 pub struct MoveRequest {
     pub source: String,
     pub destination: String,
 }
 
-/// POST /api/files/move
-pub async fn r#move(
-    State(state): State<AppState>,
-    Json(req): Json<MoveRequest>,
-) -> Result<StatusCode, AppError> {
+/// 移动、复制共用的检查，返回源路径和目标路径
+fn transfer_paths(state: &AppState, req: &MoveRequest) -> Result<(PathBuf, PathBuf), AppError> {
     let from = state.path_safety.resolve(&req.source)?;
+    if from == state.root {
+        return Err(AppError::Forbidden("cannot move or copy root directory"));
+    }
+    if !from.exists() {
+        return Err(AppError::NotFound(req.source.clone()));
+    }
     let dest_dir = state.path_safety.resolve(&req.destination)?;
+    if !dest_dir.is_dir() {
+        return Err(AppError::NotFound(req.destination.clone()));
+    }
+    // 目录不能放进自己或自己的子目录，否则复制会无限递归
+    if dest_dir.starts_with(&from) {
+        return Err(AppError::BadRequest("cannot move or copy a directory into itself".into()));
+    }
     let name = from
         .file_name()
         .ok_or(AppError::BadRequest("no filename".into()))?;
     let to = dest_dir.join(name);
+    Ok((from, to))
+}
+
+/// 目标已存在 (包括只差大小写的同名项) 时返回 409
+pub(crate) async fn ensure_vacant(state: &AppState, to: &Path) -> Result<(), AppError> {
+    if to.exists() {
+        return Err(AppError::AlreadyExists(relative_path(to, &state.root)));
+    }
+    let (Some(dir), Some(name)) = (to.parent(), to.file_name()) else {
+        return Ok(());
+    };
+    if let Some(existing) = state.filenames.existing_case(dir, &name.to_string_lossy()).await {
+        return Err(AppError::AlreadyExists(relative_path(&dir.join(existing), &state.root)));
+    }
+    Ok(())
+}
+
+/// POST /api/files/move
+pub async fn r#move(
+    State(state): State<AppState>,
+    Json(req): Json<MoveRequest>,
+) -> Result<StatusCode, AppError> {
+    let (from, to) = transfer_paths(&state, &req)?;
+    // 移到所在目录，什么都不用做
+    if to == from {
+        return Ok(StatusCode::OK);
+    }
+    state.permissions.check(&from, Access::Delete)?;
+    state.permissions.check(&to, Access::Write)?;
+    ensure_vacant(&state, &to).await?;
     operations::move_entry(&from, &to).await?;
     Ok(StatusCode::OK)
 }
@@ -163,12 +490,10 @@ pub async fn copy(
     State(state): State<AppState>,
     Json(req): Json<MoveRequest>,
 ) -> Result<StatusCode, AppError> {
-    let from = state.path_safety.resolve(&req.source)?;
-    let dest_dir = state.path_safety.resolve(&req.destination)?;
-    let name = from
-        .file_name()
-        .ok_or(AppError::BadRequest("no filename".into()))?;
-    let to = dest_dir.join(name);
+    let (from, to) = transfer_paths(&state, &req)?;
+    state.permissions.check(&from, Access::Read)?;
+    state.permissions.check(&to, Access::Write)?;
+    ensure_vacant(&state, &to).await?;
     operations::copy_file(&from, &to).await?;
     Ok(StatusCode::CREATED)
 }
@@ -178,20 +503,167 @@ pub struct BatchDeleteRequest {
     pub paths: Vec<String>,
 }
 
-/// POST /api/files/delete
+#[derive(Serialize)]
+pub struct BatchDeleteResponse {
+    /// 移进回收站的条目；未启用回收站时是只保留到撤销期结束的条目，不提供撤销时为空
+    pub trashed: Vec<TrashItem>,
+    /// 撤销这次删除用的令牌，不提供撤销时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub undo: Option<UndoToken>,
+}
+
+#[derive(Serialize)]
+pub struct UndoToken {
+    pub token: String,
+    /// 剩余可撤销的秒数
+    pub expires_in: u64,
+}
+
+/// POST /api/files/delete — 启用回收站或提供撤销时移进回收站，否则彻底删除
 pub async fn batch_delete(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     Json(req): Json<BatchDeleteRequest>,
-) -> Result<StatusCode, AppError> {
+) -> Result<Json<BatchDeleteResponse>, AppError> {
+    let mut paths = Vec::with_capacity(req.paths.len());
     for path_str in &req.paths {
         let path = state.path_safety.resolve(path_str)?;
         // 不允许删除根目录
         if path == state.root {
             return Err(AppError::Forbidden("cannot delete root directory"));
         }
-        operations::delete(&path).await?;
+        state.permissions.check(&path, Access::Delete)?;
+        paths.push(path);
+    }
+    let mut trashed = Vec::new();
+    for path in &paths {
+        // 同一批中的上级目录已经删掉了
+        if !path.exists() {
+            continue;
+        }
+        let is_dir = path.is_dir();
+        let relative = relative_path(path, &state.root);
+        if state.trash.keeps_deleted() {
+            let item = state
+                .trash
+                .put(path, relative.clone(), auth::current_user(), Some(peer.ip()))
+                .await?;
+            trashed.push(item);
+        } else {
+            operations::delete(path).await?;
+        }
+        state.hooks.fire(
+            HookEvent::Delete,
+            serde_json::json!({
+                "path": relative,
+                "is_dir": is_dir,
+                "trashed": state.trash.keeps_deleted(),
+                "client_ip": peer.ip(),
+                "user": auth::current_user(),
+            }),
+        );
+    }
+    let ids: Vec<i64> = trashed.iter().map(|item| item.id).collect();
+    let undo = state.trash.register_undo(ids.clone()).map(|token| UndoToken {
+        token,
+        expires_in: state.trash.undo_window().as_secs(),
+    });
+    if !state.trash.is_enabled() && !ids.is_empty() {
+        let state = state.clone();
+        tokio::spawn(async move { state.trash.purge_after_undo(ids).await });
+    }
+    Ok(Json(BatchDeleteResponse { trashed, undo }))
+}
+
+/// 一次最多查询的路径数
+const MAX_STAT_PATHS: usize = 10_000;
+
+#[derive(Deserialize)]
+pub struct StatRequest {
+    pub paths: Vec<String>,
+    /// 同时返回文件的 SHA-256（按大小 + mtime 缓存）
+    #[serde(default)]
+    pub hash: bool,
+}
+
+#[derive(Serialize)]
+pub struct PathStat {
+    pub path: String,
+    pub exists: bool,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// POST /api/files/stat — 批量查询文件是否存在及大小、修改时间，供客户端跳过未变化的文件
+pub async fn stat(
+    State(state): State<AppState>,
+    Json(req): Json<StatRequest>,
+) -> Result<Json<Vec<PathStat>>, AppError> {
+    if req.paths.len() > MAX_STAT_PATHS {
+        return Err(AppError::BadRequest(format!(
+            "too many paths (max {})",
+            MAX_STAT_PATHS
+        )));
+    }
+    let mut stats = Vec::with_capacity(req.paths.len());
+    for path in req.paths {
+        // 父目录不存在时 resolve 返回 NotFound，同样视为不存在
+        let abs = match state.path_safety.resolve(&path) {
+            Ok(abs) => abs,
+            Err(AppError::NotFound(_)) => {
+                stats.push(PathStat::missing(path));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        state.permissions.check(&abs, Access::Read)?;
+        let meta = match tokio::fs::metadata(&abs).await {
+            Ok(meta) if !state.path_safety.is_hidden(&abs) => meta,
+            Ok(_) => {
+                stats.push(PathStat::missing(path));
+                continue;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                stats.push(PathStat::missing(path));
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let sha256 = if req.hash && meta.is_file() {
+            Some(state.checksums.sha256(&state.blocking, &abs).await?)
+        } else {
+            None
+        };
+        stats.push(PathStat {
+            path,
+            exists: true,
+            is_dir: meta.is_dir(),
+            size: vault::content_len(state.vault.as_deref(), &abs, meta.len()).await,
+            modified: meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            sha256,
+        });
+    }
+    Ok(Json(stats))
+}
+
+impl PathStat {
+    fn missing(path: String) -> Self {
+        Self {
+            path,
+            exists: false,
+            is_dir: false,
+            size: 0,
+            modified: None,
+            sha256: None,
+        }
     }
-    Ok(StatusCode::OK)
 }
 
 #[derive(Deserialize)]
@@ -218,16 +690,25 @@ pub async fn search(
         state.path_safety.resolve(&params.path)?
     };
 
+    state.permissions.check(&base, Access::Read)?;
+
     let query = params.q.to_lowercase();
     let limit = params.limit.min(200);
 
     let base_clone = base.clone();
+    let path_safety = state.path_safety.clone();
+    let permissions = state.permissions.clone();
+    let authenticated = auth::current_user().is_some();
     let results = tokio::task::spawn_blocking(move || {
         let mut found = Vec::new();
         for entry in walkdir::WalkDir::new(&base_clone)
             .min_depth(1)
             .max_depth(10)
             .into_iter()
+            .filter_entry(|e| {
+                !path_safety.is_hidden(e.path())
+                    && permissions.permits(e.path(), Access::Read, authenticated)
+            })
             .filter_map(Result::ok)
         {
             let name = entry.file_name().to_string_lossy().to_lowercase();
@@ -246,11 +727,10 @@ pub async fn search(
     let mut metas = Vec::with_capacity(results.len());
     for path in results {
         if let Ok(mut meta) = FileMeta::from_path(&path).await {
-            meta.path = path
-                .strip_prefix(&state.root)
-                .unwrap_or(&path)
-                .to_string_lossy()
-                .to_string();
+            if !meta.is_dir {
+                meta.size = vault::content_len(state.vault.as_deref(), &path, meta.size).await;
+            }
+            meta.path = relative_path(&path, &state.root);
             metas.push(meta);
         }
     }
@@ -35,3 +35,45 @@ pub async fn ready(State(state): State<AppState>) -> Result<Json<HealthResponse>
         version: env!("CARGO_PKG_VERSION"),
     }))
 }
+
+#[derive(Serialize)]
+pub struct InfoResponse {
+    version: &'static str,
+    /// 投递箱模式，前端只显示上传区
+    drop_box: bool,
+    /// 只读模式，前端隐藏上传、删除等操作
+    read_only: bool,
+    /// 回收站保留天数，0 表示不使用回收站；前端据此显示回收站入口
+    trash_days: u64,
+    /// 删除后可以撤销的秒数，0 表示不提供撤销
+    undo_secs: u64,
+    /// 单文件上传上限 (字节, 0 = 无限制)
+    max_upload_size: u64,
+    /// 启用了 LocalSend，前端显示推送到附近设备的入口
+    localsend: bool,
+    /// 接收模式，前端订阅待确认的接收请求
+    localsend_confirm: bool,
+    /// 剪贴板同步是否开启
+    clipboard: bool,
+    /// 维护中暂停上传时的说明，前端显示为横幅
+    maintenance: Option<String>,
+}
+
+/// GET /api/info — 前端启动时读取的服务端模式
+pub async fn info(State(state): State<AppState>) -> Json<InfoResponse> {
+    Json(InfoResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        drop_box: state.config.drop_box,
+        read_only: state.config.read_only,
+        trash_days: state.config.trash_days,
+        undo_secs: state.config.undo_secs,
+        max_upload_size: state.live.max_upload_size(),
+        localsend: state.localsend.is_some(),
+        localsend_confirm: state
+            .localsend
+            .as_ref()
+            .is_some_and(|ls| ls.approvals.is_some()),
+        clipboard: state.config.clipboard_ttl_secs > 0,
+        maintenance: state.maintenance.notice(),
+    })
+}
@@ -0,0 +1,113 @@
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::header::*;
+use axum::http::{Response, StatusCode};
+use tokio_util::io::ReaderStream;
+
+use crate::download::etag;
+use crate::error::AppError;
+use crate::fs::permissions::Access;
+use crate::preview::hls::{self, HlsManager};
+use crate::state::AppState;
+
+fn manager(state: &AppState) -> Result<&HlsManager, AppError> {
+    state
+        .hls
+        .as_ref()
+        .ok_or(AppError::BadRequest("transcoding is disabled (start with --ffmpeg)".into()))
+}
+
+/// GET /api/hls/{*path} — HLS 播放列表，首次请求时启动转码
+pub async fn playlist(
+    State(state): State<AppState>,
+    Path(rel): Path<String>,
+) -> Result<Response<Body>, AppError> {
+    let hls = manager(&state)?;
+    let abs = state.path_safety.resolve(&rel)?;
+    if abs.is_dir() {
+        return Err(AppError::IsADirectory);
+    }
+    state.permissions.check(&abs, Access::Read)?;
+    if !hls::needs_transcode(&abs) {
+        return Err(AppError::BadRequest("file can be played natively".into()));
+    }
+
+    let meta = tokio::fs::metadata(&abs).await?;
+    let key = HlsManager::cache_key(&abs, &etag::compute_etag(&meta));
+    let playlist = hls.playlist(&abs, &key).await?;
+
+    // 分片相对路径改写为分片接口
+    let content = tokio::fs::read_to_string(&playlist).await?;
+    let rewritten: String = content
+        .lines()
+        .map(|line| {
+            if line.is_empty() || line.starts_with('#') {
+                format!("{}\n", line)
+            } else {
+                format!("/api/hls-segment/{}/{}\n", key, line.trim())
+            }
+        })
+        .collect();
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/vnd.apple.mpegurl")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(Body::from(rewritten))
+        .unwrap())
+}
+
+/// GET /api/hls-segment/{key}/{name} — HLS 分片
+pub async fn segment(
+    State(state): State<AppState>,
+    Path((key, name)): Path<(String, String)>,
+) -> Result<Response<Body>, AppError> {
+    let path = manager(&state)?
+        .segment_path(&key, &name)
+        .ok_or(AppError::BadRequest("invalid segment".into()))?;
+    let file = tokio::fs::File::open(&path)
+        .await
+        .map_err(|_| AppError::NotFound(name))?;
+    let len = file.metadata().await?.len();
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "video/mp2t")
+        .header(CONTENT_LENGTH, len)
+        .header(CACHE_CONTROL, "public, max-age=86400")
+        .body(Body::from_stream(ReaderStream::new(file)))
+        .unwrap())
+}
+
+/// GET /api/transcode/{*path} — fragmented MP4 渐进流，给不支持 HLS 的浏览器
+pub async fn mp4(
+    State(state): State<AppState>,
+    Path(rel): Path<String>,
+) -> Result<Response<Body>, AppError> {
+    let hls = manager(&state)?;
+    let abs = state.path_safety.resolve(&rel)?;
+    if abs.is_dir() {
+        return Err(AppError::IsADirectory);
+    }
+    state.permissions.check(&abs, Access::Read)?;
+
+    let mut child = hls.spawn_mp4_stream(&abs)?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| AppError::Internal(anyhow::anyhow!("ffmpeg stdout unavailable")))?;
+
+    // 客户端断开时 body 被 drop，kill_on_drop 随之结束 ffmpeg
+    let stream = ReaderStream::with_capacity(stdout, 256 * 1024);
+    let body = Body::from_stream(futures_util::StreamExt::map(stream, move |chunk| {
+        let _keep_alive = &child;
+        chunk
+    }));
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "video/mp4")
+        .header(CACHE_CONTROL, "no-store")
+        .body(body)
+        .unwrap())
+}
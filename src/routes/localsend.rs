@@ -0,0 +1,394 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Path, Query, State};
+use axum::http::header::ACCEPT_ENCODING;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use futures_util::{Stream, StreamExt};
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::fs::permissions::Access;
+use crate::fs::vault;
+use crate::localsend::approval::{Approvals, PendingGuard, ReceiveRequest};
+use crate::localsend::protocol::{DeviceInfo, PrepareUploadRequest, PrepareUploadResponse};
+use crate::localsend::receiver::IncomingFile;
+use crate::localsend::sender::{SendFile, SendTask};
+use crate::localsend::{LocalSend, NearbyDevice};
+use crate::middleware::auth;
+use crate::routes::upload::{place_file, record_upload};
+use crate::routes::zipdl::collect_files;
+use crate::state::AppState;
+use crate::upload::writer::ChunkWriter;
+use crate::util::encoding;
+
+/// 接收模式下无人确认时按拒绝处理
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+/// 未启用 --localsend 时所有接口返回 404
+fn enabled(state: &AppState) -> Result<&LocalSend, AppError> {
+    state
+        .localsend
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("localsend".into()))
+}
+
+/// GET /api/localsend/v2/info — 设备信息
+pub async fn info(State(state): State<AppState>) -> Result<Json<DeviceInfo>, AppError> {
+    Ok(Json(enabled(&state)?.info.without_address()))
+}
+
+/// POST /api/localsend/v2/register — 对方收到我们的组播公告后回应
+pub async fn register(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Json(device): Json<DeviceInfo>,
+) -> Result<Json<DeviceInfo>, AppError> {
+    let ls = enabled(&state)?;
+    if device.fingerprint != ls.info.fingerprint {
+        ls.devices.upsert(peer.ip(), &device);
+    }
+    Ok(Json(ls.info.without_address()))
+}
+
+#[derive(Deserialize)]
+pub struct PrepareParams {
+    pin: Option<String>,
+}
+
+/// POST /api/localsend/v2/prepare-upload — 发送方请求传输
+///
+/// 默认直接接受，接收模式下等待控制台或网页确认；无写权限或超过上传上限的文件不出现在响应中
+pub async fn prepare_upload(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Query(params): Query<PrepareParams>,
+    Json(req): Json<PrepareUploadRequest>,
+) -> Result<Response, AppError> {
+    let ls = enabled(&state)?;
+    if let Some(pin) = &ls.pin {
+        if params.pin.as_deref() != Some(pin.as_str()) {
+            return Err(AppError::Unauthorized);
+        }
+    }
+    if req.files.is_empty() {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    let mut base_dir = state.root.clone();
+    if let Some(sub) = state.config.upload_subdir.path(
+        time::OffsetDateTime::now_utc(),
+        Some(peer.ip()),
+        auth::current_user().as_deref(),
+    ) {
+        base_dir.push(sub);
+    }
+
+    let max_size = state.live.max_upload_size();
+    let mut accepted = Vec::new();
+    for (id, file) in req.files {
+        let filename = file
+            .file_name
+            .rsplit(['/', '\\'])
+            .next()
+            .map(|name| state.filenames.sanitize(name))
+            .unwrap_or_default();
+        if filename.is_empty() {
+            continue;
+        }
+        if max_size > 0 && file.size > max_size {
+            continue;
+        }
+        let target_dir = base_dir.join(state.filenames.relative_dir(&file.file_name));
+        if state
+            .permissions
+            .check(&target_dir.join(&filename), Access::Write)
+            .is_err()
+        {
+            continue;
+        }
+        accepted.push(IncomingFile {
+            id,
+            target_dir,
+            file_name: filename,
+            size: file.size,
+        });
+    }
+    if accepted.is_empty() {
+        return Err(AppError::Forbidden("no file accepted"));
+    }
+
+    if let Some(approvals) = &ls.approvals {
+        let listed: Vec<(String, u64)> = accepted
+            .iter()
+            .map(|f| (f.file_name.clone(), f.size))
+            .collect();
+        let request = ReceiveRequest::new(
+            req.info.alias.clone(),
+            req.info.device_model.clone(),
+            peer.ip(),
+            &listed,
+        );
+        let _guard = PendingGuard {
+            approvals,
+            id: request.id.clone(),
+        };
+        let decision = approvals.request(request);
+        tracing::info!(sender = %req.info.alias, ip = %peer.ip(), "localsend transfer awaiting confirmation");
+        match tokio::time::timeout(APPROVAL_TIMEOUT, decision).await {
+            Ok(Ok(true)) => {}
+            _ => {
+                tracing::info!(sender = %req.info.alias, "localsend transfer declined");
+                return Err(AppError::Forbidden("rejected"));
+            }
+        }
+    }
+
+    let count = accepted.len();
+    let (session_id, files) = ls.receiver.prepare(peer.ip(), accepted)?;
+    tracing::info!(
+        sender = %req.info.alias,
+        ip = %peer.ip(),
+        files = count,
+        "localsend transfer accepted"
+    );
+    // 非标准扩展：同为本程序的发送方据此用 zstd 压缩文件内容，官方 LocalSend 客户端会忽略
+    Ok((
+        [(ACCEPT_ENCODING, encoding::ZSTD)],
+        Json(PrepareUploadResponse { session_id, files }),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadParams {
+    session_id: String,
+    file_id: String,
+    token: String,
+}
+
+/// POST /api/localsend/v2/upload — 请求体是单个文件的原始内容
+pub async fn upload(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Query(params): Query<UploadParams>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<StatusCode, AppError> {
+    let ls = enabled(&state)?;
+    let file = ls
+        .receiver
+        .begin(&params.session_id, &params.file_id, &params.token, peer.ip())?;
+
+    let tmp_path = state
+        .upload_manager
+        .tmp_dir()
+        .join(format!("localsend-{}.part", uuid::Uuid::new_v4().simple()));
+    let result = async {
+        let mut writer = ChunkWriter::open(&tmp_path, 0).await?;
+        let mut size: u64 = 0;
+        let mut stream = encoding::decode_body(&headers, body)?;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| AppError::BadRequest(e.to_string()))?;
+            size += chunk.len() as u64;
+            if size > file.size {
+                return Err(AppError::SizeMismatch {
+                    expected: file.size,
+                    actual: size,
+                });
+            }
+            writer.write_all(&chunk).await?;
+        }
+        writer.flush_data().await?;
+        if size != file.size {
+            return Err(AppError::SizeMismatch {
+                expected: file.size,
+                actual: size,
+            });
+        }
+        place_file(&state, &tmp_path, &file.target_dir, &file.file_name).await
+    }
+    .await;
+
+    match result {
+        Ok(final_path) => {
+            ls.receiver.finish(&params.session_id, &params.file_id, true);
+            let rel_path = record_upload(&state, &final_path, file.size, peer);
+            tracing::info!(path = %rel_path, size = file.size, "localsend file received");
+            Ok(StatusCode::OK)
+        }
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            ls.receiver.finish(&params.session_id, &params.file_id, false);
+            Err(e)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelParams {
+    session_id: String,
+}
+
+/// POST /api/localsend/v2/cancel — 发送方取消传输
+pub async fn cancel(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Query(params): Query<CancelParams>,
+) -> Result<StatusCode, AppError> {
+    let ls = enabled(&state)?;
+    if ls.receiver.cancel(&params.session_id, peer.ip()) {
+        tracing::info!(ip = %peer.ip(), "localsend transfer cancelled by sender");
+    }
+    Ok(StatusCode::OK)
+}
+
+/// GET /api/localsend/requests — SSE 推送待确认的接收请求
+///
+/// 连接后立即发送一次当前列表，之后每次变化发送 `requests` 事件（完整列表）
+pub async fn requests(
+    State(state): State<AppState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let approvals = approvals(&state)?;
+    let rx = approvals.subscribe();
+    let stream = futures_util::stream::unfold((rx, true), |(mut rx, first)| async move {
+        if !first {
+            rx.changed().await.ok()?;
+        }
+        let list = rx.borrow_and_update().clone();
+        let event = Event::default()
+            .event("requests")
+            .json_data(&list)
+            .unwrap_or_else(|_| Event::default().event("requests").data("[]"));
+        Some((Ok(event), (rx, false)))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Deserialize)]
+pub struct DecideBody {
+    pub accept: bool,
+}
+
+/// POST /api/localsend/requests/{id} — 接受或拒绝接收请求
+pub async fn decide(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<DecideBody>,
+) -> Result<StatusCode, AppError> {
+    let approvals = approvals(&state)?;
+    if approvals.decide(&id, body.accept) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(id))
+    }
+}
+
+/// 接收模式的确认队列；决定能否写入共享目录，要求根目录的写权限
+fn approvals(state: &AppState) -> Result<&Approvals, AppError> {
+    let approvals = enabled(state)?
+        .approvals
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("localsend receive mode".into()))?;
+    state.permissions.check(&state.root, Access::Write)?;
+    Ok(approvals)
+}
+
+/// GET /api/localsend/devices — 附近的 LocalSend 设备
+pub async fn devices(State(state): State<AppState>) -> Result<Json<Vec<NearbyDevice>>, AppError> {
+    Ok(Json(enabled(&state)?.devices.list()))
+}
+
+#[derive(Deserialize)]
+pub struct SendBody {
+    /// 设备指纹
+    pub device: String,
+    /// 文件或目录（相对 root），目录会展开并保留目录结构
+    pub paths: Vec<String>,
+}
+
+/// POST /api/localsend/send — 把共享目录中的文件推送到附近设备
+pub async fn send(
+    State(state): State<AppState>,
+    Json(body): Json<SendBody>,
+) -> Result<(StatusCode, Json<SendTask>), AppError> {
+    let ls = enabled(&state)?;
+    let device = ls
+        .devices
+        .get(&body.device)
+        .ok_or_else(|| AppError::NotFound(body.device.clone()))?;
+
+    let entries: Vec<PathBuf> = body
+        .paths
+        .iter()
+        .filter(|p| !p.is_empty())
+        .map(|p| state.path_safety.resolve(p))
+        .collect::<Result<_, _>>()?;
+    if entries.is_empty() {
+        return Err(AppError::BadRequest("no paths specified".into()));
+    }
+    for entry in &entries {
+        state.permissions.check(entry, Access::Read)?;
+    }
+
+    let authenticated = auth::current_user().is_some();
+    let mut files = Vec::new();
+    for entry in entries {
+        // 对方看到的名称相对所选条目的父目录，选中目录时保留目录名
+        let base = entry.parent().map(PathBuf::from).unwrap_or_default();
+        let found = collect_files(
+            vec![entry],
+            state.path_safety.clone(),
+            state.permissions.clone(),
+            authenticated,
+        )
+        .await?;
+        for (path, size) in found {
+            let name = path
+                .strip_prefix(&base)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let size = vault::content_len(state.vault.as_deref(), &path, size).await;
+            files.push(SendFile { path, name, size });
+        }
+    }
+    if files.is_empty() {
+        return Err(AppError::BadRequest("no files to send".into()));
+    }
+
+    let task = ls.sender.start(device, files, state.vault.clone());
+    Ok((StatusCode::ACCEPTED, Json(task)))
+}
+
+/// GET /api/localsend/sends/{id} — 推送进度
+pub async fn send_status(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<SendTask>, AppError> {
+    enabled(&state)?
+        .sender
+        .get(&id)
+        .map(Json)
+        .ok_or(AppError::NotFound(id))
+}
+
+/// DELETE /api/localsend/sends/{id} — 取消推送或移除已结束的记录
+pub async fn send_cancel(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if enabled(&state)?.sender.cancel(&id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(id))
+    }
+}
@@ -0,0 +1,509 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::http::header::*;
+use axum::http::{HeaderMap, Response, StatusCode};
+use axum::Json;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::error::AppError;
+use crate::fs::path_safety::relative_path;
+use crate::fs::permissions::Access;
+use crate::fs::vault;
+use crate::middleware::auth;
+use crate::routes::zipdl::collect_files;
+use crate::state::AppState;
+use crate::util::hash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha256,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManifestFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Deserialize)]
+pub struct ManifestParams {
+    #[serde(default)]
+    pub path: String,
+    /// 不指定时只列出大小和修改时间，不读文件内容
+    pub hash: Option<HashAlgorithm>,
+    #[serde(default)]
+    pub format: ManifestFormat,
+}
+
+/// 清单中的一个文件，路径相对于生成清单的目录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// GET /api/manifest?path=&hash=sha256&format=csv — 目录下所有文件的清单，接收方据此核对批量下载是否完整
+///
+/// 边计算边输出；文件很多或首次计算校验和时也不会等到全部完成才有响应
+pub async fn export(
+    State(state): State<AppState>,
+    Query(params): Query<ManifestParams>,
+) -> Result<Response<Body>, AppError> {
+    let base = if params.path.is_empty() {
+        state.root.clone()
+    } else {
+        state.path_safety.resolve(&params.path)?
+    };
+    if !base.is_dir() {
+        return Err(AppError::BadRequest("not a directory".into()));
+    }
+    state.permissions.check(&base, Access::Read)?;
+
+    let mut files = collect_files(
+        vec![base.clone()],
+        state.path_safety.clone(),
+        state.permissions.clone(),
+        auth::current_user().is_some(),
+    )
+    .await?;
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let display = relative_path(&base, &state.root);
+    let stem = match base.file_name() {
+        Some(name) if base != state.root => name.to_string_lossy().to_string(),
+        _ => "root".to_string(),
+    };
+    let (content_type, ext) = match params.format {
+        ManifestFormat::Json => ("application/json", "json"),
+        ManifestFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+    };
+
+    let (tx, rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(16);
+    tokio::spawn(async move {
+        let header = match params.format {
+            ManifestFormat::Json => format!(
+                "{{\"path\":{},\"generated\":{},\"hash\":{},\"files\":[\n",
+                serde_json::to_string(&display).unwrap_or_default(),
+                crate::db::now_secs(),
+                serde_json::to_string(&params.hash).unwrap_or_default(),
+            ),
+            ManifestFormat::Csv => "path,size,modified,sha256\n".to_string(),
+        };
+        if tx.send(Ok(header.into())).await.is_err() {
+            return;
+        }
+
+        let mut first = true;
+        for (path, _) in files {
+            let entry = match entry(&state, &base, &path, params.hash).await {
+                Ok(Some(entry)) => entry,
+                Ok(None) => continue,
+                Err(e) => {
+                    // 中途出错时截断响应，不输出不完整却看似正常的清单
+                    tracing::warn!(path = %path.display(), error = %e, "manifest aborted");
+                    let _ = tx.send(Err(std::io::Error::other(e.to_string()))).await;
+                    return;
+                }
+            };
+            let line = match params.format {
+                ManifestFormat::Json => {
+                    let sep = if first { "" } else { ",\n" };
+                    format!("{}{}", sep, serde_json::to_string(&entry).unwrap_or_default())
+                }
+                ManifestFormat::Csv => csv_row(&entry),
+            };
+            first = false;
+            if tx.send(Ok(line.into())).await.is_err() {
+                return;
+            }
+        }
+
+        if params.format == ManifestFormat::Json {
+            let _ = tx.send(Ok(Bytes::from_static(b"\n]}\n"))).await;
+        }
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, content_type)
+        .header(CACHE_CONTROL, "no-store")
+        .header(
+            CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}-manifest.{}\"", stem, ext),
+        )
+        .body(Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)))
+        .unwrap())
+}
+
+#[derive(Deserialize)]
+pub struct VerifyParams {
+    /// 清单对应的目录；清单里自带的 path 不使用，上传到别处后也能核对
+    #[serde(default)]
+    pub path: String,
+}
+
+/// 导出的 JSON 清单；只用到 files，其余字段忽略
+#[derive(Deserialize)]
+struct ManifestBody {
+    files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyStatus {
+    Ok,
+    Missing,
+    NotAFile,
+    SizeMismatch,
+    ChecksumMismatch,
+    /// 路径越界或校验和格式不对
+    Invalid,
+}
+
+#[derive(Serialize)]
+pub struct VerifyResult {
+    pub path: String,
+    pub status: VerifyStatus,
+    pub expected_size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_sha256: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct VerifyReport {
+    pub path: String,
+    /// 清单中的文件全部存在且一致
+    pub ok: bool,
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub files: Vec<VerifyResult>,
+    /// 目录中有、清单中没有的文件
+    pub unexpected: Vec<String>,
+}
+
+/// POST /api/manifest/verify?path= — 按客户端提交的清单逐个核对目录中的文件，返回每个文件的结果
+///
+/// 请求体为导出的 JSON 清单，`Content-Type: text/csv` 时为导出的 CSV 清单；
+/// 清单带 sha256 的文件会计算校验和（按大小 + mtime 缓存），大小不一致时不再计算
+pub async fn verify(
+    State(state): State<AppState>,
+    Query(params): Query<VerifyParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<VerifyReport>, AppError> {
+    let base = if params.path.is_empty() {
+        state.root.clone()
+    } else {
+        state.path_safety.resolve(&params.path)?
+    };
+    if !base.is_dir() {
+        return Err(AppError::BadRequest("not a directory".into()));
+    }
+    state.permissions.check(&base, Access::Read)?;
+
+    let is_csv = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/csv"));
+    let entries = if is_csv {
+        let text = std::str::from_utf8(&body)
+            .map_err(|_| AppError::BadRequest("manifest is not valid UTF-8".into()))?;
+        parse_csv(text).map_err(|e| AppError::BadRequest(format!("invalid manifest: {}", e)))?
+    } else {
+        serde_json::from_slice::<ManifestBody>(&body)
+            .map_err(|e| AppError::BadRequest(format!("invalid manifest: {}", e)))?
+            .files
+    };
+
+    let authenticated = auth::current_user().is_some();
+    let mut listed = HashSet::with_capacity(entries.len());
+    let mut files = Vec::with_capacity(entries.len());
+    for expected in entries {
+        let result = check(&state, &base, &expected, authenticated).await?;
+        listed.insert(expected.path);
+        files.push(result);
+    }
+
+    let mut unexpected: Vec<String> = collect_files(
+        vec![base.clone()],
+        state.path_safety.clone(),
+        state.permissions.clone(),
+        authenticated,
+    )
+    .await?
+    .into_iter()
+    .map(|(path, _)| relative_path(&path, &base))
+    .filter(|rel| !listed.contains(rel))
+    .collect();
+    unexpected.sort();
+
+    let passed = files.iter().filter(|f| f.status == VerifyStatus::Ok).count();
+    let failed = files.len() - passed;
+    tracing::info!(path = %params.path, total = files.len(), failed, "manifest verified");
+    Ok(Json(VerifyReport {
+        path: relative_path(&base, &state.root),
+        ok: failed == 0,
+        total: files.len(),
+        passed,
+        failed,
+        files,
+        unexpected,
+    }))
+}
+
+/// 核对清单中的一个文件；不可读或在 root 之外的路径按不存在处理
+async fn check(
+    state: &AppState,
+    base: &Path,
+    expected: &ManifestEntry,
+    authenticated: bool,
+) -> Result<VerifyResult, AppError> {
+    let mut result = VerifyResult {
+        path: expected.path.clone(),
+        status: VerifyStatus::Ok,
+        expected_size: expected.size,
+        actual_size: None,
+        expected_sha256: None,
+        actual_sha256: None,
+    };
+    let sha256 = match expected.sha256.as_deref().filter(|s| !s.is_empty()) {
+        Some(value) => match hash::parse_sha256(value) {
+            Some(hex) => Some(hex),
+            None => {
+                result.status = VerifyStatus::Invalid;
+                return Ok(result);
+            }
+        },
+        None => None,
+    };
+    result.expected_sha256 = sha256.clone();
+    let Some(path) = entry_path(base, &expected.path) else {
+        result.status = VerifyStatus::Invalid;
+        return Ok(result);
+    };
+
+    let visible = match dunce::canonicalize(&path) {
+        Ok(canonical) => {
+            canonical.starts_with(&state.root)
+                && !state.path_safety.is_hidden(&canonical)
+                && state.permissions.permits(&canonical, Access::Read, authenticated)
+        }
+        Err(_) => false,
+    };
+    let meta = match tokio::fs::metadata(&path).await {
+        Ok(meta) if visible => meta,
+        Ok(_) => {
+            result.status = VerifyStatus::Missing;
+            return Ok(result);
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            result.status = VerifyStatus::Missing;
+            return Ok(result);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !meta.is_file() {
+        result.status = VerifyStatus::NotAFile;
+        return Ok(result);
+    }
+
+    let size = vault::content_len(state.vault.as_deref(), &path, meta.len()).await;
+    result.actual_size = Some(size);
+    if size != expected.size {
+        result.status = VerifyStatus::SizeMismatch;
+        return Ok(result);
+    }
+    if let Some(expected_sha256) = sha256 {
+        let actual = state.checksums.sha256(&state.blocking, &path).await?;
+        if actual != expected_sha256 {
+            result.status = VerifyStatus::ChecksumMismatch;
+        }
+        result.actual_sha256 = Some(actual);
+    }
+    Ok(result)
+}
+
+/// 清单中的相对路径拼到目录下，含 `..`、绝对路径或空路径时返回 None
+fn entry_path(base: &Path, rel: &str) -> Option<PathBuf> {
+    let mut path = base.to_path_buf();
+    let mut empty = true;
+    for part in rel.split('/') {
+        match part {
+            "" | "." => continue,
+            ".." => return None,
+            part if part.contains('\\') => return None,
+            part => path.push(part),
+        }
+        empty = false;
+    }
+    (!empty).then_some(path)
+}
+
+/// 解析导出的 CSV 清单，按表头定位列；sha256、modified 列可以没有
+fn parse_csv(text: &str) -> Result<Vec<ManifestEntry>, String> {
+    let mut rows = csv_records(text)?.into_iter();
+    let header = rows.next().ok_or("empty manifest")?;
+    let column = |name: &str| header.iter().position(|h| h.trim() == name);
+    let path_col = column("path").ok_or("missing path column")?;
+    let size_col = column("size").ok_or("missing size column")?;
+    let sha_col = column("sha256");
+
+    rows.enumerate()
+        .filter(|(_, row)| !(row.len() == 1 && row[0].is_empty()))
+        .map(|(i, row)| {
+            let field = |col: usize| row.get(col).map(String::as_str).unwrap_or_default();
+            Ok(ManifestEntry {
+                path: field(path_col).to_string(),
+                size: field(size_col)
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("line {}: invalid size", i + 2))?,
+                modified: None,
+                sha256: sha_col.map(|c| field(c).trim().to_string()).filter(|s| !s.is_empty()),
+            })
+        })
+        .collect()
+}
+
+/// RFC 4180 记录，带引号的字段里可以有逗号、换行和两个连续引号
+fn csv_records(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".into());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// 单个文件的清单条目；遍历之后被删除的文件返回 None
+async fn entry(
+    state: &AppState,
+    base: &Path,
+    path: &Path,
+    hash: Option<HashAlgorithm>,
+) -> Result<Option<ManifestEntry>, AppError> {
+    let meta = match tokio::fs::metadata(path).await {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    let sha256 = match hash {
+        Some(HashAlgorithm::Sha256) => Some(state.checksums.sha256(&state.blocking, path).await?),
+        None => None,
+    };
+    Ok(Some(ManifestEntry {
+        path: relative_path(path, base),
+        size: vault::content_len(state.vault.as_deref(), path, meta.len()).await,
+        modified,
+        sha256,
+    }))
+}
+
+/// RFC 4180：含逗号、引号或换行的字段加引号，引号写两遍
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_row(entry: &ManifestEntry) -> String {
+    format!(
+        "{},{},{},{}\n",
+        csv_field(&entry.path),
+        entry.size,
+        entry.modified.map(|m| m.to_string()).unwrap_or_default(),
+        entry.sha256.as_deref().unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_row() {
+        let entry = ManifestEntry {
+            path: "a, \"b\".txt".into(),
+            size: 3,
+            modified: None,
+            sha256: Some("ab".into()),
+        };
+        assert_eq!(csv_row(&entry), "\"a, \"\"b\"\".txt\",3,,ab\n");
+    }
+
+    #[test]
+    fn test_parse_csv_round_trip() {
+        let entry = ManifestEntry {
+            path: "dir/a, \"b\"\nc.txt".into(),
+            size: 3,
+            modified: Some(1),
+            sha256: Some("ab".into()),
+        };
+        let text = format!("path,size,modified,sha256\r\n{}f.txt,0,,\n", csv_row(&entry));
+        let parsed = parse_csv(&text).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].path, entry.path);
+        assert_eq!(parsed[0].sha256.as_deref(), Some("ab"));
+        assert_eq!(parsed[1].sha256, None);
+
+        assert!(parse_csv("name,size\na,1\n").is_err());
+    }
+
+    #[test]
+    fn test_entry_path() {
+        let base = Path::new("/share/a");
+        assert_eq!(entry_path(base, "b/./c.txt"), Some(PathBuf::from("/share/a/b/c.txt")));
+        assert_eq!(entry_path(base, "/b.txt"), Some(PathBuf::from("/share/a/b.txt")));
+        assert_eq!(entry_path(base, "../b.txt"), None);
+        assert_eq!(entry_path(base, ""), None);
+    }
+}
@@ -0,0 +1,142 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::Json;
+use futures_util::Stream;
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::board::{BoardEvent, MAX_AUTHOR_CHARS, MAX_TEXT_CHARS};
+use crate::db::messages::Message;
+use crate::error::AppError;
+use crate::fs::permissions::Access;
+use crate::middleware::auth;
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct ListParams {
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+    /// 只返回 ID 小于它的留言，用于向前翻页
+    pub before: Option<i64>,
+}
+
+fn default_limit() -> u32 {
+    50
+}
+
+/// GET /api/messages?limit=&before= — 最近的留言，新的在前
+pub async fn list(
+    State(state): State<AppState>,
+    Query(params): Query<ListParams>,
+) -> Result<Json<Vec<Message>>, AppError> {
+    // 留言对能浏览共享目录的人可见，投递箱访客看不到
+    state.permissions.check(&state.root, Access::Read)?;
+    let messages = state
+        .db
+        .recent_messages(params.limit.clamp(1, 200), params.before)
+        .await?;
+    Ok(Json(messages))
+}
+
+#[derive(Deserialize)]
+pub struct PostBody {
+    pub text: String,
+    /// 署名，默认取认证用户名或客户端 IP
+    pub author: Option<String>,
+    /// 留言所在目录（相对 root）
+    pub path: Option<String>,
+}
+
+/// POST /api/messages — 发布留言
+pub async fn post(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Json(body): Json<PostBody>,
+) -> Result<(StatusCode, Json<Message>), AppError> {
+    state.permissions.check(&state.root, Access::Read)?;
+    let text = body.text.trim().to_string();
+    if text.is_empty() {
+        return Err(AppError::BadRequest("empty message".into()));
+    }
+    if text.chars().count() > MAX_TEXT_CHARS {
+        return Err(AppError::BadRequest(format!(
+            "message longer than {} characters",
+            MAX_TEXT_CHARS
+        )));
+    }
+    let author = body
+        .author
+        .map(|a| a.trim().chars().take(MAX_AUTHOR_CHARS).collect::<String>())
+        .filter(|a| !a.is_empty())
+        .or_else(auth::current_user)
+        .unwrap_or_else(|| peer.ip().to_string());
+    let path = match body.path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(p) => {
+            let abs = state.path_safety.resolve(p)?;
+            if !abs.is_dir() {
+                return Err(AppError::BadRequest("path is not a directory".into()));
+            }
+            state.permissions.check(&abs, Access::Read)?;
+            // 存规范化后的路径，前端可以直接跳转
+            abs.strip_prefix(&state.root)
+                .ok()
+                .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+                .filter(|rel| !rel.is_empty())
+        }
+        None => None,
+    };
+
+    let message = state
+        .db
+        .insert_message(author, text, path, Some(peer.ip()))
+        .await?;
+    tracing::info!(id = message.id, author = %message.author, "message posted");
+    state.board.publish(BoardEvent::Posted(message.clone()));
+    Ok((StatusCode::CREATED, Json(message)))
+}
+
+/// DELETE /api/messages/{id} — 删除留言；只有发布者（同一 IP）或有根目录写权限的人可以删除
+pub async fn delete(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    state.permissions.check(&state.root, Access::Read)?;
+    let message = state
+        .db
+        .get_message(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(id.to_string()))?;
+    if message.client_ip != Some(peer.ip()) {
+        state.permissions.check(&state.root, Access::Write)?;
+    }
+    if state.db.delete_message(id).await? {
+        state.board.publish(BoardEvent::Deleted(id));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/messages/stream — SSE 推送新留言（`message` 事件）和删除（`deleted` 事件，数据为 ID）
+pub async fn stream(
+    State(state): State<AppState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    state.permissions.check(&state.root, Access::Read)?;
+    let rx = state.board.subscribe();
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        let event = match rx.recv().await {
+            Ok(BoardEvent::Posted(message)) => {
+                Event::default().event("message").json_data(&message).ok()?
+            }
+            Ok(BoardEvent::Deleted(id)) => Event::default().event("deleted").data(id.to_string()),
+            // 落后时让前端重新拉取列表
+            Err(RecvError::Lagged(_)) => Event::default().event("reload").data(""),
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(event), rx))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -1,35 +1,219 @@
+pub mod admin;
+pub mod checksum;
+pub mod clipboard;
+pub mod delta;
 pub mod download;
+pub mod fetch;
 pub mod files;
 pub mod health;
+pub mod hls;
+pub mod localsend;
+pub mod manifest;
+pub mod messages;
 pub mod preview;
+pub mod qr;
+pub mod signaling;
 pub mod static_assets;
+pub mod tail;
+pub mod trash;
 pub mod upload;
+pub mod usage;
 pub mod zipdl;
 
+use std::time::Duration;
+
+use axum::extract::DefaultBodyLimit;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{Extensions, HeaderMap, StatusCode, Version};
 use axum::Router;
 use tower::ServiceBuilder;
 use tower_http::catch_panic::CatchPanicLayer;
+use tower_http::compression::predicate::{DefaultPredicate, Predicate};
 use tower_http::compression::CompressionLayer;
 use tower_http::cors::CorsLayer;
 use tower_http::normalize_path::NormalizePathLayer;
-use tower_http::request_id::SetRequestIdLayer;
+use tower_http::request_id::{PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
 
-use crate::middleware::request_id::MakeRequestUuid;
-use crate::middleware::trace::CustomMakeSpan;
+use crate::middleware::auth;
+use crate::middleware::request_id::{self, MakeRequestUuid};
+use crate::middleware::trace::{CustomMakeSpan, CustomOnResponse};
 use crate::state::AppState;
+use crate::util::mime;
+
+/// 管理类接口的请求体上限
+const CONTROL_BODY_LIMIT: usize = 1024 * 1024;
 
 /// 构建完整的路由树
 pub fn build_router(state: AppState) -> Router {
-    let api = Router::new()
+    let timeout = Duration::from_secs(state.config.api_timeout_secs);
+    let api = control_routes(timeout)
+        .merge(upload_routes(&state))
+        .merge(stream_routes())
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            crate::middleware::rate_limit::limit,
+        ));
+
+    let router = Router::new()
+        .nest("/api", api)
+        // 静态资源
+        .route("/", axum::routing::get(static_assets::index))
+        .route("/usage", axum::routing::get(static_assets::usage))
+        .route("/devices", axum::routing::get(static_assets::devices))
+        .route("/favicon.ico", axum::routing::get(static_assets::favicon))
+        .route("/site.webmanifest", axum::routing::get(static_assets::manifest))
+        .route("/branding/logo", axum::routing::get(static_assets::logo))
+        .route("/assets/{*path}", axum::routing::get(static_assets::serve))
+        .route("/static/{*path}", axum::routing::get(static_assets::serve))
+        .fallback(static_assets::index)
+        .with_state(state.clone());
+    // 在认证之后运行，插件能看到用户名
+    #[cfg(feature = "plugins")]
+    let router = router.layer(axum::middleware::from_fn_with_state(
+        state.clone(),
+        crate::plugins::filter,
+    ));
+
+    router.layer(
+        ServiceBuilder::new()
+            .layer(NormalizePathLayer::trim_trailing_slash())
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+            .layer(PropagateRequestIdLayer::x_request_id())
+            .layer(axum::middleware::from_fn(request_id::scope))
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                auth::identify,
+            ))
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                crate::clients::track,
+            ))
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                crate::middleware::access_log::log,
+            ))
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                auth::require,
+            ))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(CustomMakeSpan)
+                    .on_response(CustomOnResponse::default()),
+            )
+            .layer(
+                CompressionLayer::new()
+                    .br(true)
+                    .gzip(true)
+                    .no_br()  // 只用 gzip，br 对动态内容收益不大
+                    .zstd(true) // 实例之间同步时客户端只声明 zstd
+                    .compress_when(DefaultPredicate::new().and(compressible)),
+            )
+            .layer(CorsLayer::very_permissive())
+            .layer(CatchPanicLayer::new()),
+    )
+}
+
+/// 已压缩的媒体和归档直接发送，不浪费 CPU
+fn compressible(_: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions) -> bool {
+    !headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(mime::is_compressed)
+}
+
+/// 元数据和管理类接口：请求体都是小 JSON，统一超时和 1 MiB 的 body 上限
+fn control_routes(timeout: Duration) -> Router<AppState> {
+    Router::new()
         // 文件 CRUD
         .route("/files", axum::routing::get(files::list))
         .route("/files/mkdir", axum::routing::post(files::mkdir))
         .route("/files/rename", axum::routing::post(files::rename))
         .route("/files/move", axum::routing::post(files::r#move))
         .route("/files/copy", axum::routing::post(files::copy))
+        .route("/files/chmod", axum::routing::post(files::chmod))
         .route("/files/delete", axum::routing::post(files::batch_delete))
         .route("/files/search", axum::routing::get(files::search))
+        .route("/files/stats", axum::routing::get(files::stats))
+        .route("/dirs", axum::routing::get(files::dirs))
+        // 回收站
+        .route(
+            "/trash",
+            axum::routing::get(trash::list).delete(trash::empty),
+        )
+        .route("/trash/{id}", axum::routing::delete(trash::purge))
+        .route("/trash/{id}/restore", axum::routing::post(trash::restore))
+        .route("/undo/{token}", axum::routing::post(trash::undo))
+        // 远程抓取
+        .route(
+            "/fetch",
+            axum::routing::get(fetch::list).post(fetch::start),
+        )
+        .route(
+            "/fetch/{id}",
+            axum::routing::get(fetch::status).delete(fetch::cancel),
+        )
+        // 文本 / Markdown 预览
+        .route("/preview/{*path}", axum::routing::get(preview::get))
+        // 下载链接二维码
+        .route("/qr/{*path}", axum::routing::get(qr::get))
+        // 磁盘占用
+        .route("/usage", axum::routing::get(usage::get))
+        .route("/usage/rescan", axum::routing::post(usage::rescan))
+        // 留言板
+        .route(
+            "/messages",
+            axum::routing::get(messages::list).post(messages::post),
+        )
+        .route("/messages/{id}", axum::routing::delete(messages::delete))
+        // LocalSend 协议（上传在 upload_routes，prepare-upload 在 stream_routes）
+        .route("/localsend/v2/info", axum::routing::get(localsend::info))
+        .route("/localsend/v2/register", axum::routing::post(localsend::register))
+        .route("/localsend/v2/cancel", axum::routing::post(localsend::cancel))
+        .route(
+            "/localsend/requests/{id}",
+            axum::routing::post(localsend::decide),
+        )
+        // 推送到附近的 LocalSend 设备
+        .route("/localsend/devices", axum::routing::get(localsend::devices))
+        .route("/localsend/send", axum::routing::post(localsend::send))
+        .route(
+            "/localsend/sends/{id}",
+            axum::routing::get(localsend::send_status).delete(localsend::send_cancel),
+        )
+        // 管理
+        .route("/admin/uploads", axum::routing::get(admin::list_uploads))
+        .route(
+            "/admin/uploads/{file_id}",
+            axum::routing::delete(admin::cancel_upload),
+        )
+        .route("/admin/metrics", axum::routing::get(admin::metrics))
+        .route("/admin/transfers", axum::routing::get(admin::transfers))
+        .route("/admin/downloads", axum::routing::get(admin::downloads))
+        .route("/admin/clients", axum::routing::get(admin::clients))
+        .route("/admin/maintenance", axum::routing::get(admin::maintenance))
+        .route("/admin/maintenance/pause", axum::routing::post(admin::pause))
+        .route("/admin/maintenance/resume", axum::routing::post(admin::resume))
+        .route("/admin/shutdown", axum::routing::post(admin::shutdown))
+        // 健康检查
+        .route("/info", axum::routing::get(health::info))
+        .route("/healthz", axum::routing::get(health::live))
+        .route("/readyz", axum::routing::get(health::ready))
+        .layer(
+            ServiceBuilder::new()
+                .layer(DefaultBodyLimit::max(CONTROL_BODY_LIMIT))
+                .layer(TimeoutLayer::with_status_code(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    timeout,
+                )),
+        )
+}
+
+/// 上传：请求体大小由 Upload-Length / --max-upload-size 控制，不设超时；维护期间拒绝新的写请求
+fn upload_routes(state: &AppState) -> Router<AppState> {
+    Router::new()
         // tus 上传
         .route(
             "/upload",
@@ -41,36 +225,53 @@ pub fn build_router(state: AppState) -> Router {
                 .patch(upload::patch)
                 .delete(upload::cancel),
         )
-        // 下载
-        .route("/download/{*path}", axum::routing::get(download::get))
-        .route("/download-zip", axum::routing::get(zipdl::get))
-        // 预览
-        .route("/preview/{*path}", axum::routing::get(preview::get))
-        // 健康检查
-        .route("/healthz", axum::routing::get(health::live))
-        .route("/readyz", axum::routing::get(health::ready));
+        .route("/upload/form", axum::routing::post(upload::form))
+        // 长度未知的管道上传
+        .route("/put/{*path}", axum::routing::put(upload::put))
+        .route("/localsend/v2/upload", axum::routing::post(localsend::upload))
+        // 按差量更新已有文件，签名在 stream_routes
+        .route("/delta/{*path}", axum::routing::post(delta::apply))
+        .layer(DefaultBodyLimit::disable())
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            crate::middleware::maintenance::guard,
+        ))
+}
 
+/// 下载和流式响应：响应时长取决于文件大小或连接时长，不设超时
+fn stream_routes() -> Router<AppState> {
     Router::new()
-        .nest("/api", api)
-        // 静态资源
-        .route("/", axum::routing::get(static_assets::index))
-        .route("/static/{*path}", axum::routing::get(static_assets::serve))
-        .fallback(static_assets::index)
-        .with_state(state)
-        .layer(
-            ServiceBuilder::new()
-                .layer(NormalizePathLayer::trim_trailing_slash())
-                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
-                .layer(
-                    TraceLayer::new_for_http().make_span_with(CustomMakeSpan),
-                )
-                .layer(
-                    CompressionLayer::new()
-                        .br(true)
-                        .gzip(true)
-                        .no_br()  // 只用 gzip，br 对动态内容收益不大
-                )
-                .layer(CorsLayer::very_permissive())
-                .layer(CatchPanicLayer::new()),
+        // 下载
+        .route("/download/{*path}", axum::routing::get(download::get))
+        .route(
+            "/download-zip",
+            axum::routing::get(zipdl::get).post(zipdl::post),
+        )
+        .route("/archive", axum::routing::post(zipdl::archive))
+        .route("/archive-progress/{id}", axum::routing::get(zipdl::progress))
+        // 大文件首次计算校验和可能超过管理接口的超时
+        .route("/checksum/{*path}", axum::routing::get(checksum::get))
+        .route("/manifest", axum::routing::get(manifest::export))
+        .route(
+            "/manifest/verify",
+            axum::routing::post(manifest::verify).layer(DefaultBodyLimit::max(64 * 1024 * 1024)),
+        )
+        .route("/files/stat", axum::routing::post(files::stat))
+        .route("/delta/{*path}", axum::routing::get(delta::signature))
+        // 媒体与实时跟随
+        .route("/hls/{*path}", axum::routing::get(hls::playlist))
+        .route("/hls-segment/{key}/{name}", axum::routing::get(hls::segment))
+        .route("/transcode/{*path}", axum::routing::get(hls::mp4))
+        .route("/tail/{*path}", axum::routing::get(tail::get))
+        // 浏览器间直传的信令
+        .route("/signal", axum::routing::get(signaling::connect))
+        .route("/messages/stream", axum::routing::get(messages::stream))
+        // 剪贴板同步
+        .route("/clipboard", axum::routing::get(clipboard::connect))
+        // 接收模式下 prepare-upload 一直挂起到有人确认
+        .route(
+            "/localsend/v2/prepare-upload",
+            axum::routing::post(localsend::prepare_upload),
         )
+        .route("/localsend/requests", axum::routing::get(localsend::requests))
 }
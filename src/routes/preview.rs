@@ -4,6 +4,7 @@ use axum::http::header::*;
 use axum::http::{Response, StatusCode};
 
 use crate::error::AppError;
+use crate::fs::permissions::Access;
 use crate::state::AppState;
 use crate::util::mime::guess_mime;
 
@@ -17,6 +18,7 @@ pub async fn get(
     if abs.is_dir() {
         return Err(AppError::IsADirectory);
     }
+    state.permissions.check(&abs, Access::Read)?;
 
     let mime = guess_mime(&abs);
 
@@ -24,7 +26,8 @@ pub async fn get(
     if mime == "text/markdown"
         || abs.extension().map(|e| e == "md").unwrap_or(false)
     {
-        let content = tokio::fs::read_to_string(&abs).await?;
+        let content = String::from_utf8(read_content(&state, &abs).await?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
         let html = render_markdown(&content);
         return Ok(Response::builder()
             .status(StatusCode::OK)
@@ -35,7 +38,7 @@ pub async fn get(
 
     // 文本文件: 限读首 1MB
     if mime.starts_with("text/") || is_code_file(&abs) {
-        let data = tokio::fs::read(&abs).await?;
+        let data = read_content(&state, &abs).await?;
         let limited = if data.len() > 1024 * 1024 {
             &data[..1024 * 1024]
         } else {
@@ -58,19 +61,26 @@ pub async fn get(
     }
 
     // 其它类型（图片/视频/音频/PDF）: 直接透传，前端处理
-    let meta = tokio::fs::metadata(&abs).await?;
-    let size = meta.len();
+    let data = read_content(&state, &abs).await?;
 
     // 对于需要 Range 的大文件，重定向到 download 端点
     Ok(Response::builder()
         .status(StatusCode::OK)
         .header(CONTENT_TYPE, &mime)
-        .header(CONTENT_LENGTH, size)
+        .header(CONTENT_LENGTH, data.len())
         .header("X-Preview-Type", preview_type(&mime))
-        .body(Body::from(tokio::fs::read(&abs).await?))
+        .body(Body::from(data))
         .unwrap())
 }
 
+/// 读取整个文件，启用落盘加密时解密
+async fn read_content(state: &AppState, path: &std::path::Path) -> std::io::Result<Vec<u8>> {
+    match &state.vault {
+        Some(vault) => vault.read(path).await,
+        None => tokio::fs::read(path).await,
+    }
+}
+
 fn render_markdown(input: &str) -> String {
     let parser = pulldown_cmark::Parser::new(input);
     let mut html = String::with_capacity(input.len() * 2);
@@ -0,0 +1,99 @@
+use axum::extract::{Path, State};
+use axum::http::header::HOST;
+use axum::http::HeaderMap;
+use axum::Json;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::fs::permissions::Access;
+use crate::state::AppState;
+
+#[derive(Serialize)]
+pub struct QrResponse {
+    /// 二维码中编码的下载地址
+    pub url: String,
+    pub svg: String,
+}
+
+/// GET /api/qr/{*path} — 文件下载链接的二维码，方便手机扫码直接下载
+pub async fn get(
+    State(state): State<AppState>,
+    Path(rel): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<QrResponse>, AppError> {
+    let abs = state.path_safety.resolve(&rel)?;
+    if tokio::fs::metadata(&abs).await?.is_dir() {
+        return Err(AppError::IsADirectory);
+    }
+    state.permissions.check(&abs, Access::Read)?;
+
+    let encoded: Vec<String> = rel
+        .trim_matches('/')
+        .split('/')
+        .map(|seg| {
+            percent_encoding::utf8_percent_encode(seg, percent_encoding::NON_ALPHANUMERIC)
+                .to_string()
+        })
+        .collect();
+    let url = format!(
+        "{}/api/download/{}?download=1",
+        base_url(&state, &headers),
+        encoded.join("/")
+    );
+    let svg = QrCode::new(url.as_bytes())
+        .map_err(|e| AppError::BadRequest(format!("cannot encode qr code: {}", e)))?
+        .render::<svg::Color>()
+        .min_dimensions(240, 240)
+        .quiet_zone(true)
+        .build();
+    Ok(Json(QrResponse { url, svg }))
+}
+
+/// 手机要能访问到的地址：优先 --public-url，其次浏览器使用的 Host，
+/// 在本机用 localhost 打开时改用局域网 IP
+fn base_url(state: &AppState, headers: &HeaderMap) -> String {
+    if state.config.public_url.is_some() {
+        return state.config.base_url();
+    }
+    let host = headers
+        .get(HOST)
+        .and_then(|v| v.to_str().ok())
+        .filter(|h| !is_loopback_host(h));
+    match host {
+        Some(host) => {
+            let protocol = if state.config.tls_cert.is_some() { "https" } else { "http" };
+            format!("{}://{}", protocol, host)
+        }
+        None => state.config.base_url(),
+    }
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    // 去掉端口；IPv6 字面量带方括号
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    name.eq_ignore_ascii_case("localhost")
+        || name
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback() || ip.is_unspecified())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loopback_host() {
+        assert!(is_loopback_host("localhost:8080"));
+        assert!(is_loopback_host("127.0.0.1:8080"));
+        assert!(is_loopback_host("[::1]:8080"));
+        assert!(is_loopback_host("[::1]"));
+        assert!(!is_loopback_host("192.168.1.5:8080"));
+        assert!(!is_loopback_host("nas.local"));
+    }
+}
@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+
+use crate::signaling::{ClientMessage, ServerMessage};
+use crate::state::AppState;
+
+/// SDP 和 ICE 候选都很小，限制单条消息避免被当作中转通道
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+/// 定期 ping，及时发现断开的连接
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize)]
+pub struct SignalParams {
+    /// 显示给其他设备的名称
+    #[serde(default)]
+    pub name: String,
+}
+
+/// GET /api/signal?name= — WebRTC 信令 WebSocket
+pub async fn connect(
+    State(state): State<AppState>,
+    Query(params): Query<SignalParams>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.max_message_size(MAX_MESSAGE_SIZE)
+        .on_upgrade(move |socket| handle(state, socket, params.name))
+}
+
+async fn handle(state: AppState, mut socket: WebSocket, name: String) {
+    let Some((id, mut rx)) = state.signaling.join(&name) else {
+        let _ = socket
+            .send(Message::Close(Some(CloseFrame {
+                code: 1013,
+                reason: "too many peers".into(),
+            })))
+            .await;
+        return;
+    };
+    tracing::debug!(peer = %id, "signaling peer joined");
+
+    let (mut sink, mut stream) = socket.split();
+    let outgoing = async {
+        let mut ping = tokio::time::interval(PING_INTERVAL);
+        ping.tick().await;
+        loop {
+            let msg = tokio::select! {
+                text = rx.recv() => match text {
+                    Some(text) => Message::Text(text.into()),
+                    None => break,
+                },
+                _ = ping.tick() => Message::Ping(Default::default()),
+            };
+            if sink.send(msg).await.is_err() {
+                break;
+            }
+        }
+    };
+    let incoming = async {
+        while let Some(Ok(msg)) = stream.next().await {
+            let text = match msg {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(ClientMessage::Signal { to, data }) => {
+                    if !state.signaling.relay(&id, &to, data) {
+                        state.signaling.notify(&id, &ServerMessage::Error {
+                            message: "peer is offline".into(),
+                        });
+                    }
+                }
+                Err(e) => state.signaling.notify(&id, &ServerMessage::Error {
+                    message: format!("invalid message: {}", e),
+                }),
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = outgoing => {}
+        _ = incoming => {}
+    }
+    state.signaling.leave(&id);
+    tracing::debug!(peer = %id, "signaling peer left");
+}
@@ -1,51 +1,122 @@
 use axum::body::Body;
-use axum::extract::Path;
+use axum::extract::{Path, State};
 use axum::http::header::*;
-use axum::http::{Response, StatusCode};
+use axum::http::{HeaderMap, Response, StatusCode};
 use rust_embed::Embed;
 
+use crate::branding::Branding;
+use crate::state::AppState;
+
 #[derive(Embed)]
 #[folder = "static/"]
 struct StaticAssets;
 
 /// GET / — SPA 入口
-pub async fn index() -> Response<Body> {
-    serve_embedded("index.html")
+pub async fn index(State(state): State<AppState>) -> Response<Body> {
+    serve_page("index.html", &state.branding)
 }
 
-/// GET /static/{*path} — 静态资源
-pub async fn serve(Path(path): Path<String>) -> Response<Body> {
-    serve_embedded(&path)
+/// GET /usage — 磁盘占用页面
+pub async fn usage(State(state): State<AppState>) -> Response<Body> {
+    serve_page("usage.html", &state.branding)
 }
 
-fn serve_embedded(path: &str) -> Response<Body> {
-    match StaticAssets::get(path) {
-        Some(asset) => {
-            let mime = mime_guess::from_path(path)
-                .first_or_octet_stream()
-                .to_string();
-
-            Response::builder()
-                .status(StatusCode::OK)
-                .header(CONTENT_TYPE, mime)
-                .header(CACHE_CONTROL, "public, max-age=3600")
-                .body(Body::from(asset.data.to_vec()))
-                .unwrap()
-        }
-        None => {
-            // SPA fallback: 返回 index.html
-            if let Some(index) = StaticAssets::get("index.html") {
-                Response::builder()
-                    .status(StatusCode::OK)
-                    .header(CONTENT_TYPE, "text/html; charset=utf-8")
-                    .body(Body::from(index.data.to_vec()))
-                    .unwrap()
-            } else {
-                Response::builder()
-                    .status(StatusCode::NOT_FOUND)
-                    .body(Body::from("not found"))
-                    .unwrap()
-            }
+/// GET /devices — 已连接设备页面
+pub async fn devices(State(state): State<AppState>) -> Response<Body> {
+    serve_page("devices.html", &state.branding)
+}
+
+/// GET /branding/logo — --brand-logo 指定的图片
+pub async fn logo(State(state): State<AppState>) -> Response<Body> {
+    let Some(path) = state.branding.logo() else {
+        return not_found();
+    };
+    match tokio::fs::read(path).await {
+        Ok(data) => Response::builder()
+            .status(StatusCode::OK)
+            .header(
+                CONTENT_TYPE,
+                mime_guess::from_path(path).first_or_octet_stream().as_ref(),
+            )
+            .header(CACHE_CONTROL, "no-cache")
+            .body(Body::from(data))
+            .unwrap(),
+        Err(e) => {
+            tracing::warn!(error = %e, path = %path.display(), "cannot read brand logo");
+            not_found()
         }
     }
 }
+
+/// GET /assets/{*path} — 嵌入的 CSS、JS、图标和字体；/static/ 为旧路径，行为相同
+pub async fn serve(Path(path): Path<String>, headers: HeaderMap) -> Response<Body> {
+    serve_asset(&path, &headers)
+}
+
+/// GET /favicon.ico — 浏览器默认请求的图标，未在页面声明图标时也能显示
+pub async fn favicon(headers: HeaderMap) -> Response<Body> {
+    serve_asset("icons/favicon.ico", &headers)
+}
+
+/// GET /site.webmanifest — 添加到主屏幕时使用的名称和图标
+pub async fn manifest(headers: HeaderMap) -> Response<Body> {
+    serve_asset("site.webmanifest", &headers)
+}
+
+/// 带内容哈希 ETag，缓存过期后浏览器只需一次 304 校验
+fn serve_asset(path: &str, headers: &HeaderMap) -> Response<Body> {
+    let Some(asset) = StaticAssets::get(path) else {
+        // 资源缺失返回 404，而不是把 index.html 当成脚本或样式
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .header(CACHE_CONTROL, "no-store")
+            .body(Body::from("not found"))
+            .unwrap();
+    };
+
+    let etag = format!("\"{}\"", hex::encode(&asset.metadata.sha256_hash()[..16]));
+    let cache_control = "public, max-age=3600, must-revalidate";
+    if headers
+        .get(IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|t| t.trim() == etag))
+    {
+        return Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(ETAG, &etag)
+            .header(CACHE_CONTROL, cache_control)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(
+            CONTENT_TYPE,
+            mime_guess::from_path(path).first_or_octet_stream().as_ref(),
+        )
+        .header(ETAG, &etag)
+        .header(CACHE_CONTROL, cache_control)
+        .body(Body::from(asset.data.into_owned()))
+        .unwrap()
+}
+
+/// HTML 页面不缓存，升级后立即引用新的资源
+fn serve_page(path: &str, branding: &Branding) -> Response<Body> {
+    match StaticAssets::get(path) {
+        Some(page) => Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "text/html; charset=utf-8")
+            .header(CACHE_CONTROL, "no-cache")
+            .body(Body::from(branding.apply(&String::from_utf8_lossy(&page.data))))
+            .unwrap(),
+        None => not_found(),
+    }
+}
+
+fn not_found() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body(Body::from("not found"))
+        .unwrap()
+}
@@ -0,0 +1,67 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::extract::{Path, Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::{stream, Stream, StreamExt};
+use serde::Deserialize;
+use tokio::io::AsyncReadExt;
+
+use crate::error::AppError;
+use crate::fs::permissions::Access;
+use crate::preview::tail::{self, TailEvent};
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct TailParams {
+    /// 初始输出的末尾行数
+    #[serde(default = "default_lines")]
+    pub lines: usize,
+}
+
+fn default_lines() -> usize {
+    200
+}
+
+/// GET /api/tail/{*path}?lines=200 — 通过 SSE 持续推送文件新增的行
+///
+/// 事件：`lines`（JSON 字符串数组）、`truncated`（文件被截断，前端应清空）
+pub async fn get(
+    State(state): State<AppState>,
+    Path(rel): Path<String>,
+    Query(params): Query<TailParams>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let abs = state.path_safety.resolve(&rel)?;
+    if abs.is_dir() {
+        return Err(AppError::IsADirectory);
+    }
+    state.permissions.check(&abs, Access::Read)?;
+
+    // 只跟随文本文件
+    let mut head = Vec::with_capacity(8192);
+    tokio::fs::File::open(&abs)
+        .await?
+        .take(8192)
+        .read_to_end(&mut head)
+        .await?;
+    if content_inspector::inspect(&head).is_binary() {
+        return Err(AppError::BadRequest("not a text file".into()));
+    }
+
+    let (initial, offset) = tail::last_lines(&abs, params.lines.min(5000)).await?;
+
+    let first = stream::once(async move { lines_event(initial) });
+    let rest = tail::follow(abs, offset, Duration::from_millis(500)).map(|ev| match ev {
+        TailEvent::Lines(lines) => lines_event(lines),
+        TailEvent::Truncated => Ok(Event::default().event("truncated").data("")),
+    });
+
+    Ok(Sse::new(first.chain(rest)).keep_alive(KeepAlive::default()))
+}
+
+fn lines_event(lines: Vec<String>) -> Result<Event, Infallible> {
+    Ok(Event::default()
+        .event("lines")
+        .json_data(lines)
+        .unwrap_or_else(|_| Event::default().event("lines").data("[]")))
+}
@@ -0,0 +1,123 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Serialize;
+
+use crate::db::trash::TrashItem;
+use crate::error::AppError;
+use crate::fs::permissions::Access;
+use crate::routes::files::ensure_vacant;
+use crate::state::AppState;
+
+/// GET /api/trash — 回收站中的条目，最近删除的在前；只列出在原位置有删除权限的条目
+pub async fn list(State(state): State<AppState>) -> Result<Json<Vec<TrashItem>>, AppError> {
+    let items = state.db.list_trash().await?;
+    Ok(Json(items.into_iter().filter(|item| permitted(&state, item).is_ok()).collect()))
+}
+
+fn permitted(state: &AppState, item: &TrashItem) -> Result<(), AppError> {
+    state.permissions.check(&state.root.join(&item.path), Access::Delete)
+}
+
+async fn find(state: &AppState, id: i64) -> Result<TrashItem, AppError> {
+    let item = state
+        .db
+        .get_trash(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(id.to_string()))?;
+    permitted(state, &item)?;
+    Ok(item)
+}
+
+#[derive(Serialize)]
+pub struct RestoreResponse {
+    /// 恢复到的位置（相对 root）
+    pub path: String,
+}
+
+/// POST /api/trash/{id}/restore — 放回删除前的位置，上级目录不在了会重建；原位置已有同名项时返回 409
+pub async fn restore(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<RestoreResponse>, AppError> {
+    let item = find(&state, id).await?;
+    restore_item(&state, &item).await?;
+    Ok(Json(RestoreResponse { path: item.path }))
+}
+
+async fn restore_item(state: &AppState, item: &TrashItem) -> Result<(), AppError> {
+    let to = state.root.join(&item.path);
+    state.path_safety.check_within(&to)?;
+    state.permissions.check(&to, Access::Write)?;
+    ensure_vacant(state, &to).await?;
+    if let Some(dir) = to.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    state.trash.restore(item, &to).await
+}
+
+#[derive(Serialize)]
+pub struct UndoResponse {
+    /// 放回原位置的条目
+    pub restored: Vec<String>,
+    /// 原位置已被占用等原因没能放回的条目，仍留在回收站中 (未启用回收站时随后彻底删除)
+    pub failed: Vec<String>,
+}
+
+/// POST /api/undo/{token} — 撤销一次删除，把这次删除的条目都放回原位置；令牌过期或已用过时返回 404
+pub async fn undo(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Json<UndoResponse>, AppError> {
+    let ids = state
+        .trash
+        .take_undo(&token)
+        .ok_or_else(|| AppError::NotFound(token))?;
+    let mut restored = Vec::new();
+    let mut failed = Vec::new();
+    for id in ids {
+        // 已经从回收站中恢复或清除
+        let Some(item) = state.db.get_trash(id).await? else {
+            continue;
+        };
+        let result = match permitted(&state, &item) {
+            Ok(()) => restore_item(&state, &item).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => restored.push(item.path),
+            Err(e) => {
+                tracing::debug!(path = %item.path, error = %e, "cannot undo delete");
+                failed.push(item.path);
+            }
+        }
+    }
+    Ok(Json(UndoResponse { restored, failed }))
+}
+
+/// DELETE /api/trash/{id} — 彻底删除一项
+pub async fn purge(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    find(&state, id).await?;
+    state.trash.purge(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize)]
+pub struct EmptyResponse {
+    pub purged: usize,
+}
+
+/// DELETE /api/trash — 清空回收站中当前用户能看到的条目
+pub async fn empty(State(state): State<AppState>) -> Result<Json<EmptyResponse>, AppError> {
+    let mut purged = 0;
+    for item in state.db.list_trash().await? {
+        if permitted(&state, &item).is_ok() {
+            state.trash.purge(item.id).await?;
+            purged += 1;
+        }
+    }
+    Ok(Json(EmptyResponse { purged }))
+}
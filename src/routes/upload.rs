@@ -1,22 +1,34 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
 use axum::body::Body;
-use axum::extract::{Path, State};
+use axum::extract::{ConnectInfo, FromRequest, Multipart, Path, Query, Request, State};
 use axum::http::{HeaderMap, Response, StatusCode};
+use bytes::Bytes;
 use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tracing::field::Empty;
+
+use crate::db::{self, TransferKind, TransferRecord};
 use crate::error::AppError;
+use crate::fs::operations::{self, ConflictPolicy};
+use crate::fs::path_safety::relative_path;
+use crate::fs::permissions::Access;
+use crate::hooks::HookEvent;
+use crate::middleware::auth;
+use crate::notifier::UploadNotice;
 use crate::state::AppState;
 use crate::upload::session::UploadSession;
+use crate::upload::untar::{self, Extracted};
 use crate::upload::writer::ChunkWriter;
+use crate::util::{encoding, hash};
 
 const TUS_VERSION: &str = "1.0.0";
 const TUS_EXTENSIONS: &str = "creation,creation-with-upload,termination,expiration";
 
 /// OPTIONS /api/upload — tus 能力发现
 pub async fn options(State(state): State<AppState>) -> Response<Body> {
-    let max_size = if state.config.max_upload_size > 0 {
-        state.config.max_upload_size.to_string()
-    } else {
-        "0".to_string()
-    };
+    let max_size = state.live.max_upload_size().to_string();
 
     Response::builder()
         .status(StatusCode::NO_CONTENT)
@@ -31,6 +43,7 @@ pub async fn options(State(state): State<AppState>) -> Response<Body> {
 /// POST /api/upload — 创建上传会话
 pub async fn create(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     headers: HeaderMap,
 ) -> Result<Response<Body>, AppError> {
     let upload_length: u64 = headers
@@ -40,7 +53,8 @@ pub async fn create(
         .ok_or_else(|| AppError::BadRequest("missing Upload-Length".into()))?;
 
     // 检查大小限制
-    if state.config.max_upload_size > 0 && upload_length > state.config.max_upload_size {
+    let max_size = state.live.max_upload_size();
+    if max_size > 0 && upload_length > max_size {
         return Err(AppError::PayloadTooLarge);
     }
 
@@ -50,19 +64,38 @@ pub async fn create(
         .get("filename")
         .cloned()
         .unwrap_or_else(|| "unnamed".to_string());
-    let filename = sanitize_filename::sanitize(&filename);
-    let relative_path = metadata.get("relativePath").cloned();
+    let filename = state.filenames.sanitize(&filename);
+    // 落盘时按清洗后的相对路径拼出目录
+    let relative_path = metadata.get("relativePath").map(|rel| state.filenames.relative(rel));
     let target_dir_str = metadata
         .get("targetDir")
         .cloned()
         .unwrap_or_default();
     let mime_hint = metadata.get("filetype").cloned();
+    // X-File-Mtime 优先，其次是 tus 元数据中的 lastModified
+    let modified = file_mtime(&headers).or_else(|| metadata.get("lastModified").and_then(|v| v.parse().ok()));
+    // 可选的整文件 SHA-256，finalize 时校验
+    let expected_checksum = match metadata.get("checksum") {
+        Some(raw) => Some(
+            hash::parse_sha256(raw)
+                .ok_or_else(|| AppError::BadRequest("unsupported checksum, expected sha256".into()))?,
+        ),
+        None => None,
+    };
 
-    let target_dir = if target_dir_str.is_empty() {
+    let mut target_dir = if target_dir_str.is_empty() {
         state.root.clone()
     } else {
         state.path_safety.resolve(&target_dir_str)?
     };
+    // 按日期或客户端自动分目录，目录在 finalize 时创建
+    if let Some(sub) = state.config.upload_subdir.path(
+        time::OffsetDateTime::now_utc(),
+        Some(peer.ip()),
+        auth::current_user().as_deref(),
+    ) {
+        target_dir.push(sub);
+    }
 
     let file_id = uuid::Uuid::new_v4().to_string().replace('-', "");
 
@@ -80,13 +113,35 @@ pub async fn create(
         uploaded: 0,
         created_at: now,
         last_active: now,
-        expected_checksum: metadata.get("checksum").cloned(),
+        expected_checksum,
         mime_hint,
+        client_ip: Some(peer.ip()),
+        modified,
     };
 
+    // 权限按 relativePath 展开后的最终位置判断，子目录可能有单独的规则
+    let requested = session.final_path();
+    state.permissions.check(&requested, Access::Write)?;
+
+    // reject 策略下提前拒绝，避免传完才发现冲突；finalize 时仍会再原子地检查一次
+    if state.config.on_conflict == ConflictPolicy::Reject && requested.exists() {
+        return Err(AppError::AlreadyExists(session.filename));
+    }
+
+    // 另一个客户端正在上传同名文件时直接拒绝，而不是等到落盘时再改名或覆盖
     let tmp_dir = state.upload_manager.tmp_dir();
-    session.persist_meta(tmp_dir).await?;
-    state.upload_manager.create(session);
+    if let Err(other) = state.upload_manager.create(session.clone()).await {
+        tracing::info!(filename = %session.filename, other = %other.file_id, "upload target already in progress");
+        return Err(AppError::UploadInProgress {
+            uploaded: other.uploaded,
+            total_size: other.total_size,
+            last_active: other.last_active,
+        });
+    }
+    if let Err(e) = session.persist_meta(tmp_dir).await {
+        state.upload_manager.remove(&file_id);
+        return Err(e.into());
+    }
 
     let location = format!("/api/upload/{}", file_id);
 
@@ -95,6 +150,8 @@ pub async fn create(
         .header("Location", &location)
         .header("Tus-Resumable", TUS_VERSION)
         .header("Upload-Offset", "0")
+        // 分块可以用 zstd 压缩后发送（Content-Encoding: zstd），Upload-Offset 仍按原始字节计（RFC 7694）
+        .header("Accept-Encoding", encoding::ZSTD)
         .body(Body::empty())
         .unwrap())
 }
@@ -122,6 +179,13 @@ pub async fn head(
 }
 
 /// PATCH /api/upload/{file_id} — 上传分块（核心：流式写入）
+///
+/// span 上分别记录等待网络数据和写盘的耗时，便于判断慢在哪一端
+#[tracing::instrument(
+    name = "upload.chunk",
+    skip_all,
+    fields(file_id = %file_id, offset = Empty, bytes = Empty, network_ms = Empty, disk_ms = Empty)
+)]
 pub async fn patch(
     State(state): State<AppState>,
     Path(file_id): Path<String>,
@@ -133,13 +197,26 @@ pub async fn patch(
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.parse().ok())
         .ok_or_else(|| AppError::BadRequest("missing Upload-Offset".into()))?;
+    let span = tracing::Span::current();
+    span.record("offset", client_offset);
 
     let arc = state
         .upload_manager
         .get(&file_id)
         .ok_or_else(|| AppError::NotFound(file_id.clone()))?;
 
-    // 校验 offset
+    // 并行的分块请求或超时后的重试不能同时 seek/write/rename 同一个 .part，
+    // 后到的直接返回 409，客户端 HEAD 后按服务端 offset 重试
+    let _session_guard = state
+        .upload_manager
+        .try_lock_session(&file_id)
+        .ok_or(AppError::UploadBusy)?;
+    // 拿到锁之前会话可能已被其他请求完成或取消
+    if state.upload_manager.get(&file_id).is_none() {
+        return Err(AppError::NotFound(file_id));
+    }
+
+    // 校验 offset 和长度都不读请求体，发送 Expect: 100-continue 的客户端被拒绝时不会上传数据
     {
         let session = arc.read().await;
         if session.uploaded != client_offset {
@@ -148,6 +225,9 @@ pub async fn patch(
                 client: client_offset,
             });
         }
+        if encoding::identity_length(&headers).is_some_and(|len| client_offset + len > session.total_size) {
+            return Err(AppError::BadRequest("upload exceeds Upload-Length".into()));
+        }
     }
 
     let tmp_dir = state.upload_manager.tmp_dir().clone();
@@ -157,15 +237,27 @@ pub async fn patch(
     };
 
     // 流式写入 — 关键修复点：不用 to_bytes()！
+    let total_size = arc.read().await.total_size;
     let mut writer = ChunkWriter::open(&part_path, client_offset).await?;
-    let mut stream = request.into_body().into_data_stream();
+    let mut stream = encoding::decode_body(&headers, request.into_body())?;
     let mut written: u64 = 0;
     let persist_interval: u64 = 16 * 1024 * 1024; // 每 16MB 持久化一次
+    let mut network = Duration::ZERO;
+    let mut disk = Duration::ZERO;
 
-    while let Some(frame) = stream.next().await {
+    loop {
+        let waiting = Instant::now();
+        let Some(frame) = stream.next().await else {
+            break;
+        };
+        network += waiting.elapsed();
         let bytes = frame.map_err(|e| {
             AppError::Internal(anyhow::anyhow!("body read error: {}", e))
         })?;
+        if client_offset + written + bytes.len() as u64 > total_size {
+            return Err(AppError::BadRequest("upload exceeds Upload-Length".into()));
+        }
+        let writing = Instant::now();
         writer.write_all(&bytes).await?;
         written += bytes.len() as u64;
 
@@ -177,11 +269,17 @@ pub async fn patch(
             session.last_active = now_secs();
             session.persist_meta(&tmp_dir).await?;
         }
+        disk += writing.elapsed();
     }
 
     // 最终 flush
+    let writing = Instant::now();
     writer.flush_data().await?;
+    disk += writing.elapsed();
     let new_offset = client_offset + written;
+    span.record("bytes", written);
+    span.record("network_ms", network.as_millis() as u64);
+    span.record("disk_ms", disk.as_millis() as u64);
 
     {
         let mut session = arc.write().await;
@@ -196,16 +294,22 @@ pub async fn patch(
         session.is_complete()
     };
 
+    let mut builder = Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header("Upload-Offset", new_offset.to_string())
+        .header("Tus-Resumable", TUS_VERSION);
+
     if completed {
-        finalize_upload(&state, &file_id).await?;
+        let final_path = finalize_upload(&state, &file_id).await?;
+        // 冲突改名后文件名可能与上传时不同
+        builder = builder.header(
+            "X-Final-Path",
+            percent_encoding::utf8_percent_encode(&final_path, percent_encoding::NON_ALPHANUMERIC)
+                .to_string(),
+        );
     }
 
-    Ok(Response::builder()
-        .status(StatusCode::NO_CONTENT)
-        .header("Upload-Offset", new_offset.to_string())
-        .header("Tus-Resumable", TUS_VERSION)
-        .body(Body::empty())
-        .unwrap())
+    Ok(builder.body(Body::empty()).unwrap())
 }
 
 /// DELETE /api/upload/{file_id} — 取消上传
@@ -213,81 +317,533 @@ pub async fn cancel(
     State(state): State<AppState>,
     Path(file_id): Path<String>,
 ) -> Result<StatusCode, AppError> {
-    let arc = state
-        .upload_manager
-        .get(&file_id)
-        .ok_or_else(|| AppError::NotFound(file_id.clone()))?;
+    if !state.upload_manager.cancel(&file_id).await {
+        return Err(AppError::NotFound(file_id));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
 
-    let tmp_dir = state.upload_manager.tmp_dir();
-    let session = arc.read().await;
-    let _ = tokio::fs::remove_file(session.part_path(tmp_dir)).await;
-    let _ = tokio::fs::remove_file(session.meta_path(tmp_dir)).await;
-    drop(session);
+#[derive(Deserialize)]
+pub struct FormParams {
+    /// 目标目录（相对 root）
+    #[serde(default)]
+    pub path: String,
+    /// 请求体不是 multipart，而是边接收边解开的归档
+    pub extract: Option<ExtractFormat>,
+}
 
-    state.upload_manager.remove(&file_id);
+/// `?extract=` 支持的归档格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ExtractFormat {
+    #[serde(rename = "tar")]
+    Tar,
+    #[serde(rename = "tar.gz", alias = "tgz")]
+    TarGz,
+}
 
-    Ok(StatusCode::NO_CONTENT)
+/// 表单上传中单个文件的结果，每完成一个输出一行
+#[derive(Serialize)]
+struct FormFileResult {
+    name: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
 }
 
-/// 上传完成后的 finalize：校验 + 原子 rename
-async fn finalize_upload(state: &AppState, file_id: &str) -> Result<(), AppError> {
-    let arc = state
-        .upload_manager
-        .get(file_id)
-        .ok_or_else(|| AppError::NotFound(file_id.to_string()))?;
+/// POST /api/upload/form?path=xxx — multipart 多文件上传，响应为 NDJSON
+///
+/// 每个文件落盘后立即输出一行结果，单个文件失败不影响后续文件。
+/// 带 `extract=tar` 时请求体是 tar 流，边接收边解到目标目录，结果格式相同
+pub async fn form(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Query(params): Query<FormParams>,
+    headers: HeaderMap,
+    request: Request,
+) -> Result<Response<Body>, AppError> {
+    let mut target_dir = if params.path.is_empty() {
+        state.root.clone()
+    } else {
+        state.path_safety.resolve(&params.path)?
+    };
+    if !target_dir.is_dir() {
+        return Err(AppError::BadRequest("target is not a directory".into()));
+    }
+    if let Some(sub) = state.config.upload_subdir.path(
+        time::OffsetDateTime::now_utc(),
+        Some(peer.ip()),
+        auth::current_user().as_deref(),
+    ) {
+        target_dir.push(sub);
+    }
+    // 整个目录都不可写时在读请求体之前拒绝，逐个文件的检查仍在接收时进行
+    state.permissions.check_subtree(&target_dir, Access::Write)?;
 
-    let session = arc.read().await;
-    let tmp_dir = state.upload_manager.tmp_dir();
-    let part_path = session.part_path(tmp_dir);
+    // 请求级的 X-File-Mtime 作用于没有单独指定的文件
+    let default_mtime = file_mtime(&headers);
+    let (tx, rx) = tokio::sync::mpsc::channel::<Bytes>(16);
+    let user = auth::current_user();
+    match params.extract {
+        Some(format) => {
+            let body = encoding::decode_body(&headers, request.into_body())?;
+            tokio::spawn(auth::scope(user, async move {
+                receive_tar(state, body, format, target_dir, peer, tx).await;
+            }));
+        }
+        None => {
+            let multipart = Multipart::from_request(request, &state)
+                .await
+                .map_err(|e| AppError::BadRequest(e.body_text()))?;
+            tokio::spawn(auth::scope(user, async move {
+                receive_form(state, multipart, target_dir, default_mtime, peer, tx).await;
+            }));
+        }
+    }
 
-    // 计算最终路径
-    let final_dir = if let Some(ref rel) = session.relative_path {
-        if rel.is_empty() {
-            session.target_dir.clone()
-        } else {
-            // 取 relative_path 的父目录部分
-            let rel_parent = std::path::Path::new(rel)
-                .parent()
-                .filter(|p| !p.as_os_str().is_empty());
-            if let Some(parent) = rel_parent {
-                session.target_dir.join(parent)
-            } else {
-                session.target_dir.clone()
+    let body = Body::from_stream(
+        tokio_stream::wrappers::ReceiverStream::new(rx).map(Ok::<_, std::convert::Infallible>),
+    );
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/x-ndjson")
+        .header("Cache-Control", "no-store")
+        .body(body)
+        .unwrap())
+}
+
+async fn receive_form(
+    state: AppState,
+    mut multipart: Multipart,
+    target_dir: std::path::PathBuf,
+    default_mtime: Option<u64>,
+    peer: SocketAddr,
+    tx: tokio::sync::mpsc::Sender<Bytes>,
+) {
+    // 前一个 relative_path 文本字段，作用于紧随其后的文件
+    let mut pending_relative: Option<String> = None;
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => {
+                // 请求体已损坏，无法继续解析后续字段
+                let result = FormFileResult {
+                    name: String::new(),
+                    ok: false,
+                    path: None,
+                    size: None,
+                    code: Some("bad_request"),
+                    message: Some(e.body_text()),
+                };
+                let _ = tx.send(ndjson_line(&result)).await;
+                break;
+            }
+        };
+        // 没有文件名的普通表单字段：只认 relative_path，其余忽略
+        let Some(name) = field.file_name().map(str::to_string) else {
+            if field.name() == Some("relative_path") {
+                pending_relative = field.text().await.ok();
+            }
+            continue;
+        };
+        // 分段自身的 X-Relative-Path 头优先于前置的 relative_path 字段
+        let relative = field
+            .headers()
+            .get("x-relative-path")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| {
+                percent_encoding::percent_decode_str(v)
+                    .decode_utf8_lossy()
+                    .to_string()
+            })
+            .or(pending_relative.take());
+        let file_dir = match relative.as_deref() {
+            Some(rel) => target_dir.join(state.filenames.relative_dir(rel)),
+            None => target_dir.clone(),
+        };
+        let modified = file_mtime(field.headers()).or(default_mtime);
+
+        let result = match save_form_file(&state, field, &name, &file_dir, modified, peer).await {
+            Ok((path, size)) => FormFileResult {
+                name,
+                ok: true,
+                path: Some(path),
+                size: Some(size),
+                code: None,
+                message: None,
+            },
+            Err(e) => {
+                tracing::warn!(file = %name, error = %e, "form upload failed");
+                FormFileResult {
+                    name,
+                    ok: false,
+                    path: None,
+                    size: None,
+                    code: Some(e.status_and_code().1),
+                    message: Some(e.to_string()),
+                }
             }
+        };
+        if tx.send(ndjson_line(&result)).await.is_err() {
+            // 客户端已断开
+            break;
         }
-    } else {
-        session.target_dir.clone()
+    }
+}
+
+/// 在 blocking 线程中顺序解开 tar 流，每解出一个文件就落盘并输出一行结果
+///
+/// 归档内的目录结构在 target_dir 下重建；链接、设备文件和含 `..` 的条目不解出，输出失败行
+async fn receive_tar(
+    state: AppState,
+    body: futures_util::stream::BoxStream<'static, std::io::Result<Bytes>>,
+    format: ExtractFormat,
+    target_dir: std::path::PathBuf,
+    peer: SocketAddr,
+    tx: tokio::sync::mpsc::Sender<Bytes>,
+) {
+    let (items_tx, mut items) = tokio::sync::mpsc::channel(2);
+    let reader = tokio_util::io::SyncIoBridge::new(tokio_util::io::StreamReader::new(body));
+    let tmp_dir = state.upload_manager.tmp_dir().clone();
+    let max_size = state.live.max_upload_size();
+    let unpacking = tokio::task::spawn_blocking(move || {
+        untar::extract(reader, format == ExtractFormat::TarGz, &tmp_dir, max_size, &items_tx)
+    });
+
+    while let Some(item) = items.recv().await {
+        let result = match item {
+            Extracted::Dir { name } => {
+                let (parent, dirname) = split_entry(&state, &name);
+                match create_extracted_dir(&state, &target_dir.join(parent).join(dirname)).await {
+                    Ok(()) => continue,
+                    Err(e) => failed_line(name, &e),
+                }
+            }
+            Extracted::Skipped { name, reason } => {
+                failed_line(name, &AppError::BadRequest(reason))
+            }
+            Extracted::File { name, tmp, size, modified, mode } => {
+                match place_extracted(&state, &target_dir, &name, &tmp, modified, mode).await {
+                    Ok(final_path) => {
+                        let rel_path = record_upload(&state, &final_path, size, peer);
+                        FormFileResult {
+                            name,
+                            ok: true,
+                            path: Some(rel_path),
+                            size: Some(size),
+                            code: None,
+                            message: None,
+                        }
+                    }
+                    Err(e) => {
+                        let _ = tokio::fs::remove_file(&tmp).await;
+                        failed_line(name, &e)
+                    }
+                }
+            }
+        };
+        if tx.send(ndjson_line(&result)).await.is_err() {
+            // 客户端已断开；丢弃 items 后解包线程随之结束
+            break;
+        }
+    }
+    drop(items);
+
+    // 归档损坏或请求体中断：已解出的文件保留，最后输出一行错误
+    let error = match unpacking.await {
+        Ok(Ok(())) => return,
+        Ok(Err(e)) => e,
+        Err(e) => std::io::Error::other(e),
     };
+    // tar 的错误信息可能夹带损坏头部的原始字节，只记在日志里
+    tracing::warn!(target = %target_dir.display(), error = %error, "tar upload aborted");
+    let e = AppError::BadRequest("invalid or truncated tar stream".into());
+    let _ = tx.send(ndjson_line(&failed_line(String::new(), &e))).await;
+}
 
-    tokio::fs::create_dir_all(&final_dir).await?;
-
-    let mut final_path = final_dir.join(&session.filename);
-
-    // 文件名冲突处理
-    if final_path.exists() {
-        let stem = final_path
-            .file_stem()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-        let ext = final_path
-            .extension()
-            .map(|e| format!(".{}", e.to_string_lossy()))
-            .unwrap_or_default();
-        for i in 1..1000 {
-            let new_name = format!("{} ({}){}", stem, i, ext);
-            let candidate = final_dir.join(&new_name);
-            if !candidate.exists() {
-                final_path = candidate;
-                break;
+fn failed_line(name: String, e: &AppError) -> FormFileResult {
+    tracing::warn!(file = %name, error = %e, "extracted entry rejected");
+    FormFileResult {
+        name,
+        ok: false,
+        path: None,
+        size: None,
+        code: Some(e.status_and_code().1),
+        message: Some(e.to_string()),
+    }
+}
+
+/// 归档内路径拆成清洗后的父目录和名称，与表单上传的 relative_path 规则一致
+fn split_entry(state: &AppState, name: &str) -> (std::path::PathBuf, String) {
+    let last = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    (state.filenames.relative_dir(name), state.filenames.sanitize(last))
+}
+
+async fn create_extracted_dir(state: &AppState, dir: &std::path::Path) -> Result<(), AppError> {
+    state.path_safety.check_within(dir)?;
+    state.permissions.check(dir, Access::Write)?;
+    tokio::fs::create_dir_all(dir).await?;
+    Ok(())
+}
+
+/// 按冲突策略把解出的文件放到归档中的位置，并恢复修改时间和权限位
+async fn place_extracted(
+    state: &AppState,
+    target_dir: &std::path::Path,
+    name: &str,
+    tmp: &std::path::Path,
+    modified: Option<u64>,
+    mode: Option<u32>,
+) -> Result<std::path::PathBuf, AppError> {
+    let (parent, filename) = split_entry(state, name);
+    if filename.is_empty() {
+        return Err(AppError::BadRequest("invalid filename".into()));
+    }
+    let file_dir = target_dir.join(parent);
+    state.permissions.check(&file_dir.join(&filename), Access::Write)?;
+    let final_path = place_file(state, tmp, &file_dir, &filename).await?;
+
+    if let Some(secs) = modified {
+        if let Err(e) = operations::set_modified(&final_path, secs).await {
+            tracing::warn!(error = %e, path = %final_path.display(), "cannot set modified time");
+        }
+    }
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(&final_path, std::fs::Permissions::from_mode(mode)).await?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    Ok(final_path)
+}
+
+/// 把一个表单文件写入临时区，再按冲突策略落盘，返回相对路径和大小
+async fn save_form_file(
+    state: &AppState,
+    field: axum::extract::multipart::Field<'_>,
+    name: &str,
+    target_dir: &std::path::Path,
+    modified: Option<u64>,
+    peer: SocketAddr,
+) -> Result<(String, u64), AppError> {
+    let filename = state.filenames.sanitize(name);
+    if filename.is_empty() {
+        return Err(AppError::BadRequest("invalid filename".into()));
+    }
+    state.permissions.check(&target_dir.join(&filename), Access::Write)?;
+
+    let chunks = field.map(|chunk| chunk.map_err(|e| AppError::BadRequest(e.body_text())));
+    let (rel_path, size) = save_stream(state, chunks, target_dir, &filename, modified, peer).await?;
+    tracing::info!(path = %rel_path, size, "form upload saved");
+    Ok((rel_path, size))
+}
+
+/// PUT /api/put/{*path} — 把请求体原样保存为文件，长度可以事先未知
+///
+/// 适合管道：`some-command | curl -T - http://host:8080/api/put/logs/out.txt`。
+/// 分块传输时边接收边检查 --max-upload-size，落盘规则与表单上传相同
+pub async fn put(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    request: Request,
+) -> Result<Response<Body>, AppError> {
+    let (parent, filename) = split_entry(&state, &path);
+    if filename.is_empty() {
+        return Err(AppError::BadRequest("invalid filename".into()));
+    }
+    let mut file_dir = state.root.join(parent);
+    if let Some(sub) = state.config.upload_subdir.path(
+        time::OffsetDateTime::now_utc(),
+        Some(peer.ip()),
+        auth::current_user().as_deref(),
+    ) {
+        file_dir.push(sub);
+    }
+    state.path_safety.check_within(&file_dir)?;
+    state.permissions.check(&file_dir.join(&filename), Access::Write)?;
+    // 带 Content-Length 时在读请求体之前拒绝
+    let max_size = state.live.max_upload_size();
+    if max_size > 0 && encoding::identity_length(&headers).is_some_and(|len| len > max_size) {
+        return Err(AppError::PayloadTooLarge);
+    }
+    if state.config.on_conflict == ConflictPolicy::Reject && file_dir.join(&filename).exists() {
+        return Err(AppError::AlreadyExists(filename));
+    }
+
+    let modified = file_mtime(&headers);
+    let chunks = encoding::decode_body(&headers, request.into_body())?
+        .map(|chunk| chunk.map_err(|e| AppError::Internal(anyhow::anyhow!("body read error: {}", e))));
+    let (rel_path, size) = save_stream(&state, chunks, &file_dir, &filename, modified, peer).await?;
+    tracing::info!(path = %rel_path, size, "put upload saved");
+
+    let result = FormFileResult {
+        name: filename,
+        ok: true,
+        path: Some(rel_path.clone()),
+        size: Some(size),
+        code: None,
+        message: None,
+    };
+    Ok(Response::builder()
+        .status(StatusCode::CREATED)
+        .header("Content-Type", "application/json")
+        .header(
+            "X-Final-Path",
+            percent_encoding::utf8_percent_encode(&rel_path, percent_encoding::NON_ALPHANUMERIC).to_string(),
+        )
+        .body(Body::from(ndjson_line(&result)))
+        .unwrap())
+}
+
+/// 把字节流写入临时区，超过 --max-upload-size 时中止，再按冲突策略落盘，返回相对路径和大小
+async fn save_stream(
+    state: &AppState,
+    mut chunks: impl futures_util::Stream<Item = Result<Bytes, AppError>> + Unpin,
+    target_dir: &std::path::Path,
+    filename: &str,
+    modified: Option<u64>,
+    peer: SocketAddr,
+) -> Result<(String, u64), AppError> {
+    let tmp_path = state
+        .upload_manager
+        .tmp_dir()
+        .join(format!("form-{}.part", uuid::Uuid::new_v4().simple()));
+    let written = async {
+        let mut writer = ChunkWriter::open(&tmp_path, 0).await?;
+        let max_size = state.live.max_upload_size();
+        let mut size: u64 = 0;
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            size += chunk.len() as u64;
+            if max_size > 0 && size > max_size {
+                return Err(AppError::PayloadTooLarge);
             }
+            writer.write_all(&chunk).await?;
         }
+        writer.flush_data().await?;
+        Ok(size)
     }
+    .await;
+
+    let placed = match written {
+        Ok(size) => place_file(state, &tmp_path, target_dir, filename)
+            .await
+            .map(|path| (path, size)),
+        Err(e) => Err(e),
+    };
+    let (final_path, size) = match placed {
+        Ok(v) => v,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(e);
+        }
+    };
+    if let Some(secs) = modified {
+        if let Err(e) = operations::set_modified(&final_path, secs).await {
+            tracing::warn!(error = %e, path = %final_path.display(), "cannot set modified time");
+        }
+    }
+
+    Ok((record_upload(state, &final_path, size, peer), size))
+}
+
+/// 落盘后的统计、传输记录和通知，返回相对 root 的路径
+pub(crate) fn record_upload(
+    state: &AppState,
+    final_path: &std::path::Path,
+    size: u64,
+    peer: SocketAddr,
+) -> String {
+    let rel_path = relative_path(final_path, &state.root);
+    state.metrics.upload_completed(size);
+    state.db.record_transfer(TransferRecord {
+        kind: TransferKind::Upload,
+        path: rel_path.clone(),
+        size,
+        client_ip: Some(peer.ip()),
+        user: auth::current_user(),
+        at: db::now_secs(),
+    });
+    announce_upload(state, final_path, &rel_path, size, Some(peer.ip()));
+    rel_path
+}
+
+/// 上传完成通知和 upload_complete 钩子
+fn announce_upload(
+    state: &AppState,
+    final_path: &std::path::Path,
+    rel_path: &str,
+    size: u64,
+    client_ip: Option<IpAddr>,
+) {
+    let filename = final_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    state.hooks.fire(
+        HookEvent::UploadComplete,
+        serde_json::json!({
+            "path": rel_path,
+            "filename": filename,
+            "size": size,
+            "client_ip": client_ip,
+            "user": auth::current_user(),
+        }),
+    );
+    state.notifier.upload_completed(UploadNotice {
+        filename,
+        path: rel_path.to_string(),
+        size,
+        uploader: client_ip,
+    });
+}
+
+fn ndjson_line(result: &FormFileResult) -> Bytes {
+    let mut line = serde_json::to_vec(result).unwrap_or_default();
+    line.push(b'\n');
+    Bytes::from(line)
+}
+
+/// 上传完成后的 finalize：校验 + 原子 rename，返回最终文件相对 root 的路径
+#[tracing::instrument(name = "upload.finalize", skip(state))]
+async fn finalize_upload(state: &AppState, file_id: &str) -> Result<String, AppError> {
+    let arc = state
+        .upload_manager
+        .get(file_id)
+        .ok_or_else(|| AppError::NotFound(file_id.to_string()))?;
+
+    let tmp_dir = state.upload_manager.tmp_dir();
+    verify_part(state, &arc, tmp_dir).await?;
 
+    let session = arc.read().await;
+    let part_path = session.part_path(tmp_dir);
+    let final_dir = session.final_dir();
+    let filename = session.filename.clone();
+    let total_size = session.total_size;
+    let client_ip = session.client_ip;
+    let verified_checksum = session.expected_checksum.clone();
+    let modified = session.modified;
     drop(session);
 
-    // 原子 rename
-    tokio::fs::rename(&part_path, &final_path).await?;
+    let final_path = match place_file(state, &part_path, &final_dir, &filename).await {
+        Ok(path) => path,
+        Err(e @ (AppError::AlreadyExists(_) | AppError::PluginRejected { .. })) => {
+            // reject 策略或插件拒绝：已传完的数据没有用处，直接丢弃会话
+            state.upload_manager.cancel(file_id).await;
+            return Err(e);
+        }
+        Err(e) => return Err(e),
+    };
 
     // 清理 meta
     if let Some(arc) = state.upload_manager.get(file_id) {
@@ -297,15 +853,196 @@ async fn finalize_upload(state: &AppState, file_id: &str) -> Result<(), AppError
 
     state.upload_manager.remove(file_id);
 
+    // 保留客户端的修改时间，sync 等客户端据此判断文件是否变化；要在写入校验和缓存之前
+    if let Some(secs) = modified {
+        if let Err(e) = operations::set_modified(&final_path, secs).await {
+            tracing::warn!(error = %e, path = %final_path.display(), "cannot set modified time");
+        }
+    }
+
+    // 落盘前已校验过，直接写入缓存，之后查询校验和不必重读文件
+    if let Some(sha256) = verified_checksum {
+        if let Ok(meta) = tokio::fs::metadata(&final_path).await {
+            state.checksums.insert(&final_path, &meta, sha256).await;
+        }
+    }
+
     tracing::info!(
         file_id = %file_id,
         path = %final_path.display(),
         "upload finalized"
     );
 
+    let rel_path = relative_path(&final_path, &state.root);
+
+    state.metrics.upload_completed(total_size);
+    state.db.record_transfer(TransferRecord {
+        kind: TransferKind::Upload,
+        path: rel_path.clone(),
+        size: total_size,
+        client_ip,
+        user: auth::current_user(),
+        at: db::now_secs(),
+    });
+    announce_upload(state, &final_path, &rel_path, total_size, client_ip);
+
+    Ok(rel_path)
+}
+
+/// 按冲突策略把 src 放到 final_dir/filename，返回最终路径
+///
+/// 先原子地占用目标路径再替换，同一目标路径的落盘串行进行，避免 overwrite 时两次复制交错写入
+pub(crate) async fn place_file(
+    state: &AppState,
+    src: &std::path::Path,
+    final_dir: &std::path::Path,
+    filename: &str,
+) -> Result<std::path::PathBuf, AppError> {
+    state.path_safety.check_within(final_dir)?;
+    #[cfg(feature = "plugins")]
+    let renamed = crate::plugins::check_upload(state, src, final_dir, filename).await?;
+    #[cfg(feature = "plugins")]
+    let filename = renamed.as_deref().unwrap_or(filename);
+    tokio::fs::create_dir_all(final_dir).await?;
+    // 只差大小写的已有文件按同名处理
+    let existing = state.filenames.existing_case(final_dir, filename).await;
+    let filename = existing.as_deref().unwrap_or(filename);
+
+    // 启用落盘加密时先在临时区加密，明文分片随后删除
+    let sealed = match &state.vault {
+        Some(vault) => Some(seal_part(state, vault, src).await?),
+        None => None,
+    };
+    let result = reserve_and_replace(state, sealed.as_deref().unwrap_or(src), final_dir, filename).await;
+    if let Some(sealed) = &sealed {
+        match &result {
+            Ok(_) => {
+                let _ = tokio::fs::remove_file(src).await;
+            }
+            Err(_) => {
+                let _ = tokio::fs::remove_file(sealed).await;
+            }
+        }
+    }
+    result
+}
+
+/// 把临时区中的明文文件加密到同目录的 .sealed 文件，返回其路径
+pub(crate) async fn seal_part(
+    state: &AppState,
+    vault: &std::sync::Arc<crate::fs::vault::Vault>,
+    src: &std::path::Path,
+) -> Result<std::path::PathBuf, AppError> {
+    let sealed = src.with_extension("sealed");
+    let (vault, from, to) = (vault.clone(), src.to_path_buf(), sealed.clone());
+    if let Err(e) = state.blocking.run(move || vault.seal(&from, &to)).await? {
+        let _ = tokio::fs::remove_file(&sealed).await;
+        return Err(e.into());
+    }
+    Ok(sealed)
+}
+
+/// 按冲突策略占用目标路径并替换为 src
+async fn reserve_and_replace(
+    state: &AppState,
+    src: &std::path::Path,
+    final_dir: &std::path::Path,
+    filename: &str,
+) -> Result<std::path::PathBuf, AppError> {
+    let requested = final_dir.join(filename);
+    let _target_guard = state.upload_manager.lock_target(requested.clone()).await;
+
+    let final_path = match state.config.on_conflict {
+        ConflictPolicy::Rename => operations::reserve_unique(final_dir, filename).await?,
+        ConflictPolicy::Overwrite => {
+            if requested.is_dir() {
+                return Err(AppError::AlreadyExists(filename.to_string()));
+            }
+            requested
+        }
+        ConflictPolicy::Reject => match operations::reserve_exact(&requested).await {
+            Ok(()) => requested,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(AppError::AlreadyExists(filename.to_string()));
+            }
+            Err(e) => return Err(e.into()),
+        },
+    };
+
+    // 同文件系统时原子 rename，否则退化为复制
+    if let Err(e) = operations::replace_file(src, &final_path).await {
+        if state.config.on_conflict != ConflictPolicy::Overwrite {
+            let _ = tokio::fs::remove_file(&final_path).await;
+        }
+        return Err(e.into());
+    }
+    Ok(final_path)
+}
+
+/// rename 前校验 .part 的实际大小和可选的 SHA-256
+///
+/// 校验失败时保留 .part 和会话，并把进度回退到可续传的位置，客户端 HEAD 后即可重传
+async fn verify_part(
+    state: &AppState,
+    arc: &tokio::sync::RwLock<UploadSession>,
+    tmp_dir: &std::path::Path,
+) -> Result<(), AppError> {
+    let mut session = arc.write().await;
+    let part_path = session.part_path(tmp_dir);
+    let actual = tokio::fs::metadata(&part_path).await?.len();
+
+    if actual != session.total_size {
+        let expected = session.total_size;
+        if actual < expected {
+            // 从实际写入的位置续传
+            session.uploaded = actual;
+        } else {
+            // 超长说明数据已不可信，从头重传
+            tokio::fs::OpenOptions::new()
+                .write(true)
+                .open(&part_path)
+                .await?
+                .set_len(0)
+                .await?;
+            session.uploaded = 0;
+        }
+        session.persist_meta(tmp_dir).await?;
+        tracing::warn!(file_id = %session.file_id, expected, actual, "upload size mismatch");
+        return Err(AppError::SizeMismatch { expected, actual });
+    }
+
+    let Some(expected) = session.expected_checksum.as_deref() else {
+        return Ok(());
+    };
+    let Some(expected) = hash::parse_sha256(expected) else {
+        return Err(AppError::BadRequest("unsupported checksum, expected sha256".into()));
+    };
+
+    let path = part_path.clone();
+    let actual = state.blocking.run(move || hash::sha256_file(&path)).await??;
+
+    if actual != expected {
+        // 无法确定哪一段出错，整个文件重传；.part 原地覆盖写
+        session.uploaded = 0;
+        session.persist_meta(tmp_dir).await?;
+        tracing::warn!(file_id = %session.file_id, %expected, %actual, "upload checksum mismatch");
+        return Err(AppError::ChecksumMismatch { expected, actual });
+    }
     Ok(())
 }
 
+/// 大于该值的 X-File-Mtime 按毫秒处理 (以秒计是公元 5000 年以后)
+const MTIME_MILLIS_FROM: u64 = 100_000_000_000;
+
+/// 解析 X-File-Mtime：Unix 秒，或浏览器 `File.lastModified` 给出的毫秒，返回秒
+fn file_mtime(headers: &HeaderMap) -> Option<u64> {
+    let value = headers.get("x-file-mtime")?.to_str().ok()?.trim();
+    // 允许带小数的秒，如 `stat -c %.3Y` 的输出
+    let secs = value.split('.').next()?;
+    let n: u64 = secs.parse().ok()?;
+    Some(if n >= MTIME_MILLIS_FROM { n / 1000 } else { n })
+}
+
 /// 解析 tus Upload-Metadata 头
 fn parse_tus_metadata(headers: &HeaderMap) -> std::collections::HashMap<String, String> {
     use base64::Engine;
@@ -0,0 +1,95 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::fs::permissions::Access;
+use crate::fs::usage::{self, UsageEntry};
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct UsageParams {
+    #[serde(default)]
+    pub path: String,
+}
+
+#[derive(Serialize)]
+pub struct UsageResponse {
+    pub path: String,
+    /// 后台扫描是否正在进行
+    pub scanning: bool,
+    /// 尚未完成过扫描时为 None
+    pub scanned_at: Option<u64>,
+    pub elapsed_ms: Option<u64>,
+    pub size: u64,
+    pub files: u64,
+    /// 直接位于该目录下的文件
+    pub own_size: u64,
+    pub own_files: u64,
+    /// 子目录，按大小降序
+    pub entries: Vec<UsageEntry>,
+}
+
+/// GET /api/usage?path=xxx — 目录占用（来自缓存的后台扫描结果）
+pub async fn get(
+    State(state): State<AppState>,
+    Query(params): Query<UsageParams>,
+) -> Result<Json<UsageResponse>, AppError> {
+    let rel = if params.path.is_empty() {
+        state.permissions.check(&state.root, Access::Read)?;
+        String::new()
+    } else {
+        let abs = state.path_safety.resolve(&params.path)?;
+        state.permissions.check(&abs, Access::Read)?;
+        abs.strip_prefix(&state.root)
+            .unwrap_or(&abs)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+
+    let Some(report) = state.usage.report() else {
+        // 首次访问时还没有结果，触发一次扫描
+        if !state.usage.is_scanning() {
+            let state = state.clone();
+            tokio::spawn(async move { state.usage.rescan().await });
+        }
+        return Ok(Json(UsageResponse {
+            path: rel,
+            scanning: true,
+            scanned_at: None,
+            elapsed_ms: None,
+            size: 0,
+            files: 0,
+            own_size: 0,
+            own_files: 0,
+            entries: Vec::new(),
+        }));
+    };
+
+    let node = report
+        .root
+        .find(&rel)
+        .ok_or_else(|| AppError::NotFound(rel.clone()))?;
+
+    Ok(Json(UsageResponse {
+        entries: usage::sorted_children(node, &rel),
+        path: rel,
+        scanning: state.usage.is_scanning(),
+        scanned_at: Some(report.scanned_at),
+        elapsed_ms: Some(report.elapsed_ms),
+        size: node.size,
+        files: node.files,
+        own_size: node.own_size,
+        own_files: node.own_files,
+    }))
+}
+
+/// POST /api/usage/rescan — 立即重新扫描
+pub async fn rescan(State(state): State<AppState>) -> StatusCode {
+    let scanner = state.clone();
+    tokio::spawn(async move { scanner.usage.rescan().await });
+    StatusCode::ACCEPTED
+}
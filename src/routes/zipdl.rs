@@ -1,24 +1,100 @@
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::time::Duration;
+
 use axum::body::Body;
-use axum::extract::{Query, State};
+use axum::extract::{Form, Path, Query, State};
 use axum::http::header::*;
-use axum::http::{Response, StatusCode};
+use axum::http::{HeaderMap, Response, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::{Stream, StreamExt};
 use serde::Deserialize;
 use tokio_util::compat::TokioAsyncWriteCompatExt;
+use tokio_util::io::ReaderStream;
 
+use crate::download::disposition;
 use crate::error::AppError;
+use crate::fs::mode;
+use crate::fs::path_safety::relative_path;
+use crate::fs::permissions::{Access, Permissions};
+use crate::fs::vault::{self, Vault};
+use crate::middleware::auth;
 use crate::state::AppState;
+use crate::zip::aes::AesZipWriter;
+use crate::zip::progress::{self, ArchiveProgress, ArchiveStatus, SharedProgress};
+
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// 订阅可能先于打包请求到达，等待任务登记的最长时间
+const PROGRESS_REGISTER_WAIT: Duration = Duration::from_secs(10);
 
 #[derive(Deserialize)]
 pub struct ZipParams {
     pub paths: String,
     pub name: Option<String>,
+    /// 进度任务 ID，由前端生成以便在下载开始前订阅
+    pub progress: Option<String>,
+    /// 设置后生成 AES-256 加密的 ZIP
+    pub password: Option<String>,
 }
 
 /// GET /api/download-zip?paths=a,b,c — 流式 zip 打包下载
+///
+/// 响应头 `X-Archive-Progress` 给出进度任务 ID，可通过 `/api/archive-progress/{id}` 订阅
 pub async fn get(
     State(state): State<AppState>,
     Query(params): Query<ZipParams>,
 ) -> Result<Response<Body>, AppError> {
+    start(state, params)
+}
+
+/// POST /api/archive 的参数；表单提交时 paths 为重复的字段
+#[derive(Deserialize)]
+pub struct ArchiveRequest {
+    #[serde(default)]
+    pub paths: Vec<String>,
+    pub name: Option<String>,
+    pub progress: Option<String>,
+    pub password: Option<String>,
+}
+
+/// POST /api/archive — 路径以 JSON 数组或重复的表单字段提交，文件名中可以有逗号，数量不受 URL 长度限制
+pub async fn archive(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response<Body>, AppError> {
+    let is_json = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    let req: ArchiveRequest = if is_json {
+        serde_json::from_slice(&body).map_err(|e| AppError::BadRequest(format!("invalid request: {}", e)))?
+    } else {
+        serde_html_form::from_bytes(&body).map_err(|e| AppError::BadRequest(format!("invalid request: {}", e)))?
+    };
+    let entries: Vec<PathBuf> = req
+        .paths
+        .iter()
+        .filter(|p| !p.is_empty())
+        .map(|p| state.path_safety.resolve(p))
+        .collect::<Result<_, _>>()?;
+    if entries.is_empty() {
+        return Err(AppError::BadRequest("no paths specified".into()));
+    }
+    stream(state, entries, req.name, req.progress, req.password)
+}
+
+/// POST /api/download-zip — 参数同 GET，以表单提交，密码不会出现在 URL 和访问日志中
+pub async fn post(
+    State(state): State<AppState>,
+    Form(params): Form<ZipParams>,
+) -> Result<Response<Body>, AppError> {
+    start(state, params)
+}
+
+fn start(state: AppState, params: ZipParams) -> Result<Response<Body>, AppError> {
     let entries: Vec<std::path::PathBuf> = params
         .paths
         .split(',')
@@ -29,20 +105,79 @@ pub async fn get(
     if entries.is_empty() {
         return Err(AppError::BadRequest("no paths specified".into()));
     }
+    stream(state, entries, params.name, params.progress, params.password)
+}
+
+/// 把已解析的路径打包成 ZIP 流式返回；name 为空时用时间戳命名
+pub(crate) fn stream(
+    state: AppState,
+    entries: Vec<PathBuf>,
+    name: Option<String>,
+    progress: Option<String>,
+    password: Option<String>,
+) -> Result<Response<Body>, AppError> {
+    if password.as_deref() == Some("") {
+        return Err(AppError::BadRequest("password must not be empty".into()));
+    }
+    for entry in &entries {
+        state.permissions.check(entry, Access::Read)?;
+        // 响应头发出之后就无法再报错，不存在的路径提前拒绝
+        if !entry.exists() {
+            return Err(AppError::NotFound(relative_path(entry, &state.root)));
+        }
+    }
 
-    let root = state.root.clone();
+    let task_id = match progress {
+        Some(id) if progress::is_valid_id(&id) => id,
+        Some(_) => return Err(AppError::BadRequest("invalid progress id".into())),
+        None => uuid::Uuid::new_v4().simple().to_string(),
+    };
+    let progress = state.archives.register(&task_id);
+
+    let authenticated = auth::current_user().is_some();
     let (writer, reader) = tokio::io::duplex(256 * 1024);
     let reader_stream = tokio_util::io::ReaderStream::new(reader);
     let body = Body::from_stream(reader_stream);
 
     // 后台写 zip
+    let state = state.clone();
     tokio::spawn(async move {
-        if let Err(e) = write_zip(writer, entries, &root).await {
+        let result = async {
+            let mut files = collect_files(
+                entries,
+                state.path_safety.clone(),
+                state.permissions.clone(),
+                authenticated,
+            )
+            .await?;
+            // 进度按明文大小计算
+            if let Some(vault) = state.vault.as_deref() {
+                for (path, size) in &mut files {
+                    *size = vault::content_len(Some(vault), path, *size).await;
+                }
+            }
+            {
+                let mut p = progress.lock();
+                p.status = ArchiveStatus::Packing;
+                p.total_files = files.len() as u64;
+                p.total_bytes = files.iter().map(|(_, size)| size).sum();
+            }
+            match &password {
+                Some(password) => {
+                    write_encrypted_zip(writer, password, &files, &state.root, state.vault.as_deref(), &progress)
+                        .await
+                }
+                None => write_zip(writer, &files, &state.root, state.vault.as_deref(), &progress).await,
+            }
+        }
+        .await;
+        if let Err(e) = &result {
             tracing::warn!(error = %e, "zip stream failed");
         }
+        state.archives.finish(&progress, result.err().map(|e| e.to_string()));
     });
 
-    let filename = params.name.unwrap_or_else(|| {
+    let filename = name.unwrap_or_else(|| {
         let ts = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
@@ -53,18 +188,105 @@ pub async fn get(
     Ok(Response::builder()
         .status(StatusCode::OK)
         .header(CONTENT_TYPE, "application/zip")
-        .header(
-            CONTENT_DISPOSITION,
-            format!("attachment; filename=\"{}\"", filename),
-        )
+        .header("X-Archive-Progress", &task_id)
+        .header(CONTENT_DISPOSITION, disposition::attachment(&filename))
         .body(body)
         .unwrap())
 }
 
+/// GET /api/archive-progress/{id} — 通过 SSE 推送打包进度
+///
+/// 事件：`progress`（JSON，进度变化时发送，任务结束后关闭流）、`gone`（任务不存在）
+pub async fn progress(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    if !progress::is_valid_id(&id) {
+        return Err(AppError::BadRequest("invalid progress id".into()));
+    }
+
+    type Key = (ArchiveStatus, u64, u64);
+    let stream = futures_util::stream::unfold(
+        (state, id, None::<Key>, false),
+        |(state, id, last, finished)| async move {
+            if finished {
+                return None;
+            }
+            let mut waited = Duration::ZERO;
+            loop {
+                match state.archives.get(&id) {
+                    Some(p) => {
+                        let key = (p.status, p.done_bytes, p.total_bytes);
+                        if last != Some(key) {
+                            let finished = p.is_finished();
+                            return Some((progress_event(&p), (state, id, Some(key), finished)));
+                        }
+                    }
+                    None if last.is_none() && waited < PROGRESS_REGISTER_WAIT => {
+                        waited += PROGRESS_POLL_INTERVAL;
+                    }
+                    None => {
+                        let gone = Ok(Event::default().event("gone").data(""));
+                        return Some((gone, (state, id, last, true)));
+                    }
+                }
+                tokio::time::sleep(PROGRESS_POLL_INTERVAL).await;
+            }
+        },
+    );
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+fn progress_event(p: &ArchiveProgress) -> Result<Event, Infallible> {
+    Ok(Event::default()
+        .event("progress")
+        .json_data(p)
+        .unwrap_or_else(|_| Event::default().event("progress").data("{}")))
+}
+
+/// 展开目录，得到要打包的文件及大小
+pub(crate) async fn collect_files(
+    entries: Vec<PathBuf>,
+    path_safety: crate::fs::path_safety::PathSafety,
+    permissions: Permissions,
+    authenticated: bool,
+) -> anyhow::Result<Vec<(PathBuf, u64)>> {
+    let files = tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        for entry_path in entries {
+            if entry_path.is_dir() {
+                let walker = walkdir::WalkDir::new(&entry_path)
+                    .into_iter()
+                    // 打包根目录时跳过上传临时区
+                    // 跳过无读权限的子目录
+                    .filter_entry(|e| {
+                        !path_safety.is_hidden(e.path())
+                            && permissions.permits(e.path(), Access::Read, authenticated)
+                    })
+                    .filter_map(Result::ok)
+                    .filter(|e| e.file_type().is_file());
+                for e in walker {
+                    let size = e.metadata().map(|m| m.len()).unwrap_or(0);
+                    files.push((e.into_path(), size));
+                }
+            } else {
+                let size = std::fs::metadata(&entry_path).map(|m| m.len()).unwrap_or(0);
+                files.push((entry_path, size));
+            }
+        }
+        files
+    })
+    .await?;
+    Ok(files)
+}
+
 async fn write_zip(
     sink: tokio::io::DuplexStream,
-    entries: Vec<std::path::PathBuf>,
+    files: &[(PathBuf, u64)],
     root: &std::path::Path,
+    vault: Option<&Vault>,
+    progress: &SharedProgress,
 ) -> anyhow::Result<()> {
     use async_zip::base::write::ZipFileWriter;
 
@@ -72,26 +294,9 @@ async fn write_zip(
     let compat = sink.compat_write();
     let mut zip = ZipFileWriter::new(compat);
 
-    for entry_path in &entries {
-        if entry_path.is_dir() {
-            let dir = entry_path.clone();
-            let files: Vec<std::path::PathBuf> =
-                tokio::task::spawn_blocking(move || {
-                    walkdir::WalkDir::new(&dir)
-                        .into_iter()
-                        .filter_map(Result::ok)
-                        .filter(|e| e.file_type().is_file())
-                        .map(|e| e.into_path())
-                        .collect()
-                })
-                .await?;
-
-            for file in files {
-                add_file_entry(&mut zip, &file, root).await?;
-            }
-        } else {
-            add_file_entry(&mut zip, entry_path, root).await?;
-        }
+    for (file, _) in files {
+        add_file_entry(&mut zip, file, root, vault, progress).await?;
+        progress.lock().done_files += 1;
     }
 
     zip.close().await?;
@@ -102,6 +307,8 @@ async fn add_file_entry<W>(
     zip: &mut async_zip::base::write::ZipFileWriter<W>,
     file: &std::path::Path,
     root: &std::path::Path,
+    vault: Option<&Vault>,
+    progress: &SharedProgress,
 ) -> anyhow::Result<()>
 where
     W: futures_util::io::AsyncWrite + Unpin,
@@ -109,31 +316,96 @@ where
     use async_zip::{Compression, ZipEntryBuilder};
     use futures_util::io::AsyncWriteExt;
 
-    let rel = file
-        .strip_prefix(root)
-        .unwrap_or(file)
-        .to_string_lossy()
-        .to_string();
+    let rel = relative_path(file, root);
+    let meta = tokio::fs::metadata(file).await?;
 
-    let entry_builder = ZipEntryBuilder::new(
+    let mut entry_builder = ZipEntryBuilder::new(
         rel.into(),
         Compression::Stored,
     );
+    // 保留可执行位等权限，解压后脚本仍可直接运行
+    if let Some(attrs) = mode::attrs(&meta) {
+        entry_builder = entry_builder.unix_permissions((0o100000 | attrs.mode) as u16);
+    }
+    if let Some(date) = meta.modified().ok().and_then(zip_datetime) {
+        entry_builder = entry_builder.last_modification_date(date);
+    }
 
     let mut entry_writer = zip.write_entry_stream(entry_builder).await?;
 
-    // 流式读取，不全部加载到内存
-    let mut f = tokio::fs::File::open(file).await?;
-    let mut buf = vec![0u8; 256 * 1024]; // 256KB
-    loop {
-        let n = tokio::io::AsyncReadExt::read(&mut f, &mut buf).await?;
-        if n == 0 {
-            break;
-        }
-        entry_writer.write_all(&buf[..n]).await?;
+    let (_, mut stream) = open_content(file, vault).await?;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        entry_writer.write_all(&chunk).await?;
+        progress.lock().done_bytes += chunk.len() as u64;
     }
 
     entry_writer.close().await?;
 
     Ok(())
 }
+
+/// async_zip 只能写 DOS 时间 (2 秒精度)，这里按 UTC 填写；早于 1980 年的无法表示
+fn zip_datetime(t: std::time::SystemTime) -> Option<async_zip::ZipDateTime> {
+    let t = time::OffsetDateTime::from(t);
+    if t.year() < 1980 {
+        return None;
+    }
+    let date = async_zip::ZipDateTimeBuilder::new()
+        .year(t.year())
+        .month(u8::from(t.month()) as u32)
+        .day(t.day() as u32)
+        .hour(t.hour() as u32)
+        .minute(t.minute() as u32)
+        .second(t.second() as u32)
+        .build();
+    Some(date)
+}
+
+/// 写出 WinZip AES-256 加密的 ZIP，条目不压缩
+async fn write_encrypted_zip(
+    sink: tokio::io::DuplexStream,
+    password: &str,
+    files: &[(PathBuf, u64)],
+    root: &std::path::Path,
+    vault: Option<&Vault>,
+    progress: &SharedProgress,
+) -> anyhow::Result<()> {
+    let mut zip = AesZipWriter::new(sink, password);
+    for (file, _) in files {
+        let rel = relative_path(file, root);
+        let meta = tokio::fs::metadata(file).await?;
+        let modified = meta.modified().ok();
+        // 加密条目的大小写在条目头部，按打开时的大小读取
+        let (size, mut stream) = open_content(file, vault).await?;
+        let mode = mode::attrs(&meta).map(|a| a.mode);
+        let mut entry = zip.start_entry(&rel, size, modified, mode).await?;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            entry.write_all(&chunk).await?;
+            progress.lock().done_bytes += chunk.len() as u64;
+        }
+        entry.finish().await?;
+        progress.lock().done_files += 1;
+    }
+    zip.close().await?;
+    Ok(())
+}
+
+/// 打开文件的明文内容，返回大小和读取流；落盘加密的文件边读边解密
+async fn open_content(
+    file: &std::path::Path,
+    vault: Option<&Vault>,
+) -> std::io::Result<(u64, BoxStream<'static, std::io::Result<Bytes>>)> {
+    if let Some(vault) = vault {
+        if let Some(sealed) = vault.open_sealed(file).await? {
+            let size = sealed.size();
+            return Ok((size, sealed.into_stream(0, size).boxed()));
+        }
+    }
+    // 流式读取，不全部加载到内存
+    let f = tokio::fs::File::open(file).await?;
+    let size = f.metadata().await?.len();
+    let reader = tokio::io::AsyncReadExt::take(f, size);
+    Ok((size, ReaderStream::with_capacity(reader, 256 * 1024).boxed()))
+}
@@ -0,0 +1,160 @@
+use crate::config::AppConfig;
+
+/// 沙箱的实际生效程度，日志初始化之后再报告
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// 未开启 --sandbox
+    Disabled,
+    /// 全部访问控制均已生效
+    Enforced,
+    /// 内核只支持部分 Landlock 访问控制
+    Partial,
+    /// 内核不支持 Landlock，沙箱没有生效
+    Unsupported,
+}
+
+/// 开启 --sandbox 时用 Landlock 把进程的文件访问限制在共享目录、临时目录等少数路径内，
+/// 作为路径规范化检查之外的内核级防线
+///
+/// Landlock 只作用于调用线程和之后创建的线程，必须在启动运行时、日志线程之前调用
+pub fn apply(config: &AppConfig) -> anyhow::Result<Status> {
+    if !config.sandbox {
+        return Ok(Status::Disabled);
+    }
+    imp::apply(config)
+}
+
+/// 在日志中报告沙箱状态
+pub fn report(status: Status) {
+    match status {
+        Status::Disabled => {}
+        Status::Enforced => tracing::info!("filesystem sandbox enabled"),
+        Status::Partial => tracing::warn!("filesystem sandbox only partially enforced by this kernel"),
+        Status::Unsupported => {
+            tracing::warn!("kernel does not support Landlock; filesystem sandbox is NOT enforced")
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::path::{Path, PathBuf};
+
+    use anyhow::{bail, Context};
+    use landlock::{
+        path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr,
+        RulesetStatus, ABI,
+    };
+
+    use super::Status;
+    use crate::config::AppConfig;
+
+    /// 内核 6.2 起支持的访问控制；更老的内核按能支持的部分生效
+    const ABI: ABI = ABI::V3;
+
+    /// 系统程序和动态库，执行 ffmpeg、钩子以及加载 NSS 模块时需要
+    const SYSTEM_DIRS: &[&str] = &["/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64"];
+
+    /// 用户查询、域名解析、时区需要读取的系统文件
+    const SYSTEM_FILES: &[&str] = &[
+        "/etc/passwd",
+        "/etc/group",
+        "/etc/nsswitch.conf",
+        "/etc/hosts",
+        "/etc/host.conf",
+        "/etc/resolv.conf",
+        "/etc/gai.conf",
+        "/etc/hostname",
+        "/etc/localtime",
+        "/etc/ld.so.cache",
+        "/dev/urandom",
+        // /proc/net/route 等本进程的信息
+        "/proc/self",
+    ];
+
+    pub fn apply(config: &AppConfig) -> anyhow::Result<Status> {
+        let read = AccessFs::ReadFile | AccessFs::ReadDir;
+        // 可写的目录里不允许执行，上传的文件不能被当作程序运行
+        let write = AccessFs::from_all(ABI) & !AccessFs::Execute;
+
+        let mut writable = vec![config.path.clone()];
+        if let Some(dir) = &config.tmp_dir {
+            writable.push(existing(dir)?);
+        }
+        // 数据库和日志会在同目录下创建日志、轮转文件
+        if let Some(db) = &config.db_path {
+            writable.push(existing(parent(db))?);
+        }
+        for log in [&config.log_file, &config.access_log].into_iter().flatten() {
+            if log.as_os_str() != "-" {
+                writable.push(existing(parent(log))?);
+            }
+        }
+        let mut readable: Vec<PathBuf> = [&config.config, &config.tls_cert, &config.tls_key, &config.brand_logo]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .chain(config.plugins.iter().cloned())
+            .collect();
+        if let Some(key) = &config.encrypt_key {
+            // 密钥文件不存在时会在启动时生成
+            if key.exists() {
+                readable.push(key.clone());
+            } else {
+                writable.push(existing(parent(key))?);
+            }
+        }
+        let mut executable: Vec<PathBuf> = SYSTEM_DIRS.iter().map(PathBuf::from).collect();
+        executable.extend(config.ffmpeg.iter().cloned());
+        executable.extend(
+            config
+                .hooks
+                .values()
+                .filter_map(|hook| hook.program())
+                .map(PathBuf::from)
+                .filter(|program| program.is_absolute()),
+        );
+
+        let status = Ruleset::default()
+            .handle_access(AccessFs::from_all(ABI))?
+            .create()?
+            .add_rules(path_beneath_rules(&writable, write))?
+            .add_rules(path_beneath_rules(&readable, read))?
+            .add_rules(path_beneath_rules(SYSTEM_FILES, read))?
+            .add_rules(path_beneath_rules(&executable, read | AccessFs::Execute))?
+            // 子进程的标准输入输出重定向到 /dev/null
+            .add_rules(path_beneath_rules(["/dev/null"], read | AccessFs::WriteFile))?
+            .restrict_self()
+            .context("cannot enable the filesystem sandbox")?;
+        Ok(match status.ruleset {
+            RulesetStatus::FullyEnforced => Status::Enforced,
+            RulesetStatus::PartiallyEnforced => Status::Partial,
+            RulesetStatus::NotEnforced => Status::Unsupported,
+        })
+    }
+
+    fn parent(path: &Path) -> &Path {
+        match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        }
+    }
+
+    /// 沙箱开启后无法再在允许的范围之外创建目录，这里要求目录事先存在
+    fn existing(dir: &Path) -> anyhow::Result<PathBuf> {
+        if !dir.is_dir() {
+            bail!("--sandbox requires '{}' to exist; create it first", dir.display());
+        }
+        Ok(dir.to_path_buf())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use super::Status;
+    use crate::config::AppConfig;
+
+    pub fn apply(_config: &AppConfig) -> anyhow::Result<Status> {
+        anyhow::bail!("--sandbox is only supported on Linux")
+    }
+}
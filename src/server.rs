@@ -1,15 +1,44 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
 
+use tokio::net::TcpListener;
+
 use crate::config::AppConfig;
+use crate::fs::usage;
+use crate::hooks::HookEvent;
+use crate::middleware::maintenance;
 use crate::routes;
 use crate::state::{AppState, AppStateInner};
 use crate::upload;
-use crate::util::ip;
+use crate::util::{browser, ip};
+
+/// 开启端口回退时，在配置端口之后最多尝试的端口数
+const FALLBACK_PORTS: u16 = 10;
 
 /// 构建并启动服务器
-pub async fn run(config: AppConfig) -> anyhow::Result<()> {
-    let addr = SocketAddr::from((config.bind, config.port));
+pub async fn run(mut config: AppConfig) -> anyhow::Result<()> {
+    // 先绑定端口：实际端口可能因回退而变化，通知链接、LocalSend 公告等都要用最终端口
+    let listener = bind(&config).await?;
+    let addr = listener.local_addr()?;
+    if addr.port() != config.port {
+        tracing::warn!(requested = config.port, port = addr.port(), "port in use, fell back");
+        config.port = addr.port();
+    }
+
+    // 证书私钥通常只有 root 可读，切换用户之前读入
+    if let Some(cert) = config.tls_cert.as_deref().filter(|_| config.tls_generated) {
+        tracing::warn!(
+            cert = %cert.display(),
+            "generated a self-signed TLS certificate; browsers will warn until it is trusted"
+        );
+    }
+    #[cfg(feature = "tls")]
+    let tls_config = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => Some(crate::tls::load_rustls_config(cert, key)?),
+        _ => None,
+    };
+    // 端口已经绑定，之后的状态初始化、请求处理都以普通用户身份进行
+    crate::privileges::drop(&config)?;
 
     let state: AppState = Arc::new(AppStateInner::new(config.clone())?);
 
@@ -21,25 +50,48 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
 
     // 启动后台清理任务
     upload::janitor::spawn(state.clone());
+    usage::spawn(state.clone());
+    crate::reload::spawn(state.clone());
+    crate::localsend::discovery::spawn(state.clone());
+    crate::localsend::approval::spawn_console(state.clone());
+    state.hooks.fire(
+        HookEvent::ServerStart,
+        serde_json::json!({
+            "url": config.base_url(),
+            "port": addr.port(),
+            "root": dunce::simplified(&state.root),
+            "version": env!("CARGO_PKG_VERSION"),
+        }),
+    );
 
-    let app = routes::build_router(state);
+    let app = routes::build_router(state.clone());
 
     // 打印启动信息
     print_banner(&config, addr);
+    crate::portmap::spawn(&config, addr.port());
+    crate::relay::spawn(&config, addr.port());
+    if config.open {
+        browser::open(&local_url(&config, addr));
+    }
 
     // TLS 启动
     #[cfg(feature = "tls")]
-    if let (Some(cert), Some(key)) = (&config.tls_cert, &config.tls_key) {
-        let tls_config = crate::tls::load_rustls_config(cert, key)?;
+    if let Some(tls_config) = tls_config {
         tracing::info!("TLS enabled");
 
-        let listener = tokio::net::TcpListener::bind(addr).await?;
         let tls_acceptor = tokio_rustls::TlsAcceptor::from(tls_config);
 
         loop {
-            let (stream, _peer) = listener.accept().await?;
+            // 收到关机请求后不再接受新连接
+            let (stream, peer) = tokio::select! {
+                accepted = listener.accept() => accepted?,
+                _ = state.maintenance.shutdown_requested() => break,
+            };
             let acceptor = tls_acceptor.clone();
-            let app = app.clone();
+            // 手动 serve 时补上 ConnectInfo，供 handler 获取客户端地址
+            let app = app
+                .clone()
+                .layer(axum::Extension(axum::extract::ConnectInfo(peer)));
 
             tokio::spawn(async move {
                 match acceptor.accept(stream).await {
@@ -63,17 +115,69 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
                 }
             });
         }
+        drop(listener);
+        maintenance::drained(&state).await;
+        return Ok(());
     }
 
     // 非 TLS 启动
-    let listener = tokio::net::TcpListener::bind(addr).await?;
     tracing::info!(%addr, "listening");
 
-    axum::serve(listener, app).await?;
+    let shutdown = state.clone();
+    let serve = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move { shutdown.maintenance.shutdown_requested().await });
+    // 优雅关闭会等所有连接结束，WebSocket 等长连接不会自己断开，所以只等传输请求
+    tokio::select! {
+        served = serve => {
+            served?;
+            // 连接都已关闭，仍要等后台的收尾工作
+            maintenance::drained(&state).await;
+        }
+        _ = maintenance::drained(&state) => {}
+    }
 
     Ok(())
 }
 
+/// 绑定监听端口；开启 --port-fallback 时端口被占用会依次尝试后面的端口，都不可用再由系统分配
+async fn bind(config: &AppConfig) -> anyhow::Result<TcpListener> {
+    let last = if config.port_fallback && config.port != 0 {
+        config.port.saturating_add(FALLBACK_PORTS)
+    } else {
+        config.port
+    };
+    for port in config.port..=last {
+        match TcpListener::bind((config.bind, port)).await {
+            Ok(listener) => return Ok(listener),
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && config.port_fallback => continue,
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => anyhow::bail!(
+                "port {} is already in use; choose another --port or pass --port-fallback",
+                port
+            ),
+            Err(e) => {
+                return Err(anyhow::Error::new(e).context(format!("failed to bind {}:{}", config.bind, port)))
+            }
+        }
+    }
+    TcpListener::bind((config.bind, 0))
+        .await
+        .map_err(|e| anyhow::Error::new(e).context(format!("failed to bind {}", config.bind)))
+}
+
+/// 本机访问地址：监听所有地址时用 127.0.0.1
+fn local_url(config: &AppConfig, addr: SocketAddr) -> String {
+    let protocol = if config.tls_cert.is_some() { "https" } else { "http" };
+    let host = if addr.ip().is_unspecified() {
+        SocketAddr::from(([127, 0, 0, 1], addr.port()))
+    } else {
+        addr
+    };
+    format!("{}://{}", protocol, host)
+}
+
 fn print_banner(config: &AppConfig, addr: SocketAddr) {
     let protocol = if config.tls_cert.is_some() {
         "https"
@@ -81,7 +185,23 @@ fn print_banner(config: &AppConfig, addr: SocketAddr) {
         "http"
     };
 
-    let local_ip = ip::get_local_ip().unwrap_or_else(|| "unknown".to_string());
+    // 监听所有地址时每块网卡各列一个地址，多网卡、连着 VPN 时也能找到正确的那个
+    let mut rows = Vec::new();
+    if addr.ip().is_unspecified() || addr.ip().is_loopback() {
+        rows.push(format!("Local:   {}", local_url(config, addr)));
+    }
+    if addr.ip().is_unspecified() {
+        for lan in ip::lan_addrs() {
+            let url = format!("{}://{}", protocol, SocketAddr::new(lan.ip, addr.port()));
+            rows.push(format!("Network: {} ({})", url, lan.interface));
+        }
+    } else if !addr.ip().is_loopback() {
+        let url = format!("{}://{}", protocol, addr);
+        match &config.interface {
+            Some(name) => rows.push(format!("Network: {} ({})", url, name)),
+            None => rows.push(format!("Network: {}", url)),
+        }
+    }
 
     println!();
     println!("  ╔══════════════════════════════════════════════════╗");
@@ -90,17 +210,12 @@ fn print_banner(config: &AppConfig, addr: SocketAddr) {
         env!("CARGO_PKG_VERSION")
     );
     println!("  ╠══════════════════════════════════════════════════╣");
-    println!(
-        "  ║  Local:   {}://127.0.0.1:{:<21} ║",
-        protocol, addr.port()
-    );
-    println!(
-        "  ║  Network: {}://{}:{:<15} ║",
-        protocol, local_ip, addr.port()
-    );
+    for row in rows {
+        println!("  ║  {:<48}║", row);
+    }
     println!("  ╚══════════════════════════════════════════════════╝");
     println!();
-    println!("  共享目录: {}", config.path.display());
+    println!("  共享目录: {}", dunce::simplified(&config.path).display());
     println!("  按 Ctrl+C 停止服务器");
     println!();
 }
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+/// 同时在线的设备上限
+const MAX_PEERS: usize = 200;
+/// 每个连接待发送消息的上限，浏览器处理不过来时丢弃新消息
+const QUEUE_CAPACITY: usize = 64;
+const MAX_NAME_CHARS: usize = 40;
+
+/// 在线设备
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerInfo {
+    pub id: String,
+    pub name: String,
+}
+
+/// 服务端发给浏览器的消息
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ServerMessage {
+    /// 连接建立后的第一条消息
+    Welcome { id: String, peers: Vec<PeerInfo> },
+    PeerJoined { peer: PeerInfo },
+    PeerLeft { id: String },
+    /// 另一台设备转发来的数据
+    Signal { from: String, data: serde_json::Value },
+    Error { message: String },
+}
+
+/// 浏览器发给服务端的消息
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ClientMessage {
+    /// 转发给指定设备；服务端不解析 data（SDP、ICE 候选、文件清单等）
+    Signal { to: String, data: serde_json::Value },
+}
+
+struct Peer {
+    name: String,
+    tx: mpsc::Sender<String>,
+}
+
+/// WebRTC 信令中转：只转发浏览器之间的握手消息，文件数据走点对点的 DataChannel
+#[derive(Default)]
+pub struct SignalHub {
+    peers: parking_lot::RwLock<HashMap<String, Peer>>,
+}
+
+impl SignalHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记新设备，返回设备 ID 和待发送消息；在线设备已满时返回 None
+    pub fn join(&self, name: &str) -> Option<(String, mpsc::Receiver<String>)> {
+        let mut peers = self.peers.write();
+        if peers.len() >= MAX_PEERS {
+            return None;
+        }
+        let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+        let info = PeerInfo {
+            id: id.clone(),
+            name: display_name(name),
+        };
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+
+        let others = peers
+            .iter()
+            .map(|(id, p)| PeerInfo {
+                id: id.clone(),
+                name: p.name.clone(),
+            })
+            .collect();
+        send(&tx, &ServerMessage::Welcome {
+            id: id.clone(),
+            peers: others,
+        });
+        let joined = ServerMessage::PeerJoined { peer: info.clone() };
+        for peer in peers.values() {
+            send(&peer.tx, &joined);
+        }
+
+        peers.insert(id.clone(), Peer { name: info.name, tx });
+        Some((id, rx))
+    }
+
+    pub fn leave(&self, id: &str) {
+        let mut peers = self.peers.write();
+        if peers.remove(id).is_none() {
+            return;
+        }
+        let left = ServerMessage::PeerLeft { id: id.to_string() };
+        for peer in peers.values() {
+            send(&peer.tx, &left);
+        }
+    }
+
+    /// 转发信令；目标设备不在线时返回 false
+    pub fn relay(&self, from: &str, to: &str, data: serde_json::Value) -> bool {
+        let peers = self.peers.read();
+        let Some(peer) = peers.get(to) else {
+            return false;
+        };
+        send(&peer.tx, &ServerMessage::Signal {
+            from: from.to_string(),
+            data,
+        });
+        true
+    }
+
+    /// 发送给单个设备
+    pub fn notify(&self, id: &str, msg: &ServerMessage) {
+        if let Some(peer) = self.peers.read().get(id) {
+            send(&peer.tx, msg);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn send(tx: &mpsc::Sender<String>, msg: &ServerMessage) {
+    if let Ok(text) = serde_json::to_string(msg) {
+        let _ = tx.try_send(text);
+    }
+}
+
+fn display_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_CHARS)
+        .collect();
+    if name.is_empty() {
+        "未命名设备".to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recv(rx: &mut mpsc::Receiver<String>) -> serde_json::Value {
+        serde_json::from_str(&rx.try_recv().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_join_relay_leave() {
+        let hub = SignalHub::new();
+        let (a, mut rx_a) = hub.join("laptop").unwrap();
+        assert_eq!(recv(&mut rx_a)["peers"], serde_json::json!([]));
+
+        let (b, mut rx_b) = hub.join("  phone\n").unwrap();
+        let welcome = recv(&mut rx_b);
+        assert_eq!(welcome["type"], "welcome");
+        assert_eq!(welcome["peers"][0]["name"], "laptop");
+        let joined = recv(&mut rx_a);
+        assert_eq!(joined["type"], "peer-joined");
+        assert_eq!(joined["peer"]["name"], "phone");
+
+        assert!(hub.relay(&a, &b, serde_json::json!({"sdp": "x"})));
+        let signal = recv(&mut rx_b);
+        assert_eq!(signal["from"], a.as_str());
+        assert_eq!(signal["data"]["sdp"], "x");
+        assert!(!hub.relay(&a, "missing", serde_json::Value::Null));
+
+        hub.leave(&b);
+        assert_eq!(recv(&mut rx_a)["type"], "peer-left");
+        assert_eq!(hub.len(), 1);
+    }
+
+    #[test]
+    fn test_client_message() {
+        let msg: ClientMessage =
+            serde_json::from_str(r#"{"type":"signal","to":"abc","data":{"kind":"offer"}}"#).unwrap();
+        let ClientMessage::Signal { to, data } = msg;
+        assert_eq!(to, "abc");
+        assert_eq!(data["kind"], "offer");
+    }
+}
@@ -1,9 +1,35 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use crate::board::MessageBoard;
+use crate::branding::Branding;
+use crate::clients::ClientTracker;
+use crate::clipboard::ClipboardHub;
 use crate::config::AppConfig;
-use crate::fs::path_safety::PathSafety;
+use crate::db::Db;
+use crate::fs::checksums::ChecksumCache;
+use crate::fs::filename::FilenamePolicy;
+use crate::fs::path_safety::{self, PathSafety};
+use crate::fs::permissions::{Access, PermissionRule, Permissions};
+use crate::fs::trash::Trash;
+use crate::fs::usage::UsageScanner;
+use crate::fs::vault::Vault;
+use crate::hooks::Hooks;
+use crate::localsend::LocalSend;
+use crate::metrics::Metrics;
+use crate::middleware::access_log::AccessLog;
+use crate::middleware::maintenance::Maintenance;
+use crate::middleware::rate_limit::RateLimiter;
+use crate::notifier::Notifier;
+#[cfg(feature = "plugins")]
+use crate::plugins::PluginHost;
+use crate::preview::hls::HlsManager;
+use crate::reload::LiveSettings;
+use crate::signaling::SignalHub;
 use crate::upload::manager::UploadManager;
+use crate::upload::remote::FetchManager;
+use crate::util::blocking::BlockingPool;
+use crate::zip::progress::ArchiveTracker;
 
 /// 应用共享状态
 pub type AppState = Arc<AppStateInner>;
@@ -12,26 +38,232 @@ pub struct AppStateInner {
     pub config: AppConfig,
     pub root: PathBuf,
     pub path_safety: PathSafety,
+    pub permissions: Permissions,
+    /// 上传文件名的清洗规则
+    pub filenames: FilenamePolicy,
     pub upload_manager: UploadManager,
+    pub fetch_manager: FetchManager,
+    pub notifier: Notifier,
+    pub hooks: Hooks,
+    pub metrics: Metrics,
+    /// 未配置 ffmpeg 时为 None
+    pub hls: Option<HlsManager>,
+    pub usage: UsageScanner,
+    pub blocking: BlockingPool,
+    pub checksums: ChecksumCache,
+    pub db: Db,
+    /// 回收站和删除后的撤销
+    pub trash: Trash,
+    pub archives: ArchiveTracker,
+    pub signaling: SignalHub,
+    pub clipboard: ClipboardHub,
+    pub board: MessageBoard,
+    /// 可以通过重新加载配置文件修改的设置
+    pub live: LiveSettings,
+    pub rate_limiter: RateLimiter,
+    /// 最近访问过的客户端和进行中的请求
+    pub clients: ClientTracker,
+    /// 暂停上传和远程关机
+    pub maintenance: Maintenance,
+    pub branding: Branding,
+    /// 未配置 --access-log 时为 None
+    pub access_log: Option<AccessLog>,
+    /// 未启用 --localsend 时为 None
+    pub localsend: Option<LocalSend>,
+    /// 未配置 --encrypt-key 时为 None
+    pub vault: Option<Arc<Vault>>,
+    /// 未配置 --plugin 时为 None
+    #[cfg(feature = "plugins")]
+    pub plugins: Option<Arc<PluginHost>>,
 }
 
 impl AppStateInner {
     pub fn new(config: AppConfig) -> anyhow::Result<Self> {
         let root = config.path.clone();
-        let tmp_dir = root.join(".transfer-tmp");
+        let tmp_dir = config.tmp_dir();
         std::fs::create_dir_all(&tmp_dir)?;
+        let tmp_dir = path_safety::canonicalize(&tmp_dir)?;
+        warn_if_other_filesystem(&root, &tmp_dir);
 
-        let path_safety = PathSafety::new(root.clone());
+        let path_safety = PathSafety::new(root.clone()).with_hidden(tmp_dir.clone());
+        let rules = effective_rules(&config.rules, &config);
+        let permissions = Permissions::new(&root, &rules);
+        if !permissions.is_empty() {
+            tracing::info!(
+                rules = rules.len(),
+                drop_box = config.drop_box,
+                read_only = config.read_only,
+                "path permission rules loaded"
+            );
+        }
+        let filenames = FilenamePolicy::new(&config, &tmp_dir);
+        let fetch_manager = FetchManager::new(tmp_dir.clone());
+        let hls = config
+            .ffmpeg
+            .clone()
+            .map(|ffmpeg| HlsManager::new(ffmpeg, tmp_dir.join("hls")));
+        let localsend = (config.localsend || config.localsend_confirm)
+            .then(|| LocalSend::new(&config, &tmp_dir));
+        let vault = config
+            .encrypt_key
+            .as_deref()
+            .map(|key_path| open_vault(&root, key_path))
+            .transpose()?
+            .map(Arc::new);
+        #[cfg(feature = "plugins")]
+        let plugins = (!config.plugins.is_empty())
+            .then(|| PluginHost::load(&config.plugins))
+            .transpose()?
+            .map(Arc::new);
+        #[cfg(not(feature = "plugins"))]
+        if !config.plugins.is_empty() {
+            anyhow::bail!("--plugin requires a build with the `plugins` feature");
+        }
+        let usage = UsageScanner::new(root.clone(), tmp_dir.clone());
+        let checksums = ChecksumCache::load(&tmp_dir, vault.clone());
+        let db_path = config
+            .db_path
+            .clone()
+            .unwrap_or_else(|| tmp_dir.join("transfer.db"));
+        let db = Db::open(&db_path)
+            .map_err(|e| anyhow::anyhow!("cannot open database {}: {}", db_path.display(), e))?;
+        let trash = Trash::new(
+            tmp_dir.join("trash"),
+            db.clone(),
+            config.trash_days,
+            std::time::Duration::from_secs(config.undo_secs),
+        );
         let upload_manager = UploadManager::new(
             tmp_dir,
             std::time::Duration::from_secs(config.upload_expiration_secs),
         );
 
+        let clipboard =
+            ClipboardHub::new(std::time::Duration::from_secs(config.clipboard_ttl_secs));
+        let notifier = Notifier::new(&config);
+        let hooks = Hooks::new(&config.hooks, root.clone());
+        let branding = Branding::from_config(&config)?;
+        let live = LiveSettings::new(&config);
+        let rate_limiter = RateLimiter::new(&config);
+        if rate_limiter.is_enabled() {
+            tracing::info!(
+                per_minute = config.rate_limit,
+                writes_per_minute = config.rate_limit_writes,
+                "API rate limiting enabled"
+            );
+        }
+        #[cfg(feature = "plugins")]
+        let hooks = match &plugins {
+            Some(host) => hooks.with_plugins(host.clone()),
+            None => hooks,
+        };
+        let blocking = BlockingPool::new(config.cpu_workers);
+        let access_log = config
+            .access_log
+            .as_deref()
+            .map(|path| AccessLog::open(&config, path))
+            .transpose()?;
+
         Ok(Self {
             config,
             root,
             path_safety,
+            permissions,
+            filenames,
             upload_manager,
+            fetch_manager,
+            notifier,
+            hooks,
+            metrics: Metrics::new(),
+            hls,
+            usage,
+            blocking,
+            checksums,
+            db,
+            trash,
+            archives: ArchiveTracker::new(),
+            signaling: SignalHub::new(),
+            clipboard,
+            board: MessageBoard::new(),
+            live,
+            rate_limiter,
+            clients: ClientTracker::new(),
+            maintenance: Maintenance::new(),
+            branding,
+            access_log,
+            localsend,
+            vault,
+            #[cfg(feature = "plugins")]
+            plugins,
         })
     }
 }
+
+/// 配置文件中的规则加上 --drop-box / --read-only 隐含的根目录规则
+pub fn effective_rules(rules: &[PermissionRule], config: &AppConfig) -> Vec<PermissionRule> {
+    let mut rules = rules.to_vec();
+    if config.read_only {
+        // 配置文件中的规则不能再开放写入和删除
+        for rule in &mut rules {
+            rule.allow.retain(|access| *access == Access::Read);
+        }
+        rules.insert(
+            0,
+            PermissionRule {
+                path: "/".into(),
+                allow: vec![Access::Read],
+                auth: false,
+            },
+        );
+    }
+    if config.drop_box {
+        // 根目录只写；配置文件中更深的规则仍可单独开放子目录
+        rules.insert(
+            0,
+            PermissionRule {
+                path: "/".into(),
+                allow: vec![Access::Write],
+                auth: false,
+            },
+        );
+    }
+    rules
+}
+
+/// 加载主密钥；密钥与加密文件放在同一个盘上等于没有加密，拒绝共享目录内的路径
+fn open_vault(root: &std::path::Path, key_path: &std::path::Path) -> anyhow::Result<Vault> {
+    let parent = match key_path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => std::path::Path::new("."),
+    };
+    let parent = path_safety::canonicalize(parent)
+        .map_err(|e| anyhow::anyhow!("encryption key directory {}: {}", parent.display(), e))?;
+    if parent.starts_with(root) {
+        anyhow::bail!(
+            "encryption key {} must be stored outside the shared directory",
+            key_path.display()
+        );
+    }
+    let vault = Vault::load_or_create(key_path)
+        .map_err(|e| anyhow::anyhow!("cannot load encryption key {}: {}", key_path.display(), e))?;
+    tracing::info!(key = %key_path.display(), "at-rest encryption enabled");
+    Ok(vault)
+}
+
+/// 临时目录与共享目录不在同一文件系统时，完成上传需要整文件复制而不是 rename
+#[cfg(unix)]
+fn warn_if_other_filesystem(root: &std::path::Path, tmp_dir: &std::path::Path) {
+    use std::os::unix::fs::MetadataExt;
+
+    if let (Ok(a), Ok(b)) = (std::fs::metadata(root), std::fs::metadata(tmp_dir)) {
+        if a.dev() != b.dev() {
+            tracing::warn!(
+                tmp_dir = %tmp_dir.display(),
+                "tmp dir is on a different filesystem; finished uploads will be copied instead of renamed"
+            );
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_if_other_filesystem(_root: &std::path::Path, _tmp_dir: &std::path::Path) {}
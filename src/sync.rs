@@ -0,0 +1,779 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::Context;
+use async_compression::tokio::bufread::ZstdEncoder;
+use async_compression::Level;
+use clap::{Args, ValueEnum};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::delta;
+use crate::util::{encoding, hash, mime};
+
+/// 每个 PATCH 请求的大小
+const CHUNK_SIZE: usize = 8 << 20;
+/// 下载中的临时文件后缀，列出本地文件时跳过
+const PART_SUFFIX: &str = ".sync-part";
+/// 两边都不小于该大小时按差量更新远端已有的文件，更小的文件直接重传更快
+const DELTA_MIN_SIZE: u64 = 4 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Direction {
+    /// 本地 → 远端
+    Push,
+    /// 远端 → 本地
+    Pull,
+    /// 双向，两边都有且内容不同时较新的一方覆盖另一方
+    Both,
+}
+
+/// `transfer-app sync` 的参数
+#[derive(Debug, Clone, Args)]
+pub struct SyncArgs {
+    /// 本地目录
+    pub local: PathBuf,
+
+    /// 远端目录，例如 http://192.168.1.10:8080/photos（路径为共享目录中的子目录，也可以直接粘贴网页地址）
+    pub remote: String,
+
+    /// 同步方向
+    #[arg(long, value_enum, default_value = "both")]
+    pub direction: Direction,
+
+    /// 只列出将要传输的文件，不实际传输
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// 同步一次后持续监视本地目录，新增或修改的文件写完后自动上传 (需要 --direction push)
+    #[arg(long)]
+    pub watch: bool,
+
+    /// 监视模式下扫描本地目录的间隔 (秒)；文件在连续两次扫描中大小和修改时间不变才上传
+    #[arg(long, default_value_t = 5)]
+    pub interval: u64,
+
+    /// 不使用 zstd 压缩传输内容（千兆以上的局域网中压缩可能反而更慢）
+    #[arg(long)]
+    pub no_compress: bool,
+}
+
+/// 文件大小和修改时间 (Unix 秒)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stat {
+    size: u64,
+    modified: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Upload,
+    Download,
+    /// 大小相同、修改时间不同，需要比较 SHA-256
+    Compare,
+    /// 两边修改时间相同但内容不同，无法判断以哪边为准
+    Conflict,
+}
+
+#[derive(Deserialize)]
+struct ListResponse {
+    entries: Vec<RemoteEntry>,
+}
+
+#[derive(Deserialize)]
+struct RemoteEntry {
+    path: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct PathStat {
+    sha256: Option<String>,
+}
+
+/// 每次批量查询的路径数，低于服务端上限
+const STAT_BATCH: usize = 1000;
+
+/// 远端服务
+struct Remote {
+    client: reqwest::Client,
+    /// 服务地址，不带末尾斜杠
+    base: String,
+    /// 同步的远端目录（相对共享根目录），为空表示根目录
+    dir: String,
+    /// 远端声明接受时用 zstd 压缩上传分块
+    zstd: bool,
+}
+
+/// 比较两边的文件列表，只传输缺少或变化的文件；监视模式下之后持续推送
+pub async fn run(args: SyncArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        args.local.is_dir() || args.direction == Direction::Pull,
+        "local directory '{}' does not exist",
+        args.local.display()
+    );
+    anyhow::ensure!(
+        !args.watch || args.direction == Direction::Push,
+        "--watch only supports --direction push"
+    );
+    anyhow::ensure!(args.interval > 0, "--interval must be at least 1 second");
+    let (base, dir) = parse_remote(&args.remote)?;
+    let mut builder = reqwest::Client::builder();
+    if args.no_compress {
+        builder = builder.no_zstd();
+    }
+    let remote = Remote {
+        client: builder.build().context("build http client")?,
+        base,
+        dir,
+        zstd: !args.no_compress,
+    };
+
+    let failed = sync_once(&args, &remote).await?;
+    if args.watch && !args.dry_run {
+        return watch(&args, &remote).await;
+    }
+    anyhow::ensure!(failed == 0, "{} files failed to sync", failed);
+    Ok(())
+}
+
+/// 同步一轮，返回失败的文件数
+async fn sync_once(args: &SyncArgs, remote: &Remote) -> anyhow::Result<usize> {
+    let local_files = if args.local.is_dir() {
+        list_local(&args.local)?
+    } else {
+        BTreeMap::new()
+    };
+    let remote_files = remote.list().await?;
+    println!(
+        "sync {} <-> {}/{}: {} local, {} remote files",
+        args.local.display(),
+        remote.base,
+        remote.dir,
+        local_files.len(),
+        remote_files.len()
+    );
+
+    let steps = diff(&local_files, &remote_files, args.direction);
+    // 大小相同、修改时间不同的文件一次性取回远端的 SHA-256
+    let compare: Vec<&str> = steps
+        .iter()
+        .filter(|(_, step)| *step == Step::Compare)
+        .map(|(rel, _)| rel.as_str())
+        .collect();
+    let remote_hashes = remote.checksums(&compare).await?;
+
+    let (mut uploaded, mut downloaded, mut unchanged, mut conflicts, mut failed) = (0, 0, 0, 0, 0);
+    let mut bytes = 0u64;
+    for (rel, step) in steps {
+        let local_stat = local_files.get(&rel).copied();
+        let remote_stat = remote_files.get(&rel).copied();
+        let step = match step {
+            Step::Compare => {
+                let (Some(l), Some(r)) = (local_stat, remote_stat) else {
+                    continue;
+                };
+                let path = args.local.join(&rel);
+                let local_hash = tokio::task::spawn_blocking(move || hash::sha256_file(&path)).await??;
+                if remote_hashes.get(&rel) == Some(&local_hash) {
+                    unchanged += 1;
+                    continue;
+                }
+                newer(l, r, args.direction)
+            }
+            step => step,
+        };
+        let result = match step {
+            Step::Conflict => {
+                println!("  conflict  {}", rel);
+                conflicts += 1;
+                continue;
+            }
+            Step::Upload => {
+                let stat = local_stat.context("missing local file")?;
+                println!("  push      {}", rel);
+                uploaded += 1;
+                if args.dry_run {
+                    bytes += stat.size;
+                    continue;
+                }
+                remote
+                    .upload(&args.local.join(&rel), &rel, stat, remote_stat)
+                    .await
+                    .map(|sent| bytes += sent)
+            }
+            Step::Download => {
+                let stat = remote_stat.context("missing remote file")?;
+                println!("  pull      {}", rel);
+                downloaded += 1;
+                bytes += stat.size;
+                if args.dry_run {
+                    continue;
+                }
+                remote.download(&rel, &args.local.join(&rel), stat).await
+            }
+            Step::Compare => unreachable!(),
+        };
+        if let Err(e) = result {
+            eprintln!("  failed    {}: {:#}", rel, e);
+            failed += 1;
+        }
+    }
+    unchanged += local_files
+        .iter()
+        .filter(|(rel, stat)| remote_files.get(*rel) == Some(stat))
+        .count();
+
+    println!(
+        "{}pushed {}, pulled {} ({}), skipped {} unchanged, {} conflicts, {} failed",
+        if args.dry_run { "dry run: " } else { "" },
+        uploaded,
+        downloaded,
+        humansize::format_size(bytes, humansize::BINARY),
+        unchanged,
+        conflicts,
+        failed
+    );
+    Ok(failed)
+}
+
+/// 定期扫描本地目录，把写完的新文件和修改过的文件推送到远端，直到 Ctrl+C
+///
+/// 相机、录屏等程序会边写边增长文件，连续两次扫描大小和修改时间都不变才认为写完
+async fn watch(args: &SyncArgs, remote: &Remote) -> anyhow::Result<()> {
+    // 远端已有的版本；上传成功后更新，失败的文件下一轮重试
+    let mut uploaded = remote.list().await?;
+    let mut previous = BTreeMap::new();
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(args.interval));
+    println!("watching {} (Ctrl+C to stop)", args.local.display());
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+        let current = match list_local(&args.local) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("scan {} failed: {:#}", args.local.display(), e);
+                continue;
+            }
+        };
+        for (rel, stat) in &current {
+            if uploaded.get(rel) == Some(stat) || previous.get(rel) != Some(stat) {
+                continue;
+            }
+            let result = remote
+                .upload(&args.local.join(rel), rel, *stat, uploaded.get(rel).copied())
+                .await;
+            match result {
+                Ok(sent) if sent < stat.size => {
+                    println!(
+                        "  push      {} ({}, delta {})",
+                        rel,
+                        humansize::format_size(stat.size, humansize::BINARY),
+                        humansize::format_size(sent, humansize::BINARY)
+                    );
+                    uploaded.insert(rel.clone(), *stat);
+                }
+                Ok(_) => {
+                    println!(
+                        "  push      {} ({})",
+                        rel,
+                        humansize::format_size(stat.size, humansize::BINARY)
+                    );
+                    uploaded.insert(rel.clone(), *stat);
+                }
+                Err(e) => eprintln!("  failed    {}: {:#}", rel, e),
+            }
+        }
+        previous = current;
+    }
+}
+
+/// 需要处理的文件及初步动作；两边相同或方向不允许的不出现在结果中
+fn diff(
+    local: &BTreeMap<String, Stat>,
+    remote: &BTreeMap<String, Stat>,
+    direction: Direction,
+) -> Vec<(String, Step)> {
+    let push = direction != Direction::Pull;
+    let pull = direction != Direction::Push;
+    let mut steps = Vec::new();
+    for (rel, l) in local {
+        match remote.get(rel) {
+            None if push => steps.push((rel.clone(), Step::Upload)),
+            None => {}
+            Some(r) if r == l => {}
+            Some(r) if r.size == l.size => steps.push((rel.clone(), Step::Compare)),
+            Some(r) => steps.push((rel.clone(), newer(*l, *r, direction))),
+        }
+    }
+    if pull {
+        steps.extend(
+            remote
+                .keys()
+                .filter(|rel| !local.contains_key(*rel))
+                .map(|rel| (rel.clone(), Step::Download)),
+        );
+    }
+    steps.sort_by(|a, b| a.0.cmp(&b.0));
+    steps
+}
+
+/// 两边内容不同时以哪边为准
+fn newer(local: Stat, remote: Stat, direction: Direction) -> Step {
+    match direction {
+        Direction::Push => Step::Upload,
+        Direction::Pull => Step::Download,
+        Direction::Both if local.modified > remote.modified => Step::Upload,
+        Direction::Both if local.modified < remote.modified => Step::Download,
+        Direction::Both => Step::Conflict,
+    }
+}
+
+/// 拆出服务地址和远端目录；网页地址中目录在 `#` 之后
+fn parse_remote(s: &str) -> anyhow::Result<(String, String)> {
+    let mut url = reqwest::Url::parse(s).with_context(|| format!("invalid remote url '{}'", s))?;
+    anyhow::ensure!(
+        matches!(url.scheme(), "http" | "https"),
+        "remote url must start with http:// or https://"
+    );
+    let dir = match url.fragment() {
+        Some(fragment) if !fragment.trim_matches('/').is_empty() => fragment.to_string(),
+        _ => url.path().to_string(),
+    };
+    let dir = percent_encoding::percent_decode_str(&dir)
+        .decode_utf8_lossy()
+        .trim_matches('/')
+        .to_string();
+    url.set_fragment(None);
+    url.set_query(None);
+    url.set_path("");
+    Ok((url.as_str().trim_end_matches('/').to_string(), dir))
+}
+
+/// 本地目录下的所有文件，键为用 `/` 分隔的相对路径
+fn list_local(root: &Path) -> anyhow::Result<BTreeMap<String, Stat>> {
+    let mut files = BTreeMap::new();
+    for entry in walkdir::WalkDir::new(root) {
+        let entry = entry?;
+        if !entry.file_type().is_file() || entry.file_name().to_string_lossy().ends_with(PART_SUFFIX) {
+            continue;
+        }
+        let meta = entry.metadata()?;
+        let rel = entry
+            .path()
+            .strip_prefix(root)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.insert(
+            rel,
+            Stat {
+                size: meta.len(),
+                modified: meta
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0),
+            },
+        );
+    }
+    Ok(files)
+}
+
+impl Remote {
+    /// 相对共享根目录的路径
+    fn path(&self, rel: &str) -> String {
+        if self.dir.is_empty() {
+            rel.to_string()
+        } else {
+            format!("{}/{}", self.dir, rel)
+        }
+    }
+
+    fn encoded(&self, rel: &str) -> String {
+        self.path(rel)
+            .split('/')
+            .map(|seg| {
+                percent_encoding::utf8_percent_encode(seg, percent_encoding::NON_ALPHANUMERIC)
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// 递归列出远端目录；目录不存在时视为空，推送时自动创建
+    async fn list(&self) -> anyhow::Result<BTreeMap<String, Stat>> {
+        let mut files = BTreeMap::new();
+        let prefix = if self.dir.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", self.dir)
+        };
+        let mut pending = vec![self.dir.clone()];
+        while let Some(current) = pending.pop() {
+            let resp = self
+                .client
+                .get(format!("{}/api/files", self.base))
+                .query(&[("path", &current)])
+                .send()
+                .await?;
+            if resp.status() == reqwest::StatusCode::NOT_FOUND && current == self.dir {
+                break;
+            }
+            let list: ListResponse = resp
+                .error_for_status()
+                .with_context(|| format!("list '{}'", current))?
+                .json()
+                .await?;
+            for entry in list.entries {
+                if entry.is_dir {
+                    pending.push(entry.path);
+                } else if let Some(rel) = entry.path.strip_prefix(&prefix) {
+                    files.insert(
+                        rel.to_string(),
+                        Stat {
+                            size: entry.size,
+                            modified: entry.modified.unwrap_or(0),
+                        },
+                    );
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// 批量查询远端文件的 SHA-256，键为相对同步目录的路径；远端已不存在的文件不在结果中
+    async fn checksums(&self, rels: &[&str]) -> anyhow::Result<HashMap<String, String>> {
+        let mut hashes = HashMap::new();
+        for batch in rels.chunks(STAT_BATCH) {
+            let paths: Vec<String> = batch.iter().map(|rel| self.path(rel)).collect();
+            let stats: Vec<PathStat> = self
+                .client
+                .post(format!("{}/api/files/stat", self.base))
+                .json(&serde_json::json!({ "paths": paths, "hash": true }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            for (rel, stat) in batch.iter().zip(stats) {
+                // 服务端按请求顺序返回
+                if let Some(sha256) = stat.sha256 {
+                    hashes.insert(rel.to_string(), sha256);
+                }
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// 先写入同目录的临时文件，完成后改名并设置修改时间；
+    /// 可压缩的文件由服务端 zstd 压缩后发送，reqwest 自动解压
+    async fn download(&self, rel: &str, target: &Path, stat: Stat) -> anyhow::Result<()> {
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut part = target.as_os_str().to_owned();
+        part.push(PART_SUFFIX);
+        let part = PathBuf::from(part);
+
+        let resp = self
+            .client
+            .get(format!("{}/api/download/{}", self.base, self.encoded(rel)))
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut file = tokio::fs::File::create(&part).await?;
+        let mut stream = resp.bytes_stream();
+        let mut received = 0u64;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            received += chunk.len() as u64;
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        drop(file);
+        if received != stat.size {
+            let _ = tokio::fs::remove_file(&part).await;
+            anyhow::bail!("downloaded {} of {} bytes", received, stat.size);
+        }
+        tokio::fs::rename(&part, target).await?;
+        crate::fs::operations::set_modified(target, stat.modified).await?;
+        Ok(())
+    }
+
+    /// 上传本地文件，返回实际发送的内容字节数
+    ///
+    /// 远端已有较大的旧版本时先尝试只发送差量；否则 tus 上传并带上修改时间，
+    /// 远端已有旧版本时先删除，避免按冲突策略改名
+    async fn upload(
+        &self,
+        source: &Path,
+        rel: &str,
+        stat: Stat,
+        existing: Option<Stat>,
+    ) -> anyhow::Result<u64> {
+        use base64::Engine;
+        let b64 = |s: &str| base64::engine::general_purpose::STANDARD.encode(s);
+
+        if existing.is_some_and(|e| e.size >= DELTA_MIN_SIZE && stat.size >= DELTA_MIN_SIZE) {
+            if let Some(sent) = self.upload_delta(source, rel, stat).await? {
+                return Ok(sent);
+            }
+        }
+        let path = self.path(rel);
+        if existing.is_some() {
+            self.client
+                .post(format!("{}/api/files/delete", self.base))
+                .json(&serde_json::json!({ "paths": [path] }))
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+        let (dir, name) = path.rsplit_once('/').unwrap_or(("", path.as_str()));
+        let resp = self
+            .client
+            .post(format!("{}/api/upload", self.base))
+            .header("Tus-Resumable", "1.0.0")
+            .header("Upload-Length", stat.size.to_string())
+            .header(
+                "Upload-Metadata",
+                format!(
+                    "filename {},targetDir {},lastModified {}",
+                    b64(name),
+                    b64(dir),
+                    b64(&stat.modified.to_string())
+                ),
+            )
+            .send()
+            .await?
+            .error_for_status()?;
+        // 旧版本服务端不在创建响应中声明 zstd，按原样发送
+        let compress = self.zstd
+            && encoding::accepts_zstd(resp.headers())
+            && !mime::is_compressed(&mime::guess_mime(source));
+        let location = resp
+            .headers()
+            .get("location")
+            .and_then(|v| v.to_str().ok())
+            .context("missing Location header")?
+            .to_string();
+
+        let mut file = tokio::fs::File::open(source).await?;
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut offset = 0u64;
+        // 空文件也要发一次 PATCH 才会落盘
+        loop {
+            let n = read_full(&mut file, &mut buf).await?;
+            let chunk = buf[..n].to_vec();
+            let request = self
+                .client
+                .patch(format!("{}{}", self.base, location))
+                .header("Tus-Resumable", "1.0.0")
+                .header("Upload-Offset", offset.to_string())
+                .header("Content-Type", "application/offset+octet-stream");
+            // 每个分块单独压缩，服务端按解压后的字节推进 Upload-Offset
+            let request = if compress && n > 0 {
+                let compressed = tokio::task::spawn_blocking(move || {
+                    zstd::bulk::compress(&chunk, encoding::ZSTD_LEVEL)
+                })
+                .await??;
+                request
+                    .header("Content-Encoding", encoding::ZSTD)
+                    .body(compressed)
+            } else {
+                request.body(chunk)
+            };
+            request
+                .send()
+                .await?
+                .error_for_status()?;
+            offset += n as u64;
+            if n < buf.len() {
+                break;
+            }
+        }
+        anyhow::ensure!(
+            offset == stat.size,
+            "file changed while uploading ({} of {} bytes)",
+            offset,
+            stat.size
+        );
+        Ok(offset)
+    }
+
+    /// 取远端旧文件的分块签名，只发送本地文件中对不上的部分；远端不支持时返回 None
+    async fn upload_delta(&self, source: &Path, rel: &str, stat: Stat) -> anyhow::Result<Option<u64>> {
+        let url = format!("{}/api/delta/{}", self.base, self.encoded(rel));
+        let resp = self.client.get(&url).send().await?;
+        // 旧版本服务端没有这个接口，未知路径返回网页
+        let is_json = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("application/json"));
+        if resp.status() == reqwest::StatusCode::NOT_FOUND || (resp.status().is_success() && !is_json) {
+            return Ok(None);
+        }
+        let resp = resp.error_for_status()?;
+        let etag = resp
+            .headers()
+            .get(reqwest::header::ETAG)
+            .cloned()
+            .context("missing ETag header")?;
+        let compress = self.zstd
+            && encoding::accepts_zstd(resp.headers())
+            && !mime::is_compressed(&mime::guess_mime(source));
+        let sig: delta::Signature = resp.json().await?;
+
+        // 差量在 blocking 线程中边读边生成，经通道流式发送
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        let path = source.to_path_buf();
+        let task = tokio::task::spawn_blocking(move || {
+            let mut writer = ChannelWriter {
+                tx: tx.clone(),
+                buf: Vec::new(),
+            };
+            let result = std::fs::File::open(&path)
+                .and_then(|file| delta::delta(&sig, std::io::BufReader::new(file), &mut writer))
+                .and_then(|stats| writer.flush().map(|()| stats));
+            if let Err(e) = &result {
+                // 让请求以错误结束，而不是被服务端当作截断的差量
+                let _ = tx.blocking_send(Err(std::io::Error::new(e.kind(), e.to_string())));
+            }
+            result
+        });
+        let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
+        let request = self
+            .client
+            .post(&url)
+            .query(&[("modified", stat.modified)])
+            .header(reqwest::header::IF_MATCH, etag);
+        let request = if compress {
+            let encoder = ZstdEncoder::with_quality(
+                tokio_util::io::StreamReader::new(stream),
+                Level::Precise(encoding::ZSTD_LEVEL),
+            );
+            request
+                .header(reqwest::header::CONTENT_ENCODING, encoding::ZSTD)
+                .body(reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::new(encoder)))
+        } else {
+            request.body(reqwest::Body::wrap_stream(stream))
+        };
+
+        let resp = request.send().await;
+        let stats = task.await?;
+        let resp = match resp {
+            Ok(resp) => resp,
+            Err(e) => {
+                // 本地读取失败时请求也会失败，优先报告本地原因
+                stats?;
+                return Err(e.into());
+            }
+        };
+        resp.error_for_status()
+            .context("remote file changed or rejected the delta")?;
+        Ok(Some(stats?.literal))
+    }
+}
+
+/// 把同步写入攒成块后经通道发送，供 blocking 线程生成流式请求体
+struct ChannelWriter {
+    tx: tokio::sync::mpsc::Sender<std::io::Result<bytes::Bytes>>,
+    buf: Vec<u8>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= 256 * 1024 {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = bytes::Bytes::from(std::mem::take(&mut self.buf));
+        self.tx
+            .blocking_send(Ok(chunk))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "request aborted"))
+    }
+}
+
+/// 读满 buf 或到文件末尾
+async fn read_full(file: &mut tokio::fs::File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = file.read(&mut buf[filled..]).await?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_remote() {
+        assert_eq!(
+            parse_remote("http://192.168.1.10:8080/photos/2024/").unwrap(),
+            ("http://192.168.1.10:8080".to_string(), "photos/2024".to_string())
+        );
+        assert_eq!(
+            parse_remote("https://nas.local/#/%E7%85%A7%E7%89%87").unwrap(),
+            ("https://nas.local".to_string(), "照片".to_string())
+        );
+        assert_eq!(parse_remote("http://host:8080").unwrap().1, "");
+        assert!(parse_remote("ftp://host/").is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let stat = |size, modified| Stat { size, modified };
+        let local = BTreeMap::from([
+            ("same".to_string(), stat(1, 10)),
+            ("new-local".to_string(), stat(1, 10)),
+            ("touched".to_string(), stat(5, 20)),
+            ("edited".to_string(), stat(6, 30)),
+        ]);
+        let remote = BTreeMap::from([
+            ("same".to_string(), stat(1, 10)),
+            ("new-remote".to_string(), stat(1, 10)),
+            ("touched".to_string(), stat(5, 10)),
+            ("edited".to_string(), stat(7, 40)),
+        ]);
+        assert_eq!(
+            diff(&local, &remote, Direction::Both),
+            vec![
+                ("edited".to_string(), Step::Download),
+                ("new-local".to_string(), Step::Upload),
+                ("new-remote".to_string(), Step::Download),
+                ("touched".to_string(), Step::Compare),
+            ]
+        );
+        assert_eq!(
+            diff(&local, &remote, Direction::Push),
+            vec![
+                ("edited".to_string(), Step::Upload),
+                ("new-local".to_string(), Step::Upload),
+                ("touched".to_string(), Step::Compare),
+            ]
+        );
+        assert_eq!(newer(stat(1, 5), stat(2, 5), Direction::Both), Step::Conflict);
+    }
+}
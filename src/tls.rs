@@ -1,6 +1,13 @@
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::path::Path;
 use std::sync::Arc;
 
+use sha2::{Digest, Sha256};
+
+/// 自签名证书的有效期
+const SELF_SIGNED_DAYS: i64 = 3650;
+
 /// 从 PEM 文件加载 rustls ServerConfig
 pub fn load_rustls_config(
     cert_path: &Path,
@@ -18,9 +25,95 @@ pub fn load_rustls_config(
     let key = rustls_pemfile::private_key(&mut BufReader::new(key_file))?
         .ok_or_else(|| anyhow::anyhow!("no private key found in {}", key_path.display()))?;
 
-    let config = rustls::ServerConfig::builder()
+    // 自签名证书在浏览器中告警时，可以对照日志中的指纹确认没有被中间人替换
+    if let Some(leaf) = certs.first() {
+        tracing::info!(cert = %cert_path.display(), sha256 = %fingerprint(leaf), "TLS enabled");
+    }
+
+    // reqwest 启用了 ring，与 rustls 默认的 aws-lc-rs 同时存在时无法自动选择，这里明确指定
+    let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+    let config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
         .with_no_client_auth()
         .with_single_cert(certs, key)?;
 
     Ok(Arc::new(config))
 }
+
+/// 生成自签名证书，名称包括 localhost、回环地址和给出的 IP
+pub fn generate_self_signed(cert_path: &Path, key_path: &Path, ips: &[IpAddr]) -> anyhow::Result<()> {
+    use rcgen::{CertificateParams, DnType, KeyPair, SanType};
+
+    let mut params = CertificateParams::new(vec!["localhost".to_string()])?;
+    params.distinguished_name.push(DnType::CommonName, "FileTransfer");
+    for ip in [IpAddr::V4(Ipv4Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST)]
+        .into_iter()
+        .chain(ips.iter().copied())
+    {
+        if !params.subject_alt_names.contains(&SanType::IpAddress(ip)) {
+            params.subject_alt_names.push(SanType::IpAddress(ip));
+        }
+    }
+    let now = time::OffsetDateTime::now_utc();
+    params.not_before = now - time::Duration::days(1);
+    params.not_after = now + time::Duration::days(SELF_SIGNED_DAYS);
+
+    let key = KeyPair::generate()?;
+    let cert = params.self_signed(&key)?;
+
+    for path in [cert_path, key_path] {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+    }
+    std::fs::write(cert_path, cert.pem())?;
+    write_key_file(key_path, &key.serialize_pem())?;
+    Ok(())
+}
+
+/// 证书 DER 的 SHA-256，冒号分隔的大写 hex，与浏览器证书详情中的格式一致
+fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(unix)]
+fn write_key_file(path: &Path, key: &str) -> std::io::Result<()> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(key.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_key_file(path: &Path, key: &str) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    file.write_all(key.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_self_signed() {
+        let dir = std::env::temp_dir().join(format!("tls-test-{}", uuid::Uuid::new_v4().simple()));
+        let cert = dir.join("cert.pem");
+        let key = dir.join("key.pem");
+        generate_self_signed(&cert, &key, &[IpAddr::from([192, 168, 1, 20])]).unwrap();
+        assert!(load_rustls_config(&cert, &key).is_ok());
+        // 已有的私钥不会被覆盖
+        assert!(generate_self_signed(&cert, &key, &[]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,16 +1,53 @@
 use std::time::Duration;
 
+use crate::fs::path_safety::relative_path;
+use crate::hooks::HookEvent;
+use crate::preview::thumbnail;
 use crate::state::AppState;
 
-/// 启动后台清理任务，定期清理过期的上传会话
+/// 启动后台清理任务，定期清理过期的上传会话、孤立的分片文件、回收站中过期的条目和图片缩放缓存
 pub fn spawn(state: AppState) {
+    let period = Duration::from_secs(state.config.janitor_interval_secs.max(60));
+    let orphan_age = Duration::from_secs(state.config.orphan_max_age_secs);
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(3600));
+        let mut interval = tokio::time::interval(period);
         loop {
             interval.tick().await;
             let cleaned = state.upload_manager.cleanup_expired().await;
-            if cleaned > 0 {
-                tracing::info!(count = cleaned, "cleaned expired upload sessions");
+            if !cleaned.is_empty() {
+                tracing::info!(count = cleaned.len(), "cleaned expired upload sessions");
+            }
+            for session in cleaned {
+                state.hooks.fire(
+                    HookEvent::SessionExpired,
+                    serde_json::json!({
+                        "file_id": session.file_id,
+                        "filename": session.filename,
+                        "relative_path": session.relative_path,
+                        "dir": relative_path(&session.target_dir, &state.root),
+                        "size": session.total_size,
+                        "uploaded": session.uploaded,
+                        "client_ip": session.client_ip,
+                    }),
+                );
+            }
+            let orphans = state.upload_manager.sweep_orphans(orphan_age).await;
+            if orphans > 0 {
+                tracing::info!(count = orphans, "removed orphaned upload files");
+            }
+            match state.trash.expire().await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(count, "purged expired trash"),
+                Err(e) => tracing::warn!(error = %e, "failed to purge expired trash"),
+            }
+            let thumbs = state.upload_manager.tmp_dir().join("thumbs");
+            let pruned = thumbnail::prune(&thumbs, thumbnail::CACHE_MAX_AGE).await;
+            if pruned > 0 {
+                tracing::info!(count = pruned, "pruned resized image cache");
+            }
+            state.fetch_manager.prune_finished(24 * 3600);
+            if let Some(ls) = &state.localsend {
+                ls.sender.prune_finished(24 * 3600);
             }
         }
     });
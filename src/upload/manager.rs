@@ -6,13 +6,24 @@ use std::time::Duration;
 
 use tokio::sync::RwLock;
 
+use crate::db::now_secs;
 use crate::upload::session::UploadSession;
+use crate::util::locks::{KeyGuard, KeyedLocks};
+
+/// 同名会话超过该时长没有写入即视为已放弃，不再阻止新的上传
+const ABANDONED_AFTER: Duration = Duration::from_secs(10 * 60);
 
 /// 管理所有上传会话
 pub struct UploadManager {
     sessions: parking_lot::RwLock<HashMap<String, Arc<RwLock<UploadSession>>>>,
     tmp_dir: PathBuf,
     expiration: Duration,
+    /// 按最终目标路径加锁，同名文件的落盘依次进行
+    target_locks: KeyedLocks<PathBuf>,
+    /// 按 file_id 加锁，同一会话的分块写入和 finalize 不会并发
+    session_locks: KeyedLocks<String>,
+    /// 串行化会话创建，同名检查和登记之间不会插入其他创建
+    create_lock: tokio::sync::Mutex<()>,
 }
 
 impl UploadManager {
@@ -21,6 +32,9 @@ impl UploadManager {
             sessions: parking_lot::RwLock::new(HashMap::new()),
             tmp_dir,
             expiration,
+            target_locks: KeyedLocks::new(),
+            session_locks: KeyedLocks::new(),
+            create_lock: tokio::sync::Mutex::new(()),
         }
     }
 
@@ -32,11 +46,35 @@ impl UploadManager {
         self.expiration
     }
 
+    /// 独占目标路径，直到 guard 被释放
+    pub async fn lock_target(&self, path: PathBuf) -> KeyGuard<'_, PathBuf> {
+        self.target_locks.lock(path).await
+    }
+
+    /// 独占上传会话；已有请求在写入时返回 None
+    pub fn try_lock_session(&self, file_id: &str) -> Option<KeyGuard<'_, String>> {
+        self.session_locks.try_lock(file_id.to_string())
+    }
+
     /// 创建新的上传会话
-    pub fn create(&self, session: UploadSession) -> Arc<RwLock<UploadSession>> {
+    ///
+    /// 同一目标路径上已有未完成、且最近写入过的会话时不创建，返回那个会话的快照
+    pub async fn create(&self, session: UploadSession) -> Result<Arc<RwLock<UploadSession>>, UploadSession> {
+        let _guard = self.create_lock.lock().await;
+        let target = session.final_path();
+        let now = now_secs();
+        let all: Vec<Arc<RwLock<UploadSession>>> = self.sessions.read().values().cloned().collect();
+        for arc in all {
+            let other = arc.read().await;
+            let active = now.saturating_sub(other.last_active) < ABANDONED_AFTER.as_secs();
+            if active && !other.is_complete() && other.final_path() == target {
+                return Err(other.clone());
+            }
+        }
+
         let arc = Arc::new(RwLock::new(session.clone()));
         self.sessions.write().insert(session.file_id.clone(), arc.clone());
-        arc
+        Ok(arc)
     }
 
     /// 获取上传会话
@@ -49,6 +87,27 @@ impl UploadManager {
         self.sessions.write().remove(file_id);
     }
 
+    /// 当前所有会话的快照
+    pub async fn list(&self) -> Vec<UploadSession> {
+        let all: Vec<Arc<RwLock<UploadSession>>> = self.sessions.read().values().cloned().collect();
+        let mut out = Vec::with_capacity(all.len());
+        for arc in all {
+            out.push(arc.read().await.clone());
+        }
+        out
+    }
+
+    /// 取消会话并删除其分片和 meta；会话不存在时返回 false
+    pub async fn cancel(&self, file_id: &str) -> bool {
+        let Some(arc) = self.sessions.write().remove(file_id) else {
+            return false;
+        };
+        let session = arc.read().await;
+        let _ = tokio::fs::remove_file(session.part_path(&self.tmp_dir)).await;
+        let _ = tokio::fs::remove_file(session.meta_path(&self.tmp_dir)).await;
+        true
+    }
+
     /// 启动时恢复未完成的上传会话
     pub async fn boot_recover(&self) -> anyhow::Result<usize> {
         let mut count = 0;
@@ -86,26 +145,73 @@ impl UploadManager {
         Ok(count)
     }
 
-    /// 清理过期的会话
-    pub async fn cleanup_expired(&self) -> usize {
+    /// 清理临时区中没有对应会话的 .part / .meta / .sealed（例如损坏的 meta、异常退出留下的分片）
+    pub async fn sweep_orphans(&self, max_age: Duration) -> usize {
+        let Ok(mut entries) = tokio::fs::read_dir(&self.tmp_dir).await else {
+            return 0;
+        };
+        let now = std::time::SystemTime::now();
+        let mut removed = 0;
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            let is_upload_file = matches!(
+                path.extension().and_then(OsStr::to_str),
+                Some("part" | "meta" | "sealed")
+            );
+            if !is_upload_file {
+                continue;
+            }
+            let Some(file_id) = path.file_stem().and_then(OsStr::to_str) else {
+                continue;
+            };
+            // 远程抓取的临时文件由 FetchManager 管理
+            if file_id.starts_with("fetch-") || self.sessions.read().contains_key(file_id) {
+                continue;
+            }
+
+            let idle = entry
+                .metadata()
+                .await
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| now.duration_since(t).ok())
+                .unwrap_or_default();
+            if idle < max_age {
+                continue;
+            }
+
+            if tokio::fs::remove_file(&path).await.is_ok() {
+                tracing::info!(path = %path.display(), "removed orphaned upload file");
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// 清理过期的会话，返回被清理的会话
+    pub async fn cleanup_expired(&self) -> Vec<UploadSession> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs();
         let expiry = self.expiration.as_secs();
 
-        let expired: Vec<String> = {
-            let sessions = self.sessions.read();
-            let mut ids = Vec::new();
-            for (id, arc) in sessions.iter() {
-                let s = arc.blocking_read();
-                if now.saturating_sub(s.last_active) > expiry {
-                    ids.push(id.clone());
-                }
+        // 运行时内不能 blocking_read，先复制出列表再逐个 await
+        let all: Vec<(String, Arc<RwLock<UploadSession>>)> = self
+            .sessions
+            .read()
+            .iter()
+            .map(|(id, arc)| (id.clone(), arc.clone()))
+            .collect();
+        let mut expired = Vec::new();
+        for (id, arc) in all {
+            if now.saturating_sub(arc.read().await.last_active) > expiry {
+                expired.push(id);
             }
-            ids
-        };
+        }
 
+        let mut cleaned = Vec::with_capacity(expired.len());
         for id in &expired {
             let arc = {
                 self.sessions.write().remove(id)
@@ -115,9 +221,48 @@ impl UploadManager {
                 let _ = tokio::fs::remove_file(s.part_path(&self.tmp_dir)).await;
                 let _ = tokio::fs::remove_file(s.meta_path(&self.tmp_dir)).await;
                 tracing::info!(file_id = %id, "cleaned expired upload session");
+                cleaned.push(s.clone());
             }
         }
 
-        expired.len()
+        cleaned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(file_id: &str, filename: &str) -> UploadSession {
+        let now = now_secs();
+        UploadSession {
+            file_id: file_id.to_string(),
+            filename: filename.to_string(),
+            relative_path: None,
+            target_dir: PathBuf::from("/share"),
+            total_size: 100,
+            uploaded: 40,
+            created_at: now,
+            last_active: now,
+            expected_checksum: None,
+            mime_hint: None,
+            client_ip: None,
+            modified: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_rejects_same_target() {
+        let manager = UploadManager::new(PathBuf::from("/tmp"), Duration::from_secs(3600));
+        assert!(manager.create(session("a", "x.iso")).await.is_ok());
+        assert!(manager.create(session("b", "y.iso")).await.is_ok());
+
+        let other = manager.create(session("c", "x.iso")).await.unwrap_err();
+        assert_eq!(other.file_id, "a");
+        assert_eq!(other.uploaded, 40);
+
+        // 长时间没有写入的会话不再占用目标
+        manager.get("a").unwrap().write().await.last_active -= ABANDONED_AFTER.as_secs();
+        assert!(manager.create(session("d", "x.iso")).await.is_ok());
     }
 }
@@ -1,4 +1,7 @@
 pub mod janitor;
 pub mod manager;
+pub mod remote;
 pub mod session;
+pub mod subdir;
+pub mod untar;
 pub mod writer;
@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures_util::StreamExt;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::util::ip;
+
+/// 远程抓取任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchStatus {
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// 服务端从远程 URL 下载到共享目录的任务
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchTask {
+    pub id: String,
+    pub url: String,
+    pub filename: String,
+    /// 完成后的相对路径
+    pub path: Option<String>,
+    pub total: Option<u64>,
+    pub downloaded: u64,
+    pub status: FetchStatus,
+    pub error: Option<String>,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+    /// 发起任务的认证用户，只在服务端用于检查查询和取消
+    #[serde(skip)]
+    pub creator: Option<String>,
+    /// 保存到的目录，查询和取消时据此检查权限
+    #[serde(skip)]
+    pub target_dir: PathBuf,
+}
+
+struct FetchEntry {
+    task: Arc<parking_lot::Mutex<FetchTask>>,
+    cancel: CancellationToken,
+}
+
+/// 管理所有远程抓取任务
+pub struct FetchManager {
+    tasks: parking_lot::RwLock<HashMap<String, FetchEntry>>,
+    client: reqwest::Client,
+    allow: AddrFilter,
+    tmp_dir: PathBuf,
+}
+
+/// 下载完成后把临时文件（路径、大小）放进共享目录，返回相对 root 的路径
+///
+/// 由调用方按上传的冲突策略、文件名规则和插件检查落盘
+pub type Placer =
+    Box<dyn FnOnce(PathBuf, u64) -> Pin<Box<dyn Future<Output = Result<String, String>> + Send>> + Send>;
+
+/// 启动抓取所需的参数
+pub struct FetchRequest {
+    pub url: reqwest::Url,
+    pub filename: String,
+    pub target_dir: PathBuf,
+    /// 0 = 无限制
+    pub max_size: u64,
+    pub creator: Option<String>,
+    pub place: Placer,
+}
+
+/// 判断目标地址是否允许连接
+type AddrFilter = fn(IpAddr) -> bool;
+
+/// 跟随重定向的最大次数
+const MAX_REDIRECTS: usize = 10;
+
+impl FetchManager {
+    pub fn new(tmp_dir: PathBuf) -> Self {
+        Self::with_filter(tmp_dir, ip::is_public)
+    }
+
+    fn with_filter(tmp_dir: PathBuf, allow: AddrFilter) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(std::time::Duration::from_secs(15))
+            // 按原样保存对方返回的内容，并保留 Content-Length 用于显示进度
+            .no_zstd()
+            // 域名只会解析到检查过的地址，代理会绕过这一检查
+            .dns_resolver(Arc::new(GuardedResolver { allow }))
+            .no_proxy()
+            .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("too many redirects");
+                }
+                match check_url_with(attempt.url(), allow) {
+                    Ok(()) => attempt.follow(),
+                    Err(e) => attempt.error(e),
+                }
+            }))
+            .build()
+            .expect("build http client");
+        Self {
+            tasks: parking_lot::RwLock::new(HashMap::new()),
+            client,
+            allow,
+            tmp_dir,
+        }
+    }
+
+    /// 创建任务并在后台开始下载
+    pub fn start(&self, req: FetchRequest) -> FetchTask {
+        let id = uuid::Uuid::new_v4().to_string().replace('-', "");
+        let task = FetchTask {
+            id: id.clone(),
+            url: req.url.to_string(),
+            filename: req.filename.clone(),
+            path: None,
+            total: None,
+            downloaded: 0,
+            status: FetchStatus::Running,
+            error: None,
+            created_at: now_secs(),
+            finished_at: None,
+            creator: req.creator.clone(),
+            target_dir: req.target_dir.clone(),
+        };
+        let shared = Arc::new(parking_lot::Mutex::new(task.clone()));
+        let cancel = CancellationToken::new();
+        self.tasks.write().insert(
+            id.clone(),
+            FetchEntry {
+                task: shared.clone(),
+                cancel: cancel.clone(),
+            },
+        );
+
+        let (client, allow) = (self.client.clone(), self.allow);
+        let part_path = self.tmp_dir.join(format!("fetch-{}.part", id));
+        let FetchRequest { url, max_size, place, .. } = req;
+        tokio::spawn(async move {
+            let downloaded = tokio::select! {
+                r = run_fetch(&client, allow, &url, max_size, &part_path, &shared) => r,
+                _ = cancel.cancelled() => Err(FetchError::Cancelled),
+            };
+            // 落盘不随取消中断，避免留下占位的空文件
+            let result = match downloaded {
+                Ok(size) => place(part_path.clone(), size).await.map_err(FetchError::Place),
+                Err(e) => Err(e),
+            };
+
+            let mut task = shared.lock();
+            task.finished_at = Some(now_secs());
+            match result {
+                Ok(rel) => {
+                    tracing::info!(id = %task.id, url = %task.url, path = %rel, "remote fetch done");
+                    task.status = FetchStatus::Done;
+                    task.path = Some(rel);
+                }
+                Err(e) => {
+                    let _ = std::fs::remove_file(&part_path);
+                    task.status = match e {
+                        FetchError::Cancelled => FetchStatus::Cancelled,
+                        _ => FetchStatus::Failed,
+                    };
+                    tracing::warn!(id = %task.id, url = %task.url, error = %e, "remote fetch failed");
+                    task.error = Some(e.to_string());
+                }
+            }
+        });
+
+        task
+    }
+
+    pub fn get(&self, id: &str) -> Option<FetchTask> {
+        self.tasks.read().get(id).map(|e| e.task.lock().clone())
+    }
+
+    /// 进行中的抓取任务数
+    pub fn running(&self) -> usize {
+        self.tasks
+            .read()
+            .values()
+            .filter(|e| e.task.lock().status == FetchStatus::Running)
+            .count()
+    }
+
+    pub fn list(&self) -> Vec<FetchTask> {
+        let mut list: Vec<FetchTask> = self
+            .tasks
+            .read()
+            .values()
+            .map(|e| e.task.lock().clone())
+            .collect();
+        list.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+        list
+    }
+
+    /// 取消运行中的任务，或移除已结束的任务
+    pub fn cancel(&self, id: &str) -> bool {
+        let mut tasks = self.tasks.write();
+        let Some(entry) = tasks.get(id) else {
+            return false;
+        };
+        if entry.task.lock().status == FetchStatus::Running {
+            entry.cancel.cancel();
+        } else {
+            tasks.remove(id);
+        }
+        true
+    }
+
+    /// 清理结束超过 max_age 秒的任务记录
+    pub fn prune_finished(&self, max_age: u64) -> usize {
+        let now = now_secs();
+        let mut tasks = self.tasks.write();
+        let before = tasks.len();
+        tasks.retain(|_, e| {
+            let t = e.task.lock();
+            t.finished_at
+                .map(|f| now.saturating_sub(f) <= max_age)
+                .unwrap_or(true)
+        });
+        before - tasks.len()
+    }
+}
+
+/// 只放行公网地址的 DNS 解析器，防止通过域名访问内网（SSRF）
+struct GuardedResolver {
+    allow: AddrFilter,
+}
+
+impl reqwest::dns::Resolve for GuardedResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let allow = self.allow;
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|a| allow(a.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} does not resolve to a public address", host).into());
+            }
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// 检查抓取地址：只允许 http/https，且 IP 字面量主机必须是公网地址
+pub fn check_url(url: &reqwest::Url) -> Result<(), String> {
+    check_url_with(url, ip::is_public)
+}
+
+fn check_url_with(url: &reqwest::Url, allow: AddrFilter) -> Result<(), String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("only http/https urls are supported".into());
+    }
+    let host = url.host_str().ok_or("url has no host")?;
+    // 域名在解析时由 GuardedResolver 过滤
+    let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() else {
+        return Ok(());
+    };
+    if allow(ip) {
+        Ok(())
+    } else {
+        Err(format!("address {} is not allowed", ip))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum FetchError {
+    #[error("cancelled")]
+    Cancelled,
+    #[error("remote responded {0}")]
+    Status(reqwest::StatusCode),
+    #[error("file exceeds size limit")]
+    TooLarge,
+    #[error("{0}")]
+    Blocked(String),
+    #[error("{0}")]
+    Place(String),
+    #[error("{0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+}
+
+async fn run_fetch(
+    client: &reqwest::Client,
+    allow: AddrFilter,
+    url: &reqwest::Url,
+    max_size: u64,
+    part_path: &Path,
+    task: &parking_lot::Mutex<FetchTask>,
+) -> Result<u64, FetchError> {
+    check_url_with(url, allow).map_err(FetchError::Blocked)?;
+    let resp = client.get(url.clone()).send().await?;
+    if !resp.status().is_success() {
+        return Err(FetchError::Status(resp.status()));
+    }
+
+    let total = resp.content_length();
+    if let Some(len) = total {
+        if max_size > 0 && len > max_size {
+            return Err(FetchError::TooLarge);
+        }
+    }
+    task.lock().total = total;
+
+    let mut file = tokio::io::BufWriter::with_capacity(
+        1024 * 1024,
+        tokio::fs::File::create(part_path).await?,
+    );
+    let mut stream = resp.bytes_stream();
+    let mut downloaded: u64 = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        downloaded += chunk.len() as u64;
+        if max_size > 0 && downloaded > max_size {
+            return Err(FetchError::TooLarge);
+        }
+        file.write_all(&chunk).await?;
+        task.lock().downloaded = downloaded;
+    }
+    file.flush().await?;
+    file.get_ref().sync_data().await?;
+    drop(file);
+
+    Ok(downloaded)
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_rejects_internal_literal_hosts() {
+        for url in [
+            "http://127.0.0.1/",
+            "http://[::1]:8080/a",
+            "http://169.254.169.254/latest/meta-data/",
+            "https://10.0.0.1/",
+            "ftp://example.com/",
+        ] {
+            assert!(check_url(&url.parse().unwrap()).is_err(), "{url}");
+        }
+        assert!(check_url(&"https://example.com/a.zip".parse().unwrap()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_redirect_to_blocked_address_fails() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut sock, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = sock.read(&mut buf).await;
+                let resp = format!(
+                    "HTTP/1.1 302 Found\r\nLocation: http://127.0.0.2:{}/secret\r\nContent-Length: 0\r\n\r\n",
+                    port
+                );
+                let _ = sock.write_all(resp.as_bytes()).await;
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let manager = FetchManager::with_filter(dir.path().to_path_buf(), |ip| {
+            ip == IpAddr::from([127, 0, 0, 1])
+        });
+        let task = manager.start(FetchRequest {
+            url: format!("http://127.0.0.1:{}/file", port).parse().unwrap(),
+            filename: "file".into(),
+            target_dir: dir.path().join("out"),
+            max_size: 0,
+            creator: None,
+            place: Box::new(|_, _| Box::pin(async { Err("unreachable".to_string()) })),
+        });
+        let mut task = task;
+        for _ in 0..100 {
+            task = manager.get(&task.id).unwrap();
+            if task.status != FetchStatus::Running {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(task.status, FetchStatus::Failed);
+        assert!(task.error.unwrap().contains("redirect"));
+        assert!(!dir.path().join("out").exists());
+    }
+}
@@ -1,7 +1,10 @@
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
+use crate::fs::path_safety::PathSafety;
+
 /// 上传会话，持久化为 .meta JSON 文件
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadSession {
@@ -15,6 +18,11 @@ pub struct UploadSession {
     pub last_active: u64,
     pub expected_checksum: Option<String>,
     pub mime_hint: Option<String>,
+    #[serde(default)]
+    pub client_ip: Option<IpAddr>,
+    /// 客户端提供的修改时间 (Unix 秒)，落盘后设置到最终文件上
+    #[serde(default)]
+    pub modified: Option<u64>,
 }
 
 impl UploadSession {
@@ -40,6 +48,19 @@ impl UploadSession {
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
     }
 
+    /// 文件最终所在目录：目标目录加上 relative_path 的父目录部分（已清洗 .. 等）
+    pub fn final_dir(&self) -> PathBuf {
+        match self.relative_path.as_deref() {
+            Some(rel) => self.target_dir.join(PathSafety::relative_dir(rel)),
+            None => self.target_dir.clone(),
+        }
+    }
+
+    /// 落盘时的目标路径 (按冲突策略改名之前)
+    pub fn final_path(&self) -> PathBuf {
+        self.final_dir().join(&self.filename)
+    }
+
     pub fn is_complete(&self) -> bool {
         self.uploaded >= self.total_size
     }
@@ -0,0 +1,76 @@
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+/// 上传文件自动归档到的子目录
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadSubdir {
+    /// 直接保存到目标目录
+    #[default]
+    None,
+    /// `YYYY/MM/DD/`（UTC）
+    Date,
+    /// 按客户端：已认证时为用户名，否则为 IP
+    Client,
+}
+
+impl UploadSubdir {
+    /// 相对目标目录的子目录，None 模式返回 None
+    pub fn path(
+        self,
+        now: OffsetDateTime,
+        ip: Option<IpAddr>,
+        user: Option<&str>,
+    ) -> Option<PathBuf> {
+        match self {
+            Self::None => None,
+            Self::Date => Some(
+                [
+                    format!("{:04}", now.year()),
+                    format!("{:02}", u8::from(now.month())),
+                    format!("{:02}", now.day()),
+                ]
+                .iter()
+                .collect(),
+            ),
+            Self::Client => {
+                let name = match (user, ip) {
+                    (Some(user), _) => sanitize_filename::sanitize(user),
+                    // IPv6 中的 ':' 在 Windows 上不能用作文件名
+                    (None, Some(ip)) => ip.to_string().replace(':', "-"),
+                    (None, None) => "unknown".to_string(),
+                };
+                Some(PathBuf::from(if name.is_empty() { "unknown".into() } else { name }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subdir_path() {
+        // 2024-03-07 23:59 UTC
+        let now = OffsetDateTime::from_unix_timestamp(1_709_855_940).unwrap();
+        let ip: IpAddr = "fe80::1".parse().unwrap();
+
+        assert_eq!(UploadSubdir::None.path(now, Some(ip), None), None);
+        assert_eq!(
+            UploadSubdir::Date.path(now, None, None),
+            Some(PathBuf::from("2024/03/07"))
+        );
+        assert_eq!(
+            UploadSubdir::Client.path(now, Some(ip), None),
+            Some(PathBuf::from("fe80--1"))
+        );
+        assert_eq!(
+            UploadSubdir::Client.path(now, Some(ip), Some("../alice")),
+            Some(PathBuf::from("..alice"))
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use tokio::sync::mpsc;
+
+use crate::archive::normalize;
+
+/// 从上传的 tar 流中解出的一项；文件已完整写入临时区，由接收方放到目标位置
+#[derive(Debug)]
+pub enum Extracted {
+    Dir {
+        /// 规范化后的内部路径（以 / 分隔）
+        name: String,
+    },
+    File {
+        name: String,
+        tmp: PathBuf,
+        size: u64,
+        modified: Option<u64>,
+        mode: Option<u32>,
+    },
+    /// 不安全的路径、链接和设备文件等，不解出
+    Skipped { name: String, reason: String },
+}
+
+/// 顺序读取 tar 流，逐项写入临时区并交给 tx（同步，需放在 blocking 线程执行）
+///
+/// tx 一次只缓冲少量条目，接收方处理不过来时读取会暂停，临时区只保留正在处理的几个文件。
+/// 接收方断开时提前结束；`max_size` 为单个文件的大小上限 (0 = 无限制)
+pub fn extract(
+    reader: impl Read,
+    gzip: bool,
+    tmp_dir: &Path,
+    max_size: u64,
+    tx: &mpsc::Sender<Extracted>,
+) -> io::Result<()> {
+    if gzip {
+        unpack(tar::Archive::new(flate2::read::GzDecoder::new(reader)), tmp_dir, max_size, tx)
+    } else {
+        unpack(tar::Archive::new(reader), tmp_dir, max_size, tx)
+    }
+}
+
+fn unpack<R: Read>(
+    mut archive: tar::Archive<R>,
+    tmp_dir: &Path,
+    max_size: u64,
+    tx: &mpsc::Sender<Extracted>,
+) -> io::Result<()> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let raw_name = entry.path()?.to_string_lossy().to_string();
+        let header = entry.header();
+        let kind = header.entry_type();
+        let size = header.size()?;
+
+        let item = match normalize(&raw_name) {
+            None if kind.is_dir() && !raw_name.contains("..") => continue, // ./ 这样的根目录条目
+            None => Extracted::Skipped {
+                name: raw_name,
+                reason: "unsafe path".into(),
+            },
+            Some(name) if kind.is_dir() => Extracted::Dir { name },
+            Some(name) if !kind.is_file() && !kind.is_gnu_sparse() => Extracted::Skipped {
+                name,
+                reason: format!("unsupported entry type {:?}", kind),
+            },
+            Some(name) if max_size > 0 && size > max_size => Extracted::Skipped {
+                name,
+                reason: format!("file exceeds the {} byte upload limit", max_size),
+            },
+            Some(name) => {
+                let modified = header.mtime().ok();
+                let mode = header.mode().ok().map(|m| m & 0o777);
+                let tmp = tmp_dir.join(format!("untar-{}.part", uuid::Uuid::new_v4().simple()));
+                if let Err(e) = write_tmp(&mut entry, &tmp) {
+                    let _ = std::fs::remove_file(&tmp);
+                    return Err(e);
+                }
+                Extracted::File {
+                    name,
+                    tmp,
+                    size,
+                    modified,
+                    mode,
+                }
+            }
+        };
+        if let Err(mpsc::error::SendError(item)) = tx.blocking_send(item) {
+            if let Extracted::File { tmp, .. } = item {
+                let _ = std::fs::remove_file(tmp);
+            }
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn write_tmp(entry: &mut impl Read, tmp: &Path) -> io::Result<()> {
+    let mut file = BufWriter::with_capacity(1024 * 1024, File::create(tmp)?);
+    io::copy(entry, &mut file)?;
+    file.flush()?;
+    file.into_inner().map_err(|e| e.into_error())?.sync_data()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn append(builder: &mut tar::Builder<Vec<u8>>, kind: tar::EntryType, name: &str, data: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(kind);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o755);
+        header.set_mtime(1_700_000_000);
+        // set_path 会拒绝 ..，直接写入名称字段模拟恶意归档
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_cksum();
+        builder.append(&header, data).unwrap();
+    }
+
+    #[test]
+    fn test_extract_entries() {
+        let mut builder = tar::Builder::new(Vec::new());
+        append(&mut builder, tar::EntryType::Directory, "./", b"");
+        append(&mut builder, tar::EntryType::Directory, "docs/", b"");
+        append(&mut builder, tar::EntryType::Regular, "docs/a.txt", b"hello");
+        append(&mut builder, tar::EntryType::Regular, "../evil.txt", b"x");
+        append(&mut builder, tar::EntryType::Symlink, "link", b"");
+        append(&mut builder, tar::EntryType::Regular, "big.bin", &[1; 64]);
+        let data = builder.into_inner().unwrap();
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let (tx, mut rx) = mpsc::channel(16);
+        extract(&data[..], false, dir.path(), 32, &tx).unwrap();
+        drop(tx);
+
+        let mut items = Vec::new();
+        while let Ok(item) = rx.try_recv() {
+            items.push(item);
+        }
+        assert_eq!(items.len(), 5);
+        assert!(matches!(&items[0], Extracted::Dir { name } if name == "docs"));
+        let Extracted::File { name, tmp, size, modified, mode } = &items[1] else {
+            panic!("expected file, got {:?}", items[1]);
+        };
+        assert_eq!((name.as_str(), *size, *modified, *mode), ("docs/a.txt", 5, Some(1_700_000_000), Some(0o755)));
+        assert_eq!(std::fs::read(tmp).unwrap(), b"hello");
+        assert!(matches!(&items[2], Extracted::Skipped { reason, .. } if reason == "unsafe path"));
+        assert!(matches!(&items[3], Extracted::Skipped { name, .. } if name == "link"));
+        assert!(matches!(&items[4], Extracted::Skipped { name, .. } if name == "big.bin"));
+    }
+}
@@ -6,9 +6,20 @@ use tokio::io::{AsyncSeekExt, AsyncWriteExt, BufWriter};
 
 const BUF_CAPACITY: usize = 4 * 1024 * 1024; // 4MB
 
+/// 按该粒度检测全零块；不小于常见文件系统的块大小，空洞才真正省下空间
+const HOLE_BLOCK: u64 = 64 * 1024;
+
 /// 流式分块写入器
+///
+/// 写到文件原有长度之外的全零块不落盘，留作空洞，磁盘镜像这类大部分为空的文件上传后仍是稀疏的
 pub struct ChunkWriter {
     inner: BufWriter<tokio::fs::File>,
+    /// 已交给 inner 的数据的结束位置
+    written: u64,
+    /// 推迟写入的零字节数，位于 written 之后
+    zeros: u64,
+    /// 打开时的文件长度；此前的内容可能是回退进度后残留的旧数据，零块必须实际写入覆盖
+    initial_len: u64,
 }
 
 impl ChunkWriter {
@@ -22,23 +33,102 @@ impl ChunkWriter {
             .await?;
 
         let mut file = file;
+        let initial_len = file.metadata().await?.len();
         if offset > 0 {
             file.seek(SeekFrom::Start(offset)).await?;
         }
 
         Ok(Self {
             inner: BufWriter::with_capacity(BUF_CAPACITY, file),
+            written: offset,
+            zeros: 0,
+            initial_len,
         })
     }
 
     /// 写入数据
-    pub async fn write_all(&mut self, data: &[u8]) -> std::io::Result<()> {
-        self.inner.write_all(data).await
+    pub async fn write_all(&mut self, mut data: &[u8]) -> std::io::Result<()> {
+        while !data.is_empty() {
+            // 按文件内的绝对位置切到 HOLE_BLOCK 边界
+            let pos = self.written + self.zeros;
+            let n = ((HOLE_BLOCK - pos % HOLE_BLOCK) as usize).min(data.len());
+            let (block, rest) = data.split_at(n);
+            if self.written >= self.initial_len && is_zero(block) {
+                self.zeros += n as u64;
+            } else {
+                self.write_zeros().await?;
+                self.inner.write_all(block).await?;
+                self.written += n as u64;
+            }
+            data = rest;
+        }
+        Ok(())
+    }
+
+    /// 把推迟的零字节落到文件中：足够长时跳过形成空洞，否则照常写入
+    async fn write_zeros(&mut self) -> std::io::Result<()> {
+        if self.zeros == 0 {
+            return Ok(());
+        }
+        if self.zeros >= HOLE_BLOCK {
+            self.written += self.zeros;
+            self.inner.seek(SeekFrom::Start(self.written)).await?;
+        } else {
+            self.inner.write_all(&[0; HOLE_BLOCK as usize][..self.zeros as usize]).await?;
+            self.written += self.zeros;
+        }
+        self.zeros = 0;
+        Ok(())
     }
 
     /// flush + sync_data (仅同步数据，不同步 metadata)
+    ///
+    /// 末尾的空洞用 set_len 补齐，返回后文件长度总是等于已写入的位置
     pub async fn flush_data(&mut self) -> std::io::Result<()> {
+        let hole = self.zeros >= HOLE_BLOCK;
+        self.write_zeros().await?;
         self.inner.flush().await?;
+        if hole {
+            self.inner.get_ref().set_len(self.written).await?;
+        }
         self.inner.get_ref().sync_data().await
     }
 }
+
+fn is_zero(data: &[u8]) -> bool {
+    // 按 16 字节比较，编译器能向量化
+    let (chunks, tail) = data.split_at(data.len() / 16 * 16);
+    chunks
+        .chunks_exact(16)
+        .all(|c| u128::from_ne_bytes(c.try_into().unwrap()) == 0)
+        && tail.iter().all(|&b| b == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_zero_blocks_become_holes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("disk.img.part");
+
+        // 旧数据上写零必须实际覆盖
+        std::fs::write(&path, vec![0xAAu8; 100_000]).unwrap();
+        let mut writer = ChunkWriter::open(&path, 0).await.unwrap();
+        let zeros = vec![0u8; 3000];
+        for _ in 0..1000 {
+            writer.write_all(&zeros).await.unwrap();
+        }
+        writer.write_all(b"tail").await.unwrap();
+        writer.write_all(&vec![0u8; 200_000]).await.unwrap();
+        writer.flush_data().await.unwrap();
+        drop(writer);
+
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(data.len(), 3_200_004);
+        assert!(data[..3_000_000].iter().all(|&b| b == 0));
+        assert_eq!(&data[3_000_000..3_000_004], b"tail");
+        assert!(data[3_000_004..].iter().all(|&b| b == 0));
+    }
+}
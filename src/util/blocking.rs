@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::error::AppError;
+
+/// 每个 worker 允许排队等待的任务数，超过后直接返回 503
+const QUEUE_PER_WORKER: usize = 8;
+
+/// CPU 密集任务（哈希、图片缩放、解压）的执行池
+///
+/// 任务在 tokio 的 blocking 线程上运行，同时执行的数量受 worker 数限制，
+/// 排队过长时拒绝新任务，避免大量并发传输时拖慢整个服务
+pub struct BlockingPool {
+    permits: Arc<Semaphore>,
+    workers: usize,
+    pending: AtomicUsize,
+    max_pending: usize,
+}
+
+impl BlockingPool {
+    /// workers 为 0 时取 CPU 核数
+    pub fn new(workers: usize) -> Self {
+        let workers = if workers == 0 {
+            std::thread::available_parallelism().map_or(4, |n| n.get())
+        } else {
+            workers
+        };
+        Self {
+            permits: Arc::new(Semaphore::new(workers)),
+            workers,
+            pending: AtomicUsize::new(0),
+            max_pending: workers * QUEUE_PER_WORKER,
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// 正在执行和排队中的任务数
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
+    }
+
+    /// 占用一个 worker 名额，用于自行管理的 CPU 密集步骤
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, AppError> {
+        if self.pending.fetch_add(1, Ordering::AcqRel) >= self.max_pending {
+            self.pending.fetch_sub(1, Ordering::AcqRel);
+            return Err(AppError::Busy);
+        }
+        let _pending = PendingGuard(&self.pending);
+        self.permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("blocking pool closed: {}", e)))
+    }
+
+    /// 在 blocking 线程上执行 f
+    pub async fn run<F, R>(&self, f: F) -> Result<R, AppError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let permit = self.acquire().await?;
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            f()
+        })
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("blocking task: {}", e)))
+    }
+}
+
+/// 排队计数在拿到名额或放弃等待时归还
+struct PendingGuard<'a>(&'a AtomicUsize);
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rejects_when_queue_full() {
+        let pool = BlockingPool {
+            permits: Arc::new(Semaphore::new(1)),
+            workers: 1,
+            pending: AtomicUsize::new(0),
+            max_pending: 1,
+        };
+        assert_eq!(pool.run(|| 1 + 1).await.unwrap(), 2);
+
+        let held = pool.acquire().await.unwrap();
+        // 唯一的名额被占用，排队上限为 1：第一个等待者排队，第二个被拒绝
+        let waiting = pool.acquire();
+        tokio::pin!(waiting);
+        assert!(futures_util::poll!(&mut waiting).is_pending());
+        assert!(matches!(pool.acquire().await, Err(AppError::Busy)));
+
+        drop(held);
+        assert!(waiting.await.is_ok());
+        assert_eq!(pool.pending(), 0);
+    }
+}
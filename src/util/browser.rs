@@ -0,0 +1,30 @@
+use std::process::{Command, Stdio};
+
+/// 用系统默认浏览器打开 URL；失败只记录日志，不影响服务
+pub fn open(url: &str) {
+    let mut cmd = if cfg!(target_os = "windows") {
+        // start 的第一个带引号参数是窗口标题
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    let spawned = cmd
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match spawned {
+        // 不等待浏览器进程退出，在后台回收避免留下僵尸进程
+        Ok(mut child) => {
+            std::thread::spawn(move || {
+                let _ = child.wait();
+            });
+        }
+        Err(e) => tracing::warn!(error = %e, %url, "failed to open browser"),
+    }
+}
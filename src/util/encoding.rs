@@ -0,0 +1,93 @@
+use std::io;
+
+use async_compression::tokio::bufread::ZstdDecoder;
+use async_compression::zstd::DParameter;
+use axum::body::Body;
+use axum::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH};
+use axum::http::HeaderMap;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::{StreamExt, TryStreamExt};
+use tokio_util::io::{ReaderStream, StreamReader};
+
+use crate::error::AppError;
+
+/// 实例之间传输时使用的请求体编码；服务端在响应头 Accept-Encoding 中声明支持（RFC 7694）
+pub const ZSTD: &str = "zstd";
+
+/// 压缩级别，兼顾局域网下的 CPU 开销
+pub const ZSTD_LEVEL: i32 = 3;
+
+/// 解压窗口上限（8 MiB），防止恶意请求占用大量内存；默认级别压缩的窗口远小于此
+const MAX_WINDOW_LOG: u32 = 23;
+
+/// 对方响应头的 Accept-Encoding 是否声明了 zstd，没有声明时按原样发送
+pub fn accepts_zstd(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|e| e.trim().eq_ignore_ascii_case(ZSTD))
+}
+
+/// 请求体未经编码时的 Content-Length，即解码后的原始长度；压缩或分块传输时未知
+pub fn identity_length(headers: &HeaderMap) -> Option<u64> {
+    let encoding = headers.get(CONTENT_ENCODING).and_then(|v| v.to_str().ok());
+    if encoding.is_some_and(|e| !e.eq_ignore_ascii_case("identity")) {
+        return None;
+    }
+    headers.get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+/// 按 Content-Encoding 解码请求体，得到原始内容的字节流
+pub fn decode_body(
+    headers: &HeaderMap,
+    body: Body,
+) -> Result<BoxStream<'static, io::Result<Bytes>>, AppError> {
+    let stream = body.into_data_stream().map_err(io::Error::other);
+    match headers.get(CONTENT_ENCODING).and_then(|v| v.to_str().ok()) {
+        None => Ok(stream.boxed()),
+        Some(encoding) if encoding.eq_ignore_ascii_case("identity") => Ok(stream.boxed()),
+        Some(encoding) if encoding.eq_ignore_ascii_case(ZSTD) => {
+            let decoder = ZstdDecoder::with_params(
+                StreamReader::new(stream),
+                &[DParameter::window_log_max(MAX_WINDOW_LOG)],
+            );
+            Ok(ReaderStream::with_capacity(decoder, 256 * 1024).boxed())
+        }
+        Some(encoding) => Err(AppError::BadRequest(format!(
+            "unsupported Content-Encoding: {}",
+            encoding
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn decode(encoding: Option<&str>, body: Vec<u8>) -> Result<Vec<u8>, AppError> {
+        let mut headers = HeaderMap::new();
+        if let Some(encoding) = encoding {
+            headers.insert(CONTENT_ENCODING, encoding.parse().unwrap());
+        }
+        let mut stream = decode_body(&headers, Body::from(body))?;
+        let mut out = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            out.extend_from_slice(&chunk?);
+        }
+        Ok(out)
+    }
+
+    #[tokio::test]
+    async fn test_decode_body() {
+        let data = b"hello hello hello hello".repeat(1000);
+        assert_eq!(decode(None, data.clone()).await.unwrap(), data);
+        let compressed = zstd::bulk::compress(&data, ZSTD_LEVEL).unwrap();
+        assert!(compressed.len() < data.len());
+        assert_eq!(decode(Some("zstd"), compressed).await.unwrap(), data);
+        assert!(decode(Some("zstd"), data.clone()).await.is_err());
+        assert!(decode(Some("br"), data).await.is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use std::io::Read;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+/// 计算文件的 SHA-256（同步，需放在 blocking 线程执行）
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    sha256_reader(std::fs::File::open(path)?)
+}
+
+/// 计算读取到的全部内容的 SHA-256（同步）
+pub fn sha256_reader<R: Read>(mut file: R) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// 解析客户端给出的校验值，支持 `sha256 <hex>`、`sha256:<hex>` 和裸 hex
+pub fn parse_sha256(value: &str) -> Option<String> {
+    let value = value.trim();
+    let hex = value
+        .strip_prefix("sha256")
+        .map(|rest| rest.trim_start_matches([' ', ':']))
+        .unwrap_or(value);
+    (hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| hex.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sha256() {
+        let hex = "a".repeat(64);
+        assert_eq!(parse_sha256(&hex).as_deref(), Some(hex.as_str()));
+        assert_eq!(parse_sha256(&format!("sha256 {}", hex)).as_deref(), Some(hex.as_str()));
+        assert_eq!(parse_sha256(&format!("sha256:{}", hex.to_uppercase())).as_deref(), Some(hex.as_str()));
+        assert_eq!(parse_sha256("md5 abc"), None);
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
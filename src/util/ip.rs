@@ -1,5 +1,12 @@
 use std::net::{IpAddr, Ipv4Addr, UdpSocket};
 
+/// 某块网卡上的一个地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanAddr {
+    pub interface: String,
+    pub ip: IpAddr,
+}
+
 /// 通过 UDP socket 探测本机局域网 IP
 pub fn get_local_ip() -> Option<String> {
     let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
@@ -12,3 +19,107 @@ pub fn get_local_ip() -> Option<String> {
         _ => None,
     }
 }
+
+/// 所有已启用网卡上的 IPv4 局域网地址（排除回环和链路本地）
+///
+/// 私有地址的普通网卡排在前面，VPN 这类点对点隧道排在后面；同类中默认路由所在的地址优先
+pub fn lan_addrs() -> Vec<LanAddr> {
+    let Ok(interfaces) = if_addrs::get_if_addrs() else {
+        return Vec::new();
+    };
+    let preferred = get_local_ip().and_then(|ip| ip.parse::<IpAddr>().ok());
+    let mut addrs: Vec<(bool, LanAddr)> = interfaces
+        .into_iter()
+        .filter(|i| i.ip().is_ipv4() && !i.is_loopback() && !i.is_link_local())
+        .filter(|i| i.oper_status != if_addrs::IfOperStatus::Down)
+        .map(|i| {
+            let p2p = i.is_p2p();
+            (p2p, LanAddr { ip: i.ip(), interface: i.name })
+        })
+        .collect();
+    addrs.sort_by_key(|(p2p, a)| {
+        let private = matches!(a.ip, IpAddr::V4(ip) if ip.is_private());
+        (*p2p, !private, Some(a.ip) != preferred)
+    });
+    addrs.into_iter().map(|(_, a)| a).collect()
+}
+
+/// 指定网卡上的地址，IPv4 在前；找不到网卡时报错并列出可用的网卡
+pub fn interface_addrs(name: &str) -> anyhow::Result<Vec<IpAddr>> {
+    let interfaces = if_addrs::get_if_addrs()?;
+    let mut addrs: Vec<IpAddr> = interfaces
+        .iter()
+        .filter(|i| i.name == name)
+        .map(|i| i.ip())
+        .collect();
+    if addrs.is_empty() {
+        let mut names: Vec<&str> = interfaces.iter().map(|i| i.name.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        anyhow::bail!("network interface '{}' not found or has no address (available: {})", name, names.join(", "));
+    }
+    addrs.sort_by_key(|ip| !ip.is_ipv4());
+    Ok(addrs)
+}
+
+/// 是否为公网地址；回环、私有、链路本地（含云元数据 169.254.169.254）、保留网段等返回 false
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_unspecified()
+                || v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0
+                // 100.64.0.0/10 运营商级 NAT
+                || (a == 100 && (b & 0xc0) == 64)
+                // 240.0.0.0/4 保留
+                || a >= 240)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_unspecified()
+                || v6.is_loopback()
+                || v6.is_multicast()
+                // fc00::/7 唯一本地地址
+                || (first & 0xfe00) == 0xfc00
+                // fe80::/10 链路本地
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_excludes_internal_ranges() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["93.184.216.34", "1.1.1.1", "2606:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+}
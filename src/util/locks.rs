@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// 按 key 分别加锁的异步互斥表，key 不再被持有或等待时自动移除
+pub struct KeyedLocks<K> {
+    map: parking_lot::Mutex<HashMap<K, Arc<Mutex<()>>>>,
+}
+
+impl<K: Eq + Hash + Clone> KeyedLocks<K> {
+    pub fn new() -> Self {
+        Self {
+            map: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 等待获取 key 对应的锁
+    pub async fn lock(&self, key: K) -> KeyGuard<'_, K> {
+        let mutex = self.entry(&key);
+        let guard = mutex.lock_owned().await;
+        KeyGuard {
+            locks: self,
+            key,
+            guard: Some(guard),
+        }
+    }
+
+    /// 不等待；已被占用时返回 None
+    pub fn try_lock(&self, key: K) -> Option<KeyGuard<'_, K>> {
+        let mutex = self.entry(&key);
+        match mutex.try_lock_owned() {
+            Ok(guard) => Some(KeyGuard {
+                locks: self,
+                key,
+                guard: Some(guard),
+            }),
+            Err(_) => {
+                self.release(&key);
+                None
+            }
+        }
+    }
+
+    fn entry(&self, key: &K) -> Arc<Mutex<()>> {
+        self.map
+            .lock()
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(Mutex::new(())))
+            .clone()
+    }
+
+    /// 只剩表本身持有时移除
+    fn release(&self, key: &K) {
+        let mut map = self.map.lock();
+        if map.get(key).is_some_and(|m| Arc::strong_count(m) == 1) {
+            map.remove(key);
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.map.lock().len()
+    }
+}
+
+impl<K: Eq + Hash + Clone> Default for KeyedLocks<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct KeyGuard<'a, K: Eq + Hash + Clone> {
+    locks: &'a KeyedLocks<K>,
+    key: K,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl<K: Eq + Hash + Clone> Drop for KeyGuard<'_, K> {
+    fn drop(&mut self) {
+        self.guard.take();
+        self.locks.release(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_keyed_locks() {
+        let locks = KeyedLocks::new();
+        let a = locks.lock("a").await;
+        assert!(locks.try_lock("a").is_none());
+        let b = locks.try_lock("b");
+        assert!(b.is_some());
+        assert_eq!(locks.len(), 2);
+
+        drop(a);
+        drop(b);
+        assert_eq!(locks.len(), 0);
+        assert!(locks.try_lock("a").is_some());
+    }
+}
@@ -0,0 +1,221 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use time::{Duration, OffsetDateTime, Time};
+
+/// 日志文件按时间轮转的周期 (UTC)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    /// 只按大小轮转
+    Never,
+    Hourly,
+    #[default]
+    Daily,
+}
+
+impl LogRotation {
+    /// `t` 所在周期的结束时间
+    fn period_end(self, t: OffsetDateTime) -> Option<OffsetDateTime> {
+        match self {
+            Self::Never => None,
+            Self::Hourly => Some(t.replace_time(Time::from_hms(t.hour(), 0, 0).ok()?) + Duration::HOUR),
+            Self::Daily => Some(t.replace_time(Time::MIDNIGHT) + Duration::DAY),
+        }
+    }
+}
+
+/// 按时间和大小轮转的日志文件
+///
+/// 始终写入 `path`；轮转时把它改名为 `<名称>.<YYYYMMDD-HHMMSS>.<扩展名>`，
+/// 并只保留最新的 `keep` 个旧文件
+pub struct RollingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    /// 单个文件的大小上限 (字节, 0 = 不按大小轮转)
+    max_size: u64,
+    /// 保留的旧文件数 (0 = 全部保留)
+    keep: usize,
+    file: File,
+    size: u64,
+    next_roll: Option<OffsetDateTime>,
+}
+
+impl RollingFile {
+    pub fn open(path: PathBuf, rotation: LogRotation, max_size: u64, keep: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = open_append(&path)?;
+        let meta = file.metadata()?;
+        let now = OffsetDateTime::now_utc();
+        // 续写上次运行留下的文件，周期从它最后一次写入算起
+        let last_write = meta.modified().map(OffsetDateTime::from).unwrap_or(now);
+        let mut this = Self {
+            path,
+            rotation,
+            max_size,
+            keep,
+            file,
+            size: meta.len(),
+            next_roll: rotation.period_end(last_write.min(now)),
+        };
+        if this.due(now, 0) {
+            this.roll(now);
+        }
+        Ok(this)
+    }
+
+    fn due(&self, now: OffsetDateTime, incoming: u64) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let too_big = self.max_size > 0 && self.size + incoming > self.max_size;
+        too_big || self.next_roll.is_some_and(|t| now >= t)
+    }
+
+    /// 轮转失败时继续写当前文件，到下一个周期或下一次达到上限时再试
+    fn roll(&mut self, now: OffsetDateTime) {
+        if let Err(e) = self.try_roll(now) {
+            eprintln!("log rotation of {} failed: {}", self.path.display(), e);
+        }
+        self.size = 0;
+        self.next_roll = self.rotation.period_end(now);
+    }
+
+    fn try_roll(&mut self, now: OffsetDateTime) -> io::Result<()> {
+        self.file.flush()?;
+        let target = self.rolled_name(now);
+        std::fs::rename(&self.path, &target)?;
+        self.file = open_append(&self.path)?;
+        if self.keep > 0 {
+            self.prune()?;
+        }
+        Ok(())
+    }
+
+    fn rolled_name(&self, now: OffsetDateTime) -> PathBuf {
+        let (stem, ext) = self.name_parts();
+        let stamp = format!(
+            "{:04}{:02}{:02}-{:02}{:02}{:02}",
+            now.year(),
+            u8::from(now.month()),
+            now.day(),
+            now.hour(),
+            now.minute(),
+            now.second()
+        );
+        // 同一秒内多次按大小轮转时追加序号
+        (0..)
+            .map(|n| {
+                let stamp = if n == 0 { stamp.clone() } else { format!("{}-{}", stamp, n) };
+                self.path.with_file_name(format!("{}.{}{}", stem, stamp, ext))
+            })
+            .find(|p| !p.exists())
+            .expect("unbounded range")
+    }
+
+    /// 文件名拆成主名和带点的扩展名：`transfer.log` -> (`transfer`, `.log`)
+    fn name_parts(&self) -> (String, String) {
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let ext = self
+            .path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        (stem, ext)
+    }
+
+    /// 删除超出保留数量的旧文件，按修改时间从新到旧保留
+    fn prune(&self) -> io::Result<()> {
+        let (stem, ext) = self.name_parts();
+        let prefix = format!("{}.", stem);
+        let dir = match self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            Some(dir) => dir,
+            None => Path::new("."),
+        };
+
+        let mut rolled: Vec<(SystemTime, PathBuf)> = std::fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                let name = e.file_name();
+                let name = name.to_string_lossy();
+                name.strip_prefix(&prefix)
+                    .and_then(|rest| rest.strip_suffix(ext.as_str()))
+                    .is_some_and(|stamp| stamp.len() >= 15 && stamp.bytes().take(8).all(|b| b.is_ascii_digit()))
+            })
+            .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+            .collect();
+        rolled.sort_by(|a, b| b.cmp(a));
+        for (_, path) in rolled.into_iter().skip(self.keep) {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.due(OffsetDateTime::now_utc(), buf.len() as u64) {
+            self.roll(OffsetDateTime::now_utc());
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Date, Month};
+
+    fn utc(day: u8, h: u8, m: u8, s: u8) -> OffsetDateTime {
+        Date::from_calendar_date(2026, Month::October, day)
+            .unwrap()
+            .with_hms(h, m, s)
+            .unwrap()
+            .assume_utc()
+    }
+
+    #[test]
+    fn test_period_end() {
+        let t = utc(15, 9, 41, 7);
+        assert_eq!(LogRotation::Hourly.period_end(t), Some(utc(15, 10, 0, 0)));
+        assert_eq!(LogRotation::Daily.period_end(t), Some(utc(16, 0, 0, 0)));
+        assert_eq!(LogRotation::Never.period_end(t), None);
+    }
+
+    #[test]
+    fn test_size_rotation_and_retention() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("logs/transfer.log");
+        let mut log = RollingFile::open(path.clone(), LogRotation::Never, 100, 2).unwrap();
+        let line = [b'x'; 39].iter().chain(b"\n").copied().collect::<Vec<_>>();
+        for _ in 0..10 {
+            log.write_all(&line).unwrap();
+        }
+        log.flush().unwrap();
+
+        // 每个文件最多两行，10 行共轮转 4 次，只留下 2 个旧文件
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 80);
+        let mut names: Vec<String> = std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 3);
+        assert!(names.iter().all(|n| n.starts_with("transfer.") && n.ends_with(".log")));
+    }
+}
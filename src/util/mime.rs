@@ -1,8 +1,75 @@
 use std::path::Path;
 
+use serde::Serialize;
+
 /// 根据文件路径猜测 MIME 类型
 pub fn guess_mime(path: &Path) -> String {
     mime_guess::from_path(path)
         .first_or_octet_stream()
         .to_string()
 }
+
+/// 本身已经压缩过的格式（图片、音视频、压缩包、Office 文档），再压缩几乎没有收益
+pub fn is_compressed(mime: &str) -> bool {
+    const TYPES: [&str; 15] = [
+        "video/",
+        "audio/",
+        "application/zip",
+        "application/gzip",
+        "application/x-gzip",
+        "application/x-bzip2",
+        "application/x-xz",
+        "application/zstd",
+        "application/x-7z-compressed",
+        "application/vnd.rar",
+        "application/x-rar-compressed",
+        "application/java-archive",
+        "application/epub+zip",
+        "application/vnd.openxmlformats-officedocument.",
+        "application/vnd.oasis.opendocument.",
+    ];
+    // SVG 是文本，其他图片格式都自带压缩
+    (mime.starts_with("image/") && !mime.starts_with("image/svg+xml"))
+        || TYPES.iter().any(|t| mime.starts_with(t))
+}
+
+/// 文件分类，用于目录统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileCategory {
+    Image,
+    Video,
+    Audio,
+    Document,
+    Archive,
+    Code,
+    Other,
+}
+
+/// 按扩展名和 MIME 类型归类
+pub fn categorize(path: &Path) -> FileCategory {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "zst" | "7z" | "rar" | "iso" => {
+            return FileCategory::Archive
+        }
+        "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "odt" | "ods" | "odp"
+        | "rtf" | "txt" | "md" | "csv" | "epub" => return FileCategory::Document,
+        "rs" | "go" | "py" | "js" | "ts" | "java" | "c" | "h" | "cpp" | "hpp" | "cs" | "rb"
+        | "php" | "sh" | "html" | "css" | "json" | "toml" | "yaml" | "yml" | "xml" | "sql" => {
+            return FileCategory::Code
+        }
+        _ => {}
+    }
+
+    match mime_guess::from_path(path).first() {
+        Some(m) if m.type_() == mime_guess::mime::IMAGE => FileCategory::Image,
+        Some(m) if m.type_() == mime_guess::mime::VIDEO => FileCategory::Video,
+        Some(m) if m.type_() == mime_guess::mime::AUDIO => FileCategory::Audio,
+        _ => FileCategory::Other,
+    }
+}
@@ -1,2 +1,8 @@
+pub mod blocking;
+pub mod browser;
+pub mod encoding;
+pub mod hash;
 pub mod ip;
+pub mod locks;
+pub mod logfile;
 pub mod mime;
@@ -0,0 +1,413 @@
+use std::io;
+use std::num::NonZeroU32;
+use std::time::SystemTime;
+
+use aws_lc_rs::cipher::{EncryptingKey, UnboundCipherKey, AES_256};
+use aws_lc_rs::{hmac, pbkdf2};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const VERIFIER_LEN: usize = 2;
+/// HMAC-SHA1 截取前 10 字节作为认证码
+const AUTH_LEN: usize = 10;
+const PBKDF2_ITERATIONS: u32 = 1000;
+const BLOCK_LEN: usize = 16;
+/// 每次生成的密钥流长度
+const KEYSTREAM_LEN: usize = 64 * 1024;
+
+/// 加密方法 99 表示 WinZip AES，实际压缩方式写在 0x9901 扩展字段中
+const METHOD_AES: u16 = 99;
+const METHOD_STORED: u16 = 0;
+const VERSION_NEEDED: u16 = 51;
+/// 高字节 3 = Unix
+const VERSION_MADE_BY: u16 = (3 << 8) | VERSION_NEEDED;
+const FLAG_ENCRYPTED: u16 = 1;
+const FLAG_UTF8: u16 = 1 << 11;
+const EXTRA_ZIP64: u16 = 0x0001;
+const EXTRA_AES: u16 = 0x9901;
+/// Info-ZIP 扩展时间戳：精确到秒的 UTC 修改时间，DOS 时间只有 2 秒精度且没有时区
+const EXTRA_TIMESTAMP: u16 = 0x5455;
+/// AE-2：CRC 字段固定为 0，完整性由 HMAC 保证
+const AES_VENDOR_VERSION: u16 = 2;
+/// 3 = AES-256
+const AES_STRENGTH: u8 = 3;
+
+/// 每个条目在明文之外增加的字节数：salt + 口令校验值 + 认证码
+pub const ENTRY_OVERHEAD: u64 = (SALT_LEN + VERIFIER_LEN + AUTH_LEN) as u64;
+
+/// WinZip AES-256 (AE-2) 加密的流式 ZIP 写入器，条目不压缩
+///
+/// async_zip 不能写加密条目，这里按 APPNOTE 和 WinZip AES 规范直接写出。条目大小需要预先给出，
+/// 这样本地文件头中就是最终的大小，不需要数据描述符；超过 4 GiB 的条目和归档使用 ZIP64
+pub struct AesZipWriter<W> {
+    out: W,
+    password: Vec<u8>,
+    offset: u64,
+    entries: Vec<CentralEntry>,
+}
+
+struct CentralEntry {
+    name: Vec<u8>,
+    time: u16,
+    date: u16,
+    /// Unix 秒，写入扩展时间戳
+    mtime: Option<u32>,
+    size: u64,
+    offset: u64,
+    /// Unix 权限位，写入外部属性的高 16 位
+    mode: u32,
+}
+
+impl<W: AsyncWrite + Unpin> AesZipWriter<W> {
+    pub fn new(out: W, password: &str) -> Self {
+        Self {
+            out,
+            password: password.as_bytes().to_vec(),
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// 开始一个条目；之后必须正好写入 size 字节再调用 `EntryWriter::finish`
+    ///
+    /// mode 为 Unix 权限位，未给出时按 0644 写入
+    pub async fn start_entry(
+        &mut self,
+        name: &str,
+        size: u64,
+        modified: Option<SystemTime>,
+        mode: Option<u32>,
+    ) -> io::Result<EntryWriter<'_, W>> {
+        let (time, date) = dos_datetime(modified.unwrap_or_else(SystemTime::now));
+        let entry = CentralEntry {
+            name: name.as_bytes().to_vec(),
+            time,
+            date,
+            mtime: modified
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs().min(u32::MAX as u64) as u32),
+            size,
+            offset: self.offset,
+            mode: mode.unwrap_or(0o644) & 0o7777,
+        };
+        let compressed = size + ENTRY_OVERHEAD;
+
+        let mut extra = Vec::new();
+        let zip64 = compressed >= u32::MAX as u64;
+        if zip64 {
+            push_u16(&mut extra, EXTRA_ZIP64);
+            push_u16(&mut extra, 16);
+            push_u64(&mut extra, size);
+            push_u64(&mut extra, compressed);
+        }
+        push_aes_extra(&mut extra);
+        push_timestamp_extra(&mut extra, entry.mtime);
+
+        let mut header = Vec::with_capacity(30 + entry.name.len() + extra.len());
+        push_u32(&mut header, 0x04034b50);
+        push_u16(&mut header, VERSION_NEEDED);
+        push_u16(&mut header, FLAG_ENCRYPTED | FLAG_UTF8);
+        push_u16(&mut header, METHOD_AES);
+        push_u16(&mut header, entry.time);
+        push_u16(&mut header, entry.date);
+        push_u32(&mut header, 0);
+        push_u32(&mut header, if zip64 { u32::MAX } else { compressed as u32 });
+        push_u32(&mut header, if zip64 { u32::MAX } else { size as u32 });
+        push_u16(&mut header, entry.name.len() as u16);
+        push_u16(&mut header, extra.len() as u16);
+        header.extend_from_slice(&entry.name);
+        header.extend_from_slice(&extra);
+
+        // 每个条目使用独立的 salt，由口令派生出加密密钥、HMAC 密钥和 2 字节口令校验值
+        let mut salt = [0u8; SALT_LEN];
+        aws_lc_rs::rand::fill(&mut salt).map_err(|_| crypto_error())?;
+        let mut derived = [0u8; KEY_LEN * 2 + VERIFIER_LEN];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA1,
+            NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+            &salt,
+            &self.password,
+            &mut derived,
+        );
+        let cipher = Ctr::new(&derived[..KEY_LEN])?;
+        let mac = hmac::Context::with_key(&hmac::Key::new(
+            hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+            &derived[KEY_LEN..KEY_LEN * 2],
+        ));
+        header.extend_from_slice(&salt);
+        header.extend_from_slice(&derived[KEY_LEN * 2..]);
+
+        self.write(&header).await?;
+        self.entries.push(entry);
+        Ok(EntryWriter {
+            zip: self,
+            cipher,
+            mac,
+            remaining: size,
+        })
+    }
+
+    /// 写出中央目录并 flush
+    pub async fn close(mut self) -> io::Result<()> {
+        let cd_offset = self.offset;
+        let mut cd = Vec::new();
+        for entry in &self.entries {
+            let compressed = entry.size + ENTRY_OVERHEAD;
+            // ZIP64 扩展字段只包含溢出的字段，顺序固定
+            let mut zip64 = Vec::new();
+            if entry.size >= u32::MAX as u64 {
+                push_u64(&mut zip64, entry.size);
+            }
+            if compressed >= u32::MAX as u64 {
+                push_u64(&mut zip64, compressed);
+            }
+            if entry.offset >= u32::MAX as u64 {
+                push_u64(&mut zip64, entry.offset);
+            }
+            let mut extra = Vec::new();
+            if !zip64.is_empty() {
+                push_u16(&mut extra, EXTRA_ZIP64);
+                push_u16(&mut extra, zip64.len() as u16);
+                extra.extend_from_slice(&zip64);
+            }
+            push_aes_extra(&mut extra);
+            push_timestamp_extra(&mut extra, entry.mtime);
+
+            push_u32(&mut cd, 0x02014b50);
+            push_u16(&mut cd, VERSION_MADE_BY);
+            push_u16(&mut cd, VERSION_NEEDED);
+            push_u16(&mut cd, FLAG_ENCRYPTED | FLAG_UTF8);
+            push_u16(&mut cd, METHOD_AES);
+            push_u16(&mut cd, entry.time);
+            push_u16(&mut cd, entry.date);
+            push_u32(&mut cd, 0);
+            push_u32(&mut cd, compressed.min(u32::MAX as u64) as u32);
+            push_u32(&mut cd, entry.size.min(u32::MAX as u64) as u32);
+            push_u16(&mut cd, entry.name.len() as u16);
+            push_u16(&mut cd, extra.len() as u16);
+            push_u16(&mut cd, 0); // 注释
+            push_u16(&mut cd, 0); // 起始磁盘
+            push_u16(&mut cd, 0); // 内部属性
+            push_u32(&mut cd, (0o100000 | entry.mode) << 16);
+            push_u32(&mut cd, entry.offset.min(u32::MAX as u64) as u32);
+            cd.extend_from_slice(&entry.name);
+            cd.extend_from_slice(&extra);
+        }
+        let cd_size = cd.len() as u64;
+        let count = self.entries.len() as u64;
+
+        let mut end = Vec::new();
+        if count >= u16::MAX as u64 || cd_offset >= u32::MAX as u64 || cd_size >= u32::MAX as u64 {
+            let record_offset = cd_offset + cd_size;
+            push_u32(&mut end, 0x06064b50);
+            push_u64(&mut end, 44);
+            push_u16(&mut end, VERSION_MADE_BY);
+            push_u16(&mut end, VERSION_NEEDED);
+            push_u32(&mut end, 0);
+            push_u32(&mut end, 0);
+            push_u64(&mut end, count);
+            push_u64(&mut end, count);
+            push_u64(&mut end, cd_size);
+            push_u64(&mut end, cd_offset);
+            // ZIP64 end of central directory locator
+            push_u32(&mut end, 0x07064b50);
+            push_u32(&mut end, 0);
+            push_u64(&mut end, record_offset);
+            push_u32(&mut end, 1);
+        }
+        push_u32(&mut end, 0x06054b50);
+        push_u16(&mut end, 0);
+        push_u16(&mut end, 0);
+        push_u16(&mut end, count.min(u16::MAX as u64) as u16);
+        push_u16(&mut end, count.min(u16::MAX as u64) as u16);
+        push_u32(&mut end, cd_size.min(u32::MAX as u64) as u32);
+        push_u32(&mut end, cd_offset.min(u32::MAX as u64) as u32);
+        push_u16(&mut end, 0);
+
+        self.write(&cd).await?;
+        self.write(&end).await?;
+        self.out.flush().await
+    }
+
+    async fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.out.write_all(data).await?;
+        self.offset += data.len() as u64;
+        Ok(())
+    }
+}
+
+/// 单个条目的写入器
+pub struct EntryWriter<'a, W> {
+    zip: &'a mut AesZipWriter<W>,
+    cipher: Ctr,
+    mac: hmac::Context,
+    remaining: u64,
+}
+
+impl<W: AsyncWrite + Unpin> EntryWriter<'_, W> {
+    /// 加密并写入一段明文；总长度超过 start_entry 给出的大小时出错
+    pub async fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        if data.len() as u64 > self.remaining {
+            return Err(io::Error::other("file grew while archiving"));
+        }
+        let mut buf = data.to_vec();
+        self.cipher.apply(&mut buf)?;
+        self.mac.update(&buf);
+        self.remaining -= buf.len() as u64;
+        self.zip.write(&buf).await
+    }
+
+    /// 写入认证码；写入的明文少于声明的大小时出错
+    pub async fn finish(self) -> io::Result<()> {
+        if self.remaining != 0 {
+            return Err(io::Error::other("file shrank while archiving"));
+        }
+        let tag = self.mac.sign();
+        self.zip.write(&tag.as_ref()[..AUTH_LEN]).await
+    }
+}
+
+/// WinZip AES 使用的 CTR 模式：计数器从 1 开始、按小端序递增，与常见的大端 CTR 不同，
+/// 所以用 ECB 加密计数器块生成密钥流
+struct Ctr {
+    key: EncryptingKey,
+    counter: u128,
+    keystream: Vec<u8>,
+    used: usize,
+}
+
+impl Ctr {
+    fn new(key: &[u8]) -> io::Result<Self> {
+        let key = UnboundCipherKey::new(&AES_256, key).map_err(|_| crypto_error())?;
+        Ok(Self {
+            key: EncryptingKey::ecb(key).map_err(|_| crypto_error())?,
+            counter: 0,
+            keystream: Vec::new(),
+            used: 0,
+        })
+    }
+
+    fn apply(&mut self, mut data: &mut [u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            if self.used == self.keystream.len() {
+                self.refill()?;
+            }
+            let n = data.len().min(self.keystream.len() - self.used);
+            let (head, rest) = data.split_at_mut(n);
+            for (byte, key) in head.iter_mut().zip(&self.keystream[self.used..]) {
+                *byte ^= key;
+            }
+            self.used += n;
+            data = rest;
+        }
+        Ok(())
+    }
+
+    fn refill(&mut self) -> io::Result<()> {
+        self.keystream.clear();
+        for _ in 0..KEYSTREAM_LEN / BLOCK_LEN {
+            self.counter += 1;
+            self.keystream.extend_from_slice(&self.counter.to_le_bytes());
+        }
+        self.key
+            .encrypt(&mut self.keystream)
+            .map_err(|_| crypto_error())?;
+        self.used = 0;
+        Ok(())
+    }
+}
+
+fn push_aes_extra(extra: &mut Vec<u8>) {
+    push_u16(extra, EXTRA_AES);
+    push_u16(extra, 7);
+    push_u16(extra, AES_VENDOR_VERSION);
+    extra.extend_from_slice(b"AE");
+    extra.push(AES_STRENGTH);
+    push_u16(extra, METHOD_STORED);
+}
+
+/// 本地文件头和中央目录中的格式相同：只带修改时间
+fn push_timestamp_extra(extra: &mut Vec<u8>, mtime: Option<u32>) {
+    if let Some(mtime) = mtime {
+        push_u16(extra, EXTRA_TIMESTAMP);
+        push_u16(extra, 5);
+        extra.push(1);
+        push_u32(extra, mtime);
+    }
+}
+
+/// MS-DOS 格式的时间和日期 (UTC)，早于 1980 年的按 1980-01-01 处理
+fn dos_datetime(t: SystemTime) -> (u16, u16) {
+    let t = time::OffsetDateTime::from(t);
+    if t.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let time = ((t.hour() as u16) << 11) | ((t.minute() as u16) << 5) | (t.second() as u16 / 2);
+    let date = (((t.year() - 1980) as u16) << 9) | ((t.month() as u16) << 5) | t.day() as u16;
+    (time, date)
+}
+
+fn push_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn push_u64(buf: &mut Vec<u8>, v: u64) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn crypto_error() -> io::Error {
+    io::Error::other("zip encryption failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ctr_little_endian_counter() {
+        // 第二个密钥流块由计数器 2（小端序）加密得到
+        let key = [7u8; KEY_LEN];
+        let mut ctr = Ctr::new(&key).unwrap();
+        let mut data = vec![0u8; BLOCK_LEN * 2];
+        ctr.apply(&mut data).unwrap();
+
+        let mut block = 2u128.to_le_bytes();
+        EncryptingKey::ecb(UnboundCipherKey::new(&AES_256, &key).unwrap())
+            .unwrap()
+            .encrypt(&mut block)
+            .unwrap();
+        assert_eq!(data[BLOCK_LEN..], block);
+    }
+
+    #[tokio::test]
+    async fn test_archive_layout() {
+        let mut out = Vec::new();
+        let mut zip = AesZipWriter::new(&mut out, "secret");
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let mut entry = zip.start_entry("a/b.txt", 5, Some(modified), Some(0o755)).await.unwrap();
+        entry.write_all(b"hel").await.unwrap();
+        entry.write_all(b"lo").await.unwrap();
+        entry.finish().await.unwrap();
+        let entry = zip.start_entry("c.txt", 3, None, None).await.unwrap();
+        assert!(entry.finish().await.is_err());
+        zip.close().await.unwrap();
+
+        assert_eq!(&out[..4], &0x04034b50u32.to_le_bytes());
+        assert!(!out.windows(5).any(|w| w == b"hello"));
+        let eocd = &out[out.len() - 22..];
+        assert_eq!(&eocd[..4], &0x06054b50u32.to_le_bytes());
+        assert_eq!(u16::from_le_bytes([eocd[10], eocd[11]]), 2);
+        // 本地文件头和中央目录各有一个扩展时间戳
+        let mut timestamp = vec![0x55, 0x54, 5, 0, 1];
+        timestamp.extend_from_slice(&1_700_000_000u32.to_le_bytes());
+        assert_eq!(out.windows(timestamp.len()).filter(|w| *w == timestamp).count(), 2);
+        // 第一个条目的外部属性带上权限位
+        let cd = u32::from_le_bytes(eocd[16..20].try_into().unwrap()) as usize;
+        assert_eq!(u32::from_le_bytes(out[cd + 38..cd + 42].try_into().unwrap()) >> 16, 0o100755);
+    }
+}
@@ -1 +1,3 @@
+pub mod aes;
+pub mod progress;
 pub mod streaming;
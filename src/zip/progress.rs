@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// 结束的任务保留多久，供晚连上的订阅者读取最终状态
+const RETAIN_FINISHED: Duration = Duration::from_secs(60);
+
+/// 打包任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveStatus {
+    /// 正在遍历目录，总大小未知
+    Scanning,
+    Packing,
+    Done,
+    Failed,
+}
+
+/// 流式打包下载的进度；响应没有 Content-Length，前端通过它显示进度
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveProgress {
+    pub id: String,
+    pub status: ArchiveStatus,
+    pub total_files: u64,
+    pub done_files: u64,
+    /// 源文件总字节数，遍历完成前为 0
+    pub total_bytes: u64,
+    pub done_bytes: u64,
+    pub error: Option<String>,
+}
+
+impl ArchiveProgress {
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status, ArchiveStatus::Done | ArchiveStatus::Failed)
+    }
+}
+
+pub type SharedProgress = Arc<parking_lot::Mutex<ArchiveProgress>>;
+
+/// 所有进行中的打包任务，按任务 ID 索引
+#[derive(Default)]
+pub struct ArchiveTracker {
+    tasks: Arc<parking_lot::RwLock<HashMap<String, SharedProgress>>>,
+}
+
+impl ArchiveTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记新任务；同 ID 的旧任务被替换
+    pub fn register(&self, id: &str) -> SharedProgress {
+        let progress = Arc::new(parking_lot::Mutex::new(ArchiveProgress {
+            id: id.to_string(),
+            status: ArchiveStatus::Scanning,
+            total_files: 0,
+            done_files: 0,
+            total_bytes: 0,
+            done_bytes: 0,
+            error: None,
+        }));
+        self.tasks.write().insert(id.to_string(), progress.clone());
+        progress
+    }
+
+    pub fn get(&self, id: &str) -> Option<ArchiveProgress> {
+        self.tasks.read().get(id).map(|p| p.lock().clone())
+    }
+
+    /// 标记任务结束，保留一段时间后移除
+    pub fn finish(&self, progress: &SharedProgress, error: Option<String>) {
+        let id = {
+            let mut p = progress.lock();
+            p.status = match error {
+                Some(_) => ArchiveStatus::Failed,
+                None => ArchiveStatus::Done,
+            };
+            p.error = error;
+            p.id.clone()
+        };
+        let tasks = self.tasks.clone();
+        let progress = progress.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(RETAIN_FINISHED).await;
+            let mut tasks = tasks.write();
+            // 期间同 ID 重新登记的任务不删
+            if tasks.get(&id).is_some_and(|p| Arc::ptr_eq(p, &progress)) {
+                tasks.remove(&id);
+            }
+        });
+    }
+}
+
+/// 客户端提供的任务 ID 只允许字母、数字、`-`、`_`
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_id() {
+        assert!(is_valid_id("zip-1a2b_3C"));
+        assert!(!is_valid_id(""));
+        assert!(!is_valid_id("../x"));
+        assert!(!is_valid_id(&"a".repeat(65)));
+    }
+
+    #[tokio::test]
+    async fn test_register_and_finish() {
+        let tracker = ArchiveTracker::new();
+        let p = tracker.register("t1");
+        p.lock().done_bytes = 10;
+        assert_eq!(tracker.get("t1").unwrap().done_bytes, 10);
+        tracker.finish(&p, None);
+        let snapshot = tracker.get("t1").unwrap();
+        assert_eq!(snapshot.status, ArchiveStatus::Done);
+        assert!(snapshot.is_finished());
+        assert!(tracker.get("missing").is_none());
+    }
+}
@@ -5,16 +5,24 @@
 import { state, getRaw } from './store.js';
 import * as api from './api.js';
 import { showToast } from './components/toast.js';
+import { addDownload, addArchiveDownload, addLocalSend } from './components/upload-panel.js';
+import { MEMORY_LIMIT, canStreamToDisk, pickSaveTarget } from './downloader.js';
+import { formatMode } from './utils/format.js';
+import { pickFolder } from './components/folder-picker.js';
 
 /** 加载文件列表 */
 export async function loadFiles(path) {
+    // 投递箱模式下没有列目录权限
+    if (state.dropBox) return;
     state.loading = true;
     try {
         const data = await api.listFiles(path);
         state.files = data.entries || [];
+        state.archive = data.archive || null;
     } catch (e) {
         showToast(`加载失败: ${e.message}`, 'error');
         state.files = [];
+        state.archive = null;
     } finally {
         state.loading = false;
     }
@@ -47,14 +55,83 @@ export async function renameEntry(path, newName) {
     }
 }
 
+/** 修改权限 */
+export async function changeMode(path, mode) {
+    try {
+        const res = await api.chmod(path, mode);
+        showToast(`权限已改为 ${formatMode(res.mode)}`);
+        await refresh();
+    } catch (e) {
+        showToast(`修改权限失败: ${e.message}`, 'error');
+    }
+}
+
+/** 把选中项移动或复制到另一个目录 */
+export async function transferSelected(mode) {
+    const raw = getRaw();
+    const paths = [...raw.selected];
+    if (!paths.length) return;
+    const verb = mode === 'move' ? '移动' : '复制';
+    const dest = await pickFolder({
+        title: `${verb} ${paths.length} 个项目到`,
+        confirmLabel: `${verb}到这里`,
+        current: state.currentPath,
+    });
+    if (dest === null) return;
+    await transferPaths(mode, paths, dest);
+}
+
+/** 列表中拖动条目时 dataTransfer 使用的类型，与从系统拖入的文件区分 */
+export const DRAG_TYPE = 'application/x-transfer-paths';
+
+/** 拖放到目录上的条目移动过去；已在该目录中的条目和目录自身、上级目录跳过 */
+export async function moveDropped(dataTransfer, dest) {
+    let paths;
+    try {
+        paths = JSON.parse(dataTransfer.getData(DRAG_TYPE) || '[]');
+    } catch {
+        return;
+    }
+    const parentOf = (p) => p.includes('/') ? p.slice(0, p.lastIndexOf('/')) : '';
+    paths = paths.filter(p => p !== dest && !dest.startsWith(`${p}/`) && parentOf(p) !== dest);
+    if (paths.length) await transferPaths('move', paths, dest);
+}
+
+async function transferPaths(mode, paths, dest) {
+    const verb = mode === 'move' ? '移动' : '复制';
+    const op = mode === 'move' ? api.moveEntry : api.copyEntry;
+    let done = 0;
+    try {
+        for (const path of paths) {
+            await op(path, dest);
+            done++;
+        }
+        showToast(`已${verb} ${done} 个项目到 /${dest}`);
+    } catch (e) {
+        showToast(`${verb}失败（已完成 ${done} 个）: ${e.message}`, 'error');
+    }
+    if (mode === 'move') state.selected = [];
+    await refresh();
+}
+
 /** 删除选中文件 */
 export async function deleteSelected() {
     const raw = getRaw();
     const paths = [...raw.selected];
     if (!paths.length) return;
     try {
-        await api.batchDelete(paths);
-        showToast(`已删除 ${paths.length} 个项目`);
+        const res = await api.batchDelete(paths);
+        const message = state.trashDays
+            ? `已将 ${paths.length} 个项目移到回收站`
+            : `已删除 ${paths.length} 个项目`;
+        if (res?.undo) {
+            showToast(message, 'info', res.undo.expires_in * 1000, {
+                label: '撤销',
+                onClick: () => undoDelete(res.undo.token),
+            });
+        } else {
+            showToast(message);
+        }
         state.selected = [];
         await refresh();
     } catch (e) {
@@ -62,9 +139,43 @@ export async function deleteSelected() {
     }
 }
 
-/** 下载文件 */
-export function downloadFile(path) {
+/** 撤销刚才的删除，把删除的条目放回原位置 */
+async function undoDelete(token) {
+    try {
+        const res = await api.undoDelete(token);
+        if (res.failed.length) {
+            showToast(`已恢复 ${res.restored.length} 个项目，${res.failed.length} 个无法放回原位置（可能已有同名项目）: ${res.failed.join('、')}`, 'error', 6000);
+        } else {
+            showToast(`已撤销删除 ${res.restored.length} 个项目`);
+        }
+    } catch (e) {
+        showToast(e.code === 'not_found' ? '已超过可撤销的时间' : `撤销失败: ${e.message}`, 'error');
+    }
+    await refresh();
+}
+
+/**
+ * 下载文件：在页面内下载，断线后自动续传；大文件直接写入用户选择的位置，
+ * 浏览器不支持写文件时交给浏览器原生下载
+ */
+export async function downloadFile(path) {
     const url = api.downloadUrl(path, true);
+    const file = getRaw().files.find(f => f.path === path);
+    if (file && !file.is_dir) {
+        if (file.size <= MEMORY_LIMIT) {
+            addDownload(url, file.name, file.size);
+            return;
+        }
+        if (canStreamToDisk()) {
+            try {
+                const handle = await pickSaveTarget(file.name);
+                if (handle) addDownload(url, file.name, file.size, handle);
+            } catch (e) {
+                showToast(`无法写入文件: ${e.message}`, 'error');
+            }
+            return;
+        }
+    }
     const a = document.createElement('a');
     a.href = url;
     a.download = '';
@@ -76,11 +187,74 @@ export function downloadSelectedAsZip() {
     const raw = getRaw();
     const paths = [...raw.selected];
     if (!paths.length) return;
-    const url = api.zipDownloadUrl(paths);
+    // 响应没有 Content-Length，进度通过单独的 SSE 获取
+    const progressId = `${Date.now().toString(36)}${Math.random().toString(36).slice(2, 10)}`;
+    api.submitArchive(paths, progressId);
+    addArchiveDownload(progressId, `打包下载（${paths.length} 项）`);
+}
+
+/** 把整个目录打包为 ZIP 下载，文件名为目录名 */
+export function downloadFolderAsZip(path) {
+    const progressId = `${Date.now().toString(36)}${Math.random().toString(36).slice(2, 10)}`;
     const a = document.createElement('a');
-    a.href = url;
+    a.href = api.folderZipUrl(path, progressId);
     a.download = '';
     a.click();
+    addArchiveDownload(progressId, `${path.split('/').pop()}.zip`);
+}
+
+/** 下载选中文件为带密码的 ZIP（AES-256） */
+export function downloadSelectedAsProtectedZip() {
+    const paths = [...getRaw().selected];
+    if (!paths.length) return;
+    const password = prompt('设置 ZIP 密码（需用 7-Zip、WinZip 等支持 AES 的工具解压）');
+    if (password === null) return;
+    if (!password) {
+        showToast('密码不能为空', 'error');
+        return;
+    }
+    const progressId = `${Date.now().toString(36)}${Math.random().toString(36).slice(2, 10)}`;
+    api.submitArchive(paths, progressId, password);
+    addArchiveDownload(progressId, `加密打包下载（${paths.length} 项）`);
+}
+
+/** 把选中的文件推送到附近的 LocalSend 设备 */
+export async function sendSelectedToLocalSend() {
+    const paths = [...getRaw().selected];
+    if (!paths.length) return;
+    let devices;
+    try {
+        devices = await api.localSendDevices();
+    } catch (e) {
+        showToast(`获取设备失败: ${e.message}`, 'error');
+        return;
+    }
+    if (!devices.length) {
+        showToast('附近没有发现 LocalSend 设备，请在对方设备上打开 LocalSend', 'info');
+        return;
+    }
+    let device = devices[0];
+    if (devices.length > 1) {
+        const list = devices.map((d, i) => `${i + 1}. ${d.alias}（${d.ip}）`).join('\n');
+        const choice = prompt(`发送到哪台设备？\n${list}`, '1');
+        if (choice === null) return;
+        device = devices[parseInt(choice, 10) - 1];
+        if (!device) {
+            showToast('无效的设备编号', 'error');
+            return;
+        }
+    }
+    await sendToDevice(device, paths);
+}
+
+/** 把文件推送到指定的附近设备，进度显示在传输面板中 */
+export async function sendToDevice(device, paths) {
+    try {
+        const task = await api.startLocalSend(device.fingerprint, paths);
+        addLocalSend(task);
+    } catch (e) {
+        showToast(`发送失败: ${e.message}`, 'error');
+    }
 }
 
 /** 搜索 */
@@ -122,13 +296,28 @@ export function toggleSelect(path) {
     state.selected = [...set];
 }
 
-/** 全选/取消全选 */
+/** 选中两个条目之间（按当前排序和过滤，含两端）的所有条目，保留已有的选中 */
+export function selectRange(from, to) {
+    const paths = getSortedFiles().map(f => f.path);
+    const a = paths.indexOf(from);
+    const b = paths.indexOf(to);
+    if (a < 0 || b < 0) {
+        toggleSelect(to);
+        return;
+    }
+    const set = new Set(getRaw().selected);
+    paths.slice(Math.min(a, b), Math.max(a, b) + 1).forEach(p => set.add(p));
+    state.selected = [...set];
+}
+
+/** 全选/取消全选当前显示的条目 */
 export function toggleSelectAll() {
-    const raw = getRaw();
-    if (raw.selected.length === raw.files.length) {
+    const visible = getSortedFiles().map(f => f.path);
+    const selected = new Set(getRaw().selected);
+    if (visible.length && visible.every(p => selected.has(p))) {
         state.selected = [];
     } else {
-        state.selected = raw.files.map(f => f.path);
+        state.selected = visible;
     }
 }
 
@@ -5,10 +5,13 @@
 const BASE = '/api';
 
 class ApiError extends Error {
-    constructor(status, code, message) {
-        super(message);
+    constructor(status, code, message, detail = null, requestId = null) {
+        // 带上请求 ID，用户反馈的报错可以直接在服务端日志里搜到
+        super(requestId ? `${message}（请求 ID: ${requestId}）` : message);
         this.status = status;
         this.code = code;
+        this.detail = detail;
+        this.requestId = requestId;
     }
 }
 
@@ -29,23 +32,37 @@ async function request(method, path, opts = {}) {
     if (!resp.ok) {
         let code = 'unknown';
         let msg = resp.statusText;
+        let detail = null;
+        let requestId = resp.headers.get('X-Request-Id');
         try {
             const data = await resp.json();
             code = data.code || code;
             msg = data.message || msg;
+            detail = data.detail || null;
+            requestId = data.request_id || requestId;
         } catch { /* ignore */ }
-        throw new ApiError(resp.status, code, msg);
+        throw new ApiError(resp.status, code, msg, detail, requestId);
     }
     const ct = resp.headers.get('content-type') || '';
     if (ct.includes('application/json')) return resp.json();
     return resp;
 }
 
+/** 服务端模式（投递箱等） */
+export function getInfo() {
+    return request('GET', '/info');
+}
+
 /** 文件列表 */
 export function listFiles(path = '') {
     return request('GET', '/files', { params: { path } });
 }
 
+/** 只列出下一级子目录，用于目录选择框 */
+export function listDirs(path = '') {
+    return request('GET', '/dirs', { params: { path } });
+}
+
 /** 创建目录 */
 export function mkdir(path, name) {
     return request('POST', '/files/mkdir', { body: { path, name } });
@@ -56,26 +73,140 @@ export function rename(path, newName) {
     return request('POST', '/files/rename', { body: { path, new_name: newName } });
 }
 
-/** 移动 */
-export function moveEntry(src, dest) {
-    return request('POST', '/files/move', { body: { src, dest } });
+/** 修改权限位：mode 为八进制（755）或符号形式（+x、u+x,go-w） */
+export function chmod(path, mode) {
+    return request('POST', '/files/chmod', { body: { path, mode } });
+}
+
+/** 移动到目录 destination */
+export function moveEntry(source, destination) {
+    return request('POST', '/files/move', { body: { source, destination } });
 }
 
-/** 复制 */
-export function copyEntry(src, dest) {
-    return request('POST', '/files/copy', { body: { src, dest } });
+/** 复制到目录 destination */
+export function copyEntry(source, destination) {
+    return request('POST', '/files/copy', { body: { source, destination } });
 }
 
-/** 批量删除 */
+/** 批量删除；启用回收站时返回移进回收站的条目 */
 export function batchDelete(paths) {
     return request('POST', '/files/delete', { body: { paths } });
 }
 
+/** 撤销一次删除，token 来自删除接口的响应 */
+export function undoDelete(token) {
+    return request('POST', `/undo/${encodeURIComponent(token)}`);
+}
+
+/** 回收站中的条目，最近删除的在前 */
+export function listTrash() {
+    return request('GET', '/trash');
+}
+
+/** 放回原位置 */
+export function restoreTrash(id) {
+    return request('POST', `/trash/${encodeURIComponent(id)}/restore`);
+}
+
+/** 彻底删除回收站中的一项 */
+export function purgeTrash(id) {
+    return request('DELETE', `/trash/${encodeURIComponent(id)}`);
+}
+
+/** 清空回收站 */
+export function emptyTrash() {
+    return request('DELETE', '/trash');
+}
+
 /** 搜索 */
 export function search(path, query) {
     return request('GET', '/files/search', { params: { path, q: query } });
 }
 
+/** 当前目录按文件类型统计 */
+export function fileStats(path = '') {
+    return request('GET', '/files/stats', { params: { path } });
+}
+
+/** 服务端抓取远程 URL */
+export function startFetch(url, path) {
+    return request('POST', '/fetch', { body: { url, path } });
+}
+
+/** 查询抓取进度 */
+export function getFetch(id) {
+    return request('GET', `/fetch/${encodeURIComponent(id)}`);
+}
+
+/** 取消抓取 */
+export function cancelFetch(id) {
+    return request('DELETE', `/fetch/${encodeURIComponent(id)}`);
+}
+
+/** 附近的 LocalSend 设备 */
+export function localSendDevices() {
+    return request('GET', '/localsend/devices');
+}
+
+/** 把文件或目录推送到 LocalSend 设备 */
+export function startLocalSend(device, paths) {
+    return request('POST', '/localsend/send', { body: { device, paths } });
+}
+
+/** 查询推送进度 */
+export function getLocalSend(id) {
+    return request('GET', `/localsend/sends/${encodeURIComponent(id)}`);
+}
+
+/** 取消推送 */
+export function cancelLocalSend(id) {
+    return request('DELETE', `/localsend/sends/${encodeURIComponent(id)}`);
+}
+
+/** 接收模式下待确认请求的 SSE 地址 */
+export function localSendRequestsUrl() {
+    return `${BASE}/localsend/requests`;
+}
+
+/** 接受或拒绝接收请求 */
+export function decideLocalSend(id, accept) {
+    return request('POST', `/localsend/requests/${encodeURIComponent(id)}`, { body: { accept } });
+}
+
+/**
+ * 查询 tus 上传会话的服务端进度
+ * @returns {Promise<{offset: number, length: number} | null>} 会话不存在时为 null
+ */
+export async function getUploadOffset(uploadUrl) {
+    const resp = await fetch(uploadUrl, { method: 'HEAD', headers: { 'Tus-Resumable': '1.0.0' } });
+    if (resp.status === 404) return null;
+    if (!resp.ok) throw new ApiError(resp.status, 'unknown', resp.statusText);
+    return {
+        offset: Number(resp.headers.get('Upload-Offset') || 0),
+        length: Number(resp.headers.get('Upload-Length') || 0),
+    };
+}
+
+/** 终止 tus 上传会话并删除分片 */
+export function terminateUpload(uploadUrl) {
+    return fetch(uploadUrl, { method: 'DELETE', headers: { 'Tus-Resumable': '1.0.0' } });
+}
+
+/** 目录磁盘占用 */
+export function getUsage(path = '') {
+    return request('GET', '/usage', { params: { path } });
+}
+
+/** 触发重新扫描磁盘占用 */
+export function rescanUsage() {
+    return request('POST', '/usage/rescan');
+}
+
+/** 最近访问过的客户端及其进行中的传输 */
+export function getClients() {
+    return request('GET', '/admin/clients');
+}
+
 /** 获取下载 URL */
 export function downloadUrl(path, asAttachment = true) {
     const encoded = path.split('/').map(encodeURIComponent).join('/');
@@ -84,10 +215,125 @@ export function downloadUrl(path, asAttachment = true) {
         : `${BASE}/download/${encoded}`;
 }
 
-/** ZIP 下载 URL */
-export function zipDownloadUrl(paths) {
-    const params = paths.map(p => `paths=${encodeURIComponent(p)}`).join('&');
-    return `${BASE}/download-zip?${params}`;
+/** 批量查询文件是否存在及大小、修改时间 */
+export function statPaths(paths) {
+    return request('POST', '/files/stat', { body: { paths } });
+}
+
+/** 文件下载链接的二维码：{ url, svg } */
+export function getQrCode(path) {
+    const encoded = path.split('/').map(encodeURIComponent).join('/');
+    return request('GET', `/qr/${encoded}`);
+}
+
+/** 缩放后的图片 URL（服务端缓存） */
+export function thumbnailUrl(path, width, height) {
+    const encoded = path.split('/').map(encodeURIComponent).join('/');
+    const params = new URLSearchParams();
+    if (width) params.set('w', Math.round(width));
+    if (height) params.set('h', Math.round(height));
+    return `${BASE}/download/${encoded}?${params}`;
+}
+
+/** 目录打包为 ZIP 的下载 URL */
+export function folderZipUrl(path, progressId) {
+    const encoded = path.split('/').map(encodeURIComponent).join('/');
+    const params = new URLSearchParams({ zip: '1' });
+    if (progressId) params.set('progress', progressId);
+    return `${BASE}/download/${encoded}?${params}`;
+}
+
+/** 目录清单（CSV，含 SHA-256）下载 URL */
+export function manifestUrl(path) {
+    const params = new URLSearchParams({ path, hash: 'sha256', format: 'csv' });
+    return `${BASE}/manifest?${params}`;
+}
+
+/**
+ * 打包下载用表单 POST 提交：路径逐个作为字段，文件名中的逗号不受影响，选中再多也不受 URL 长度限制；
+ * 密码不出现在 URL 中。progressId 用于订阅打包进度
+ */
+export function submitArchive(paths, progressId, password) {
+    const form = document.createElement('form');
+    form.method = 'POST';
+    form.action = `${BASE}/archive`;
+    form.target = 'zip-download-frame';
+    form.hidden = true;
+    const fields = paths.map(path => ['paths', path]);
+    fields.push(['progress', progressId]);
+    if (password) fields.push(['password', password]);
+    for (const [name, value] of fields) {
+        const input = document.createElement('input');
+        input.type = 'hidden';
+        input.name = name;
+        input.value = value;
+        form.appendChild(input);
+    }
+    let frame = document.querySelector('iframe[name="zip-download-frame"]');
+    if (!frame) {
+        frame = document.createElement('iframe');
+        frame.name = 'zip-download-frame';
+        frame.hidden = true;
+        document.body.appendChild(frame);
+    }
+    document.body.appendChild(form);
+    form.submit();
+    form.remove();
+}
+
+/** 浏览器间直传的信令 WebSocket URL */
+export function signalUrl(name) {
+    const scheme = location.protocol === 'https:' ? 'wss:' : 'ws:';
+    return `${scheme}//${location.host}${BASE}/signal?name=${encodeURIComponent(name)}`;
+}
+
+/** 最近的留言 */
+export function listMessages() {
+    return request('GET', '/messages');
+}
+
+/** 发布留言；path 为空时是全局留言 */
+export function postMessage(text, author, path) {
+    return request('POST', '/messages', { body: { text, author, path } });
+}
+
+/** 删除留言 */
+export function deleteMessage(id) {
+    return request('DELETE', `/messages/${encodeURIComponent(id)}`);
+}
+
+/** 留言推送的 SSE 地址 */
+export function messageStreamUrl() {
+    return `${BASE}/messages/stream`;
+}
+
+/** 剪贴板同步 WebSocket 地址 */
+export function clipboardUrl(name) {
+    const scheme = location.protocol === 'https:' ? 'wss:' : 'ws:';
+    return `${scheme}//${location.host}${BASE}/clipboard?name=${encodeURIComponent(name)}`;
+}
+
+/** 打包进度 SSE URL */
+export function archiveProgressUrl(id) {
+    return `${BASE}/archive-progress/${encodeURIComponent(id)}`;
+}
+
+/** HLS 播放列表 URL（服务端 ffmpeg 转码） */
+export function hlsUrl(path) {
+    const encoded = path.split('/').map(encodeURIComponent).join('/');
+    return `${BASE}/hls/${encoded}`;
+}
+
+/** 转码后的 fragmented MP4 流 URL */
+export function transcodeUrl(path) {
+    const encoded = path.split('/').map(encodeURIComponent).join('/');
+    return `${BASE}/transcode/${encoded}`;
+}
+
+/** 跟随文件新增内容的 SSE URL */
+export function tailUrl(path, lines = 200) {
+    const encoded = path.split('/').map(encodeURIComponent).join('/');
+    return `${BASE}/tail/${encoded}?lines=${lines}`;
 }
 
 /** 预览 URL */
@@ -1,9 +1,11 @@
 /**
  * 面包屑导航组件
+ * 列表中的条目可以拖到上级目录上移动过去
  */
 
 import { state, subscribe } from '../store.js';
 import { navigate } from '../router.js';
+import { moveDropped, DRAG_TYPE } from '../actions.js';
 
 export function initBreadcrumb() {
     const el = document.getElementById('breadcrumb');
@@ -37,6 +39,26 @@ export function initBreadcrumb() {
         }
     });
 
+    const target = (e) => e.dataTransfer?.types.includes(DRAG_TYPE) ? e.target.closest('[data-path]') : null;
+    el.addEventListener('dragover', (e) => {
+        const link = target(e);
+        if (!link) return;
+        e.preventDefault();
+        e.dataTransfer.dropEffect = 'move';
+        link.classList.add('drop-target');
+    });
+    el.addEventListener('dragleave', (e) => {
+        e.target.closest('[data-path]')?.classList.remove('drop-target');
+    });
+    el.addEventListener('drop', (e) => {
+        const link = target(e);
+        if (!link) return;
+        e.preventDefault();
+        e.stopPropagation();
+        link.classList.remove('drop-target');
+        moveDropped(e.dataTransfer, link.dataset.path);
+    });
+
     subscribe('currentPath', render);
     render();
 }
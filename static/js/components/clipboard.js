@@ -0,0 +1,169 @@
+/**
+ * 跨设备剪贴板
+ * 在一台设备上粘贴的文本通过 WebSocket 推送到其他打开本页面的设备，服务端只在内存中短暂保留
+ */
+
+import { clipboardUrl } from '../api.js';
+import { formatTime } from '../utils/format.js';
+import { showToast } from './toast.js';
+import { deviceName } from './p2p.js';
+
+const RECONNECT_DELAY = 3000;
+/** 列表中每条文本最多显示的字符数，完整内容通过复制取得 */
+const PREVIEW_CHARS = 300;
+
+let ws = null;
+/** 条目，新的在前 */
+let items = [];
+let unseen = 0;
+let panelEl, listEl, inputEl, toggleEl;
+
+export function initClipboard() {
+    panelEl = document.getElementById('clipboard-panel');
+    toggleEl = document.getElementById('clipboard-toggle');
+    if (!panelEl || !toggleEl) return;
+    toggleEl.hidden = false;
+    listEl = panelEl.querySelector('.clipboard-list');
+    inputEl = panelEl.querySelector('.clipboard-input');
+
+    toggleEl.addEventListener('click', () => {
+        panelEl.classList.toggle('active');
+        if (panelEl.classList.contains('active')) {
+            unseen = 0;
+            inputEl.focus();
+        }
+        render();
+    });
+    panelEl.querySelector('.clipboard-close').addEventListener('click', () => panelEl.classList.remove('active'));
+    panelEl.querySelector('.clipboard-send').addEventListener('click', sendInput);
+    inputEl.addEventListener('keydown', (e) => {
+        // Ctrl / ⌘ + Enter 发送，单独 Enter 换行
+        if (e.key === 'Enter' && (e.ctrlKey || e.metaKey)) {
+            e.preventDefault();
+            sendInput();
+        }
+    });
+    panelEl.querySelector('.clipboard-clear').addEventListener('click', () => {
+        if (items.length && confirm('清空所有设备上的剪贴板记录？')) send({ type: 'clear' });
+    });
+    listEl.addEventListener('click', (e) => {
+        const btn = e.target.closest('[data-action]');
+        if (!btn) return;
+        const item = items.find(i => i.id === btn.dataset.id);
+        if (!item) return;
+        if (btn.dataset.action === 'copy') copyText(item.text);
+        else if (btn.dataset.action === 'remove') send({ type: 'remove', id: item.id });
+    });
+
+    connect();
+}
+
+function connect() {
+    ws = new WebSocket(clipboardUrl(deviceName()));
+    ws.onmessage = (e) => {
+        let msg;
+        try {
+            msg = JSON.parse(e.data);
+        } catch {
+            return;
+        }
+        handleMessage(msg);
+    };
+    ws.onclose = () => {
+        ws = null;
+        render();
+        setTimeout(connect, RECONNECT_DELAY);
+    };
+}
+
+function handleMessage(msg) {
+    switch (msg.type) {
+        case 'history':
+            items = msg.items;
+            break;
+        case 'added':
+            if (items.some(i => i.id === msg.item.id)) return;
+            items = [msg.item, ...items];
+            if (msg.item.from !== deviceName() && !panelEl.classList.contains('active')) {
+                unseen++;
+                showToast(`${msg.item.from} 共享了剪贴板内容`, 'info');
+            }
+            break;
+        case 'removed':
+            items = items.filter(i => i.id !== msg.id);
+            break;
+        case 'cleared':
+            items = [];
+            unseen = 0;
+            break;
+        case 'error':
+            showToast(`剪贴板: ${msg.message}`, 'error');
+            return;
+    }
+    render();
+}
+
+function send(msg) {
+    if (!ws || ws.readyState !== WebSocket.OPEN) {
+        showToast('剪贴板未连接，请稍后重试', 'error');
+        return false;
+    }
+    ws.send(JSON.stringify(msg));
+    return true;
+}
+
+function sendInput() {
+    const text = inputEl.value;
+    if (!text.trim()) return;
+    if (send({ type: 'copy', text })) inputEl.value = '';
+}
+
+async function copyText(text) {
+    try {
+        await navigator.clipboard.writeText(text);
+    } catch {
+        // 非 HTTPS 页面没有 Clipboard API，退回 execCommand
+        const ta = document.createElement('textarea');
+        ta.value = text;
+        ta.style.position = 'fixed';
+        ta.style.opacity = '0';
+        document.body.appendChild(ta);
+        ta.select();
+        const ok = document.execCommand('copy');
+        ta.remove();
+        if (!ok) {
+            showToast('复制失败，请手动选择文本', 'error');
+            return;
+        }
+    }
+    showToast('已复制', 'success');
+}
+
+function render() {
+    if (!listEl) return;
+    toggleEl.classList.toggle('has-peers', unseen > 0);
+    toggleEl.dataset.count = unseen || '';
+
+    if (!items.length) {
+        listEl.innerHTML = `<div class="p2p-empty">${ws ? '还没有内容，在上方输入或粘贴文本后发送' : '未连接'}</div>`;
+        return;
+    }
+    listEl.innerHTML = items.map(i => {
+        const preview = i.text.length > PREVIEW_CHARS ? `${i.text.slice(0, PREVIEW_CHARS)}…` : i.text;
+        return `
+        <div class="clipboard-item">
+            <div class="clipboard-text">${escapeHtml(preview)}</div>
+            <div class="clipboard-meta">
+                <span>${escapeHtml(i.from)} · ${formatTime(i.created_at)}</span>
+                <button class="btn btn-sm" data-action="copy" data-id="${i.id}">复制</button>
+                <button class="btn btn-sm btn-ghost" data-action="remove" data-id="${i.id}">删除</button>
+            </div>
+        </div>`;
+    }).join('');
+}
+
+function escapeHtml(text) {
+    const d = document.createElement('div');
+    d.textContent = text;
+    return d.innerHTML.replace(/"/g, '&quot;');
+}
@@ -3,9 +3,11 @@
  */
 
 import { state, subscribe, getRaw } from '../store.js';
-import { renameEntry, deleteSelected, downloadFile, downloadSelectedAsZip, openPreview } from '../actions.js';
-import { downloadUrl } from '../api.js';
+import { renameEntry, changeMode, deleteSelected, transferSelected, downloadFile, downloadSelectedAsZip, downloadFolderAsZip, downloadSelectedAsProtectedZip, sendSelectedToLocalSend, openPreview } from '../actions.js';
+import { downloadUrl, manifestUrl } from '../api.js';
 import { navigate } from '../router.js';
+import { isArchive } from '../utils/format.js';
+import { isTailable } from './preview-modal.js';
 
 let menuEl = null;
 
@@ -44,21 +46,56 @@ function render() {
 
     let items = [];
 
-    if (single && isDir) {
-        items.push({ action: 'open', label: '打开', icon: 'folder-open' });
-    }
-    if (single && !isDir) {
-        items.push({ action: 'preview', label: '预览', icon: 'eye' });
-        items.push({ action: 'download', label: '下载', icon: 'download' });
-    }
-    if (paths.length > 1) {
-        items.push({ action: 'zip', label: '打包下载', icon: 'archive' });
-    }
-    items.push({ divider: true });
-    if (single) {
-        items.push({ action: 'rename', label: '重命名', icon: 'edit' });
+    if (raw.archive) {
+        // 归档内部只读，只提供浏览和单文件下载
+        if (single && isDir) {
+            items.push({ action: 'open', label: '打开', icon: 'folder-open' });
+        }
+        if (single && file && !isDir) {
+            items.push({ action: 'download', label: '下载', icon: 'download' });
+        }
+        if (!items.length) {
+            menuEl.style.display = 'none';
+            return;
+        }
+    } else {
+        if (single && isDir) {
+            items.push({ action: 'open', label: '打开', icon: 'folder-open' });
+            items.push({ action: 'download-folder', label: '下载为 ZIP', icon: 'download' });
+        }
+        if (single && !isDir) {
+            if (file && isArchive(file.name)) {
+                items.push({ action: 'open', label: '浏览内容', icon: 'archive' });
+            }
+            items.push({ action: 'preview', label: '预览', icon: 'eye' });
+            if (file && isTailable(file.name)) {
+                items.push({ action: 'tail', label: '实时跟随', icon: 'activity' });
+            }
+            items.push({ action: 'download', label: '下载', icon: 'download' });
+        }
+        if (paths.length > 1) {
+            items.push({ action: 'zip', label: '打包下载', icon: 'archive' });
+        }
+        items.push({ action: 'zip-password', label: '加密打包下载', icon: 'archive' });
+        if (single && isDir) {
+            items.push({ action: 'manifest', label: '导出校验清单', icon: 'download' });
+        }
+        if (raw.localSend) {
+            items.push({ action: 'localsend', label: '发送到 LocalSend 设备', icon: 'send' });
+        }
+        if (!raw.readOnly) {
+            items.push({ divider: true });
+            if (single) {
+                items.push({ action: 'rename', label: '重命名', icon: 'edit' });
+                if (file?.mode != null) {
+                    items.push({ action: 'chmod', label: '修改权限', icon: 'lock' });
+                }
+            }
+            items.push({ action: 'move', label: '移动到…', icon: 'folder' });
+            items.push({ action: 'copy', label: '复制到…', icon: 'copy' });
+            items.push({ action: 'delete', label: '删除', icon: 'trash', danger: true });
+        }
     }
-    items.push({ action: 'delete', label: '删除', icon: 'trash', danger: true });
 
     menuEl.innerHTML = items.map(item => {
         if (item.divider) return '<div class="context-menu-divider"></div>';
@@ -94,12 +131,31 @@ function handleAction(e) {
         case 'preview':
             if (file) openPreview(file);
             break;
+        case 'tail':
+            if (file) openPreview({ ...file, tail: true });
+            break;
         case 'download':
             if (paths[0]) downloadFile(paths[0]);
             break;
         case 'zip':
             downloadSelectedAsZip();
             break;
+        case 'download-folder':
+            if (paths[0]) downloadFolderAsZip(paths[0]);
+            break;
+        case 'manifest': {
+            const a = document.createElement('a');
+            a.href = manifestUrl(paths[0]);
+            a.download = '';
+            a.click();
+            break;
+        }
+        case 'zip-password':
+            downloadSelectedAsProtectedZip();
+            break;
+        case 'localsend':
+            sendSelectedToLocalSend();
+            break;
         case 'rename': {
             if (!file) break;
             const newName = prompt('新名称:', file.name);
@@ -108,6 +164,18 @@ function handleAction(e) {
             }
             break;
         }
+        case 'chmod': {
+            if (!file) break;
+            const mode = prompt('新权限（如 755、+x、go-w）:', file.mode.toString(8).padStart(3, '0'));
+            if (mode && mode.trim()) {
+                changeMode(paths[0], mode.trim());
+            }
+            break;
+        }
+        case 'move':
+        case 'copy':
+            transferSelected(action);
+            break;
         case 'delete':
             if (confirm(`确定删除 ${paths.length} 个项目？`)) {
                 deleteSelected();
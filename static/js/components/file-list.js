@@ -1,14 +1,26 @@
 /**
  * 文件列表组件
- * 支持列表/网格视图、多选、双击导航/预览
+ * 支持列表/网格视图、多选、双击导航/预览、拖到文件夹上移动
  */
 
 import { state, subscribe, getRaw } from '../store.js';
-import { getSortedFiles, toggleSelect, openPreview, downloadFile } from '../actions.js';
+import { getSortedFiles, toggleSelect, selectRange, toggleSelectAll, openPreview, downloadFile, moveDropped, DRAG_TYPE } from '../actions.js';
 import { navigate } from '../router.js';
-import { formatSize, formatTime, fileIcon } from '../utils/format.js';
+import { thumbnailUrl } from '../api.js';
+import { showQrCode } from './qr-popup.js';
+import { formatSize, formatTime, formatMode, fileIcon, isArchive } from '../utils/format.js';
+
+const THUMB_EXTS = new Set(['jpg', 'jpeg', 'png', 'gif', 'webp', 'bmp']);
+
+const QR_BUTTON = `<button class="icon-btn file-qr" title="扫码下载" tabindex="-1">
+    <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2"><rect x="3" y="3" width="7" height="7"/><rect x="14" y="3" width="7" height="7"/><rect x="3" y="14" width="7" height="7"/><path d="M14 14h3v3h-3zM20 14v.01M14 20h.01M17 20h4v-3"/></svg>
+</button>`;
 
 let listEl = null;
+/** Shift 点击时范围选择的起点 */
+let anchor = null;
+/** 正在拖动的条目，不能放到它们自己上 */
+let dragged = [];
 
 export function initFileList() {
     listEl = document.getElementById('file-list');
@@ -18,6 +30,23 @@ export function initFileList() {
     listEl.addEventListener('click', handleClick);
     listEl.addEventListener('dblclick', handleDblClick);
     listEl.addEventListener('contextmenu', handleContextMenu);
+    listEl.addEventListener('dragstart', handleDragStart);
+    listEl.addEventListener('dragover', handleDragOver);
+    listEl.addEventListener('dragleave', (e) => {
+        e.target.closest('.file-row')?.classList.remove('drop-target');
+    });
+    listEl.addEventListener('drop', handleDrop);
+    listEl.addEventListener('dragend', () => { dragged = []; });
+
+    // Ctrl/Cmd + A 全选当前目录，输入框中保持浏览器默认行为
+    document.addEventListener('keydown', (e) => {
+        if (!(e.ctrlKey || e.metaKey) || e.key.toLowerCase() !== 'a') return;
+        if (e.target.closest('input, textarea, [contenteditable]') || getRaw().preview) return;
+        e.preventDefault();
+        toggleSelectAll();
+    });
+    // 换目录后范围选择重新开始
+    subscribe('currentPath', () => { anchor = null; });
 
     subscribe('files', render);
     subscribe('selected', render);
@@ -45,6 +74,8 @@ function render() {
     const raw = getRaw();
     const selected = new Set(raw.selected);
     const isGrid = state.viewMode === 'grid';
+    // 归档内和只读模式下不能移动
+    const draggable = !raw.archive && !raw.readOnly ? ' draggable="true"' : '';
 
     listEl.className = `file-list ${isGrid ? 'file-list-grid' : ''}`;
 
@@ -67,20 +98,22 @@ function render() {
         ].filter(Boolean).join(' ');
 
         if (isGrid) {
-            return `<div class="${cls}" data-path="${escapeAttr(f.path)}" data-dir="${f.is_dir}">
-                <div class="file-icon">${fileIcon(f)}</div>
+            return `<div class="${cls}" data-path="${escapeAttr(f.path)}" data-dir="${f.is_dir}"${draggable}>
+                <div class="file-icon">${gridIcon(f)}</div>
                 <div class="file-name" title="${escapeAttr(f.name)}">${escapeHtml(f.name)}</div>
             </div>`;
         }
 
-        return `<div class="${cls}" data-path="${escapeAttr(f.path)}" data-dir="${f.is_dir}">
+        return `<div class="${cls}" data-path="${escapeAttr(f.path)}" data-dir="${f.is_dir}"${draggable}>
             <div class="file-cell file-cell-check">
                 <input type="checkbox" ${isSelected ? 'checked' : ''} tabindex="-1">
             </div>
             <div class="file-cell file-cell-icon">${fileIcon(f)}</div>
             <div class="file-cell file-cell-name" title="${escapeAttr(f.name)}">${escapeHtml(f.name)}</div>
             <div class="file-cell file-cell-size">${f.is_dir ? '-' : formatSize(f.size)}</div>
+            ${f.mode == null ? '' : `<div class="file-cell file-cell-mode" title="${escapeAttr(`${f.owner}:${f.group} ${f.mode.toString(8).padStart(4, '0')}`)}">${formatMode(f.mode)}</div>`}
             <div class="file-cell file-cell-time">${formatTime(f.modified)}</div>
+            <div class="file-cell file-cell-qr">${f.is_dir || raw.archive ? '' : QR_BUTTON}</div>
         </div>`;
     }).join('');
 
@@ -91,21 +124,35 @@ function handleClick(e) {
     const row = e.target.closest('.file-row');
     if (!row) return;
     const path = row.dataset.path;
-    // checkbox 或行点击 → 切换选中
-    toggleSelect(path);
+    if (e.target.closest('.file-qr')) {
+        showQrCode(path);
+        return;
+    }
+    // checkbox 或行点击 → 切换选中；按住 Shift 时选中上次点击的条目到这里之间的所有条目
+    if (e.shiftKey && anchor) {
+        window.getSelection()?.removeAllRanges();
+        selectRange(anchor, path);
+    } else {
+        toggleSelect(path);
+    }
+    anchor = path;
 }
 
 function handleDblClick(e) {
     const row = e.target.closest('.file-row');
-    if (!row) return;
+    if (!row || e.target.closest('.file-qr')) return;
     const path = row.dataset.path;
     const isDir = row.dataset.dir === 'true';
 
-    if (isDir) {
+    const raw = getRaw();
+    if (isDir || (!raw.archive && isArchive(path))) {
+        // 目录或归档文件 → 进入浏览
         navigate(path);
+    } else if (raw.archive) {
+        // 归档内的文件 → 单独解压下载
+        downloadFile(path);
     } else {
         // 文件双击 → 预览
-        const raw = getRaw();
         const file = raw.files.find(f => f.path === path);
         if (file) openPreview(file);
     }
@@ -126,6 +173,49 @@ function handleContextMenu(e) {
     state.contextMenu = { x: e.clientX, y: e.clientY };
 }
 
+/** 拖动选中的条目时带上全部选中项，否则只带这一项 */
+function handleDragStart(e) {
+    const row = e.target.closest('.file-row');
+    if (!row) return;
+    const path = row.dataset.path;
+    const selected = getRaw().selected;
+    dragged = selected.includes(path) ? [...selected] : [path];
+    e.dataTransfer.setData(DRAG_TYPE, JSON.stringify(dragged));
+    e.dataTransfer.effectAllowed = 'move';
+}
+
+/** 拖动中的行所在的目录行，排除被拖动的目录自己 */
+function dropRow(e) {
+    if (!e.dataTransfer?.types.includes(DRAG_TYPE)) return null;
+    const row = e.target.closest('.file-row.is-dir');
+    return row && !dragged.includes(row.dataset.path) ? row : null;
+}
+
+function handleDragOver(e) {
+    const row = dropRow(e);
+    if (!row) return;
+    e.preventDefault();
+    e.dataTransfer.dropEffect = 'move';
+    row.classList.add('drop-target');
+}
+
+function handleDrop(e) {
+    const row = dropRow(e);
+    if (!row) return;
+    e.preventDefault();
+    e.stopPropagation();
+    row.classList.remove('drop-target');
+    moveDropped(e.dataTransfer, row.dataset.path);
+}
+
+/** 网格视图下图片显示缩略图 */
+function gridIcon(f) {
+    const ext = f.name.split('.').pop()?.toLowerCase() || '';
+    if (f.is_dir || getRaw().archive || !THUMB_EXTS.has(ext)) return fileIcon(f);
+    const size = 160 * (window.devicePixelRatio || 1);
+    return `<img class="file-thumb" src="${thumbnailUrl(f.path, size, size)}" loading="lazy" alt="">`;
+}
+
 function escapeHtml(text) {
     const d = document.createElement('div');
     d.textContent = text;
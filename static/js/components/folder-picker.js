@@ -0,0 +1,118 @@
+/**
+ * 目录选择框
+ * 移动、复制等操作选择目标目录；目录树逐级展开，每次只请求下一级子目录
+ */
+
+import { listDirs } from '../api.js';
+
+/**
+ * 显示选择框
+ * @param {{title: string, confirmLabel?: string, current?: string}} options
+ * @returns {Promise<string | null>} 选中目录的相对路径（根目录为空字符串），取消时为 null
+ */
+export function pickFolder({ title, confirmLabel = '确定', current = '' }) {
+    return new Promise((resolve) => {
+        let selected = current;
+
+        const overlay = document.createElement('div');
+        overlay.className = 'folder-picker';
+        overlay.innerHTML = `
+            <div class="upload-confirm-inner glass">
+                <div class="upload-confirm-header">
+                    <h3>${escapeHtml(title)}</h3>
+                    <span class="upload-confirm-summary"></span>
+                </div>
+                <div class="upload-confirm-tree">
+                    <div class="folder-picker-node">
+                        ${renderRow({ name: '根目录', path: '', has_children: true })}
+                    </div>
+                </div>
+                <div class="upload-confirm-actions">
+                    <button class="btn btn-ghost" data-action="cancel">取消</button>
+                    <button class="btn btn-primary" data-action="confirm">${escapeHtml(confirmLabel)}</button>
+                </div>
+            </div>`;
+        document.body.appendChild(overlay);
+
+        const tree = overlay.querySelector('.upload-confirm-tree');
+        const summaryEl = overlay.querySelector('.upload-confirm-summary');
+
+        const select = (path) => {
+            selected = path;
+            summaryEl.textContent = `/${path}`;
+            tree.querySelectorAll('.folder-picker-row').forEach(row => {
+                row.classList.toggle('selected', row.dataset.path === path);
+            });
+        };
+
+        const toggle = async (node) => {
+            const row = node.querySelector(':scope > .folder-picker-row');
+            let children = node.querySelector(':scope > .folder-picker-children');
+            if (children) {
+                children.hidden = !children.hidden;
+                row.classList.toggle('expanded', !children.hidden);
+                return;
+            }
+            children = document.createElement('div');
+            children.className = 'folder-picker-children';
+            node.appendChild(children);
+            row.classList.add('expanded', 'loading');
+            try {
+                const data = await listDirs(row.dataset.path);
+                children.innerHTML = data.dirs.length
+                    ? data.dirs.map(d => `<div class="folder-picker-node">${renderRow(d)}</div>`).join('')
+                    : '<div class="folder-picker-empty">没有子目录</div>';
+                select(selected);
+            } catch (e) {
+                children.innerHTML = `<div class="folder-picker-empty">${escapeHtml(e.message)}</div>`;
+            } finally {
+                row.classList.remove('loading');
+            }
+        };
+
+        tree.addEventListener('click', (e) => {
+            const row = e.target.closest('.folder-picker-row');
+            if (!row) return;
+            if (e.target.closest('.folder-picker-toggle')) {
+                toggle(row.parentElement);
+            } else {
+                select(row.dataset.path);
+            }
+        });
+        tree.addEventListener('dblclick', (e) => {
+            const row = e.target.closest('.folder-picker-row');
+            if (row && row.dataset.children === 'true') toggle(row.parentElement);
+        });
+
+        const close = (result) => {
+            document.removeEventListener('keydown', onKey);
+            overlay.remove();
+            resolve(result);
+        };
+        const onKey = (e) => {
+            if (e.key === 'Escape') close(null);
+        };
+        document.addEventListener('keydown', onKey);
+
+        overlay.querySelector('[data-action="cancel"]').addEventListener('click', () => close(null));
+        overlay.querySelector('[data-action="confirm"]').addEventListener('click', () => close(selected));
+
+        select(current);
+        toggle(tree.querySelector('.folder-picker-node'));
+    });
+}
+
+function renderRow(dir) {
+    return `
+        <div class="folder-picker-row" data-path="${escapeHtml(dir.path)}" data-children="${dir.has_children}">
+            <span class="folder-picker-toggle">${dir.has_children ? '▸' : ''}</span>
+            <span class="upload-confirm-name">📁 ${escapeHtml(dir.name)}</span>
+        </div>`;
+}
+
+function escapeHtml(text) {
+    const d = document.createElement('div');
+    d.textContent = text;
+    // 路径也会放进属性值，引号需要转义
+    return d.innerHTML.replace(/"/g, '&quot;');
+}
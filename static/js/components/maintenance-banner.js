@@ -0,0 +1,45 @@
+/**
+ * 维护横幅
+ * 管理员暂停上传或即将关闭服务器时显示说明，恢复后自动隐藏
+ */
+
+import { state, subscribe } from '../store.js';
+import { getInfo } from '../api.js';
+
+/** 正常时的轮询间隔；暂停期间缩短，恢复后排队的上传能尽快继续 */
+const POLL_MS = 30000;
+const PAUSED_POLL_MS = 5000;
+
+let bannerEl = null;
+let timer = null;
+
+export function initMaintenanceBanner(notice) {
+    bannerEl = document.getElementById('maintenance-banner');
+    if (!bannerEl) return;
+
+    subscribe('maintenance', () => {
+        render();
+        schedule();
+    });
+    state.maintenance = notice || null;
+    render();
+    schedule();
+}
+
+function render() {
+    bannerEl.textContent = state.maintenance || '';
+    bannerEl.hidden = !state.maintenance;
+}
+
+function schedule() {
+    clearTimeout(timer);
+    timer = setTimeout(poll, state.maintenance ? PAUSED_POLL_MS : POLL_MS);
+}
+
+async function poll() {
+    try {
+        const info = await getInfo();
+        state.maintenance = info.maintenance || null;
+    } catch { /* 服务器关闭或网络中断时保留当前状态 */ }
+    schedule();
+}
@@ -0,0 +1,137 @@
+/**
+ * 留言板
+ * 使用共享目录的人可以留下说明（如“大 ISO 还在上传，别重启”），当前目录的留言显示在文件列表上方
+ */
+
+import { listMessages, postMessage, deleteMessage, messageStreamUrl } from '../api.js';
+import { getRaw, subscribe } from '../store.js';
+import { navigate } from '../router.js';
+import { formatTime } from '../utils/format.js';
+import { showToast } from './toast.js';
+import { deviceName } from './p2p.js';
+
+/** 留言，新的在前 */
+let messages = [];
+let unseen = 0;
+let panelEl, listEl, inputEl, toggleEl, notesEl, dirOnlyEl;
+
+export function initMessageBoard() {
+    panelEl = document.getElementById('board-panel');
+    toggleEl = document.getElementById('board-toggle');
+    notesEl = document.getElementById('dir-notes');
+    if (!panelEl || !toggleEl) return;
+    toggleEl.hidden = false;
+    listEl = panelEl.querySelector('.board-list');
+    inputEl = panelEl.querySelector('.board-input');
+    dirOnlyEl = panelEl.querySelector('.board-dir-only');
+
+    toggleEl.addEventListener('click', () => {
+        panelEl.classList.toggle('active');
+        if (panelEl.classList.contains('active')) {
+            unseen = 0;
+            inputEl.focus();
+        }
+        render();
+    });
+    panelEl.querySelector('.board-close').addEventListener('click', () => panelEl.classList.remove('active'));
+    panelEl.querySelector('.board-send').addEventListener('click', send);
+    inputEl.addEventListener('keydown', (e) => {
+        if (e.key === 'Enter' && (e.ctrlKey || e.metaKey)) {
+            e.preventDefault();
+            send();
+        }
+    });
+    listEl.addEventListener('click', onListClick);
+    notesEl?.addEventListener('click', onListClick);
+    subscribe('currentPath', render);
+
+    load();
+    const source = new EventSource(messageStreamUrl());
+    source.addEventListener('message', (e) => {
+        const msg = JSON.parse(e.data);
+        if (messages.some(m => m.id === msg.id)) return;
+        messages = [msg, ...messages];
+        if (!panelEl.classList.contains('active')) unseen++;
+        render();
+    });
+    source.addEventListener('deleted', (e) => {
+        const id = Number(e.data);
+        messages = messages.filter(m => m.id !== id);
+        render();
+    });
+    source.addEventListener('reload', load);
+}
+
+async function load() {
+    try {
+        messages = await listMessages();
+        render();
+    } catch { /* 无权限时不显示 */ }
+}
+
+async function send() {
+    const text = inputEl.value.trim();
+    if (!text) return;
+    const path = dirOnlyEl.checked ? getRaw().currentPath : '';
+    try {
+        const msg = await postMessage(text, deviceName(), path);
+        inputEl.value = '';
+        if (!messages.some(m => m.id === msg.id)) messages = [msg, ...messages];
+        render();
+    } catch (e) {
+        showToast(`留言失败: ${e.message}`, 'error');
+    }
+}
+
+function onListClick(e) {
+    const pathEl = e.target.closest('[data-path]');
+    if (pathEl) {
+        navigate(pathEl.dataset.path);
+        return;
+    }
+    const delEl = e.target.closest('[data-delete]');
+    if (delEl && confirm('删除这条留言？')) {
+        deleteMessage(delEl.dataset.delete).catch(err => showToast(`删除失败: ${err.message}`, 'error'));
+    }
+}
+
+function messageHtml(m) {
+    const where = m.path
+        ? `<a class="board-path" data-path="${escapeHtml(m.path)}">📁 ${escapeHtml(m.path)}</a>`
+        : '';
+    return `
+        <div class="board-item">
+            <div class="board-text">${escapeHtml(m.text)}</div>
+            <div class="clipboard-meta">
+                <span>${escapeHtml(m.author)} · ${formatTime(m.at)}</span>
+                ${where}
+                <button class="btn btn-sm btn-ghost" data-delete="${m.id}">删除</button>
+            </div>
+        </div>`;
+}
+
+function render() {
+    if (!listEl) return;
+    toggleEl.classList.toggle('has-peers', unseen > 0);
+    toggleEl.dataset.count = unseen || '';
+
+    listEl.innerHTML = messages.length
+        ? messages.map(messageHtml).join('')
+        : '<div class="p2p-empty">还没有留言</div>';
+
+    if (!notesEl) return;
+    const current = getRaw().currentPath.replace(/^\/+|\/+$/g, '');
+    const here = messages.filter(m => m.path && m.path === current);
+    notesEl.innerHTML = here.map(m => `
+        <div class="dir-note">
+            <span class="dir-note-text">📌 ${escapeHtml(m.text)}</span>
+            <span class="dir-note-meta">${escapeHtml(m.author)} · ${formatTime(m.at)}</span>
+        </div>`).join('');
+    notesEl.hidden = !here.length;
+}
+
+function escapeHtml(text) {
+    const d = document.createElement('div');
+    d.textContent = text;
+    return d.innerHTML.replace(/"/g, '&quot;');
+}
@@ -0,0 +1,78 @@
+/**
+ * 附近设备面板
+ * 列出通过组播发现的 LocalSend 设备和其他运行中的实例，可以把选中的文件推送过去
+ */
+
+import { localSendDevices } from '../api.js';
+import { getRaw, subscribe } from '../store.js';
+import { sendToDevice } from '../actions.js';
+
+/** 设备列表刷新间隔 */
+const REFRESH_MS = 15000;
+
+let devices = [];
+let panelEl, listEl, toggleEl;
+
+export function initNearbyDevices() {
+    panelEl = document.getElementById('nearby-panel');
+    toggleEl = document.getElementById('nearby-toggle');
+    if (!panelEl || !toggleEl) return;
+    toggleEl.hidden = false;
+    listEl = panelEl.querySelector('.nearby-list');
+
+    toggleEl.addEventListener('click', () => {
+        panelEl.classList.toggle('active');
+        if (panelEl.classList.contains('active')) refresh();
+    });
+    panelEl.querySelector('.nearby-close').addEventListener('click', () => panelEl.classList.remove('active'));
+    listEl.addEventListener('click', (e) => {
+        const btn = e.target.closest('[data-send]');
+        if (!btn) return;
+        const device = devices.find(d => d.fingerprint === btn.dataset.send);
+        const paths = [...getRaw().selected];
+        if (device && paths.length) sendToDevice(device, paths);
+    });
+    // 选中项变化时更新“发送”按钮
+    subscribe('selected', render);
+
+    refresh();
+    setInterval(refresh, REFRESH_MS);
+}
+
+async function refresh() {
+    try {
+        devices = await localSendDevices();
+    } catch {
+        devices = [];
+    }
+    render();
+}
+
+function render() {
+    if (!listEl) return;
+    toggleEl.classList.toggle('has-peers', devices.length > 0);
+    toggleEl.dataset.count = devices.length || '';
+
+    if (!devices.length) {
+        listEl.innerHTML = '<div class="p2p-empty">附近没有发现设备。其他设备上打开 LocalSend，或以 --localsend 启动本程序即可出现在这里</div>';
+        return;
+    }
+    const selected = getRaw().selected.length;
+    listEl.innerHTML = devices.map(d => `
+        <div class="p2p-peer">
+            <div class="nearby-info">
+                <span class="p2p-peer-name" title="${escapeHtml(d.alias)}">${escapeHtml(d.alias)}</span>
+                <span class="nearby-meta">${escapeHtml(d.device_model || d.device_type || '未知设备')} · ${escapeHtml(d.ip)}</span>
+            </div>
+            ${d.web_url ? `<a class="btn btn-sm btn-ghost" href="${escapeHtml(d.web_url)}" target="_blank" rel="noopener">打开</a>` : ''}
+            <button class="btn btn-sm" data-send="${escapeHtml(d.fingerprint)}" ${selected ? '' : 'disabled title="先在文件列表中选择要发送的文件"'}>
+                ${selected ? `发送 ${selected} 项` : '发送'}
+            </button>
+        </div>`).join('');
+}
+
+function escapeHtml(text) {
+    const d = document.createElement('div');
+    d.textContent = text;
+    return d.innerHTML.replace(/"/g, '&quot;');
+}
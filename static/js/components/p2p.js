@@ -0,0 +1,343 @@
+/**
+ * 浏览器间直传
+ * 服务端只通过 WebSocket 转发 WebRTC 信令，文件数据经 DataChannel 点对点传输，不经过服务器磁盘
+ */
+
+import { signalUrl } from '../api.js';
+import { saveBlob } from '../downloader.js';
+import { formatSize } from '../utils/format.js';
+import { showToast } from './toast.js';
+import { trackTransfer } from './upload-panel.js';
+
+const CHUNK_SIZE = 64 * 1024;
+/** DataChannel 待发送数据超过该值时等待缓冲排空 */
+const BUFFER_HIGH = 4 * 1024 * 1024;
+const BUFFER_LOW = 1024 * 1024;
+const RECONNECT_DELAY = 3000;
+const NAME_KEY = 'deviceName';
+
+let ws = null;
+let selfId = null;
+/** 在线的其他设备 id -> {id, name} */
+const peers = new Map();
+/** 进行中的传输 transferId -> 传输状态 */
+const transfers = new Map();
+let panelEl, listEl, toggleEl, nameEl;
+
+export function initP2P() {
+    panelEl = document.getElementById('p2p-panel');
+    toggleEl = document.getElementById('p2p-toggle');
+    if (!panelEl || !toggleEl || typeof RTCPeerConnection === 'undefined') {
+        toggleEl?.remove();
+        return;
+    }
+    listEl = panelEl.querySelector('.p2p-peers');
+    nameEl = panelEl.querySelector('.p2p-self-name');
+
+    toggleEl.addEventListener('click', () => panelEl.classList.toggle('active'));
+    panelEl.querySelector('.p2p-close').addEventListener('click', () => panelEl.classList.remove('active'));
+    panelEl.querySelector('.p2p-rename').addEventListener('click', () => {
+        const name = prompt('本机名称:', deviceName());
+        if (!name || !name.trim()) return;
+        localStorage.setItem(NAME_KEY, name.trim());
+        // 名称在连接时登记，重连后生效
+        ws?.close();
+    });
+    listEl.addEventListener('click', (e) => {
+        const btn = e.target.closest('[data-peer]');
+        if (btn) pickFiles(btn.dataset.peer);
+    });
+
+    connect();
+}
+
+/** 本机显示名称，默认取浏览器和系统 */
+/** 本机显示名称，剪贴板同步也使用 */
+export function deviceName() {
+    const saved = localStorage.getItem(NAME_KEY);
+    if (saved) return saved;
+    const ua = navigator.userAgent;
+    const browser = /Edg\//.test(ua) ? 'Edge' : /Chrome\//.test(ua) ? 'Chrome'
+        : /Firefox\//.test(ua) ? 'Firefox' : /Safari\//.test(ua) ? 'Safari' : '浏览器';
+    const os = /Android/.test(ua) ? 'Android' : /iPhone|iPad/.test(ua) ? 'iOS'
+        : /Windows/.test(ua) ? 'Windows' : /Mac OS/.test(ua) ? 'macOS' : /Linux/.test(ua) ? 'Linux' : '';
+    return os ? `${browser} · ${os}` : browser;
+}
+
+function connect() {
+    ws = new WebSocket(signalUrl(deviceName()));
+    ws.onmessage = (e) => {
+        let msg;
+        try {
+            msg = JSON.parse(e.data);
+        } catch {
+            return;
+        }
+        handleServerMessage(msg);
+    };
+    ws.onclose = () => {
+        ws = null;
+        selfId = null;
+        peers.clear();
+        for (const t of transfers.values()) failTransfer(t, '信令连接已断开');
+        render();
+        setTimeout(connect, RECONNECT_DELAY);
+    };
+}
+
+function handleServerMessage(msg) {
+    switch (msg.type) {
+        case 'welcome':
+            selfId = msg.id;
+            msg.peers.forEach(p => peers.set(p.id, p));
+            break;
+        case 'peer-joined':
+            peers.set(msg.peer.id, msg.peer);
+            break;
+        case 'peer-left':
+            peers.delete(msg.id);
+            for (const t of transfers.values()) {
+                if (t.peer === msg.id) failTransfer(t, '对方已离线');
+            }
+            break;
+        case 'signal':
+            handleSignal(msg.from, msg.data).catch(e => console.warn('p2p signal failed', e));
+            break;
+        case 'error':
+            console.warn('signaling error:', msg.message);
+            break;
+    }
+    render();
+}
+
+function signal(to, data) {
+    if (ws?.readyState === WebSocket.OPEN) {
+        ws.send(JSON.stringify({ type: 'signal', to, data }));
+    }
+}
+
+async function handleSignal(from, data) {
+    const t = transfers.get(data.transferId);
+    switch (data.kind) {
+        case 'request':
+            return receiveRequest(from, data);
+        case 'accept':
+            if (t?.role === 'send') await startSending(t);
+            return;
+        case 'reject':
+            if (t) {
+                transfers.delete(t.id);
+                showToast(`${peerName(from)} 拒绝了文件`, 'error');
+            }
+            return;
+        case 'offer':
+            if (t?.role === 'recv') await answerOffer(t, data.sdp);
+            return;
+        case 'answer':
+            if (t?.pc) {
+                await t.pc.setRemoteDescription(data.sdp);
+                await flushIce(t);
+            }
+            return;
+        case 'ice':
+            if (!t) return;
+            // 远端描述设置之前到达的候选先排队
+            if (t.pc?.remoteDescription) await t.pc.addIceCandidate(data.candidate);
+            else t.pendingIce.push(data.candidate);
+            return;
+        case 'cancel':
+            if (t) failTransfer(t, '对方已取消');
+            return;
+    }
+}
+
+function peerName(id) {
+    return peers.get(id)?.name || '对方';
+}
+
+function pickFiles(peerId) {
+    const input = document.createElement('input');
+    input.type = 'file';
+    input.multiple = true;
+    input.addEventListener('change', () => {
+        if (input.files.length) sendFiles(peerId, [...input.files]);
+    });
+    input.click();
+}
+
+function sendFiles(peerId, files) {
+    const t = newTransfer(peerId, 'send');
+    t.files = files;
+    signal(peerId, {
+        kind: 'request',
+        transferId: t.id,
+        files: files.map(f => ({ name: f.name, size: f.size })),
+    });
+    showToast(`等待 ${peerName(peerId)} 接收…`);
+}
+
+function receiveRequest(from, data) {
+    const files = Array.isArray(data.files) ? data.files : [];
+    const total = files.reduce((sum, f) => sum + (Number(f.size) || 0), 0);
+    const names = files.slice(0, 10).map(f => `· ${f.name}`).join('\n');
+    const more = files.length > 10 ? `\n… 等 ${files.length} 个文件` : '';
+    const ok = confirm(`${peerName(from)} 想发送 ${files.length} 个文件（${formatSize(total)}）：\n${names}${more}\n\n是否接收？`);
+    if (!ok) {
+        signal(from, { kind: 'reject', transferId: data.transferId });
+        return;
+    }
+    const t = newTransfer(from, 'recv', data.transferId);
+    signal(from, { kind: 'accept', transferId: t.id });
+}
+
+function newTransfer(peerId, role, id = `${Date.now().toString(36)}${Math.random().toString(36).slice(2, 8)}`) {
+    const t = { id, peer: peerId, role, pc: null, pendingIce: [], tracks: [], finished: false };
+    transfers.set(id, t);
+    return t;
+}
+
+function createPeerConnection(t) {
+    // 局域网内主机候选即可连通，不需要 STUN / TURN
+    const pc = new RTCPeerConnection({ iceServers: [] });
+    pc.onicecandidate = (e) => {
+        if (e.candidate) signal(t.peer, { kind: 'ice', transferId: t.id, candidate: e.candidate });
+    };
+    pc.onconnectionstatechange = () => {
+        if (pc.connectionState === 'failed') failTransfer(t, '无法建立点对点连接');
+    };
+    t.pc = pc;
+    return pc;
+}
+
+async function flushIce(t) {
+    const pending = t.pendingIce.splice(0);
+    for (const candidate of pending) await t.pc.addIceCandidate(candidate);
+}
+
+async function startSending(t) {
+    const pc = createPeerConnection(t);
+    const channel = pc.createDataChannel('files', { ordered: true });
+    channel.binaryType = 'arraybuffer';
+    channel.bufferedAmountLowThreshold = BUFFER_LOW;
+    channel.onopen = () => {
+        pumpFiles(t, channel).catch(e => failTransfer(t, e.message || '发送失败'));
+    };
+    // 接收方收完后关闭通道
+    channel.onclose = () => finishTransfer(t);
+
+    await pc.setLocalDescription(await pc.createOffer());
+    signal(t.peer, { kind: 'offer', transferId: t.id, sdp: pc.localDescription });
+}
+
+async function pumpFiles(t, channel) {
+    for (const file of t.files) {
+        const handle = track(t, file.name, file.size, 'upload');
+        channel.send(JSON.stringify({ name: file.name, size: file.size }));
+        let offset = 0;
+        while (offset < file.size) {
+            if (t.finished) return;
+            if (channel.bufferedAmount > BUFFER_HIGH) {
+                await new Promise(resolve => {
+                    channel.addEventListener('bufferedamountlow', resolve, { once: true });
+                });
+            }
+            const chunk = await file.slice(offset, offset + CHUNK_SIZE).arrayBuffer();
+            channel.send(chunk);
+            offset += chunk.byteLength;
+            handle.progress(offset);
+        }
+        handle.done();
+    }
+    channel.send(JSON.stringify({ done: true }));
+}
+
+async function answerOffer(t, sdp) {
+    const pc = createPeerConnection(t);
+    pc.ondatachannel = (e) => receiveFiles(t, e.channel);
+    await pc.setRemoteDescription(sdp);
+    await flushIce(t);
+    await pc.setLocalDescription(await pc.createAnswer());
+    signal(t.peer, { kind: 'answer', transferId: t.id, sdp: pc.localDescription });
+}
+
+function receiveFiles(t, channel) {
+    channel.binaryType = 'arraybuffer';
+    let current = null;
+    const complete = () => {
+        saveBlob(new Blob(current.chunks), current.name);
+        current.track.done();
+        current = null;
+    };
+    channel.onmessage = (e) => {
+        if (typeof e.data === 'string') {
+            const msg = JSON.parse(e.data);
+            if (msg.done) {
+                channel.close();
+                finishTransfer(t);
+                return;
+            }
+            current = { name: msg.name, size: msg.size, chunks: [], received: 0 };
+            current.track = track(t, msg.name, msg.size, 'download');
+            if (!msg.size) complete();
+            return;
+        }
+        if (!current) return;
+        current.chunks.push(e.data);
+        current.received += e.data.byteLength;
+        current.track.progress(current.received);
+        if (current.received >= current.size) complete();
+    };
+    channel.onclose = () => finishTransfer(t);
+}
+
+/** 在传输面板中显示单个文件，取消时通知对方 */
+function track(t, name, size, direction) {
+    const handle = trackTransfer({
+        name,
+        size,
+        direction,
+        onCancel: () => {
+            signal(t.peer, { kind: 'cancel', transferId: t.id });
+            failTransfer(t, '已取消');
+        },
+    });
+    t.tracks.push(handle);
+    return handle;
+}
+
+/** 连接关闭：未完成的文件标记失败 */
+function finishTransfer(t) {
+    failTransfer(t, '连接已断开');
+}
+
+function failTransfer(t, message) {
+    if (t.finished) return;
+    t.finished = true;
+    transfers.delete(t.id);
+    // 已完成的文件不受影响
+    t.tracks.forEach(handle => handle.fail(message));
+    t.pc?.close();
+}
+
+function render() {
+    if (!listEl) return;
+    toggleEl.classList.toggle('has-peers', peers.size > 0);
+    toggleEl.dataset.count = peers.size || '';
+    nameEl.textContent = selfId ? deviceName() : `${deviceName()}（未连接）`;
+
+    if (!peers.size) {
+        listEl.innerHTML = '<div class="p2p-empty">没有其他在线设备，在另一台设备上打开本页面即可看到</div>';
+        return;
+    }
+    listEl.innerHTML = [...peers.values()].map(p => `
+        <div class="p2p-peer">
+            <span class="p2p-peer-name" title="${escapeHtml(p.name)}">${escapeHtml(p.name)}</span>
+            <button class="btn btn-sm" data-peer="${escapeHtml(p.id)}">发送文件</button>
+        </div>`).join('');
+}
+
+function escapeHtml(text) {
+    const d = document.createElement('div');
+    d.textContent = text;
+    return d.innerHTML.replace(/"/g, '&quot;');
+}
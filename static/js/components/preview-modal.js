@@ -1,14 +1,21 @@
 /**
  * 文件预览模态框
- * 支持：图片、视频、音频、文本、Markdown、PDF
+ * 支持：图片、视频、音频、文本、Markdown、PDF，以及日志文件的实时跟随
  */
 
 import { state, subscribe } from '../store.js';
 import { closePreview } from '../actions.js';
-import { previewUrl, downloadUrl } from '../api.js';
+import { previewUrl, downloadUrl, thumbnailUrl, hlsUrl, transcodeUrl, tailUrl } from '../api.js';
+
+/** 浏览器无法原生播放、需服务端转码的视频格式 */
+const TRANSCODE_EXTS = new Set(['mkv', 'avi', 'wmv', 'flv', 'mpg', 'mpeg', 'ts', 'm2ts', '3gp', 'rmvb']);
+
+/** 跟随模式下保留的最大行数 */
+const TAIL_MAX_LINES = 5000;
 
 let modalEl = null;
 let contentEl = null;
+let tailSource = null;
 
 export function initPreviewModal() {
     modalEl = document.getElementById('preview-modal');
@@ -43,6 +50,7 @@ export function initPreviewModal() {
 }
 
 async function render() {
+    stopTail();
     const file = state.preview;
     if (!file) {
         modalEl.classList.remove('open');
@@ -59,13 +67,20 @@ async function render() {
     const rawUrl = downloadUrl(file.path, false);
 
     if (mime.startsWith('image/')) {
-        contentEl.innerHTML = `<img src="${rawUrl}" alt="${escapeAttr(file.name)}" class="preview-image">`;
+        // 按屏幕尺寸请求缩放图，避免移动端拉取原图
+        const dpr = window.devicePixelRatio || 1;
+        const imgUrl = mime === 'image/svg+xml'
+            ? rawUrl
+            : thumbnailUrl(file.path, window.innerWidth * dpr, window.innerHeight * dpr);
+        contentEl.innerHTML = `<img src="${imgUrl}" alt="${escapeAttr(file.name)}" class="preview-image">`;
     } else if (mime.startsWith('video/')) {
-        contentEl.innerHTML = `<video controls autoplay class="preview-video"><source src="${rawUrl}" type="${mime}"></video>`;
+        renderVideo(file, mime, rawUrl);
     } else if (mime.startsWith('audio/')) {
         contentEl.innerHTML = `<audio controls autoplay class="preview-audio"><source src="${rawUrl}" type="${mime}"></audio>`;
     } else if (mime === 'application/pdf') {
         contentEl.innerHTML = `<iframe src="${rawUrl}" class="preview-pdf"></iframe>`;
+    } else if (file.tail) {
+        renderTail(file);
     } else if (mime.startsWith('text/') || isTextLike(file.name)) {
         // 文本/Markdown：服务端预览 API
         try {
@@ -97,6 +112,77 @@ async function render() {
     }
 }
 
+/** 视频预览：不支持的容器格式走 HLS（原生支持时）或转码 MP4 流 */
+function renderVideo(file, mime, rawUrl) {
+    const ext = file.name.split('.').pop()?.toLowerCase() || '';
+    const video = document.createElement('video');
+    video.controls = true;
+    video.autoplay = true;
+    video.className = 'preview-video';
+
+    if (TRANSCODE_EXTS.has(ext)) {
+        video.src = video.canPlayType('application/vnd.apple.mpegurl')
+            ? hlsUrl(file.path)
+            : transcodeUrl(file.path);
+    } else {
+        video.innerHTML = `<source src="${rawUrl}" type="${mime}">`;
+    }
+
+    video.addEventListener('error', () => {
+        if (state.preview !== file) return;
+        contentEl.innerHTML = `<div class="preview-error">视频无法播放，服务端可能未启用转码</div>`;
+    });
+
+    contentEl.innerHTML = '';
+    contentEl.appendChild(video);
+}
+
+/** 跟随模式：通过 SSE 接收新增行，滚动条在底部时自动跟随 */
+function renderTail(file) {
+    contentEl.innerHTML = `<pre class="preview-text preview-tail"><code></code></pre>`;
+    const pre = contentEl.querySelector('.preview-tail');
+    const code = pre.querySelector('code');
+
+    let lineCount = 0;
+
+    tailSource = new EventSource(tailUrl(file.path));
+    tailSource.addEventListener('lines', (e) => {
+        const lines = JSON.parse(e.data);
+        if (!lines.length) return;
+        const atBottom = pre.scrollHeight - pre.scrollTop - pre.clientHeight < 40;
+        code.append(document.createTextNode(lines.join('\n') + '\n'));
+        lineCount += lines.length;
+        // 超出上限时丢弃最早的内容
+        if (lineCount > TAIL_MAX_LINES * 1.2) {
+            const kept = code.textContent.split('\n').slice(-TAIL_MAX_LINES - 1);
+            code.textContent = kept.join('\n');
+            lineCount = TAIL_MAX_LINES;
+        }
+        if (atBottom) pre.scrollTop = pre.scrollHeight;
+    });
+    tailSource.addEventListener('truncated', () => {
+        code.textContent = '';
+        lineCount = 0;
+    });
+    // 服务端重连会重新发送末尾行，这里不自动重连
+    tailSource.onerror = () => {
+        stopTail();
+        contentEl.insertAdjacentHTML('beforeend', `<div class="preview-error">跟随已断开</div>`);
+    };
+}
+
+function stopTail() {
+    if (tailSource) {
+        tailSource.close();
+        tailSource = null;
+    }
+}
+
+/** 可以实时跟随的文件 */
+export function isTailable(name) {
+    return /\.(log|txt|out)$/i.test(name);
+}
+
 function guessMime(name) {
     const ext = name.split('.').pop()?.toLowerCase() || '';
     const map = {
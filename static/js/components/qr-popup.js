@@ -0,0 +1,49 @@
+/**
+ * 下载二维码弹窗
+ * 站在屏幕旁边的人用手机扫码即可直接下载该文件
+ */
+
+import { getQrCode } from '../api.js';
+import { showToast } from './toast.js';
+
+/** 弹出 path 对应文件的下载二维码 */
+export async function showQrCode(path) {
+    let qr;
+    try {
+        qr = await getQrCode(path);
+    } catch (e) {
+        showToast(`生成二维码失败: ${e.message}`, 'error');
+        return;
+    }
+
+    const name = path.split('/').pop();
+    const overlay = document.createElement('div');
+    overlay.className = 'upload-confirm qr-popup';
+    overlay.innerHTML = `
+        <div class="qr-popup-inner glass">
+            <h3 class="qr-popup-name" title="${escapeHtml(name)}">${escapeHtml(name)}</h3>
+            <div class="qr-popup-code">${qr.svg}</div>
+            <div class="qr-popup-url">${escapeHtml(qr.url)}</div>
+            <button class="btn btn-ghost" data-action="close">关闭</button>
+        </div>`;
+    document.body.appendChild(overlay);
+
+    const close = () => {
+        document.removeEventListener('keydown', onKey);
+        overlay.remove();
+    };
+    const onKey = (e) => {
+        if (e.key === 'Escape') close();
+    };
+    document.addEventListener('keydown', onKey);
+    overlay.addEventListener('click', (e) => {
+        // 点击遮罩或关闭按钮
+        if (e.target === overlay || e.target.closest('[data-action="close"]')) close();
+    });
+}
+
+function escapeHtml(text) {
+    const d = document.createElement('div');
+    d.textContent = text;
+    return d.innerHTML.replace(/"/g, '&quot;');
+}
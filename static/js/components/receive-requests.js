@@ -0,0 +1,56 @@
+/**
+ * 接收模式：其他设备通过 LocalSend 推送文件时在页面上确认
+ * 控制台和所有打开的页面都会收到请求，任一处先作出的决定生效
+ */
+
+import { localSendRequestsUrl, decideLocalSend } from '../api.js';
+import { formatSize } from '../utils/format.js';
+import { showToast } from './toast.js';
+
+/** 已经弹出过确认框的请求 */
+const asked = new Set();
+/** 等待弹框的请求；confirm() 会阻塞，逐个询问 */
+const queue = [];
+let asking = false;
+
+export function initReceiveRequests() {
+    const source = new EventSource(localSendRequestsUrl());
+    source.addEventListener('requests', (e) => {
+        let list;
+        try {
+            list = JSON.parse(e.data);
+        } catch {
+            return;
+        }
+        const ids = new Set(list.map(r => r.id));
+        // 已在别处处理或超时的请求不再询问
+        for (let i = queue.length - 1; i >= 0; i--) {
+            if (!ids.has(queue[i].id)) queue.splice(i, 1);
+        }
+        for (const r of list) {
+            if (asked.has(r.id)) continue;
+            asked.add(r.id);
+            queue.push(r);
+        }
+        askNext();
+    });
+}
+
+async function askNext() {
+    if (asking || !queue.length) return;
+    asking = true;
+    const r = queue.shift();
+    const more = r.files > r.names.length ? `\n……共 ${r.files} 个文件` : '';
+    const accept = confirm(
+        `${r.sender}（${r.ip}）想发送 ${r.files} 个文件（${formatSize(r.total_size)}）到服务器：\n${r.names.join('\n')}${more}\n\n是否接收？`,
+    );
+    try {
+        await decideLocalSend(r.id, accept);
+        if (accept) showToast(`正在接收 ${r.sender} 的文件`, 'success');
+    } catch (e) {
+        // 404：已在控制台或其他页面处理，或发送方已放弃
+        if (e.status !== 404) showToast(`操作失败: ${e.message}`, 'error');
+    }
+    asking = false;
+    askNext();
+}
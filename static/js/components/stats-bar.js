@@ -0,0 +1,55 @@
+/**
+ * 目录统计栏
+ * 服务端按类型汇总当前目录的文件数量和大小
+ */
+
+import { state, subscribe, getRaw } from '../store.js';
+import { fileStats } from '../api.js';
+import { formatSize } from '../utils/format.js';
+
+const LABELS = {
+    image: '图片',
+    video: '视频',
+    audio: '音频',
+    document: '文档',
+    archive: '压缩包',
+    code: '代码',
+    other: '其他',
+};
+
+let barEl = null;
+let seq = 0;
+
+export function initStatsBar() {
+    barEl = document.getElementById('stats-bar');
+    if (!barEl) return;
+
+    // 文件列表刷新后重新统计
+    subscribe('files', load);
+}
+
+async function load() {
+    const raw = getRaw();
+    // 归档内部没有对应的目录统计
+    if (raw.archive) {
+        barEl.innerHTML = '';
+        return;
+    }
+
+    const id = ++seq;
+    try {
+        const data = await fileStats(state.currentPath);
+        if (id !== seq) return;
+        render(data);
+    } catch {
+        if (id === seq) barEl.innerHTML = '';
+    }
+}
+
+function render(data) {
+    const summary = `<span class="stats-item">${data.dirs} 个文件夹 · ${data.files} 个文件 · ${formatSize(data.size)}</span>`;
+    const cats = data.categories.map(c =>
+        `<span class="stats-item stats-cat stats-cat-${c.category}" title="${formatSize(c.size)}">${LABELS[c.category] || c.category} ${c.count} · ${formatSize(c.size)}</span>`
+    ).join('');
+    barEl.innerHTML = summary + cats;
+}
@@ -18,22 +18,36 @@ function getContainer() {
  * @param {string} message
  * @param {'info'|'success'|'error'|'warning'} type
  * @param {number} duration ms
+ * @param {{label: string, onClick: () => void}} [action] 附带的按钮，点击后关闭通知
  */
-export function showToast(message, type = 'info', duration = 3000) {
+export function showToast(message, type = 'info', duration = 3000, action = null) {
     const el = document.createElement('div');
     el.className = `toast toast-${type}`;
     el.textContent = message;
 
+    const dismiss = () => {
+        el.classList.remove('toast-show');
+        el.addEventListener('transitionend', () => el.remove(), { once: true });
+        // 兜底移除
+        setTimeout(() => el.remove(), 500);
+    };
+
+    if (action) {
+        const btn = document.createElement('button');
+        btn.className = 'toast-action';
+        btn.textContent = action.label;
+        btn.addEventListener('click', () => {
+            dismiss();
+            action.onClick();
+        }, { once: true });
+        el.appendChild(btn);
+    }
+
     const c = getContainer();
     c.appendChild(el);
 
     // 触发进入动画
     requestAnimationFrame(() => el.classList.add('toast-show'));
 
-    setTimeout(() => {
-        el.classList.remove('toast-show');
-        el.addEventListener('transitionend', () => el.remove(), { once: true });
-        // 兜底移除
-        setTimeout(() => el.remove(), 500);
-    }, duration);
+    setTimeout(dismiss, duration);
 }
@@ -3,7 +3,7 @@
  */
 
 import { state, subscribe } from '../store.js';
-import { toggleSort, searchFiles, createFolder, deleteSelected, downloadSelectedAsZip } from '../actions.js';
+import { toggleSort, searchFiles, createFolder, deleteSelected, downloadSelectedAsZip, toggleSelectAll } from '../actions.js';
 
 let searchTimer = null;
 
@@ -33,6 +33,9 @@ export function initToolbar() {
         btn.addEventListener('click', () => toggleSort(btn.dataset.sort));
     });
 
+    // 全选
+    toolbar.querySelector('.btn-select-all')?.addEventListener('click', toggleSelectAll);
+
     // 新建文件夹
     const mkdirBtn = toolbar.querySelector('.btn-mkdir');
     if (mkdirBtn) {
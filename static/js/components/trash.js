@@ -0,0 +1,111 @@
+/**
+ * 回收站
+ * 删除的文件先移到这里，可以放回原位置或彻底删除
+ */
+
+import { listTrash, restoreTrash, purgeTrash, emptyTrash } from '../api.js';
+import { subscribe } from '../store.js';
+import { refresh } from '../actions.js';
+import { formatSize, formatTime } from '../utils/format.js';
+import { showToast } from './toast.js';
+
+/** 回收站条目，最近删除的在前 */
+let items = [];
+let panelEl, listEl, toggleEl;
+
+export function initTrash(days) {
+    panelEl = document.getElementById('trash-panel');
+    toggleEl = document.getElementById('trash-toggle');
+    if (!panelEl || !toggleEl) return;
+    toggleEl.hidden = false;
+    listEl = panelEl.querySelector('.trash-list');
+    panelEl.querySelector('.trash-hint').textContent = `删除的项目保留 ${days} 天`;
+
+    toggleEl.addEventListener('click', () => {
+        panelEl.classList.toggle('active');
+        if (isOpen()) load();
+    });
+    panelEl.querySelector('.trash-close').addEventListener('click', () => panelEl.classList.remove('active'));
+    panelEl.querySelector('.trash-empty').addEventListener('click', empty);
+    listEl.addEventListener('click', onListClick);
+    // 删除文件后列表会刷新，面板开着时一起更新
+    subscribe('files', () => { if (isOpen()) load(); });
+}
+
+function isOpen() {
+    return panelEl.classList.contains('active');
+}
+
+async function load() {
+    try {
+        items = await listTrash();
+    } catch (e) {
+        items = [];
+        showToast(`加载回收站失败: ${e.message}`, 'error');
+    }
+    render();
+}
+
+async function onListClick(e) {
+    const restoreEl = e.target.closest('[data-restore]');
+    if (restoreEl) {
+        try {
+            const res = await restoreTrash(restoreEl.dataset.restore);
+            showToast(`已恢复到 /${res.path}`);
+            await refresh();
+        } catch (err) {
+            showToast(err.code === 'already_exists'
+                ? `原位置已有同名项目: /${err.detail?.path ?? ''}，请先重命名或移走`
+                : `恢复失败: ${err.message}`, 'error');
+        }
+        load();
+        return;
+    }
+    const purgeEl = e.target.closest('[data-purge]');
+    if (purgeEl && confirm('彻底删除这一项？删除后无法恢复')) {
+        try {
+            await purgeTrash(purgeEl.dataset.purge);
+        } catch (err) {
+            showToast(`删除失败: ${err.message}`, 'error');
+        }
+        load();
+    }
+}
+
+async function empty() {
+    if (!items.length || !confirm(`彻底删除回收站中的 ${items.length} 个项目？删除后无法恢复`)) return;
+    try {
+        const res = await emptyTrash();
+        showToast(`已清空回收站（${res.purged} 个项目）`);
+    } catch (e) {
+        showToast(`清空失败: ${e.message}`, 'error');
+    }
+    load();
+}
+
+function itemHtml(item) {
+    const meta = [formatSize(item.size), formatTime(item.deleted_at), item.user].filter(Boolean).join(' · ');
+    return `
+        <div class="board-item">
+            <div class="board-text" title="${escapeHtml(item.path)}">${item.is_dir ? '📁' : '📄'} ${escapeHtml(item.path)}</div>
+            <div class="clipboard-meta">
+                <span>${escapeHtml(meta)}</span>
+                <button class="btn btn-sm btn-ghost" data-restore="${item.id}">恢复</button>
+                <button class="btn btn-sm btn-ghost" data-purge="${item.id}">彻底删除</button>
+            </div>
+        </div>`;
+}
+
+function render() {
+    if (!listEl) return;
+    listEl.innerHTML = items.length
+        ? items.map(itemHtml).join('')
+        : '<div class="p2p-empty">回收站是空的</div>';
+    panelEl.querySelector('.trash-empty').disabled = !items.length;
+}
+
+function escapeHtml(text) {
+    const d = document.createElement('div');
+    d.textContent = text;
+    return d.innerHTML.replace(/"/g, '&quot;');
+}
@@ -0,0 +1,149 @@
+/**
+ * 文件夹上传确认
+ * 上传前以目录树展示将要上传的内容，可按文件或文件夹排除
+ */
+
+import { formatSize } from '../utils/format.js';
+
+/**
+ * 显示确认框
+ * @param {{file: File, relativePath: string}[]} items
+ * @returns {Promise<{file: File, relativePath: string}[] | null>} 选中的条目，取消时为 null
+ */
+export function confirmFolderUpload(items) {
+    return new Promise((resolve) => {
+        const tree = buildTree(items);
+        const selected = new Set(items.map((_, i) => i));
+
+        const overlay = document.createElement('div');
+        overlay.className = 'upload-confirm';
+        overlay.innerHTML = `
+            <div class="upload-confirm-inner glass">
+                <div class="upload-confirm-header">
+                    <h3>确认上传</h3>
+                    <span class="upload-confirm-summary"></span>
+                </div>
+                <div class="upload-confirm-tree">${renderNode(tree, true)}</div>
+                <div class="upload-confirm-actions">
+                    <button class="btn btn-ghost" data-action="cancel">取消</button>
+                    <button class="btn btn-primary" data-action="confirm"></button>
+                </div>
+            </div>`;
+        document.body.appendChild(overlay);
+
+        const summaryEl = overlay.querySelector('.upload-confirm-summary');
+        const confirmBtn = overlay.querySelector('[data-action="confirm"]');
+
+        const update = () => {
+            let size = 0;
+            selected.forEach(i => { size += items[i].file.size; });
+            summaryEl.textContent = `已选 ${selected.size} / ${items.length} 个文件，${formatSize(size)}`;
+            confirmBtn.textContent = `上传 ${selected.size} 个文件`;
+            confirmBtn.disabled = selected.size === 0;
+
+            // 文件夹复选框：全选 / 部分选中 / 未选
+            overlay.querySelectorAll('input[data-folder]').forEach(box => {
+                const indices = box.dataset.folder.split(',').filter(Boolean).map(Number);
+                const count = indices.filter(i => selected.has(i)).length;
+                box.checked = count === indices.length;
+                box.indeterminate = count > 0 && count < indices.length;
+            });
+        };
+
+        overlay.querySelector('.upload-confirm-tree').addEventListener('change', (e) => {
+            const box = e.target;
+            if (box.dataset.file !== undefined) {
+                const i = Number(box.dataset.file);
+                if (box.checked) selected.add(i); else selected.delete(i);
+            } else if (box.dataset.folder !== undefined) {
+                box.dataset.folder.split(',').filter(Boolean).map(Number).forEach(i => {
+                    if (box.checked) selected.add(i); else selected.delete(i);
+                    const fileBox = overlay.querySelector(`input[data-file="${i}"]`);
+                    if (fileBox) fileBox.checked = box.checked;
+                });
+            }
+            update();
+        });
+
+        // 点击复选框不触发 <summary> 的展开 / 折叠
+        overlay.querySelectorAll('summary input').forEach(box => {
+            box.addEventListener('click', (e) => e.stopPropagation());
+        });
+
+        const close = (result) => {
+            document.removeEventListener('keydown', onKey);
+            overlay.remove();
+            resolve(result);
+        };
+        const onKey = (e) => {
+            if (e.key === 'Escape') close(null);
+        };
+        document.addEventListener('keydown', onKey);
+
+        overlay.querySelector('[data-action="cancel"]').addEventListener('click', () => close(null));
+        confirmBtn.addEventListener('click', () => {
+            close(items.filter((_, i) => selected.has(i)));
+        });
+
+        update();
+    });
+}
+
+/** 按 relativePath 组装目录树，文件记录原始下标 */
+function buildTree(items) {
+    const root = { name: '', folders: new Map(), files: [], indices: [], size: 0 };
+    items.forEach((item, index) => {
+        const parts = (item.relativePath || item.file.name).split('/').filter(Boolean);
+        parts.pop();
+        let node = root;
+        const path = [node];
+        for (const part of parts) {
+            if (!node.folders.has(part)) {
+                node.folders.set(part, { name: part, folders: new Map(), files: [], indices: [], size: 0 });
+            }
+            node = node.folders.get(part);
+            path.push(node);
+        }
+        node.files.push({ name: item.file.name, size: item.file.size, index });
+        path.forEach(n => {
+            n.indices.push(index);
+            n.size += item.file.size;
+        });
+    });
+    return root;
+}
+
+function renderNode(node, isRoot = false) {
+    const folders = [...node.folders.values()]
+        .sort((a, b) => a.name.localeCompare(b.name))
+        .map(child => `
+            <details ${isRoot ? 'open' : ''}>
+                <summary>
+                    <label>
+                        <input type="checkbox" checked data-folder="${child.indices.join(',')}">
+                        <span class="upload-confirm-name">📁 ${escapeHtml(child.name)}</span>
+                    </label>
+                    <span class="upload-confirm-meta">${child.indices.length} 个文件 · ${formatSize(child.size)}</span>
+                </summary>
+                <div class="upload-confirm-children">${renderNode(child)}</div>
+            </details>`)
+        .join('');
+
+    const files = node.files
+        .sort((a, b) => a.name.localeCompare(b.name))
+        .map(f => `
+            <label class="upload-confirm-file">
+                <input type="checkbox" checked data-file="${f.index}">
+                <span class="upload-confirm-name">${escapeHtml(f.name)}</span>
+                <span class="upload-confirm-meta">${formatSize(f.size)}</span>
+            </label>`)
+        .join('');
+
+    return folders + files;
+}
+
+function escapeHtml(text) {
+    const d = document.createElement('div');
+    d.textContent = text;
+    return d.innerHTML;
+}
@@ -5,11 +5,25 @@
 
 import { state, subscribe, getRaw } from '../store.js';
 import { refresh } from '../actions.js';
+import { startFetch, getFetch, cancelFetch, getLocalSend, cancelLocalSend, getUploadOffset, terminateUpload, archiveProgressUrl, statPaths } from '../api.js';
 import { showToast } from './toast.js';
+import { confirmFolderUpload } from './upload-confirm.js';
+import { ResumableDownload, saveBlob } from '../downloader.js';
 
 let panelEl = null;
 let listEl = null;
 
+/** 上传记录保存在 localStorage，刷新页面后恢复 */
+const STORAGE_KEY = 'uploads';
+const MAX_HISTORY = 100;
+/** 与服务端 MAX_STAT_PATHS 一致 */
+const STAT_BATCH = 10000;
+/** 同时进行的 tus 上传数，其余排队 */
+const MAX_PARALLEL = 3;
+let saveTimer = null;
+/** 设为优先的上传；条目在状态更新时会被整体替换，优先级单独保存 */
+const prioritized = new Set();
+
 export function initUploadPanel() {
     panelEl = document.getElementById('upload-panel');
     if (!panelEl) return;
@@ -42,6 +56,17 @@ export function initUploadPanel() {
         input.click();
     });
 
+    // 清除已完成的记录
+    panelEl.querySelector('.btn-clear-done')?.addEventListener('click', () => {
+        state.uploads = getRaw().uploads.filter(u => u.status !== 'done');
+    });
+
+    // 服务端从链接抓取
+    panelEl.querySelector('.btn-fetch-url')?.addEventListener('click', () => {
+        const url = prompt('文件链接 (http/https):');
+        if (url && url.trim()) addFetch(url.trim());
+    });
+
     // 全局拖拽
     initDragDrop();
 
@@ -50,20 +75,107 @@ export function initUploadPanel() {
     });
 
     subscribe('uploads', renderList);
+    subscribe('uploads', scheduleSave);
+    // 维护结束后继续排队的上传
+    subscribe('maintenance', pump);
+    restoreUploads();
+}
+
+/** 进度更新很频繁，合并到每秒最多写一次 */
+function scheduleSave() {
+    if (saveTimer) return;
+    saveTimer = setTimeout(() => {
+        saveTimer = null;
+        const list = getRaw().uploads.slice(-MAX_HISTORY).map(u => ({
+            id: u.id,
+            name: u.name,
+            size: u.size,
+            relativePath: u.relativePath || '',
+            targetDir: u.targetDir || '',
+            fingerprint: u.fingerprint || null,
+            progress: u.progress,
+            status: u.status,
+            error: u.error,
+            uploadUrl: u.uploadUrl || null,
+            fetchId: u.fetchId || null,
+            localSendId: u.localSendId || null,
+            direction: u.direction || 'upload',
+        }));
+        try {
+            localStorage.setItem(STORAGE_KEY, JSON.stringify(list));
+        } catch { /* 配额不足或隐私模式 */ }
+    }, 1000);
+}
+
+/**
+ * 恢复刷新前的上传记录并与服务端对账
+ * 浏览器无法在刷新后重新读取本地文件，未完成的上传标记为中断，重新选择同一文件时按记录的
+ * 上传地址从服务端进度续传
+ */
+function restoreUploads() {
+    let saved = [];
+    try {
+        saved = JSON.parse(localStorage.getItem(STORAGE_KEY) || '[]');
+    } catch { /* 忽略损坏的记录 */ }
+    if (!Array.isArray(saved) || !saved.length) return;
+
+    const entries = saved.map(u => {
+        const entry = { ...u, file: null, tusUpload: null, download: null, speed: 0 };
+        if (['done', 'error'].includes(u.status)) return entry;
+        if (u.direction === 'download' || u.id.startsWith('p2p-')) {
+            // 页面内下载的数据在内存中，点对点连接随页面关闭，刷新后都无法继续
+            return { ...entry, status: 'error', error: '页面刷新后中断' };
+        }
+        return { ...entry, status: u.fetchId || u.localSendId ? 'uploading' : 'interrupted' };
+    });
+    state.uploads = [...entries, ...getRaw().uploads];
+
+    for (const entry of entries) {
+        if (entry.fetchId && entry.status === 'uploading') {
+            pollFetch(entry);
+        } else if (entry.localSendId && entry.status === 'uploading') {
+            pollLocalSend(entry);
+        } else if (entry.status === 'interrupted' && entry.uploadUrl) {
+            reconcileUpload(entry);
+        }
+    }
+}
+
+/** 按服务端会话更新中断条目的进度；会话已过期或被取消时标记失败 */
+async function reconcileUpload(entry) {
+    let info;
+    try {
+        info = await getUploadOffset(entry.uploadUrl);
+    } catch {
+        return;
+    }
+    if (!info) {
+        entry.status = 'error';
+        entry.error = '服务端上传会话已失效';
+    } else if (info.length) {
+        entry.progress = Math.floor((info.offset / info.length) * 100);
+    }
+    updateEntry(entry);
 }
 
 function initDragDrop() {
     const overlay = document.getElementById('drop-overlay');
     let dragCount = 0;
 
+    // 列表内拖动条目移动时不显示上传遮罩
+    const isFiles = (e) => e.dataTransfer?.types.includes('Files');
+
+    // 只读模式下仍要拦截 drop，否则浏览器会直接打开拖入的文件
     document.addEventListener('dragenter', (e) => {
         e.preventDefault();
+        if (getRaw().readOnly || !isFiles(e)) return;
         dragCount++;
         if (overlay) overlay.classList.add('visible');
     });
 
     document.addEventListener('dragleave', (e) => {
         e.preventDefault();
+        if (!isFiles(e)) return;
         dragCount--;
         if (dragCount <= 0) {
             dragCount = 0;
@@ -73,59 +185,209 @@ function initDragDrop() {
 
     document.addEventListener('dragover', (e) => e.preventDefault());
 
-    document.addEventListener('drop', (e) => {
+    document.addEventListener('drop', async (e) => {
         e.preventDefault();
         dragCount = 0;
         if (overlay) overlay.classList.remove('visible');
+        if (getRaw().readOnly || !isFiles(e)) return;
 
-        const files = e.dataTransfer?.files;
-        if (files?.length) {
+        const items = await collectDropped(e.dataTransfer);
+        if (items.length) {
             state.uploadPanelOpen = true;
-            addFiles(files);
+            queueItems(items);
         }
     });
 }
 
-/** 添加文件到上传队列并开始上传 */
+/** 读取拖入的文件；拖入文件夹时递归展开，并带上相对路径 */
+async function collectDropped(dataTransfer) {
+    if (!dataTransfer) return [];
+    // 必须在事件回调同步阶段取出 entry，await 之后 dataTransfer 会被清空
+    const entries = [...(dataTransfer.items || [])]
+        .map(item => item.webkitGetAsEntry?.())
+        .filter(Boolean);
+    if (!entries.length) {
+        return [...(dataTransfer.files || [])].map(file => ({ file, relativePath: '' }));
+    }
+
+    const items = [];
+    const walk = async (entry, prefix) => {
+        if (entry.isFile) {
+            const file = await new Promise((resolve, reject) => entry.file(resolve, reject));
+            items.push({ file, relativePath: prefix ? `${prefix}/${file.name}` : '' });
+        } else if (entry.isDirectory) {
+            const dir = prefix ? `${prefix}/${entry.name}` : entry.name;
+            const reader = entry.createReader();
+            // readEntries 每次最多返回一批，需要读到空为止
+            for (;;) {
+                const batch = await new Promise((resolve, reject) => reader.readEntries(resolve, reject));
+                if (!batch.length) break;
+                for (const child of batch) await walk(child, dir);
+            }
+        }
+    };
+    for (const entry of entries) {
+        try {
+            await walk(entry, '');
+        } catch {
+            showToast(`读取 ${entry.name} 失败`, 'error');
+        }
+    }
+    return items;
+}
+
+/** 选择器返回的 FileList 转为上传条目 */
 function addFiles(fileList) {
-    const raw = getRaw();
-    const uploads = [...raw.uploads];
+    queueItems([...fileList].map(file => ({ file, relativePath: file.webkitRelativePath || '' })));
+}
+
+/** 含文件夹时先确认上传内容，再加入队列 */
+async function queueItems(items) {
+    if (items.some(item => item.relativePath.includes('/'))) {
+        items = await confirmFolderUpload(items);
+        if (!items?.length) return;
+        items = await skipUnchanged(items);
+        if (!items.length) return;
+    }
+    enqueue(items);
+}
+
+/** 服务端已有大小和修改时间都相同的文件时跳过，重新上传整个文件夹只传新增和修改过的 */
+async function skipUnchanged(items) {
+    const base = state.currentPath;
+    const target = (item) => [base, item.relativePath || item.file.name].filter(Boolean).join('/');
+    const unchanged = new Set();
+    try {
+        for (let i = 0; i < items.length; i += STAT_BATCH) {
+            const batch = items.slice(i, i + STAT_BATCH);
+            const stats = await statPaths(batch.map(target));
+            stats.forEach((s, j) => {
+                const file = batch[j].file;
+                if (s.exists && !s.is_dir && s.size === file.size
+                    && s.modified === Math.floor(file.lastModified / 1000)) {
+                    unchanged.add(batch[j]);
+                }
+            });
+        }
+    } catch {
+        // 无读权限（如投递箱）或旧版本服务端：全部上传
+        return items;
+    }
+    if (unchanged.size) {
+        showToast(`跳过 ${unchanged.size} 个未变化的文件`, 'info');
+    }
+    return items.filter(item => !unchanged.has(item));
+}
+
+/**
+ * 标识“同一目标位置的同一个文件”：目标目录、相对路径、大小和本地修改时间
+ * 同一文件上传到另一个目录时不会误续传到原来的位置
+ */
+function fingerprintOf(file, relativePath, targetDir) {
+    return ['transfer', targetDir, relativePath || file.name, file.size, file.lastModified].join('|');
+}
 
-    for (const file of fileList) {
-        const id = `${Date.now()}-${Math.random().toString(36).slice(2, 8)}`;
-        const entry = {
-            id,
+/** 添加文件到上传队列，按名额依次开始上传 */
+function enqueue(items) {
+    const raw = getRaw();
+    const targetDir = state.currentPath;
+    // 重新选择了刷新前中断的文件：移除旧条目，沿用它的上传地址从服务端进度续传
+    const interrupted = new Map();
+    for (const u of raw.uploads) {
+        if (u.status === 'interrupted' && u.fingerprint) interrupted.set(u.fingerprint, u);
+    }
+    // 旧版本保存的记录没有指纹，按文件名和大小匹配，由 tus 自己保存的指纹续传
+    const legacy = (u, file, relativePath) => u.status === 'interrupted' && !u.fingerprint
+        && u.name === file.name && u.size === file.size && (u.relativePath || '') === relativePath;
+    const matched = new Set();
+    const added = items.map(({ file, relativePath }) => {
+        const fingerprint = fingerprintOf(file, relativePath, targetDir);
+        const previous = interrupted.get(fingerprint);
+        if (previous) matched.add(previous.id);
+        return {
+            id: `${Date.now()}-${Math.random().toString(36).slice(2, 8)}`,
             file,
             name: file.name,
             size: file.size,
-            relativePath: file.webkitRelativePath || '',
-            progress: 0,
+            relativePath,
+            targetDir,
+            fingerprint,
+            uploadUrl: previous?.uploadUrl || null,
+            progress: previous?.progress || 0,
             speed: 0,
             status: 'pending', // pending | uploading | paused | done | error
             tusUpload: null,
             error: null,
         };
-        uploads.push(entry);
-        startUpload(entry);
-    }
+    });
+    const uploads = raw.uploads.filter(u => !matched.has(u.id)
+        && !items.some(i => legacy(u, i.file, i.relativePath)));
+    uploads.push(...added);
 
     state.uploads = uploads;
+    pump();
+}
+
+/** tus 上传 (有本地文件) 的条目；抓取、推送、下载不占上传名额 */
+function isUpload(u) {
+    return Boolean(u.file) && u.direction !== 'download';
 }
 
-/** 使用 tus 协议上传单个文件 */
+/**
+ * 按优先级和列表顺序启动排队的上传，直到占满名额
+ * 优先的任务没有名额时，让出排在最后的普通任务；被让出的任务回到队列，之后从服务端进度续传
+ */
+function pump() {
+    // 服务器暂停上传期间不启动新的上传
+    if (getRaw().maintenance) return;
+    const uploads = getRaw().uploads;
+    const rank = (u) => (prioritized.has(u.id) ? 0 : 1);
+    const byRank = (a, b) => rank(a) - rank(b) || uploads.indexOf(a) - uploads.indexOf(b);
+    const active = uploads.filter(u => isUpload(u) && u.status === 'uploading');
+    const waiting = uploads.filter(u => isUpload(u) && u.status === 'pending').sort(byRank);
+
+    for (const next of waiting) {
+        if (active.length >= MAX_PARALLEL) {
+            const victim = active.filter(u => rank(u) > rank(next)).sort(byRank).pop();
+            if (!victim) break;
+            victim.tusUpload?.abort();
+            victim.status = 'pending';
+            updateEntry(victim);
+            active.splice(active.indexOf(victim), 1);
+        }
+        startUpload(next);
+        active.push(next);
+    }
+}
+
+/** 当前列表中的条目；tus 回调持有的是创建时的对象 */
+function current(id) {
+    return getRaw().uploads.find(u => u.id === id);
+}
+
+/** 使用 tus 协议上传单个文件；已创建过的 (暂停或被让出) 从服务端进度继续 */
 function startUpload(entry) {
+    if (entry.tusUpload) {
+        entry.tusUpload.start();
+        entry.status = 'uploading';
+        updateEntry(entry);
+        return;
+    }
+
     // tus-js-client 通过 vendor 全局加载
     if (typeof tus === 'undefined') {
         entry.status = 'error';
         entry.error = 'tus-js-client 未加载';
         updateEntry(entry);
+        pump();
         return;
     }
 
+    // 排队期间用户可能已切换到其他目录，以加入队列时的目录为准
     const metadata = {
         filename: entry.file.name,
         filetype: entry.file.type,
-        targetDir: state.currentPath,
+        targetDir: entry.targetDir,
     };
     if (entry.relativePath) {
         metadata.relativePath = entry.relativePath;
@@ -136,40 +398,357 @@ function startUpload(entry) {
         retryDelays: [0, 1000, 3000, 5000, 10000, 20000],
         chunkSize: 8 * 1024 * 1024, // 8MB
         metadata,
+        // 保留本地修改时间（毫秒），再次上传同一文件夹时据此跳过未变化的文件
+        headers: { 'X-File-Mtime': String(entry.file.lastModified) },
+        // 有刷新前记录的上传地址时先 HEAD 查询服务端进度，会话已失效则自动新建
+        uploadUrl: entry.uploadUrl || null,
+        fingerprint: () => Promise.resolve(entry.fingerprint),
         storeFingerprintForResuming: true,
         removeFingerprintOnSuccess: true,
+        // 同名文件正被其他设备上传时重试没有意义，其余沿用 tus 默认的重试规则
+        onShouldRetry: (error) => {
+            const code = errorBody(error)?.code;
+            if (code === 'upload_in_progress' || code === 'paused') return false;
+            const status = error.originalResponse?.getStatus() ?? 0;
+            const clientError = status >= 400 && status < 500 && status !== 409 && status !== 423;
+            return !clientError && navigator.onLine !== false;
+        },
 
         onProgress: (bytesUploaded, bytesTotal) => {
+            // 暂停或让出之后仍可能收到已发出请求的进度
+            if (current(entry.id)?.status !== 'uploading') return;
+            entry.uploadUrl = upload.url;
             entry.progress = Math.round((bytesUploaded / bytesTotal) * 100);
             entry.status = 'uploading';
             updateEntry(entry);
         },
-        onSuccess: () => {
+        onSuccess: (payload) => {
             entry.progress = 100;
             entry.status = 'done';
             updateEntry(entry);
-            showToast(`${entry.name} 上传完成`, 'success');
+            prioritized.delete(entry.id);
+            pump();
+            // 同名冲突时服务端会另存为 "name (1).ext"
+            const finalPath = payload?.lastResponse?.getHeader('X-Final-Path');
+            const finalName = finalPath ? decodeURIComponent(finalPath).split('/').pop() : entry.name;
+            if (finalName !== entry.name) {
+                showToast(`${entry.name} 上传完成，已保存为 ${finalName}`, 'success');
+            } else {
+                showToast(`${entry.name} 上传完成`, 'success');
+            }
             refresh();
         },
         onError: (error) => {
+            const body = errorBody(error);
+            if (body?.code === 'upload_in_progress') {
+                const { uploaded, total_size: total } = body.detail || {};
+                const percent = total ? Math.floor((uploaded / total) * 100) : 0;
+                entry.status = 'error';
+                entry.error = `另一个设备正在上传同名文件（已完成 ${percent}%）`;
+                updateEntry(entry);
+                pump();
+                showToast(`${entry.name}: ${entry.error}`, 'error');
+                return;
+            }
+            // 服务器维护中：回到队列，维护结束后从服务端进度续传
+            if (body?.code === 'paused') {
+                entry.status = 'pending';
+                updateEntry(entry);
+                state.maintenance = body.detail?.notice || '服务器维护中，暂时无法上传';
+                return;
+            }
             entry.status = 'error';
             entry.error = error.message || '上传失败';
             updateEntry(entry);
-            showToast(`${entry.name} 上传失败`, 'error');
+            pump();
+            const requestId = error.originalResponse?.getHeader('X-Request-Id');
+            showToast(
+                requestId ? `${entry.name} 上传失败（请求 ID: ${requestId}）` : `${entry.name} 上传失败`,
+                'error',
+            );
         },
     });
 
     entry.tusUpload = upload;
+    entry.status = 'uploading';
+    updateEntry(entry);
 
-    // 尝试恢复之前的上传
+    if (entry.uploadUrl) {
+        upload.start();
+        return;
+    }
+    // 尝试恢复之前的上传；查找期间可能已被暂停、让出或取消
     upload.findPreviousUploads().then(prev => {
         if (prev.length) {
             upload.resumeFromPreviousUpload(prev[0]);
         }
-        upload.start();
-        entry.status = 'uploading';
+        if (current(entry.id)?.status === 'uploading') upload.start();
+    });
+}
+
+/** 解析 tus 错误里服务端返回的 JSON 错误体 */
+function errorBody(error) {
+    try {
+        return JSON.parse(error.originalResponse?.getBody() || 'null');
+    } catch {
+        return null;
+    }
+}
+
+/** 让服务端下载远程文件，并轮询进度 */
+async function addFetch(url) {
+    let task;
+    try {
+        task = await startFetch(url, state.currentPath);
+    } catch (e) {
+        showToast(`抓取失败: ${e.message}`, 'error');
+        return;
+    }
+
+    const entry = {
+        id: `fetch-${task.id}`,
+        fetchId: task.id,
+        name: task.filename,
+        size: 0,
+        progress: 0,
+        speed: 0,
+        status: 'uploading',
+        error: null,
+    };
+    state.uploads = [...getRaw().uploads, entry];
+    pollFetch(entry);
+}
+
+/** 轮询抓取任务直到结束 */
+function pollFetch(entry) {
+    const poll = async () => {
+        let t;
+        try {
+            t = await getFetch(entry.fetchId);
+        } catch (e) {
+            // 服务端重启后任务记录不再存在
+            if (e.status === 404) {
+                entry.status = 'error';
+                entry.error = '抓取任务已不存在';
+                updateEntry(entry);
+            }
+            return;
+        }
+        if (!getRaw().uploads.some(u => u.id === entry.id)) return;
+        entry.size = t.total || 0;
+        entry.progress = t.total ? Math.round((t.downloaded / t.total) * 100) : 0;
+        if (t.status === 'running') {
+            updateEntry(entry);
+            setTimeout(poll, 1000);
+            return;
+        }
+        if (t.status === 'done') {
+            entry.progress = 100;
+            entry.status = 'done';
+            showToast(`${entry.name} 已保存到服务器`, 'success');
+            refresh();
+        } else {
+            entry.status = 'error';
+            entry.error = t.error || '抓取失败';
+            if (t.status === 'failed') showToast(`${entry.name} 抓取失败: ${entry.error}`, 'error');
+        }
         updateEntry(entry);
+    };
+    setTimeout(poll, 500);
+}
+
+/**
+ * 跟踪服务端推送到 LocalSend 设备的任务
+ * @param {{id: string, device: string, files: number, total: number}} task
+ */
+export function addLocalSend(task) {
+    const entry = {
+        id: `ls-${task.id}`,
+        localSendId: task.id,
+        name: `发送到 ${task.device}（${task.files} 个文件）`,
+        size: task.total,
+        progress: 0,
+        speed: 0,
+        status: 'uploading',
+        error: null,
+    };
+    state.uploads = [...getRaw().uploads, entry];
+    state.uploadPanelOpen = true;
+    pollLocalSend(entry);
+}
+
+/** 轮询推送任务直到结束；等待对方确认时进度保持为 0 */
+function pollLocalSend(entry) {
+    const poll = async () => {
+        let t;
+        try {
+            t = await getLocalSend(entry.localSendId);
+        } catch (e) {
+            if (e.status === 404) {
+                entry.status = 'error';
+                entry.error = '推送任务已不存在';
+                updateEntry(entry);
+            }
+            return;
+        }
+        if (!getRaw().uploads.some(u => u.id === entry.id)) return;
+        entry.size = t.total || 0;
+        entry.progress = t.total ? Math.floor((t.sent / t.total) * 100) : 0;
+        if (t.status === 'waiting' || t.status === 'sending') {
+            updateEntry(entry);
+            setTimeout(poll, 1000);
+            return;
+        }
+        if (t.status === 'done') {
+            entry.progress = 100;
+            entry.status = 'done';
+            showToast(`已发送到 ${t.device}`, 'success');
+        } else {
+            entry.status = 'error';
+            entry.error = t.status === 'rejected' ? '对方拒绝接收' : (t.error || '发送失败');
+            if (t.status !== 'cancelled') showToast(`发送到 ${t.device} 失败: ${entry.error}`, 'error');
+        }
+        updateEntry(entry);
+    };
+    setTimeout(poll, 500);
+}
+
+/**
+ * 页面内下载文件，进度显示在传输面板中；网络中断后自动按 Range 续传
+ * @param {string} url
+ * @param {string} name 保存的文件名
+ * @param {number} size
+ * @param {FileSystemFileHandle|null} file 直接写入的目标文件，为空时在内存中缓存
+ */
+export function addDownload(url, name, size, file = null) {
+    const entry = {
+        id: `dl-${Date.now()}-${Math.random().toString(36).slice(2, 8)}`,
+        direction: 'download',
+        name,
+        size,
+        progress: 0,
+        speed: 0,
+        status: 'uploading',
+        error: null,
+    };
+    entry.download = new ResumableDownload(url, {
+        onProgress: (received, total) => {
+            const progress = total ? Math.floor((received / total) * 100) : 0;
+            if (progress === entry.progress) return;
+            entry.progress = progress;
+            updateEntry(entry);
+        },
+        file,
     });
+    state.uploads = [...getRaw().uploads, entry];
+    state.uploadPanelOpen = true;
+    runDownload(entry);
+}
+
+/**
+ * 在传输面板中显示其他模块的传输进度
+ * @param {{name: string, size: number, direction: 'upload'|'download', onCancel?: () => void}} opts
+ * @returns {{progress: (bytes: number) => void, done: () => void, fail: (message: string) => void}}
+ */
+export function trackTransfer({ name, size, direction, onCancel }) {
+    const entry = {
+        id: `p2p-${Date.now()}-${Math.random().toString(36).slice(2, 8)}`,
+        direction,
+        name,
+        size,
+        progress: 0,
+        speed: 0,
+        status: 'uploading',
+        error: null,
+        onCancel,
+    };
+    state.uploads = [...getRaw().uploads, entry];
+    state.uploadPanelOpen = true;
+    return {
+        progress(bytes) {
+            const progress = size ? Math.floor((bytes / size) * 100) : 0;
+            if (progress === entry.progress) return;
+            entry.progress = progress;
+            updateEntry(entry);
+        },
+        done() {
+            entry.progress = 100;
+            entry.status = 'done';
+            updateEntry(entry);
+        },
+        fail(message) {
+            if (entry.status === 'done') return;
+            entry.status = 'error';
+            entry.error = message;
+            updateEntry(entry);
+        },
+    };
+}
+
+/**
+ * 跟踪浏览器原生下载的打包进度
+ * @param {string} progressId 下载请求中带上的进度任务 ID
+ * @param {string} name
+ */
+export function addArchiveDownload(progressId, name) {
+    const entry = {
+        id: `zip-${progressId}`,
+        direction: 'download',
+        name,
+        size: 0,
+        progress: 0,
+        speed: 0,
+        status: 'uploading',
+        error: null,
+    };
+    // 打包速度受浏览器读取速度约束，服务端进度即下载进度
+    const source = new EventSource(archiveProgressUrl(progressId));
+    entry.archiveSource = source;
+    const finish = (status, error = null) => {
+        source.close();
+        entry.archiveSource = null;
+        entry.status = status;
+        entry.error = error;
+        updateEntry(entry);
+    };
+    source.addEventListener('progress', (e) => {
+        const p = JSON.parse(e.data);
+        entry.size = p.total_bytes;
+        entry.progress = p.total_bytes ? Math.floor((p.done_bytes / p.total_bytes) * 100) : 0;
+        if (p.status === 'done') {
+            entry.progress = 100;
+            finish('done');
+        } else if (p.status === 'failed') {
+            finish('error', p.error || '打包失败');
+        } else {
+            updateEntry(entry);
+        }
+    });
+    source.addEventListener('gone', () => finish('error', '打包任务不存在'));
+    // 流在任务结束后关闭，不自动重连
+    source.onerror = () => {
+        source.close();
+        entry.archiveSource = null;
+    };
+    state.uploads = [...getRaw().uploads, entry];
+    state.uploadPanelOpen = true;
+}
+
+async function runDownload(entry) {
+    try {
+        const blob = await entry.download.start();
+        entry.progress = 100;
+        entry.status = 'done';
+        entry.download = null;
+        if (blob) saveBlob(blob, entry.name);
+    } catch (e) {
+        // 暂停或取消
+        if (e.name === 'AbortError') return;
+        entry.status = 'error';
+        entry.error = e.message || '下载失败';
+        showToast(`${entry.name} 下载失败`, 'error');
+    }
+    updateEntry(entry);
 }
 
 /** 更新上传条目状态 */
@@ -179,25 +758,36 @@ function updateEntry(entry) {
     state.uploads = uploads;
 }
 
-/** 暂停上传 */
+/** 暂停上传；排队中的任务暂停后不再被调度 */
 export function pauseUpload(id) {
     const raw = getRaw();
     const entry = raw.uploads.find(u => u.id === id);
-    if (entry?.tusUpload) {
-        entry.tusUpload.abort();
+    if (entry && isUpload(entry)) {
+        entry.tusUpload?.abort();
+        entry.status = 'paused';
+        updateEntry(entry);
+        pump();
+    }
+    if (entry?.download) {
+        entry.download.pause();
         entry.status = 'paused';
         updateEntry(entry);
     }
 }
 
-/** 恢复上传 */
+/** 恢复上传：回到队列，按优先级和顺序等待名额 */
 export function resumeUpload(id) {
     const raw = getRaw();
     const entry = raw.uploads.find(u => u.id === id);
-    if (entry?.tusUpload) {
-        entry.tusUpload.start();
+    if (entry && isUpload(entry)) {
+        entry.status = 'pending';
+        updateEntry(entry);
+        pump();
+    }
+    if (entry?.download) {
         entry.status = 'uploading';
         updateEntry(entry);
+        runDownload(entry);
     }
 }
 
@@ -208,7 +798,44 @@ export function cancelUpload(id) {
     if (entry?.tusUpload) {
         entry.tusUpload.abort(true);
     }
+    if (entry?.fetchId) {
+        cancelFetch(entry.fetchId).catch(() => {});
+    }
+    if (entry?.localSendId) {
+        cancelLocalSend(entry.localSendId).catch(() => {});
+    }
+    entry?.download?.cancel();
+    // 原生下载无法从页面取消，只停止跟踪进度
+    entry?.archiveSource?.close();
+    if (entry?.status === 'uploading') entry.onCancel?.();
+    // 刷新后中断的上传没有 tus 实例，直接终止服务端会话
+    if (entry?.status === 'interrupted' && entry.uploadUrl) {
+        terminateUpload(entry.uploadUrl).catch(() => {});
+    }
+    prioritized.delete(id);
     state.uploads = raw.uploads.filter(u => u.id !== id);
+    pump();
+}
+
+/** 设为优先 / 取消优先；优先的任务排在队列最前，必要时让出正在上传的普通任务 */
+export function togglePriority(id) {
+    if (!prioritized.delete(id)) prioritized.add(id);
+    // 优先级不在条目上，触发一次重新渲染
+    state.uploads = [...getRaw().uploads];
+    pump();
+}
+
+/** 在排队和暂停的上传之间上移 (-1) 或下移 (1) */
+export function moveUpload(id, delta) {
+    const uploads = [...getRaw().uploads];
+    const queued = uploads.filter(u => isUpload(u) && (u.status === 'pending' || u.status === 'paused'));
+    const from = queued.findIndex(u => u.id === id);
+    const other = queued[from + delta];
+    if (from < 0 || !other) return;
+    const a = uploads.indexOf(queued[from]);
+    const b = uploads.indexOf(other);
+    [uploads[a], uploads[b]] = [uploads[b], uploads[a]];
+    state.uploads = uploads;
 }
 
 /** 渲染上传列表 */
@@ -224,11 +851,24 @@ function renderList() {
 
     listEl.innerHTML = uploads.map(u => {
         const statusIcon = {
-            pending: '⏳', uploading: '⬆️', paused: '⏸️', done: '✅', error: '❌'
+            pending: '⏳', uploading: u.direction === 'download' ? '⬇️' : '⬆️', paused: '⏸️', done: '✅', error: '❌', interrupted: '⚠️'
         }[u.status] || '';
+        const hint = u.status === 'interrupted'
+            ? `页面刷新后中断，在 /${u.targetDir || ''} 重新选择该文件即可从服务端进度续传`
+            : (u.status === 'error' ? u.error || '' : '');
 
         const actions = [];
-        if (u.status === 'uploading') {
+        const queued = isUpload(u) && (u.status === 'pending' || u.status === 'paused');
+        if (queued) {
+            actions.push(`<button class="upload-action" data-action="up" data-id="${u.id}" title="上移">↑</button>`);
+            actions.push(`<button class="upload-action" data-action="down" data-id="${u.id}" title="下移">↓</button>`);
+        }
+        if (isUpload(u) && (queued || u.status === 'uploading')) {
+            const label = prioritized.has(u.id) ? '取消优先' : '优先';
+            actions.push(`<button class="upload-action" data-action="priority" data-id="${u.id}">${label}</button>`);
+        }
+        // 只有 tus 上传和页面内下载可以暂停
+        if ((u.status === 'uploading' && (u.tusUpload || u.download)) || (u.status === 'pending' && isUpload(u))) {
             actions.push(`<button class="upload-action" data-action="pause" data-id="${u.id}">暂停</button>`);
         }
         if (u.status === 'paused') {
@@ -238,10 +878,10 @@ function renderList() {
             actions.push(`<button class="upload-action" data-action="cancel" data-id="${u.id}">取消</button>`);
         }
 
-        return `<div class="upload-item ${u.status}">
+        return `<div class="upload-item ${u.status}${prioritized.has(u.id) ? ' prioritized' : ''}">
             <div class="upload-item-info">
                 <span class="upload-item-name" title="${escapeAttr(u.name)}">${escapeHtml(u.name)}</span>
-                <span class="upload-item-status">${statusIcon} ${u.progress}%</span>
+                <span class="upload-item-status" title="${escapeAttr(hint)}">${statusIcon} ${u.progress}%</span>
             </div>
             <div class="upload-item-progress">
                 <div class="upload-item-bar" style="width:${u.progress}%"></div>
@@ -258,6 +898,9 @@ function renderList() {
             if (action === 'pause') pauseUpload(id);
             else if (action === 'resume') resumeUpload(id);
             else if (action === 'cancel') cancelUpload(id);
+            else if (action === 'priority') togglePriority(id);
+            else if (action === 'up') moveUpload(id, -1);
+            else if (action === 'down') moveUpload(id, 1);
         });
     });
 }
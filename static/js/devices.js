@@ -0,0 +1,112 @@
+/**
+ * 已连接设备页面入口
+ * 每 2 秒刷新一次最近访问过的客户端和进行中的传输，速度由两次刷新之间的字节差计算
+ */
+
+import { getClients } from './api.js';
+import { initThemeToggle } from './components/theme-toggle.js';
+import { showToast } from './components/toast.js';
+import { formatSize, formatTime } from './utils/format.js';
+
+const POLL_MS = 2000;
+
+let listEl = null;
+let summaryEl = null;
+/** 上一次刷新时各传输的字节数，key 为 ip + 开始时间 + 路径 */
+let lastBytes = new Map();
+let lastAt = 0;
+let failed = false;
+
+async function load() {
+    try {
+        const clients = await getClients();
+        render(clients);
+        failed = false;
+    } catch (e) {
+        // 服务端暂时不可达时只提示一次
+        if (!failed) showToast(`加载失败: ${e.message}`, 'error');
+        failed = true;
+    }
+    setTimeout(load, POLL_MS);
+}
+
+function render(clients) {
+    const now = Date.now();
+    const elapsed = lastAt ? (now - lastAt) / 1000 : 0;
+    const bytes = new Map();
+
+    const online = clients.filter(c => c.active_requests > 0).length;
+    const transfers = clients.reduce((n, c) => n + c.transfers.length, 0);
+    summaryEl.textContent = `${online} 台在线 · ${transfers} 个传输进行中 · 最近 24 小时 ${clients.length} 台`;
+
+    if (!clients.length) {
+        listEl.innerHTML = '<div class="empty-state">还没有设备访问过</div>';
+        return;
+    }
+
+    listEl.innerHTML = clients.map(c => {
+        const rows = c.transfers.map(t => {
+            const key = `${c.ip}|${t.started}|${t.path}`;
+            bytes.set(key, t.bytes);
+            const prev = lastBytes.get(key);
+            const speed = prev != null && elapsed > 0 ? ` · ${formatSize((t.bytes - prev) / elapsed)}/s` : '';
+            const arrow = t.kind === 'upload' ? '↑' : '↓';
+            return `<div class="device-transfer">${arrow} ${escapeHtml(displayPath(t.path))} · ${formatSize(t.bytes)}${speed}</div>`;
+        }).join('');
+        const who = c.user ? ` · ${escapeHtml(c.user)}` : '';
+        return `<div class="file-row device-row ${c.active_requests > 0 ? 'is-active' : ''}">
+            <span class="device-status" title="${c.active_requests} 个请求进行中"></span>
+            <div class="file-cell file-cell-name">
+                <span>${escapeHtml(c.ip)}${who}</span>
+                <span class="device-agent" title="${escapeAttr(c.user_agent || '')}">${escapeHtml(describeAgent(c.user_agent))}</span>
+            </div>
+            <div class="file-cell device-traffic" title="${c.requests} 次请求">↓ ${formatSize(c.bytes_sent)} · ↑ ${formatSize(c.bytes_received)}</div>
+            <div class="file-cell device-seen">${c.active_requests > 0 ? '在线' : formatTime(c.last_seen)}</div>
+            ${rows ? `<div class="device-transfers">${rows}</div>` : ''}
+        </div>`;
+    }).join('');
+
+    lastBytes = bytes;
+    lastAt = now;
+}
+
+/** /api/download/a%20b.txt -> a b.txt */
+function displayPath(path) {
+    const rest = path.replace(/^\/api\/(download|upload|transcode|hls|delta|localsend\/v2)\/?/, '');
+    try {
+        return decodeURIComponent(rest) || path;
+    } catch {
+        return rest || path;
+    }
+}
+
+/** 从 User-Agent 中取出常见的浏览器和系统名称，其他客户端（curl 等）原样显示 */
+function describeAgent(ua) {
+    if (!ua) return '未知客户端';
+    const browser = [['Edg/', 'Edge'], ['Firefox/', 'Firefox'], ['Chrome/', 'Chrome'], ['Safari/', 'Safari']]
+        .find(([token]) => ua.includes(token));
+    const os = [['Android', 'Android'], ['iPhone', 'iOS'], ['iPad', 'iPadOS'], ['Windows', 'Windows'], ['Mac OS X', 'macOS'], ['Linux', 'Linux']]
+        .find(([token]) => ua.includes(token));
+    if (!browser) return ua;
+    return os ? `${browser[1]} · ${os[1]}` : browser[1];
+}
+
+function escapeHtml(text) {
+    const div = document.createElement('div');
+    div.textContent = text;
+    return div.innerHTML;
+}
+
+function escapeAttr(text) {
+    return text.replace(/&/g, '&amp;').replace(/"/g, '&quot;').replace(/</g, '&lt;');
+}
+
+function init() {
+    listEl = document.getElementById('device-list');
+    summaryEl = document.querySelector('.devices-summary');
+
+    initThemeToggle();
+    load();
+}
+
+init();
@@ -0,0 +1,244 @@
+/**
+ * 页面内下载
+ * 大文件拆成多个 Range 分段并发下载，每段网络中断时从已收到的字节继续；
+ * If-Range 保证文件变化后从头下载而不是拼接新旧内容。
+ * 浏览器支持 File System Access API 时，大文件按位置直接写入用户选择的文件，不占内存
+ */
+
+/** 内存中缓存整个文件的上限，更大的文件写入磁盘或交给浏览器原生下载 */
+export const MEMORY_LIMIT = 512 * 1024 * 1024;
+
+/** 小于该大小的文件不拆分 */
+const PARALLEL_MIN_SIZE = 16 * 1024 * 1024;
+const DEFAULT_CONNECTIONS = 4;
+const MAX_RETRIES = 5;
+/** 下载过程中文件被修改时最多从头重来的次数 */
+const MAX_RESTARTS = 3;
+
+class HttpError extends Error {
+    constructor(status) {
+        super(`HTTP ${status}`);
+        this.status = status;
+    }
+}
+
+/** If-Range 不匹配：服务端的文件已经变化 */
+class FileChanged extends Error {
+    constructor() {
+        super('文件在下载过程中被修改');
+    }
+}
+
+/** 可被中止的等待 */
+function sleep(ms, signal) {
+    return new Promise((resolve, reject) => {
+        const timer = setTimeout(resolve, ms);
+        signal.addEventListener('abort', () => {
+            clearTimeout(timer);
+            reject(signal.reason);
+        }, { once: true });
+    });
+}
+
+/** 是否可以把下载直接写入磁盘 */
+export function canStreamToDisk() {
+    return typeof window !== 'undefined' && typeof window.showSaveFilePicker === 'function';
+}
+
+/**
+ * 让用户选择保存位置，须在点击等用户操作中调用
+ * @returns {Promise<FileSystemFileHandle|null>} 用户取消时为 null
+ */
+export async function pickSaveTarget(name) {
+    try {
+        return await window.showSaveFilePicker({ suggestedName: name });
+    } catch (e) {
+        if (e.name === 'AbortError') return null;
+        throw e;
+    }
+}
+
+export class ResumableDownload {
+    /**
+     * @param {string} url
+     * @param {{onProgress?: (received: number, total: number|null) => void, connections?: number, file?: FileSystemFileHandle}} opts
+     */
+    constructor(url, { onProgress, connections = DEFAULT_CONNECTIONS, file = null } = {}) {
+        this.url = url;
+        this.onProgress = onProgress;
+        /** 目标文件；为空时在内存中拼成 Blob */
+        this.file = file;
+        /** @type {FileSystemWritableFileStream|null} 暂停期间保持打开，close() 时才落盘 */
+        this.writable = null;
+        this.connections = Math.max(1, connections);
+        this.total = null;
+        /** 首次响应的 ETag（没有时用 Last-Modified），分段请求都带上它作为 If-Range */
+        this.validator = null;
+        this.contentType = '';
+        /** @type {{start: number, end: number|null, chunks: Uint8Array[], received: number, ranged: boolean, done: boolean}[] | null} */
+        this.segments = null;
+        this.controller = null;
+    }
+
+    get received() {
+        return (this.segments || []).reduce((sum, s) => sum + s.received, 0);
+    }
+
+    /**
+     * 开始或继续下载；pause() 后 reject 为 AbortError
+     * @returns {Promise<Blob|null>} 内存模式下返回 Blob，写入磁盘时为 null
+     */
+    async start() {
+        let restarts = 0;
+        if (this.file && !this.writable) {
+            this.writable = await this.file.createWritable({ keepExistingData: true });
+        }
+        for (;;) {
+            this.controller = new AbortController();
+            const { signal } = this.controller;
+            try {
+                if (!this.segments) await this.probe(signal);
+                await this.runSegments(signal);
+                if (this.writable) {
+                    await this.writable.close();
+                    this.writable = null;
+                    return null;
+                }
+                return new Blob(this.segments.flatMap(s => s.chunks), { type: this.contentType });
+            } catch (e) {
+                if (e instanceof FileChanged && ++restarts <= MAX_RESTARTS) {
+                    this.segments = null;
+                    await this.writable?.truncate(0);
+                    continue;
+                }
+                if (e.name !== 'AbortError') await this.discard();
+                throw e;
+            }
+        }
+    }
+
+    /** 中止所有请求，已收到的数据保留，再次 start() 时续传 */
+    pause() {
+        this.controller?.abort();
+    }
+
+    /** 中止下载并丢弃已写入磁盘的数据 */
+    async cancel() {
+        this.controller?.abort();
+        await this.discard();
+    }
+
+    async discard() {
+        const writable = this.writable;
+        this.writable = null;
+        await writable?.abort().catch(() => {});
+    }
+
+    /** 用 1 字节的 Range 请求取得大小和校验值，再决定是否拆分 */
+    async probe(signal) {
+        const resp = await fetch(this.url, { headers: { Range: 'bytes=0-0' }, signal });
+        if (!resp.ok) throw new HttpError(resp.status);
+        this.validator = resp.headers.get('ETag') || resp.headers.get('Last-Modified');
+        this.contentType = resp.headers.get('Content-Type') || '';
+
+        if (resp.status === 206) {
+            await resp.body?.cancel();
+            this.total = Number((resp.headers.get('Content-Range') || '').split('/')[1]) || 0;
+            const count = this.total >= PARALLEL_MIN_SIZE ? this.connections : 1;
+            const step = Math.ceil(this.total / count);
+            this.segments = [];
+            for (let start = 0; start < this.total; start += step) {
+                const end = Math.min(start + step, this.total) - 1;
+                this.segments.push({ start, end, chunks: [], received: 0, ranged: true, done: false });
+            }
+            return;
+        }
+
+        // 不支持 Range（如归档内的文件）或空文件：直接读取这个完整响应，失败时只能从头重试
+        // 动态 gzip 时没有 Content-Length，服务端另外给出原始大小
+        const size = resp.headers.get('X-File-Size') || resp.headers.get('Content-Length');
+        this.total = size ? Number(size) : null;
+        const seg = { start: 0, end: null, chunks: [], received: 0, ranged: false, done: false };
+        this.segments = [seg];
+        await this.readBody(resp, seg);
+        seg.done = this.total === null || seg.received >= this.total;
+    }
+
+    /** 并发下载各分段，一段失败时停止其他分段，已收到的数据保留 */
+    async runSegments(signal) {
+        const runs = this.segments.map(seg => this.runSegment(seg, signal).catch(e => {
+            this.controller.abort();
+            throw e;
+        }));
+        // 等所有分段都停下再返回，否则续传时旧请求可能还在写入，已收字节数会错位
+        const failed = (await Promise.allSettled(runs))
+            .filter(r => r.status === 'rejected')
+            .map(r => r.reason);
+        if (failed.length) throw failed.find(e => e.name !== 'AbortError') ?? failed[0];
+    }
+
+    async runSegment(seg, signal) {
+        let attempt = 0;
+        while (!seg.done) {
+            try {
+                await this.fetchSegment(seg, signal);
+                seg.done = true;
+            } catch (e) {
+                if (e.name === 'AbortError' || e instanceof FileChanged) throw e;
+                // 4xx 重试也不会成功
+                if (e instanceof HttpError && e.status < 500) throw e;
+                if (++attempt > MAX_RETRIES) throw e;
+                await sleep(Math.min(1000 * 2 ** (attempt - 1), 10000), signal);
+            }
+        }
+    }
+
+    async fetchSegment(seg, signal) {
+        if (!seg.ranged) {
+            seg.chunks = [];
+            seg.received = 0;
+            await this.writable?.truncate(0);
+            const resp = await fetch(this.url, { signal });
+            if (!resp.ok) throw new HttpError(resp.status);
+            await this.readBody(resp, seg);
+            if (this.total !== null && seg.received < this.total) throw new Error('连接提前结束');
+            return;
+        }
+
+        const headers = { Range: `bytes=${seg.start + seg.received}-${seg.end}` };
+        if (this.validator) headers['If-Range'] = this.validator;
+        const resp = await fetch(this.url, { headers, signal });
+        if (resp.status === 200) {
+            await resp.body?.cancel();
+            throw new FileChanged();
+        }
+        if (!resp.ok) throw new HttpError(resp.status);
+        await this.readBody(resp, seg);
+        if (seg.received < seg.end - seg.start + 1) throw new Error('连接提前结束');
+    }
+
+    async readBody(resp, seg) {
+        const reader = resp.body.getReader();
+        for (;;) {
+            const { done, value } = await reader.read();
+            if (done) break;
+            if (this.writable) {
+                await this.writable.write({ type: 'write', position: seg.start + seg.received, data: value });
+            } else {
+                seg.chunks.push(value);
+            }
+            seg.received += value.length;
+            this.onProgress?.(this.received, this.total);
+        }
+    }
+}
+
+/** 把 Blob 交给浏览器保存 */
+export function saveBlob(blob, name) {
+    const url = URL.createObjectURL(blob);
+    const a = document.createElement('a');
+    a.href = url;
+    a.download = name;
+    a.click();
+    setTimeout(() => URL.revokeObjectURL(url), 60_000);
+}
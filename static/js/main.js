@@ -10,10 +10,19 @@ import { initFileList } from './components/file-list.js';
 import { initUploadPanel } from './components/upload-panel.js';
 import { initPreviewModal } from './components/preview-modal.js';
 import { initContextMenu } from './components/context-menu.js';
+import { initStatsBar } from './components/stats-bar.js';
+import { initP2P } from './components/p2p.js';
+import { initReceiveRequests } from './components/receive-requests.js';
+import { initClipboard } from './components/clipboard.js';
+import { initMessageBoard } from './components/message-board.js';
+import { initTrash } from './components/trash.js';
+import { initNearbyDevices } from './components/nearby-devices.js';
+import { initMaintenanceBanner } from './components/maintenance-banner.js';
 import { state } from './store.js';
+import { getInfo } from './api.js';
 
 /** 应用初始化 */
-function init() {
+async function init() {
     initThemeToggle();
     initBreadcrumb();
     initToolbar();
@@ -21,6 +30,8 @@ function init() {
     initUploadPanel();
     initPreviewModal();
     initContextMenu();
+    initStatsBar();
+    initP2P();
 
     // 移动端浮动上传按钮
     const floatBtn = document.getElementById('upload-float');
@@ -30,6 +41,30 @@ function init() {
         });
     }
 
+    // 投递箱模式：隐藏文件列表，上传面板常驻
+    try {
+        const info = await getInfo();
+        state.localSend = !!info.localsend;
+        state.trashDays = info.trash_days || 0;
+        initMaintenanceBanner(info.maintenance);
+        if (info.localsend_confirm) initReceiveRequests();
+        if (info.localsend && !info.drop_box) initNearbyDevices();
+        if (info.clipboard && !info.drop_box) initClipboard();
+        if (!info.drop_box) initMessageBoard();
+        if (info.read_only) {
+            state.readOnly = true;
+            document.body.classList.add('read-only-mode');
+        } else if (info.trash_days && !info.drop_box) {
+            initTrash(info.trash_days);
+        }
+        if (info.drop_box) {
+            state.dropBox = true;
+            document.body.classList.add('drop-box-mode');
+            state.uploadPanelOpen = true;
+            return;
+        }
+    } catch { /* 旧版本服务端没有 /api/info，按普通模式处理 */ }
+
     // 路由最后初始化（触发首次加载）
     initRouter();
 }
@@ -54,10 +54,22 @@ function flush() {
 
 /** 初始状态 */
 const raw = {
+    /** 投递箱模式：只显示上传区 */
+    dropBox: false,
+    /** 只读模式：隐藏上传、删除等操作 */
+    readOnly: false,
+    /** 回收站保留天数，0 表示删除即彻底删除（可能仍能在撤销期内撤销） */
+    trashDays: 0,
+    /** 管理员暂停上传时的说明，未暂停时为 null */
+    maintenance: null,
+    /** 服务端启用了 LocalSend */
+    localSend: false,
     /** 当前路径 */
     currentPath: '',
     /** 文件列表 */
     files: [],
+    /** 当前所在归档（相对路径），不在归档内时为 null */
+    archive: null,
     /** 加载状态 */
     loading: false,
     /** 排序：name | size | modified */
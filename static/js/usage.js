@@ -0,0 +1,126 @@
+/**
+ * 磁盘占用页面入口
+ * 按目录展示后台扫描得到的占用，子目录按大小降序
+ */
+
+import { getUsage, rescanUsage } from './api.js';
+import { initThemeToggle } from './components/theme-toggle.js';
+import { showToast } from './components/toast.js';
+import { formatSize, formatTime } from './utils/format.js';
+
+let listEl = null;
+let summaryEl = null;
+let crumbEl = null;
+let pollTimer = null;
+
+function currentPath() {
+    const hash = location.hash.slice(1) || '/';
+    return decodeURIComponent(hash).replace(/^\/+/, '').replace(/\/+$/, '');
+}
+
+async function load() {
+    clearTimeout(pollTimer);
+    const path = currentPath();
+    try {
+        const data = await getUsage(path);
+        render(data);
+        // 扫描进行中时定期刷新
+        if (data.scanning) pollTimer = setTimeout(load, 2000);
+    } catch (e) {
+        showToast(`加载失败: ${e.message}`, 'error');
+    }
+}
+
+function render(data) {
+    renderBreadcrumb(data.path);
+
+    if (!data.scanned_at) {
+        summaryEl.textContent = '正在扫描…';
+        listEl.innerHTML = '<div class="empty-state">首次扫描进行中，请稍候</div>';
+        return;
+    }
+
+    const scanning = data.scanning ? '，正在重新扫描…' : '';
+    summaryEl.textContent =
+        `共 ${formatSize(data.size)}，${data.files} 个文件 · 扫描于 ${formatTime(data.scanned_at)}${scanning}`;
+
+    const rows = data.entries.map(e => ({ ...e, isDir: true }));
+    if (data.own_files > 0) {
+        rows.push({ name: '（本目录下的文件）', size: data.own_size, files: data.own_files, isDir: false });
+        rows.sort((a, b) => b.size - a.size);
+    }
+    if (!rows.length) {
+        listEl.innerHTML = '<div class="empty-state">空目录</div>';
+        return;
+    }
+
+    const total = data.size || 1;
+    listEl.innerHTML = rows.map(r => {
+        const pct = (r.size / total) * 100;
+        const attr = r.isDir ? `data-path="${escapeAttr(r.path)}"` : '';
+        return `<div class="file-row usage-row ${r.isDir ? 'is-dir' : ''}" ${attr}>
+            <div class="usage-bar" style="width:${pct.toFixed(2)}%"></div>
+            <div class="file-cell file-cell-name">${escapeHtml(r.name)}</div>
+            <div class="file-cell usage-files">${r.files} 个文件</div>
+            <div class="file-cell usage-pct">${pct.toFixed(1)}%</div>
+            <div class="file-cell file-cell-size">${formatSize(r.size)}</div>
+        </div>`;
+    }).join('');
+}
+
+function renderBreadcrumb(path) {
+    const parts = path ? path.split('/').filter(Boolean) : [];
+
+    let html = `<a class="breadcrumb-item breadcrumb-root" href="#/">
+        <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+            <path d="M3 9l9-7 9 7v11a2 2 0 01-2 2H5a2 2 0 01-2-2V9z"/>
+        </svg>
+    </a>`;
+
+    let cumulative = '';
+    for (const part of parts) {
+        cumulative += (cumulative ? '/' : '') + part;
+        html += `<span class="breadcrumb-sep">/</span>`;
+        html += `<a class="breadcrumb-item" href="#/${escapeAttr(encodeURIComponent(cumulative))}">${escapeHtml(part)}</a>`;
+    }
+
+    crumbEl.innerHTML = html;
+}
+
+function escapeHtml(text) {
+    const div = document.createElement('div');
+    div.textContent = text;
+    return div.innerHTML;
+}
+
+function escapeAttr(text) {
+    return text.replace(/&/g, '&amp;').replace(/"/g, '&quot;').replace(/</g, '&lt;');
+}
+
+function init() {
+    listEl = document.getElementById('usage-list');
+    summaryEl = document.querySelector('.usage-summary');
+    crumbEl = document.getElementById('usage-breadcrumb');
+
+    initThemeToggle();
+
+    listEl.addEventListener('click', (e) => {
+        const row = e.target.closest('.usage-row[data-path]');
+        if (row) location.hash = `/${encodeURIComponent(row.dataset.path)}`;
+    });
+
+    document.querySelector('.usage-rescan').addEventListener('click', async () => {
+        try {
+            await rescanUsage();
+            showToast('已开始重新扫描');
+            setTimeout(load, 500);
+        } catch (e) {
+            showToast(`扫描失败: ${e.message}`, 'error');
+        }
+    });
+
+    window.addEventListener('hashchange', load);
+    load();
+}
+
+init();
@@ -35,6 +35,22 @@ export function formatTime(ts) {
     return `${y}-${m}-${day}`;
 }
 
+/** 格式化 Unix 权限位，如 0o755 -> rwxr-xr-x */
+export function formatMode(mode) {
+    if (mode == null) return '';
+    const bits = 'rwxrwxrwx';
+    let out = '';
+    for (let i = 0; i < 9; i++) {
+        out += mode & (1 << (8 - i)) ? bits[i] : '-';
+    }
+    return out;
+}
+
+/** 是否为可浏览的归档（zip / tar / tar.gz） */
+export function isArchive(name) {
+    return /\.(zip|tar|tar\.gz|tgz)$/i.test(name);
+}
+
 /** 文件图标 SVG */
 export function fileIcon(file) {
     if (file.is_dir) {